@@ -1,18 +1,20 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use genco::{prelude::*, tokens::Tokens};
-use wit_bindgen_core::wit_parser::{Resolve, SizeAlign, World};
+use wit_bindgen_core::wit_parser::{
+    Function, Resolve, SizeAlign, Type, TypeDefKind, World, WorldItem,
+};
 
 use crate::{
     codegen::{
         ExportGenerator, FactoryGenerator,
         exports::ExportConfig,
-        factory::FactoryConfig,
+        factory::{FactoryConfig, runtime_package},
         imports::{ImportAnalyzer, ImportCodeGenerator},
         ir::AnalyzedImports,
-        wasm::{Wasm, WasmData},
+        wasm::{Compression, Wasm, WasmData},
     },
-    go::GoIdentifier,
+    go::{GoIdentifier, comment, tuple_name},
 };
 
 /// The WIT bindings for a world.
@@ -28,6 +30,53 @@ pub struct Bindings<'a> {
 
     /// The sizes of the architecture.
     sizes: &'a SizeAlign,
+
+    /// Whether the guest async-lifts and async-lowers the functions declared
+    /// `async`.
+    asynchronous: bool,
+
+    /// Whether `option` and `result` are carried in the generated generic
+    /// `Option[T]` and `Result[T, E]`.
+    generic_containers: bool,
+
+    /// Whether the generated factory runs the guest in wazero's interpreter
+    /// by default.
+    interpreter: bool,
+
+    /// Whether calls into the guest and host functions are traced with
+    /// OpenTelemetry.
+    tracing: bool,
+
+    /// Whether host functions are handed views of guest memory for strings
+    /// and `list<u8>`s rather than copies.
+    zero_copy: bool,
+
+    /// Whether instances write the arguments of calls into the guest to a
+    /// reused scratch buffer.
+    scratch_buffer: bool,
+
+    /// Whether the host's copies of guest data, and the scratch buffer, are
+    /// zeroed once done with.
+    zero_buffers: bool,
+
+    /// Whether the factory constructor takes the guest's Wasm rather than
+    /// the bindings including it.
+    external_wasm: bool,
+
+    /// How the Wasm included in the bindings is compressed, if it is.
+    compression: Option<Compression>,
+
+    /// Whether the guest was pre-initialized, so the factory mustn't run its
+    /// start functions.
+    pre_initialized: bool,
+
+    /// The names the guest's mutable globals are exported under, which
+    /// instance snapshots capture.
+    mutable_globals: Vec<String>,
+
+    /// The import path of the runtime package whose helpers the bindings
+    /// use, if any.
+    runtime_package: Option<&'a str>,
 }
 
 impl<'a> Bindings<'a> {
@@ -41,9 +90,125 @@ impl<'a> Bindings<'a> {
             out: Tokens::new(),
             raw_wasm_var: wasm_var,
             sizes,
+            asynchronous: false,
+            generic_containers: false,
+            interpreter: false,
+            tracing: false,
+            zero_copy: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+            external_wasm: false,
+            compression: None,
+            pre_initialized: false,
+            mutable_globals: Vec::new(),
+            runtime_package: None,
         }
     }
 
+    /// Generate bindings for the component model's async ABI: the functions
+    /// declared `async` are called through the async intrinsics, which the
+    /// bindings provide.
+    pub fn with_async(mut self, asynchronous: bool) -> Self {
+        self.asynchronous = asynchronous;
+        self
+    }
+
+    /// Generate the generic `Option[T]` and `Result[T, E]` types and carry
+    /// every `option` and `result` in them, rather than in a pointer and a Go
+    /// `error`.
+    pub fn with_generic_containers(mut self, generic_containers: bool) -> Self {
+        self.generic_containers = generic_containers;
+        self
+    }
+
+    /// Default the generated factory to wazero's interpreter, for platforms
+    /// the compiler doesn't support. Factories can still opt back into the
+    /// compiler with `WithCompiler`.
+    pub fn with_interpreter(mut self, interpreter: bool) -> Self {
+        self.interpreter = interpreter;
+        self
+    }
+
+    /// Instrument the bindings with OpenTelemetry spans around calls into the
+    /// guest and host functions, recorded once the factory is given a
+    /// `WithTracerProvider` option. The generated code then depends on
+    /// `go.opentelemetry.io/otel/trace`.
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
+    /// Hand host functions the strings and `list<u8>`s the guest calls them
+    /// with as views of guest memory, sparing a copy per call. The views
+    /// are only valid until the host function returns, so the host must not
+    /// keep them, or anything sharing their memory, around.
+    pub fn with_zero_copy(mut self, zero_copy: bool) -> Self {
+        self.zero_copy = zero_copy;
+        self
+    }
+
+    /// Have instances write the strings and lists passed to the guest to a
+    /// grow-only scratch buffer in its memory, reused across calls, rather
+    /// than allocating each of them with `cabi_realloc`. Only guests which
+    /// neither free their arguments nor keep them past the call may be
+    /// generated with this.
+    pub fn with_scratch_buffer(mut self, scratch_buffer: bool) -> Self {
+        self.scratch_buffer = scratch_buffer;
+        self
+    }
+
+    /// Zero the host's temporary copies of the strings written to guest
+    /// memory and host functions' copies of the `list<u8>`s they're called
+    /// with once done with them, along with the part of the scratch buffer
+    /// each call used, for worlds passing secrets. Go strings can't be
+    /// zeroed, so secrets should be passed as `list<u8>`.
+    pub fn with_zero_buffers(mut self, zero_buffers: bool) -> Self {
+        self.zero_buffers = zero_buffers;
+        self
+    }
+
+    /// Have the bindings use the helpers of the runtime package imported
+    /// from `runtime_package`, generated by [`Bindings::generate_runtime`],
+    /// rather than having copies of their own, so the bindings of several
+    /// worlds can share them.
+    pub fn with_runtime_package(mut self, runtime_package: Option<&'a str>) -> Self {
+        self.runtime_package = runtime_package;
+        self
+    }
+
+    /// Have the generated factory constructor take the guest's Wasm as its
+    /// `wasm` parameter, so it can be updated without regenerating the
+    /// bindings. The Wasm shouldn't be included in the bindings then.
+    pub fn with_external_wasm(mut self, external_wasm: bool) -> Self {
+        self.external_wasm = external_wasm;
+        self
+    }
+
+    /// Have the generated factory constructor decompress the Wasm included
+    /// in the bindings, which must have been compressed with
+    /// [`Compression::compress`].
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Generate bindings for a guest pre-initialized by a tool like Wizer,
+    /// which snapshots the guest after running its initialization. The
+    /// factory then doesn't run the guest's `_start` function again.
+    pub fn with_pre_initialized(mut self, pre_initialized: bool) -> Self {
+        self.pre_initialized = pre_initialized;
+        self
+    }
+
+    /// Have instance snapshots capture the guest's mutable globals, exported
+    /// under the given names by [`export_mutable_globals`].
+    ///
+    /// [`export_mutable_globals`]: crate::codegen::export_mutable_globals
+    pub fn with_mutable_globals(mut self, mutable_globals: Vec<String>) -> Self {
+        self.mutable_globals = mutable_globals;
+        self
+    }
+
     /// Adds the given Wasm to the bindings.
     pub fn include_wasm(&mut self, wasm: WasmData) {
         Wasm::new(&self.raw_wasm_var, wasm).format_into(&mut self.out)
@@ -51,20 +216,140 @@ impl<'a> Bindings<'a> {
 
     /// Generate the bindings.
     ///
-    /// This generates the imports (interfaces, types, functions), the factory and instance
-    /// type, and the exports (functions).
+    /// This generates the tuple structs, the generic containers, the imports (interfaces,
+    /// types, functions), the factory and instance type, and the exports (functions).
     pub fn generate(&mut self) {
+        self.generate_tuples();
+        self.generate_containers();
         let (imports, chains) = self.generate_imports();
         self.generate_factory(&imports, chains);
         self.generate_exports(&imports.instance_name);
     }
 
+    /// Generates a generic `TupleN` struct for every tuple arity in use.
+    ///
+    /// Tuples are anonymous in WIT, so rather than naming a struct per tuple
+    /// type, all tuples of the same arity share one generic struct.
+    fn generate_tuples(&mut self) {
+        let arities = self
+            .resolve
+            .types
+            .iter()
+            .filter_map(|(_, def)| match &def.kind {
+                TypeDefKind::Tuple(tuple) => Some(tuple.types.len()),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+
+        for arity in arities {
+            let name = &tuple_name(arity);
+            let doc = format!("{name} holds the values of a WIT `tuple` with {arity} elements.");
+            quote_in! { self.out =>
+                $['\n']
+                $(comment([doc]))
+                type $name[$(for nth in 0..arity join (, ) => $(format!("T{nth}")) any)] struct {
+                    $(for nth in 0..arity join ($['\r']) => $(format!("F{nth}")) $(format!("T{nth}")))
+                }
+            }
+        }
+    }
+
+    /// Generates the generic `Option[T]` and `Result[T, E]` types when
+    /// generic containers are enabled and the world uses them.
+    ///
+    /// Results that aren't a function's own result are always carried in a
+    /// `Result[T, E]`, so it's generated for them regardless.
+    fn generate_containers(&mut self) {
+        let kinds = || self.resolve.types.iter().map(|(_, def)| &def.kind);
+        let options =
+            self.generic_containers && kinds().any(|kind| matches!(kind, TypeDefKind::Option(_)));
+        let results = if self.generic_containers {
+            kinds().any(|kind| matches!(kind, TypeDefKind::Result(_)))
+        } else {
+            self.uses_nested_results()
+        };
+
+        if options {
+            quote_in! { self.out =>
+                $['\n']
+                $(comment(&["Option holds a WIT `option<T>`: either some value or none."]))
+                type Option[T any] struct {
+                    value T
+                    some bool
+                }
+                $['\n']
+                $(comment(&["OptionSome returns an Option holding the given value."]))
+                func OptionSome[T any](value T) Option[T] {
+                    return Option[T]{value: value, some: true}
+                }
+                $['\n']
+                $(comment(&["OptionNone returns an Option holding no value."]))
+                func OptionNone[T any]() Option[T] {
+                    return Option[T]{}
+                }
+                $['\n']
+                $(comment(&["IsSome reports whether the Option holds a value."]))
+                func (o Option[T]) IsSome() bool {
+                    return o.some
+                }
+                $['\n']
+                $(comment(&["Get returns the value the Option holds, and whether it holds one."]))
+                func (o Option[T]) Get() (T, bool) {
+                    return o.value, o.some
+                }
+            }
+        }
+
+        if results {
+            quote_in! { self.out =>
+                $['\n']
+                $(comment(&["Result holds a WIT `result<T, E>`: either an ok value or an error value."]))
+                type Result[T, E any] struct {
+                    ok T
+                    err E
+                    isErr bool
+                }
+                $['\n']
+                $(comment(&["ResultOk returns a Result holding the given ok value."]))
+                func ResultOk[T, E any](value T) Result[T, E] {
+                    return Result[T, E]{ok: value}
+                }
+                $['\n']
+                $(comment(&["ResultErr returns a Result holding the given error value."]))
+                func ResultErr[T, E any](err E) Result[T, E] {
+                    return Result[T, E]{err: err, isErr: true}
+                }
+                $['\n']
+                $(comment(&["IsErr reports whether the Result holds an error value."]))
+                func (r Result[T, E]) IsErr() bool {
+                    return r.isErr
+                }
+                $['\n']
+                $(comment(&["Ok returns the ok value the Result holds, and whether it holds one."]))
+                func (r Result[T, E]) Ok() (T, bool) {
+                    return r.ok, !r.isErr
+                }
+                $['\n']
+                $(comment(&["Err returns the error value the Result holds, and whether it holds one."]))
+                func (r Result[T, E]) Err() (E, bool) {
+                    return r.err, r.isErr
+                }
+            }
+        }
+    }
+
     /// Generates the imports for the bindings.
     fn generate_imports(&mut self) -> (AnalyzedImports, BTreeMap<String, Tokens<Go>>) {
-        let analyzer = ImportAnalyzer::new(self.resolve, self.world);
+        let analyzer = ImportAnalyzer::new(self.resolve, self.world)
+            .with_generic_containers(self.generic_containers);
         let analyzed = analyzer.analyze();
 
-        let generator = ImportCodeGenerator::new(self.resolve, &analyzed, self.sizes);
+        let generator = ImportCodeGenerator::new(self.resolve, &analyzed, self.sizes)
+            .with_async(self.asynchronous)
+            .with_generic_containers(self.generic_containers)
+            .with_tracing(self.tracing)
+            .with_zero_copy(self.zero_copy)
+            .with_zero_buffers(self.zero_buffers);
         let import_chains = generator.import_chains();
         generator.format_into(&mut self.out);
         (analyzed, import_chains)
@@ -75,26 +360,246 @@ impl<'a> Bindings<'a> {
     fn generate_factory(
         &mut self,
         analyzed_imports: &AnalyzedImports,
-        import_chains: BTreeMap<String, Tokens<Go>>,
+        mut import_chains: BTreeMap<String, Tokens<Go>>,
     ) {
+        // The guest imports the intrinsics of the resources, futures and
+        // streams of its exports from host modules of their own.
+        let exports = ExportGenerator::new(ExportConfig {
+            instance: &analyzed_imports.instance_name,
+            world: self.world,
+            resolve: self.resolve,
+            sizes: self.sizes,
+            asynchronous: self.asynchronous,
+            generic_containers: self.generic_containers,
+            tracing: self.tracing,
+            scratch_buffer: self.scratch_buffer,
+            zero_buffers: self.zero_buffers,
+        });
+        import_chains.extend(exports.export_chains());
+
         let config = FactoryConfig {
             analyzed_imports,
+            world: &self.world.name,
             import_chains,
-            wasm_var_name: &self.raw_wasm_var,
+            wasm_var_name: (!self.external_wasm).then_some(&self.raw_wasm_var),
+            compression: self.compression,
+            error_context: self.uses_error_context(),
+            resources: exports.resource_handles(),
+            futures: self.uses_futures(),
+            streams: self.uses_streams(),
+            asynchronous: self.asynchronous && self.uses_async(),
+            interpreter: self.interpreter,
+            tracing: self.tracing,
+            pre_initialized: self.pre_initialized,
+            mutable_globals: self.mutable_globals.clone(),
+            scratch_buffer: self.scratch_buffer,
+            zero_buffers: self.zero_buffers,
+            runtime_package: self.runtime_package,
         };
         FactoryGenerator::new(config).format_into(&mut self.out)
     }
 
-    /// Generates all exports for the world.
-    ///
-    /// Note: for now this only generates functions; types and interfaces are
-    /// still TODO
+    /// Whether any type in the world is a `future`.
+    fn uses_futures(&self) -> bool {
+        self.resolve
+            .types
+            .iter()
+            .any(|(_, def)| matches!(def.kind, TypeDefKind::Future(_)))
+    }
+
+    /// Whether any type in the world is a `stream`.
+    fn uses_streams(&self) -> bool {
+        self.resolve
+            .types
+            .iter()
+            .any(|(_, def)| matches!(def.kind, TypeDefKind::Stream(_)))
+    }
+
+    /// Whether any function the world imports or exports is `async`.
+    fn uses_async(&self) -> bool {
+        self.world
+            .imports
+            .values()
+            .chain(self.world.exports.values())
+            .any(|item| match item {
+                WorldItem::Function(func) => crate::is_async(func),
+                WorldItem::Interface { id, .. } => self.resolve.interfaces[*id]
+                    .functions
+                    .values()
+                    .any(crate::is_async),
+                WorldItem::Type { .. } => false,
+            })
+    }
+
+    /// Whether a `result` is used other than as a function's result: named,
+    /// nested in another type, or as a parameter.
+    fn uses_nested_results(&self) -> bool {
+        let is_result = |typ: &Type| match typ {
+            Type::Id(id) => matches!(self.resolve.types[*id].kind, TypeDefKind::Result(_)),
+            _ => false,
+        };
+        let in_params = |func: &Function| func.params.iter().any(|param| is_result(&param.ty));
+
+        let in_types = self.resolve.types.iter().any(|(_, def)| match &def.kind {
+            TypeDefKind::Result(result) => {
+                def.name.is_some()
+                    || result.ok.as_ref().is_some_and(is_result)
+                    || result.err.as_ref().is_some_and(is_result)
+            }
+            TypeDefKind::Record(record) => record.fields.iter().any(|f| is_result(&f.ty)),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .any(|case| case.ty.as_ref().is_some_and(is_result)),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().any(is_result),
+            TypeDefKind::Map(key, value) => is_result(key) || is_result(value),
+            TypeDefKind::Future(Some(typ)) | TypeDefKind::Stream(Some(typ)) => is_result(typ),
+            TypeDefKind::Type(typ)
+            | TypeDefKind::Option(typ)
+            | TypeDefKind::List(typ)
+            | TypeDefKind::FixedLengthList(typ, _) => is_result(typ),
+            _ => false,
+        });
+
+        let in_functions = self
+            .world
+            .imports
+            .values()
+            .chain(self.world.exports.values())
+            .any(|item| match item {
+                WorldItem::Function(func) => in_params(func),
+                WorldItem::Interface { id, .. } => self.resolve.interfaces[*id]
+                    .functions
+                    .values()
+                    .any(in_params),
+                WorldItem::Type { .. } => false,
+            });
+
+        in_types || in_functions
+    }
+
+    /// Whether any function or type in the world passes an `error-context`.
+    fn uses_error_context(&self) -> bool {
+        let is_error_context = |typ: &Type| matches!(typ, Type::ErrorContext);
+        let in_function = |func: &Function| {
+            func.params.iter().any(|param| is_error_context(&param.ty))
+                || func.result.as_ref().is_some_and(is_error_context)
+        };
+
+        let in_types = self.resolve.types.iter().any(|(_, def)| match &def.kind {
+            TypeDefKind::Record(record) => record.fields.iter().any(|f| is_error_context(&f.ty)),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .any(|case| case.ty.as_ref().is_some_and(is_error_context)),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().any(is_error_context),
+            TypeDefKind::Result(result) => {
+                result.ok.as_ref().is_some_and(is_error_context)
+                    || result.err.as_ref().is_some_and(is_error_context)
+            }
+            TypeDefKind::Map(key, value) => is_error_context(key) || is_error_context(value),
+            TypeDefKind::Type(typ)
+            | TypeDefKind::Option(typ)
+            | TypeDefKind::List(typ)
+            | TypeDefKind::FixedLengthList(typ, _) => is_error_context(typ),
+            _ => false,
+        });
+
+        let in_functions = self
+            .world
+            .imports
+            .values()
+            .chain(self.world.exports.values())
+            .any(|item| match item {
+                WorldItem::Function(func) => in_function(func),
+                WorldItem::Interface { id, .. } => self.resolve.interfaces[*id]
+                    .functions
+                    .values()
+                    .any(in_function),
+                WorldItem::Type { .. } => false,
+            });
+
+        in_types || in_functions
+    }
+
+    /// Generate Go benchmarks calling each of the functions the guest exports
+    /// with zero values, for a `_bench_test.go` file in the package of the
+    /// bindings. The bindings must include the Wasm, as the benchmarks have
+    /// none to pass the factory constructor.
+    pub fn generate_benchmarks(&self) -> Tokens<Go> {
+        let imports = self.analyze_imports();
+        self.export_generator(&imports).benchmarks(&imports)
+    }
+
+    /// Generate Go fuzz targets for the functions the guest exports taking
+    /// strings or `list<u8>`s, for a `_fuzz_test.go` file in the package of
+    /// the bindings, which must include the Wasm.
+    pub fn generate_fuzz_targets(&self) -> Tokens<Go> {
+        let imports = self.analyze_imports();
+        self.export_generator(&imports).fuzz_targets(&imports)
+    }
+
+    /// Generate a runnable `main` package using the bindings, imported from
+    /// `package`, which creates a factory and an instance and calls one of
+    /// the functions the guest exports. Like the benchmarks, it needs the
+    /// bindings to include the Wasm.
+    pub fn generate_example(&self, package: &str) -> Tokens<Go> {
+        let imports = self.analyze_imports();
+        self.export_generator(&imports).example(&imports, package)
+    }
+
+    /// Generate a skeleton of tests checking the host's implementations of
+    /// the imported interfaces, for a `_conformance_test.go` file in the
+    /// package of the bindings. It tests the generated `Noop` ones until
+    /// edited to test the host's instead.
+    pub fn generate_conformance_tests(&self) -> Tokens<Go> {
+        let imports = self.analyze_imports();
+        ImportCodeGenerator::new(self.resolve, &imports, self.sizes)
+            .with_generic_containers(self.generic_containers)
+            .conformance_tests()
+    }
+
+    /// Generate the runtime package holding the helpers that don't depend on
+    /// the world, for the bindings given its import path with
+    /// [`Bindings::with_runtime_package`] to share.
+    pub fn generate_runtime() -> Tokens<Go> {
+        runtime_package()
+    }
+
+    fn analyze_imports(&self) -> AnalyzedImports {
+        ImportAnalyzer::new(self.resolve, self.world)
+            .with_generic_containers(self.generic_containers)
+            .analyze()
+    }
+
+    fn export_generator<'b>(&'b self, imports: &'b AnalyzedImports) -> ExportGenerator<'b> {
+        ExportGenerator::new(ExportConfig {
+            instance: &imports.instance_name,
+            world: self.world,
+            resolve: self.resolve,
+            sizes: self.sizes,
+            asynchronous: self.asynchronous,
+            generic_containers: self.generic_containers,
+            tracing: self.tracing,
+            scratch_buffer: self.scratch_buffer,
+            zero_buffers: self.zero_buffers,
+        })
+    }
+
+    /// Generates all exports for the world: its functions, and the functions
+    /// and resources of its exported interfaces, whose types are generated
+    /// with the imports'.
     fn generate_exports(&mut self, instance: &GoIdentifier) {
         let config = ExportConfig {
             instance,
             world: self.world,
             resolve: self.resolve,
             sizes: self.sizes,
+            asynchronous: self.asynchronous,
+            generic_containers: self.generic_containers,
+            tracing: self.tracing,
+            scratch_buffer: self.scratch_buffer,
+            zero_buffers: self.zero_buffers,
         };
         ExportGenerator::new(config).format_into(&mut self.out)
     }
@@ -1,22 +1,66 @@
-use genco::prelude::*;
-use wit_bindgen_core::wit_parser::{Function, Param, Resolve, SizeAlign, World, WorldItem};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
 
-use crate::go::{imports::CONTEXT_CONTEXT, GoIdentifier, GoResult, GoType};
+use genco::{lang::go, prelude::*};
+use wit_bindgen_core::{
+    abi::{AbiVariant, LiftLower},
+    wit_parser::{
+        Function, FunctionKind, InterfaceId, Param, Resolve, SizeAlign, Type, TypeDefKind, TypeId,
+        World, WorldItem, WorldKey,
+    },
+};
+
+use crate::{
+    codegen::{
+        imports::{go_module_func, host_param_type},
+        ir::AnalyzedImports,
+    },
+    go::{
+        GoIdentifier, GoResult, GoType, Operand, comment,
+        imports::{
+            CONTEXT_BACKGROUND, CONTEXT_CONTEXT, ERRORS_NEW, FMT_PRINTLN, LOG_FATAL, TESTING_B,
+            TESTING_F, TESTING_T, WAZERO_API_FUNCTION, WAZERO_API_MODULE,
+        },
+    },
+};
 
 pub struct ExportConfig<'a> {
     pub instance: &'a GoIdentifier,
     pub world: &'a World,
     pub resolve: &'a Resolve,
     pub sizes: &'a SizeAlign,
+    /// Whether the guest async-lifts the functions declared `async`.
+    pub asynchronous: bool,
+    /// Whether `option` and `result` are carried in the generated generic
+    /// `Option[T]` and `Result[T, E]`.
+    pub generic_containers: bool,
+    /// Whether calls into the guest are traced with OpenTelemetry.
+    pub tracing: bool,
+    /// Whether arguments are written to the instance's scratch buffer.
+    pub scratch_buffer: bool,
+    /// Whether the scratch buffer is zeroed after every call.
+    pub zero_buffers: bool,
 }
 
 pub struct ExportGenerator<'a> {
     config: ExportConfig<'a>,
+    /// The functions the core module exports which the generated code calls,
+    /// gathered while generating it for the `exportedFunctions` struct.
+    exported_functions: RefCell<BTreeSet<String>>,
+    /// The signatures of the instance's methods calling into the guest,
+    /// gathered while generating them for the instance's interface.
+    instance_methods: RefCell<Vec<Tokens<Go>>>,
 }
 
 impl<'a> ExportGenerator<'a> {
     pub fn new(config: ExportConfig<'a>) -> Self {
-        Self { config }
+        Self {
+            config,
+            exported_functions: Default::default(),
+            instance_methods: Default::default(),
+        }
     }
 
     /// Generate the Go function code for the given function.
@@ -32,64 +76,1254 @@ impl<'a> ExportGenerator<'a> {
     ///   times, one for each instruction in the function, and `Func::emit` will generate
     ///   Go code for each instruction
     fn generate_function(&self, func: &Function, tokens: &mut Tokens<Go>) {
-        let params = func
-            .params
+        self.generate_instance_function(func, func.name.clone(), tokens)
+    }
+
+    /// Generate a method of the instance calling the function the core module
+    /// exports as `wasm_name`. See [`Self::generate_function`].
+    fn generate_instance_function(
+        &self,
+        func: &Function,
+        wasm_name: String,
+        tokens: &mut Tokens<Go>,
+    ) {
+        let params = self.params(&func.params);
+        let f = self.call(func, wasm_name);
+
+        let arg_assignments = f
+            .args()
             .iter()
-            .map(
-                |Param { name, ty, .. }| match crate::resolve_param_type(ty, self.config.resolve) {
-                    GoType::ValueOrOk(t) => (GoIdentifier::local(name), *t),
-                    t => (GoIdentifier::local(name), t),
-                },
-            )
+            .zip(&params)
+            .map(|(arg, (param, _))| (arg, param))
             .collect::<Vec<_>>();
+        let fn_name = &crate::function_go_name(func);
+        self.instance_methods.borrow_mut().push(quote! {
+            $fn_name(ctx $CONTEXT_CONTEXT$(for (name, typ) in &params => , $name $typ)) $(f.result())
+        });
+        quote_in! { *tokens =>
+            $['\n']
+            func (i *$(self.config.instance)) $fn_name(
+                $['\r']
+                ctx $CONTEXT_CONTEXT,
+                $(for (name, typ) in &params join ($['\r']) => $name $typ,)
+            ) $(f.result()) {
+                $(if self.config.scratch_buffer => i.scratch.reset())
+                $(for (arg, param) in arg_assignments join ($['\r']) => $arg := $param)
+                $(f.body())
+            }
+        }
+    }
 
-        let result = if let Some(wit_type) = &func.result {
-            GoResult::Anon(crate::resolve_type(wit_type, self.config.resolve))
+    /// Generate the body of a call to the function the core module exports
+    /// as `wasm_name`.
+    ///
+    /// An async-lifted function hands its results over through
+    /// `[task-return]` instead of returning them, so only its arguments are
+    /// lowered here.
+    fn call(&self, func: &Function, wasm_name: String) -> crate::Func<'a> {
+        let mut f = crate::Func::export(self.result(func), self.config.sizes)
+            .with_name(wasm_name.clone())
+            .with_wasm_name(wasm_name)
+            .with_generic_containers(self.config.generic_containers)
+            .with_tracing(self.config.tracing)
+            .with_scratch_buffer(self.config.scratch_buffer)
+            .with_zero_buffers(self.config.zero_buffers)
+            .with_wit_result(func.result);
+        f.begin_call();
+        let without_result;
+        let func = if self.is_async_lift(func) {
+            f = f.with_async();
+            without_result = Function {
+                result: None,
+                ..func.clone()
+            };
+            &without_result
         } else {
-            GoResult::Empty
+            func
         };
-
-        let mut f = crate::Func::export(result, self.config.sizes);
         wit_bindgen_core::abi::call(
             self.config.resolve,
-            wit_bindgen_core::abi::AbiVariant::GuestExport,
-            wit_bindgen_core::abi::LiftLower::LowerArgsLiftResults,
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
             func,
             &mut f,
-            // async is not currently supported
+            // The async ABI is handled by `Func` itself
+            false,
+        );
+        self.exported_functions
+            .borrow_mut()
+            .extend(f.exported_functions().iter().cloned());
+        f
+    }
+
+    /// Whether the guest async-lifts a function.
+    fn is_async_lift(&self, func: &Function) -> bool {
+        self.config.asynchronous && crate::is_async(func)
+    }
+
+    /// Resolves a WIT type to the Go type the bindings carry it in.
+    fn resolve_type(&self, typ: &Type) -> GoType {
+        crate::resolve_type_with(typ, self.config.resolve, self.config.generic_containers)
+    }
+
+    /// The Go names and types of a function's parameters.
+    fn params(&self, params: &[Param]) -> Vec<(GoIdentifier, GoType)> {
+        params
+            .iter()
+            .map(|Param { name, ty, .. }| {
+                match crate::resolve_param_type_with(
+                    ty,
+                    self.config.resolve,
+                    self.config.generic_containers,
+                ) {
+                    GoType::ValueOrOk(t) => (GoIdentifier::local(name), *t),
+                    t => (GoIdentifier::local(name), t),
+                }
+            })
+            .collect()
+    }
+
+    /// The Go names and types of the parameters of a function of an exported
+    /// interface. Unlike the world's own functions, see [`Self::params`],
+    /// these take variants as their marker interface, as there are no callers
+    /// passing them `interface{}` to keep working.
+    fn interface_params(&self, params: &[Param]) -> Vec<(GoIdentifier, GoType)> {
+        params
+            .iter()
+            .map(|Param { name, ty, .. }| match self.resolve_type(ty) {
+                GoType::ValueOrOk(t) => (GoIdentifier::local(name), *t),
+                t => (GoIdentifier::local(name), t),
+            })
+            .collect()
+    }
+
+    /// The Go result of a function.
+    fn result(&self, func: &Function) -> GoResult {
+        if let Some(wit_type) = &func.result {
+            GoResult::Anon(crate::resolve_return_type_with(
+                wit_type,
+                self.config.resolve,
+                self.config.generic_containers,
+            ))
+        } else {
+            GoResult::Empty
+        }
+    }
+
+    /// The resources declared by an exported interface.
+    fn resources(&self, interface: InterfaceId) -> impl Iterator<Item = TypeId> + '_ {
+        self.config.resolve.interfaces[interface]
+            .types
+            .values()
+            .copied()
+            .filter(|&id| matches!(self.config.resolve.types[id].kind, TypeDefKind::Resource))
+    }
+
+    /// The tables of the handles to the resources the guest exports, which
+    /// have to be released along with an instance.
+    pub fn resource_handles(&self) -> Vec<GoIdentifier> {
+        let resolve = self.config.resolve;
+        self.config
+            .world
+            .exports
+            .values()
+            .flat_map(|item| match item {
+                WorldItem::Interface { id, .. } => self.resources(*id).collect(),
+                _ => vec![],
+            })
+            .map(|resource| crate::resource_handles(resource, resolve))
+            .collect()
+    }
+
+    /// Generate the host modules providing the intrinsics the guest imports
+    /// for its exports, keyed by module name.
+    ///
+    /// The guest creates a handle to a resource it exports with
+    /// `[resource-new]`, looks up what it represents with `[resource-rep]`
+    /// and drops it with `[resource-drop]`, which calls the resource's
+    /// destructor. The `[future-*]` and `[stream-*]` intrinsics create and
+    /// write the futures and streams its functions return, and async-lifted
+    /// functions return through `[task-return]`.
+    pub fn export_chains(&self) -> BTreeMap<String, Tokens<Go>> {
+        let resolve = self.config.resolve;
+        let mut modules = BTreeMap::<String, Vec<Tokens<Go>>>::new();
+        let mut root_task_cancel = false;
+
+        for (key, item) in &self.config.world.exports {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    let interface_name = resolve.name_world_key(key);
+                    let asynchronous = resolve.interfaces[*id]
+                        .functions
+                        .values()
+                        .any(|func| self.is_async_lift(func));
+                    let functions = self
+                        .resources(*id)
+                        .map(|resource| self.resource_intrinsics(&interface_name, resource))
+                        .chain(
+                            resolve.interfaces[*id]
+                                .functions
+                                .values()
+                                .flat_map(|func| self.function_intrinsics(func)),
+                        )
+                        .chain(asynchronous.then(|| self.task_cancel_intrinsic()))
+                        .collect::<Vec<_>>();
+                    if !functions.is_empty() {
+                        modules
+                            .entry(format!("[export]{interface_name}"))
+                            .or_default()
+                            .extend(functions);
+                    }
+                }
+                WorldItem::Function(func) => {
+                    let functions = self.function_intrinsics(func);
+                    if !functions.is_empty() {
+                        let module = modules.entry("[export]$root".to_string()).or_default();
+                        // `[task-cancel]` is shared by every function of the
+                        // module, so it's only added along with the first one.
+                        if self.is_async_lift(func) && !root_task_cancel {
+                            module.push(self.task_cancel_intrinsic());
+                            root_task_cancel = true;
+                        }
+                        module.extend(functions);
+                    }
+                }
+                WorldItem::Type { .. } => {}
+            }
+        }
+
+        modules
+            .into_iter()
+            .enumerate()
+            .map(|(i, (module_name, functions))| {
+                let host_module = &GoIdentifier::private(format!("export-module{i}"));
+                let chain = quote! {
+                    $host_module, err := wazeroRuntime.NewHostModuleBuilder(options.namespace + $(quoted(&module_name))).
+                    $(for function in functions join ($['\r']) => $function)
+                    Instantiate(ctx)
+                    if err != nil {
+                        return nil, &InstantiationError{Err: err}
+                    }
+                    hostModules = append(hostModules, $host_module)
+                };
+                (module_name, chain)
+            })
+            .collect()
+    }
+
+    /// Generate the handle intrinsics of a resource the guest exports.
+    fn resource_intrinsics(&self, interface_name: &str, resource: TypeId) -> Tokens<Go> {
+        let resolve = self.config.resolve;
+        let wit_name = resolve.types[resource]
+            .name
+            .as_deref()
+            .expect("resources are named");
+        let handles = &crate::resource_handles(resource, resolve);
+        let invalid = &format!("invalid {wit_name} handle");
+        let dtor = &format!("{interface_name}#[dtor]{wit_name}");
+        let new = go_module_func(
+            &[("rep", GoType::Uint32)],
+            Some(GoType::Uint32),
+            quote! {
+                return $handles.of(mod).insert(rep)
+            },
+        );
+        let rep = go_module_func(
+            &[("handle", GoType::Uint32)],
+            Some(GoType::Uint32),
+            quote! {
+                rep, ok := $handles.of(mod).get(handle)
+                if !ok {
+                    panic($ERRORS_NEW($(quoted(invalid))))
+                }
+                return rep
+            },
+        );
+        let drop = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                rep, ok := $handles.of(mod).remove(handle)
+                if !ok {
+                    panic($ERRORS_NEW($(quoted(invalid))))
+                }
+                if dtor := mod.ExportedFunction($(quoted(dtor))); dtor != nil {
+                    if _, err := dtor.Call(ctx, uint64(rep)); err != nil {
+                        panic(err)
+                    }
+                }
+            },
+        );
+        quote! {
+            NewFunctionBuilder().
+            $new
+            Export($(quoted(format!("[resource-new]{wit_name}")))).
+            NewFunctionBuilder().
+            $rep
+            Export($(quoted(format!("[resource-rep]{wit_name}")))).
+            NewFunctionBuilder().
+            $drop
+            Export($(quoted(format!("[resource-drop]{wit_name}")))).
+        }
+    }
+
+    /// Generate the intrinsics the guest imports for a function.
+    fn function_intrinsics(&self, func: &Function) -> Vec<Tokens<Go>> {
+        let mut intrinsics = self.future_and_stream_intrinsics(func);
+        if self.is_async_lift(func) {
+            intrinsics.push(self.task_return_intrinsic(func));
+        }
+        intrinsics
+    }
+
+    /// Generate the `[task-return]` intrinsic an async-lifted function's
+    /// task hands its results over through.
+    ///
+    /// It takes the results the way a host function takes its
+    /// parameters, so it's generated as one, handing the lifted results to
+    /// the task.
+    fn task_return_intrinsic(&self, func: &Function) -> Tokens<Go> {
+        let resolve = self.config.resolve;
+        let task_return = Function {
+            name: "return".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: func
+                .result
+                .iter()
+                .map(|&ty| Param {
+                    name: "result".to_string(),
+                    ty,
+                    span: Default::default(),
+                })
+                .collect(),
+            result: None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let wasm_sig = resolve.wasm_signature(AbiVariant::GuestImport, &task_return);
+        let task = &GoIdentifier::local("task");
+        let mut f = crate::Func::import(task, GoResult::Empty, self.config.sizes)
+            .with_name(format!("[task-return]{}", func.name))
+            .with_generic_containers(self.config.generic_containers)
+            .with_wit_result(func.result);
+        wit_bindgen_core::abi::call(
+            resolve,
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            &task_return,
+            &mut f,
             false,
         );
 
+        let params = f
+            .args()
+            .iter()
+            .zip(&wasm_sig.params)
+            .map(|(arg, typ)| (arg.as_str(), host_param_type(typ)))
+            .collect::<Vec<_>>();
+        let task_return = go_module_func(
+            &params,
+            None,
+            quote! {
+                $task := asyncTaskOf(ctx)
+                $(f.body())
+            },
+        );
+
+        quote! {
+            NewFunctionBuilder().
+            $task_return
+            Export($(quoted(format!("[task-return]{}", func.name)))).
+        }
+    }
+
+    /// Generate the `[task-cancel]` intrinsic, which an async-lifted
+    /// function's task calls instead of returning once it's been cancelled.
+    fn task_cancel_intrinsic(&self) -> Tokens<Go> {
+        let task_cancel = go_module_func(
+            &[],
+            None,
+            quote! {
+                asyncTaskOf(ctx).cancelled = true
+            },
+        );
+        quote! {
+            NewFunctionBuilder().
+            $task_cancel
+            Export("[task-cancel]").
+        }
+    }
+
+    /// Generate the intrinsics of the futures and streams in a function's
+    /// signature, which the guest refers to by their index in it.
+    ///
+    /// Futures and streams only ever flow from the guest to the host, so the
+    /// host is always the reader: writes complete once the host has taken the
+    /// values, and there's never a pending read or write to cancel.
+    fn future_and_stream_intrinsics(&self, func: &Function) -> Vec<Tokens<Go>> {
+        let resolve = self.config.resolve;
+        func.find_futures_and_streams(resolve)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, ty)| match &resolve.types[ty].kind {
+                TypeDefKind::Future(payload) => {
+                    Some(self.future_intrinsic(func, index, payload.as_ref()))
+                }
+                TypeDefKind::Stream(payload) => {
+                    Some(self.stream_intrinsic(func, index, payload.as_ref()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Generate the intrinsics of the `index`th future of a function.
+    fn future_intrinsic(
+        &self,
+        func: &Function,
+        index: usize,
+        payload: Option<&Type>,
+    ) -> Tokens<Go> {
+        let name = |intrinsic: &str| quoted(format!("[{intrinsic}-{index}]{}", func.name));
+        let async_name =
+            |intrinsic: &str| quoted(format!("[async-lower][{intrinsic}-{index}]{}", func.name));
+        let elem = &match payload {
+            Some(typ) => quote!($(self.resolve_type(typ))),
+            None => quote!(struct{}),
+        };
+
+        // Lift the written value out of the guest's memory. That never calls
+        // into an interface, so the interface parameter goes unused.
+        let param_name = GoIdentifier::private("unused");
+        let mut f = crate::Func::import(&param_name, GoResult::Empty, self.config.sizes)
+            .with_generic_containers(self.config.generic_containers);
+        let value = match payload {
+            Some(typ) => wit_bindgen_core::abi::lift_from_memory(
+                self.config.resolve,
+                &mut f,
+                Operand::SingleValue("ptr".into()),
+                typ,
+            ),
+            None => Operand::Literal("struct{}{}".into()),
+        };
+        let lift = f.body();
+
+        let handle_and_ptr = &[("handle", GoType::Uint32), ("ptr", GoType::Uint32)];
+        let write = &go_module_func(
+            handle_and_ptr,
+            Some(GoType::Uint32),
+            quote! {
+                end, ok := futureEnds.of(mod).get(handle)
+                if !ok {
+                    panic($ERRORS_NEW("invalid future handle"))
+                }
+                $lift
+                end.(*Future[$elem]).resolve($value, nil)
+                $(comment(&["The value was written in full, so the write completed"]))
+                return 1 << 4
+            },
+        );
+        let read = &go_module_func(
+            handle_and_ptr,
+            Some(GoType::Uint32),
+            quote! {
+                panic($ERRORS_NEW("reading futures in the guest isn't supported"))
+            },
+        );
+        let cancel = &go_module_func(
+            &[("handle", GoType::Uint32)],
+            Some(GoType::Uint32),
+            quote! {
+                panic($ERRORS_NEW("no pending future operation to cancel"))
+            },
+        );
+        let new = go_module_func(
+            &[],
+            Some(GoType::Uint64),
+            quote! {
+                future := newFuture[$elem]()
+                readable := futureEnds.of(mod).insert(future)
+                writable := futureEnds.of(mod).insert(future)
+                return uint64(writable)<<32 | uint64(readable)
+            },
+        );
+        let drop_writable = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                end, ok := futureEnds.of(mod).remove(handle)
+                if !ok {
+                    panic($ERRORS_NEW("invalid future handle"))
+                }
+                $(comment(&["A future dropped before it was written never gets a value"]))
+                var value $elem
+                end.(*Future[$elem]).resolve(value, $ERRORS_NEW("future dropped without a value"))
+            },
+        );
+        let drop_readable = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                if _, ok := futureEnds.of(mod).remove(handle); !ok {
+                    panic($ERRORS_NEW("invalid future handle"))
+                }
+            },
+        );
+
+        quote! {
+            NewFunctionBuilder().
+            $new
+            Export($(name("future-new"))).
+            NewFunctionBuilder().
+            $write
+            Export($(name("future-write"))).
+            NewFunctionBuilder().
+            $write
+            Export($(async_name("future-write"))).
+            NewFunctionBuilder().
+            $read
+            Export($(name("future-read"))).
+            NewFunctionBuilder().
+            $read
+            Export($(async_name("future-read"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(name("future-cancel-write"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(async_name("future-cancel-write"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(name("future-cancel-read"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(async_name("future-cancel-read"))).
+            NewFunctionBuilder().
+            $drop_writable
+            Export($(name("future-drop-writable"))).
+            NewFunctionBuilder().
+            $drop_readable
+            Export($(name("future-drop-readable"))).
+        }
+    }
+
+    /// Generate the intrinsics of the `index`th stream of a function.
+    ///
+    /// The stream's channel is unbuffered, so a write blocks the guest until
+    /// the host has received every value: that's the backpressure.
+    fn stream_intrinsic(
+        &self,
+        func: &Function,
+        index: usize,
+        payload: Option<&Type>,
+    ) -> Tokens<Go> {
+        let name = |intrinsic: &str| quoted(format!("[{intrinsic}-{index}]{}", func.name));
+        let async_name =
+            |intrinsic: &str| quoted(format!("[async-lower][{intrinsic}-{index}]{}", func.name));
+        let elem = &match payload {
+            Some(typ) => quote!($(self.resolve_type(typ))),
+            None => quote!(struct{}),
+        };
+        let size = payload.map_or(0, |typ| self.config.sizes.size(typ).size_wasm32());
+
+        // Lift each written value out of the guest's memory. That never calls
+        // into an interface, so the interface parameter goes unused.
+        let param_name = GoIdentifier::private("unused");
+        let mut f = crate::Func::import(&param_name, GoResult::Empty, self.config.sizes)
+            .with_generic_containers(self.config.generic_containers);
+        let value = match payload {
+            Some(typ) => wit_bindgen_core::abi::lift_from_memory(
+                self.config.resolve,
+                &mut f,
+                Operand::SingleValue("elem".into()),
+                typ,
+            ),
+            None => Operand::Literal("struct{}{}".into()),
+        };
+        let lift = f.body();
+
+        let handle_ptr_and_count = &[
+            ("handle", GoType::Uint32),
+            ("ptr", GoType::Uint32),
+            ("count", GoType::Uint32),
+        ];
+        let write = &go_module_func(
+            handle_ptr_and_count,
+            Some(GoType::Uint32),
+            quote! {
+                end, ok := streamEnds.of(mod).get(handle)
+                if !ok {
+                    panic($ERRORS_NEW("invalid stream handle"))
+                }
+                stream := end.(*Stream[$elem])
+                for n := uint32(0); n < count; n++ {
+                    $(if payload.is_some() => elem := ptr + n*$size)
+                    $lift
+                    if !stream.write($value) {
+                        $(comment(&["The host closed its end, so the rest of the values are dropped"]))
+                        return n<<4 | 1
+                    }
+                }
+                return count << 4
+            },
+        );
+        let read = &go_module_func(
+            handle_ptr_and_count,
+            Some(GoType::Uint32),
+            quote! {
+                panic($ERRORS_NEW("reading streams in the guest isn't supported"))
+            },
+        );
+        let cancel = &go_module_func(
+            &[("handle", GoType::Uint32)],
+            Some(GoType::Uint32),
+            quote! {
+                panic($ERRORS_NEW("no pending stream operation to cancel"))
+            },
+        );
+        let new = go_module_func(
+            &[],
+            Some(GoType::Uint64),
+            quote! {
+                stream := newStream[$elem]()
+                readable := streamEnds.of(mod).insert(stream)
+                writable := streamEnds.of(mod).insert(stream)
+                return uint64(writable)<<32 | uint64(readable)
+            },
+        );
+        let drop_writable = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                end, ok := streamEnds.of(mod).remove(handle)
+                if !ok {
+                    panic($ERRORS_NEW("invalid stream handle"))
+                }
+                end.(*Stream[$elem]).finish()
+            },
+        );
+        let drop_readable = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                if _, ok := streamEnds.of(mod).remove(handle); !ok {
+                    panic($ERRORS_NEW("invalid stream handle"))
+                }
+            },
+        );
+
+        quote! {
+            NewFunctionBuilder().
+            $new
+            Export($(name("stream-new"))).
+            NewFunctionBuilder().
+            $write
+            Export($(name("stream-write"))).
+            NewFunctionBuilder().
+            $write
+            Export($(async_name("stream-write"))).
+            NewFunctionBuilder().
+            $read
+            Export($(name("stream-read"))).
+            NewFunctionBuilder().
+            $read
+            Export($(async_name("stream-read"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(name("stream-cancel-write"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(async_name("stream-cancel-write"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(name("stream-cancel-read"))).
+            NewFunctionBuilder().
+            $cancel
+            Export($(async_name("stream-cancel-read"))).
+            NewFunctionBuilder().
+            $drop_writable
+            Export($(name("stream-drop-writable"))).
+            NewFunctionBuilder().
+            $drop_readable
+            Export($(name("stream-drop-readable"))).
+        }
+    }
+
+    /// Generate the resources and functions of an exported interface.
+    ///
+    /// Its freestanding functions, and the constructors and static functions
+    /// of its resources, are methods of a struct standing in for the
+    /// interface, which the instance hands out behind a Go interface.
+    fn generate_interface(&self, key: &WorldKey, interface: InterfaceId, tokens: &mut Tokens<Go>) {
+        let resolve = self.config.resolve;
+        let interface_name = &resolve.name_world_key(key);
+
+        for resource in self.resources(interface) {
+            self.generate_resource(interface_name, resource, tokens);
+        }
+
+        let short_name = self.short_name(key);
+        let typ = &GoIdentifier::public(format!("{}-{short_name}", self.config.world.name));
+        let mut functions = Tokens::new();
+        let mut methods = Vec::new();
+        for func in resolve.interfaces[interface].functions.values() {
+            match func.kind {
+                FunctionKind::Method(resource) | FunctionKind::AsyncMethod(resource) => {
+                    self.generate_resource_method(interface_name, resource, func, &mut functions)
+                }
+                // Constructors and static functions have no resource to be
+                // called on, so they're called on the interface.
+                FunctionKind::Freestanding
+                | FunctionKind::AsyncFreestanding
+                | FunctionKind::Constructor(_)
+                | FunctionKind::Static(_)
+                | FunctionKind::AsyncStatic(_) => methods.push(self.generate_interface_function(
+                    interface_name,
+                    typ,
+                    func,
+                    &mut functions,
+                )),
+            }
+        }
+        if !methods.is_empty() {
+            self.generate_interface_struct(short_name, typ, &methods, tokens);
+        }
+        tokens.append(functions);
+    }
+
+    /// The name an exported interface goes by in the world, which its Go
+    /// struct and the instance method returning it are named after.
+    fn short_name<'k>(&self, key: &'k WorldKey) -> &'k str
+    where
+        'a: 'k,
+    {
+        match key {
+            WorldKey::Name(name) => name.as_str(),
+            WorldKey::Interface(id) => self.config.resolve.interfaces[*id]
+                .name
+                .as_deref()
+                .expect("interface missing name"),
+        }
+    }
+
+    /// Generate the Go struct grouping the functions of an exported
+    /// interface, the Go interface listing their signatures, `methods`, and
+    /// the instance method returning it.
+    fn generate_interface_struct(
+        &self,
+        short_name: &str,
+        typ: &GoIdentifier,
+        methods: &[Tokens<Go>],
+        tokens: &mut Tokens<Go>,
+    ) {
+        let instance = self.config.instance;
+        let iface = &GoIdentifier::public(format!("i-{}", String::from(typ)));
+        let accessor = &GoIdentifier::public(short_name);
+        let doc = format!(
+            "{} holds the functions of the `{short_name}` interface exported by the guest.",
+            String::from(typ)
+        );
+        let iface_doc = format!(
+            "{} lists the functions of the exported `{short_name}` interface, as implemented by {}.",
+            String::from(iface),
+            String::from(typ)
+        );
+        let accessor_doc = format!(
+            "{} returns the functions of the exported `{short_name}` interface.",
+            String::from(accessor)
+        );
+        self.instance_methods
+            .borrow_mut()
+            .push(quote!($accessor() $iface));
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment([doc]))
+            type $typ struct {
+                instance *$instance
+            }
+            $['\n']
+            $(comment([iface_doc]))
+            type $iface interface {
+                $(for method in methods join ($['\r']) => $method)
+            }
+            $['\n']
+            var _ $iface = (*$typ)(nil)
+            $['\n']
+            $(comment([accessor_doc]))
+            func (i *$instance) $accessor() $iface {
+                return &$typ{instance: i}
+            }
+        }
+    }
+
+    /// Generate a function of an exported interface as a method of the
+    /// interface's Go struct, returning its signature.
+    fn generate_interface_function(
+        &self,
+        interface_name: &str,
+        typ: &GoIdentifier,
+        func: &Function,
+        tokens: &mut Tokens<Go>,
+    ) -> Tokens<Go> {
+        let params = self.interface_params(&func.params);
+        let f = self.call(func, format!("{interface_name}#{}", func.name));
+
         let arg_assignments = f
             .args()
             .iter()
             .zip(&params)
             .map(|(arg, (param, _))| (arg, param))
             .collect::<Vec<_>>();
-        let fn_name = &GoIdentifier::public(&func.name);
+        let fn_name = &crate::function_go_name(func);
         quote_in! { *tokens =>
             $['\n']
-            func (i *$(self.config.instance)) $fn_name(
+            func (e *$typ) $fn_name(
+                $['\r']
+                ctx $CONTEXT_CONTEXT,
+                $(for (name, typ) in &params join ($['\r']) => $name $typ,)
+            ) $(f.result()) {
+                i := e.instance
+                $(if self.config.scratch_buffer => i.scratch.reset())
+                $(for (arg, param) in arg_assignments join ($['\r']) => $arg := $param)
+                $(f.body())
+            }
+        }
+        quote! {
+            $fn_name(ctx $CONTEXT_CONTEXT$(for (name, typ) in &params => , $name $typ)) $(f.result())
+        }
+    }
+
+    /// Generate the Go struct owning a handle to a resource the guest
+    /// exports.
+    fn generate_resource(&self, interface_name: &str, resource: TypeId, tokens: &mut Tokens<Go>) {
+        let resolve = self.config.resolve;
+        let wit_name = resolve.types[resource]
+            .name
+            .as_deref()
+            .expect("resources are named");
+        let typ = &GoIdentifier::public(crate::qualified_type_name(resource, resolve));
+        let handles = &crate::resource_handles(resource, resolve);
+        let dtor = format!("{interface_name}#[dtor]{wit_name}");
+        let dtor_field = &crate::exported_function_field(&dtor);
+        self.exported_functions.borrow_mut().insert(dtor);
+        let doc = format!(
+            "{} is a handle to a `{wit_name}` resource exported by the guest.",
+            String::from(typ)
+        );
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment([doc]))
+            type $typ struct {
+                instance *$(self.config.instance)
+                handle   uint32
+            }
+            $['\n']
+            $(comment(&["Close drops the handle, letting the guest release the resource."]))
+            func (r *$typ) Close(ctx $CONTEXT_CONTEXT) error {
+                rep, ok := $handles.of(r.instance.module).remove(r.handle)
+                if !ok {
+                    return $ERRORS_NEW($(quoted(format!("{wit_name} already closed or moved"))))
+                }
+                r.handle = 0
+                if dtor := r.instance.exports.$dtor_field; dtor != nil {
+                    if _, err := dtor.Call(ctx, uint64(rep)); err != nil {
+                        return err
+                    }
+                }
+                return nil
+            }
+            $['\n']
+            var $handles = newInstanceHandles[uint32]()
+        }
+    }
+
+    /// Generate the `exportedFunctions` struct holding the functions the core
+    /// module exports which the generated code calls, so they're looked up
+    /// once per instance rather than on every call.
+    fn generate_exported_functions(&self, tokens: &mut Tokens<Go>) {
+        let exported_functions = self.exported_functions.borrow();
+        let fields = exported_functions
+            .iter()
+            .map(|name| (crate::exported_function_field(name), name))
+            .collect::<Vec<_>>();
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(&[
+                "exportedFunctions holds the functions the guest exports which the bindings call,",
+                "looked up once when the guest is instantiated.",
+            ]))
+            type exportedFunctions struct {
+                $(for (field, _) in &fields join ($['\r']) => $field $WAZERO_API_FUNCTION)
+            }
+            $['\n']
+            func newExportedFunctions(module $WAZERO_API_MODULE) exportedFunctions {
+                return exportedFunctions{
+                    $(for (field, name) in &fields join ($['\r']) => $field: module.ExportedFunction($(quoted(name.as_str()))),)
+                }
+            }
+        }
+    }
+
+    /// Generate the interface listing the instance's methods calling into the
+    /// guest, which code using the bindings can depend on to stand in for the
+    /// guest in its tests.
+    fn generate_instance_interface(&self, tokens: &mut Tokens<Go>) {
+        let instance = self.config.instance;
+        let name = &GoIdentifier::public(format!("i-{}", String::from(instance)));
+        let doc = format!(
+            "{} lists the functions the guest exports, as implemented by {}.",
+            String::from(name),
+            String::from(instance)
+        );
+        let methods = self.instance_methods.borrow();
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment([doc]))
+            type $name interface {
+                $(for method in methods.iter() join ($['\r']) => $method)
+            }
+            $['\n']
+            var _ $name = (*$instance)(nil)
+        }
+    }
+
+    /// Generate a resource method as a method of the resource's Go struct,
+    /// with the struct standing in for the `self` parameter.
+    fn generate_resource_method(
+        &self,
+        interface_name: &str,
+        resource: TypeId,
+        func: &Function,
+        tokens: &mut Tokens<Go>,
+    ) {
+        let resolve = self.config.resolve;
+        let typ = &GoIdentifier::public(crate::qualified_type_name(resource, resolve));
+        let params = self.interface_params(&func.params[1..]);
+        let f = self.call(func, format!("{interface_name}#{}", func.name));
+
+        let (receiver_arg, args) = f.args().split_first().expect("methods take `self`");
+        let arg_assignments = args
+            .iter()
+            .zip(&params)
+            .map(|(arg, (param, _))| (arg, param))
+            .collect::<Vec<_>>();
+        let fn_name = &crate::function_go_name(func);
+        quote_in! { *tokens =>
+            $['\n']
+            func (r *$typ) $fn_name(
                 $['\r']
                 ctx $CONTEXT_CONTEXT,
                 $(for (name, typ) in &params join ($['\r']) => $name $typ,)
             ) $(f.result()) {
+                i := r.instance
+                $(if self.config.scratch_buffer => i.scratch.reset())
+                $receiver_arg := r
                 $(for (arg, param) in arg_assignments join ($['\r']) => $arg := $param)
                 $(f.body())
             }
         }
     }
+
+    /// The functions the guest exports which can be called with made up
+    /// arguments, along with what they're called on from an `instance`
+    /// variable and a prefix naming them apart from those of other
+    /// interfaces.
+    ///
+    /// Functions passing handles are left out, as the zero value isn't a
+    /// valid handle, and so are the constructors and methods of resources.
+    fn callable_functions(&self) -> Vec<(String, Tokens<Go>, &'a Function)> {
+        let resolve = self.config.resolve;
+        let mut functions = Vec::new();
+        for (key, item) in self.config.world.exports.iter() {
+            match item {
+                WorldItem::Function(func) => {
+                    functions.push((String::new(), quote!(instance), func))
+                }
+                WorldItem::Interface { id, .. } => {
+                    let short_name = self.short_name(key);
+                    let accessor = &GoIdentifier::public(short_name);
+                    for func in resolve.interfaces[*id].functions.values() {
+                        match func.kind {
+                            // Static functions are called on the interface,
+                            // like its freestanding ones.
+                            FunctionKind::Freestanding
+                            | FunctionKind::AsyncFreestanding
+                            | FunctionKind::Static(_)
+                            | FunctionKind::AsyncStatic(_) => functions.push((
+                                format!("{short_name}-"),
+                                quote!(instance.$accessor()),
+                                func,
+                            )),
+                            FunctionKind::Method(_)
+                            | FunctionKind::AsyncMethod(_)
+                            | FunctionKind::Constructor(_) => {}
+                        }
+                    }
+                }
+                WorldItem::Type { .. } => {}
+            }
+        }
+        functions.retain(|(_, _, func)| {
+            !func
+                .params
+                .iter()
+                .map(|param| &param.ty)
+                .chain(&func.result)
+                .any(|typ| crate::has_handles(typ, resolve))
+        });
+        functions
+    }
+
+    /// Generate a benchmark calling each function the guest exports with the
+    /// zero values of its parameters, for a `_bench_test.go` file alongside
+    /// the bindings. The instance is created without any of the imports.
+    pub fn benchmarks(&self, imports: &AnalyzedImports) -> Tokens<Go> {
+        let instance = self.config.instance;
+        let constructor = &imports.constructor_name;
+        // Calls to the imports trap the guest rather than the benchmark
+        // failing to set up.
+        let constructor_args = std::iter::once(quote!(ctx))
+            .chain(imports.interfaces.iter().map(|_| quote!(nil)))
+            .chain(std::iter::once(quote!(WithOptionalImports())));
+        let mut tokens = quote! {
+            func newBenchmarkInstance(b *$TESTING_B) *$instance {
+                b.Helper()
+                ctx := $CONTEXT_BACKGROUND()
+                factory, err := $constructor($(for arg in constructor_args join (, ) => $arg))
+                if err != nil {
+                    b.Fatal(err)
+                }
+                b.Cleanup(func() { factory.Close(ctx) })
+                instance, err := factory.Instantiate(ctx)
+                if err != nil {
+                    b.Fatal(err)
+                }
+                b.Cleanup(func() { instance.Close(ctx) })
+                return instance
+            }
+        };
+        for (prefix, receiver, func) in self.callable_functions() {
+            let fn_name = crate::function_go_name(func);
+            let name =
+                &GoIdentifier::public(format!("benchmark-{prefix}{}", String::from(&fn_name)));
+            let args = self
+                .params(&func.params)
+                .into_iter()
+                .enumerate()
+                .map(|(index, (_, typ))| (GoIdentifier::local(format!("arg{index}")), typ))
+                .collect::<Vec<_>>();
+            quote_in! { tokens =>
+                $['\n']
+                func $name(b *$TESTING_B) {
+                    instance := newBenchmarkInstance(b)
+                    ctx := $CONTEXT_BACKGROUND()
+                    $(for (arg, typ) in &args join ($['\r']) => var $arg $typ)
+                    for b.Loop() {
+                        $receiver.$fn_name(ctx$(for (arg, _) in &args => , $arg))
+                    }
+                }
+            }
+        }
+        tokens
+    }
+
+    /// Generate a fuzz target for each function the guest exports taking a
+    /// string or a `list<u8>`, for a `_fuzz_test.go` file alongside the
+    /// bindings. Only functions whose parameters are all types Go can fuzz
+    /// get one. Every input is tried on a new instance, as a trap may leave
+    /// the previous one broken.
+    pub fn fuzz_targets(&self, imports: &AnalyzedImports) -> Tokens<Go> {
+        let factory = &imports.factory_name;
+        let constructor = &imports.constructor_name;
+        let constructor_args = std::iter::once(quote!(ctx))
+            .chain(imports.interfaces.iter().map(|_| quote!(nil)))
+            .chain(std::iter::once(quote!(WithOptionalImports())));
+        let mut tokens = quote! {
+            func newFuzzFactory(f *$TESTING_F) *$factory {
+                f.Helper()
+                ctx := $CONTEXT_BACKGROUND()
+                factory, err := $constructor($(for arg in constructor_args join (, ) => $arg))
+                if err != nil {
+                    f.Fatal(err)
+                }
+                f.Cleanup(func() { factory.Close(ctx) })
+                return factory
+            }
+        };
+        for (prefix, receiver, func) in self.callable_functions() {
+            let params = self.params(&func.params);
+            let takes_bytes = params.iter().any(|(_, typ)| match typ {
+                GoType::String => true,
+                GoType::Slice(typ) => matches!(typ.as_ref(), GoType::Uint8),
+                _ => false,
+            });
+            let seeds = params
+                .iter()
+                .map(|(_, typ)| fuzz_seed(typ))
+                .collect::<Option<Vec<_>>>();
+            let Some(seeds) = seeds.filter(|_| takes_bytes) else {
+                continue;
+            };
+            let fn_name = crate::function_go_name(func);
+            let name = &GoIdentifier::public(format!("fuzz-{prefix}{}", String::from(&fn_name)));
+            let args = params
+                .into_iter()
+                .enumerate()
+                .map(|(index, (_, typ))| (GoIdentifier::local(format!("arg{index}")), typ))
+                .collect::<Vec<_>>();
+            quote_in! { tokens =>
+                $['\n']
+                func $name(f *$TESTING_F) {
+                    factory := newFuzzFactory(f)
+                    f.Add($(for seed in seeds join (, ) => $seed))
+                    f.Fuzz(func(t *$TESTING_T$(for (arg, typ) in &args => , $arg $typ)) {
+                        ctx := t.Context()
+                        instance, err := factory.Instantiate(ctx)
+                        if err != nil {
+                            t.Fatal(err)
+                        }
+                        defer instance.Close(ctx)
+                        $receiver.$fn_name(ctx$(for (arg, _) in &args => , $arg))
+                    })
+                }
+            }
+        }
+        tokens
+    }
+
+    /// Generate a `main` package using the bindings imported from `package`:
+    /// it creates the factory with an implementation of each import, made of
+    /// its generated `Noop` one, and calls one of the functions the guest
+    /// exports, if one only takes basic types.
+    pub fn example(&self, imports: &AnalyzedImports, package: &str) -> Tokens<Go> {
+        let qualified = |name: &GoIdentifier| go::import(package.to_string(), String::from(name));
+        let implementations = imports
+            .interfaces
+            .iter()
+            .map(|interface| {
+                let interface_name = String::from(&interface.go_interface_name);
+                let noop = &GoIdentifier::public(format!("noop-{}", &interface_name[1..]));
+                let noop = qualified(noop);
+                let doc = [
+                    format!(
+                        "{} implements {interface_name}, doing nothing but what's defined",
+                        String::from(&interface.constructor_param_name),
+                    ),
+                    "on it when the guest calls into it.".into(),
+                ];
+                (&interface.constructor_param_name, noop, doc)
+            })
+            .collect::<Vec<_>>();
+        let constructor_args = std::iter::once(quote!(ctx))
+            .chain(implementations.iter().map(|(typ, _, _)| quote!(&$(*typ){})))
+            .collect::<Vec<_>>();
+        let call = self
+            .callable_functions()
+            .into_iter()
+            .find_map(|(_, receiver, func)| {
+                let args = self
+                    .params(&func.params)
+                    .iter()
+                    .map(|(_, typ)| sample_value(typ))
+                    .collect::<Option<Vec<_>>>()?;
+                let fn_name = crate::function_go_name(func);
+                let call = quote!($receiver.$fn_name(ctx$(for arg in args => , $arg)));
+                Some(match func.result {
+                    Some(_) => quote!($FMT_PRINTLN($call)),
+                    None => call,
+                })
+            });
+
+        quote! {
+            $(for (typ, noop, doc) in implementations {
+                $(comment(doc))
+                type $typ struct {
+                    $noop
+                }
+                $['\n']
+            })
+            func main() {
+                ctx := $CONTEXT_BACKGROUND()
+                factory, err := $(qualified(&imports.constructor_name))($(for arg in constructor_args join (, ) => $arg))
+                if err != nil {
+                    $LOG_FATAL(err)
+                }
+                defer factory.Close(ctx)
+                $['\n']
+                instance, err := factory.Instantiate(ctx)
+                if err != nil {
+                    $LOG_FATAL(err)
+                }
+                defer instance.Close(ctx)
+                $(if let Some(call) = call {
+                    $['\n']
+                    $call
+                })
+            }
+        }
+    }
+}
+
+/// A value of `typ` to call a function with in an example, for the basic
+/// types which can be written without qualifying them with the package of
+/// the bindings.
+fn sample_value(typ: &GoType) -> Option<Tokens<Go>> {
+    match typ {
+        GoType::Bool => Some(quote!(true)),
+        GoType::Uint8
+        | GoType::Uint16
+        | GoType::Uint32
+        | GoType::Uint64
+        | GoType::Int8
+        | GoType::Int16
+        | GoType::Int32
+        | GoType::Int64 => Some(quote!(1)),
+        GoType::Float32 | GoType::Float64 => Some(quote!(1.5)),
+        GoType::Rune => Some(quote!('a')),
+        GoType::String => Some(quote!($(quoted("hello")))),
+        GoType::Slice(typ) if matches!(typ.as_ref(), GoType::Uint8) => {
+            Some(quote!([]byte("hello")))
+        }
+        _ => None,
+    }
+}
+
+/// The value of `typ` to seed a fuzz target's corpus with, typed as
+/// `testing.F.Add` requires, for the types Go can fuzz.
+fn fuzz_seed(typ: &GoType) -> Option<Tokens<Go>> {
+    match typ {
+        GoType::Bool => Some(quote!(false)),
+        GoType::Uint8
+        | GoType::Uint16
+        | GoType::Uint32
+        | GoType::Uint64
+        | GoType::Int8
+        | GoType::Int16
+        | GoType::Int32
+        | GoType::Int64
+        | GoType::Float32
+        | GoType::Float64
+        | GoType::Rune => Some(quote!($typ(0))),
+        GoType::String => Some(quote!($(quoted("hello")))),
+        GoType::Slice(elem) if matches!(elem.as_ref(), GoType::Uint8) => {
+            Some(quote!([]byte($(quoted("hello")))))
+        }
+        _ => None,
+    }
 }
 
 impl FormatInto<Go> for ExportGenerator<'_> {
     fn format_into(self, tokens: &mut Tokens<Go>) {
-        for item in self.config.world.exports.values() {
+        for (key, item) in self.config.world.exports.iter() {
             match item {
                 WorldItem::Function(func) => self.generate_function(func, tokens),
-                WorldItem::Interface { .. } => todo!("generate interface exports"),
+                WorldItem::Interface { id, .. } => self.generate_interface(key, *id, tokens),
                 WorldItem::Type { .. } => todo!("generate type exports"),
             }
         }
+        self.generate_instance_interface(tokens);
+        self.generate_exported_functions(tokens);
     }
 }
 
@@ -145,6 +1379,11 @@ mod tests {
             world: &world,
             resolve: &resolve,
             sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
         };
 
         let generator = ExportGenerator::new(config);
@@ -164,8 +1403,22 @@ mod tests {
 
         // Verify function body
         assert!(generated.contains("arg0 := value"));
-        assert!(generated
-            .contains("i.module.ExportedFunction(\"add_number\").Call(ctx, uint64(result0))"));
+        assert!(generated.contains("if i.callTimeout > 0 {"));
+        assert!(
+            generated.contains("timeoutCtx, cancel := context.WithTimeout(ctx, i.callTimeout)")
+        );
+        assert!(generated.contains("case i.calls <- struct{}{}:"));
+        assert!(generated.contains("panic(ctx.Err())"));
+        assert!(generated.contains("stack1 := callStacks.Get().(*callStack)"));
+        assert!(generated.contains("defer callStacks.Put(stack1)"));
+        assert!(generated.contains("raw1 := stack1[:1]"));
+        assert!(generated.contains("raw1[0] = uint64(result0)"));
+        assert!(generated.contains(
+            "err1 := callError(ctx, i.module, \"add_number\", interceptCall(ctx, i.callInterceptor, \"add_number\", func(ctx context.Context) error {"
+        ));
+        assert!(generated.contains("return i.exports.addNumber.CallWithStack(ctx, raw1)"));
+        assert!(generated.contains("started1 := callStarted(i.metrics, \"add_number\")"));
+        assert!(generated.contains("callReturned(i.metrics, \"add_number\", started1, err1)"));
         assert!(generated.contains("if err1 != nil {"));
         assert!(generated.contains("panic(err1)"));
         assert!(generated.contains("results1 := raw1[0]"));
@@ -190,26 +1443,83 @@ mod tests {
     /// uint32 value matching the VariantLower variable declaration.
     /// Previously I32FromU32 used api.EncodeU32() which returns uint64,
     /// causing a Go compile error: cannot use uint64 as uint32.
+    /// With tracing, a call into the guest records a span named after the
+    /// function, which the host functions it calls nest theirs under.
     #[test]
-    fn test_export_variant_u32_no_encode_u32() {
-        use wit_bindgen_core::wit_parser::{
-            Case, TypeDef, TypeDefKind, TypeOwner, Variant,
+    fn test_export_tracing() {
+        let func = Function {
+            name: "add-number".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
         };
-
-        let mut resolve = Resolve::new();
-
-        // variant u32-option { some-val(u32), none-val }
-        let variant_def = TypeDef {
-            name: Some("u32-option".to_string()),
-            kind: TypeDefKind::Variant(Variant {
-                cases: vec![
-                    Case {
-                        name: "some-val".to_string(),
-                        ty: Some(Type::U32),
-                        docs: Default::default(),
-                        span: Default::default(),
-                    },
-                    Case {
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("add-number".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+        let resolve = Resolve::new();
+        let sizes = SizeAlign::default();
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: true,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+        let mut tokens = Tokens::new();
+        ExportGenerator::new(config).generate_function(&func, &mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains(r#"ctx, span0 := i.tracer.Start(ctx, "add-number")"#)
+                && generated.contains("defer span0.End()"),
+            "Expected a span around the call, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("failSpan(span0, err0)"),
+            "Expected the span to be marked failed with the call's error, got:\n{generated}"
+        );
+    }
+
+    #[test]
+    fn test_export_variant_u32_no_encode_u32() {
+        use wit_bindgen_core::wit_parser::{
+            Case, TypeDef, TypeDefKind, TypeOwner, Variant,
+        };
+
+        let mut resolve = Resolve::new();
+
+        // variant u32-option { some-val(u32), none-val }
+        let variant_def = TypeDef {
+            name: Some("u32-option".to_string()),
+            kind: TypeDefKind::Variant(Variant {
+                cases: vec![
+                    Case {
+                        name: "some-val".to_string(),
+                        ty: Some(Type::U32),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                    Case {
                         name: "none-val".to_string(),
                         ty: None,
                         docs: Default::default(),
@@ -262,6 +1572,11 @@ mod tests {
             world: &world,
             resolve: &resolve,
             sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
         };
 
         let generator = ExportGenerator::new(config);
@@ -357,6 +1672,11 @@ mod tests {
             world: &world,
             resolve: &resolve,
             sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
         };
 
         let generator = ExportGenerator::new(config);
@@ -374,4 +1694,2380 @@ mod tests {
              but VariantLower variable is uint64), got:\n{generated}"
         );
     }
+
+    /// String lists are a list of pointer/length pairs, each pointing at the
+    /// string's bytes, so both directions nest the string handling inside the
+    /// element loop.
+    #[test]
+    fn test_export_list_of_strings() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let list_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::String),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "uppercase_headers".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "names".to_string(),
+                ty: Type::Id(list_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(list_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("uppercase-headers".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("names []string,") && generated.contains(") []string {"),
+            "Expected `[]string` on the Go side, got:\n{generated}"
+        );
+        // Each element is written as a string, then its pointer and length
+        // are stored in the element's 8-byte slot.
+        assert!(
+            generated.contains("writeString(ctx, e, "),
+            "Expected each element to be written as a string, got:\n{generated}"
+        );
+        // Writing the strings runs the guest's `cabi_realloc`, so the call
+        // timeout and slot have to be in place before then.
+        assert!(
+            generated.find("if i.callTimeout > 0 {").unwrap()
+                < generated.find("writeString(ctx, e, ").unwrap(),
+            "Expected the call timeout to be derived before lowering, got:\n{generated}"
+        );
+        assert!(
+            generated.find("case i.calls <- struct{}{}:").unwrap()
+                < generated.find("writeString(ctx, e, ").unwrap(),
+            "Expected a call slot to be taken before lowering, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.module.Memory().WriteUint32Le(base+4, uint32("),
+            "Expected the element's length to be stored after its pointer, got:\n{generated}"
+        );
+        // Lifting reads each element's pointer and length, then its bytes.
+        assert!(
+            generated.contains("make([]string, "),
+            "Expected the result to be lifted into a `[]string`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.module.Memory().ReadUint32Le(uint32(base + 4))"),
+            "Expected each element's length to be read, got:\n{generated}"
+        );
+        // Failing to access guest memory is an ABI error naming the function
+        // and the address.
+        assert!(
+            generated.contains(
+                r#"&ABIError{Message: fmt.Sprintf("uppercase_headers: failed to read length from memory at %d", uint32(base + 4))}"#
+            ),
+            "Expected failed reads to be reported, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("if !i.module.Memory().WriteUint32Le(base+4, uint32(")
+                && generated.contains(
+                    r#"&ABIError{Message: fmt.Sprintf("uppercase_headers: failed to write length to memory at %d", base+4)}"#
+                ),
+            "Expected failed writes to be reported, got:\n{generated}"
+        );
+    }
+
+    /// With a scratch buffer, a call's strings and lists are carved out of
+    /// the buffer, which is freed up again at the start of every call.
+    #[test]
+    fn test_export_scratch_buffer() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let list_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::String),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "join".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "names".to_string(),
+                ty: Type::Id(list_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("join".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: true,
+            zero_buffers: true,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("i.scratch.reset()"),
+            "Expected the buffer to be reset at the start of the call, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("defer i.scratch.wipe(i.module.Memory())"),
+            "Expected the buffer to be zeroed once the call returns, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.scratch.alloc(ctx, i.exports.cabiRealloc, 4, len"),
+            "Expected the list to be allocated from the buffer, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.scratch.writeString(ctx, e, "),
+            "Expected each element to be written to the buffer, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains(".Call(ctx, 0, 0, "),
+            "Expected nothing to be allocated with `cabi_realloc` directly, got:\n{generated}"
+        );
+    }
+
+    /// A returned list's pointer/length pair is read from the return area,
+    /// and the guest's post-return function is called once it's copied out.
+    #[test]
+    fn test_export_list_return() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let list_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "get-ids".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![],
+            result: Some(Type::Id(list_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("get-ids".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains(") []uint32 {"),
+            "Expected `[]uint32` on the Go side, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("ReadUint32Le(uint32(results") && generated.contains(" + 4))"),
+            "Expected the length to be read after the pointer, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("make([]uint32, "),
+            "Expected the elements to be lifted into a slice, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("(i.maxLength > 0 && ")
+                && generated.contains(r#""get-ids: length %d is out of bounds""#),
+            "Expected the length to be checked before allocating, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.exports.cabiPostGetIds; postFn != nil"),
+            "Expected the post-return function to be called, got:\n{generated}"
+        );
+    }
+
+    /// Nested lists lower and lift one loop per level, each reading the
+    /// inner list's pointer/length pair from its element slot.
+    #[test]
+    fn test_export_nested_list() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let inner_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let outer_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::Id(inner_id)),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "transpose".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "matrix".to_string(),
+                ty: Type::Id(outer_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(outer_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("transpose".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("matrix [][]uint32,") && generated.contains(") [][]uint32 {"),
+            "Expected `[][]uint32` on the Go side, got:\n{generated}"
+        );
+        // One allocation and one loop per level when lowering.
+        assert_eq!(
+            generated.matches("i.exports.cabiRealloc.Call(").count(),
+            2,
+            "Expected an allocation per list level, got:\n{generated}"
+        );
+        assert_eq!(
+            generated.matches("for idx := uint64(0);").count(),
+            2,
+            "Expected a lowering loop per list level, got:\n{generated}"
+        );
+        // And one slice and one loop per level when lifting.
+        assert!(
+            generated.contains("make([][]uint32, ") && generated.contains("make([]uint32, "),
+            "Expected a slice per list level, got:\n{generated}"
+        );
+    }
+
+    /// Tuple params are `TupleN` structs while a tuple result is returned as
+    /// multiple Go values.
+    #[test]
+    fn test_export_tuple_param_and_return() {
+        use wit_bindgen_core::wit_parser::{Tuple, TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let tuple_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Tuple(Tuple {
+                types: vec![Type::U32, Type::String],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "echo-pair".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "pair".to_string(),
+                ty: Type::Id(tuple_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(tuple_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("echo-pair".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("pair Tuple2[uint32, string],"),
+            "Expected the tuple param to be a `Tuple2`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(") (uint32, string) {"),
+            "Expected the tuple result to be multiple return values, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("tuple0_0 := arg0.F0") && generated.contains("tuple0_1 := arg0.F1"),
+            "Expected the tuple param to be split into its elements, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("Tuple2[uint32, string]{"),
+            "Expected the tuple result to be lifted into a `Tuple2`, got:\n{generated}"
+        );
+        let returned = generated
+            .lines()
+            .rfind(|line| line.trim_start().starts_with("return "))
+            .expect("expected a return statement");
+        assert!(
+            returned.contains(".F0, ") && returned.trim_end().ends_with(".F1"),
+            "Expected the tuple result to be returned element by element, got:\n{generated}"
+        );
+    }
+
+    #[test]
+    fn test_export_generic_containers() {
+        use wit_bindgen_core::wit_parser::{Result_, TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let option_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Option(Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let result_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Result(Result_ {
+                ok: Some(Type::U32),
+                err: Some(Type::String),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "lookup".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "key".to_string(),
+                ty: Type::Id(option_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(result_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("lookup".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: true,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("key Option[uint32],"),
+            "Expected the option param to be an `Option`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(") Result[uint32, string] {"),
+            "Expected the result to be a `Result`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("if variantPayload, isSome")
+                && generated.contains(" := arg0.Get(); isSome"),
+            "Expected the option to be lowered through `Get`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("= ResultOk[uint32, string]("),
+            "Expected the ok case to be lifted into a `Result`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("= ResultErr[uint32, string]("),
+            "Expected the err case to be lifted into a `Result`, got:\n{generated}"
+        );
+    }
+
+    /// A result nested in another type is carried in a `Result[T, E]`,
+    /// while the function's own result is still returned as a Go `error`.
+    #[test]
+    fn test_export_nested_option_and_result() {
+        use wit_bindgen_core::wit_parser::{Result_, TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let mut alloc = |kind| {
+            resolve.types.alloc(TypeDef {
+                name: None,
+                kind,
+                owner: TypeOwner::None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            })
+        };
+        let inner_result_id = alloc(TypeDefKind::Result(Result_ {
+            ok: Some(Type::String),
+            err: Some(Type::String),
+        }));
+        let param_id = alloc(TypeDefKind::Option(Type::Id(inner_result_id)));
+        let inner_option_id = alloc(TypeDefKind::Option(Type::U32));
+        let result_id = alloc(TypeDefKind::Result(Result_ {
+            ok: Some(Type::Id(inner_option_id)),
+            err: Some(Type::String),
+        }));
+
+        let func = Function {
+            name: "lookup".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "key".to_string(),
+                ty: Type::Id(param_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(result_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("lookup".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("key *Result[string, string],"),
+            "Expected the nested result to be a `Result`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(") (*uint32, error) {"),
+            "Expected the function's result to return an `error`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("if variantPayload.IsErr() {"),
+            "Expected the nested result to be lowered from the option's payload, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("variantPayload, _ := variantPayload.Ok()"),
+            "Expected the ok payload to be lowered, got:\n{generated}"
+        );
+    }
+
+    /// Cases with differing payloads share joined core slots, so lowering
+    /// has to bitcast each payload into the slot's type.
+    #[test]
+    fn test_export_variant_mixed_payloads() {
+        use wit_bindgen_core::wit_parser::{Case, TypeDef, TypeDefKind, TypeOwner, Variant};
+
+        let mut resolve = Resolve::new();
+
+        // variant number { small(u32), big(u64), real(f32) }
+        let variant_id = resolve.types.alloc(TypeDef {
+            name: Some("number".to_string()),
+            kind: TypeDefKind::Variant(Variant {
+                cases: [
+                    ("small", Type::U32),
+                    ("big", Type::U64),
+                    ("real", Type::F32),
+                ]
+                .into_iter()
+                .map(|(name, ty)| Case {
+                    name: name.to_string(),
+                    ty: Some(ty),
+                    docs: Default::default(),
+                    span: Default::default(),
+                })
+                .collect(),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "describe".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "num".to_string(),
+                ty: Type::Id(variant_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("describe".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        // The payload slot joins i32, i64 and f32 into an i64.
+        assert!(
+            generated.contains("var variant5_1 uint64"),
+            "Expected a joined 64-bit payload slot, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("value1 := uint64(result0)"),
+            "Expected the u32 payload to be widened into the slot, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("value4 := uint64(result3)"),
+            "Expected the f32 bits to be widened into the slot, got:\n{generated}"
+        );
+    }
+
+    /// Maps are lowered in key order, so the guest sees the same entries for
+    /// equal maps, and lifted back into a Go map.
+    #[test]
+    fn test_export_map_param_and_return() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let map_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Map(Type::String, Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "count-words".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "counts".to_string(),
+                ty: Type::Id(map_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(map_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("count-words".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("counts map[string]uint32,")
+                && generated.contains(") map[string]uint32 {"),
+            "Expected `map[string]uint32` on the Go side, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(" := slices.Sorted(maps.Keys(map"),
+            "Expected the entries to be lowered in key order, got:\n{generated}"
+        );
+        // Each entry is a `string` (pointer and length) followed by a `u32`.
+        assert!(
+            generated.contains(" + uint64(idx) * uint64(12))"),
+            "Expected 12-byte entries, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("make(map[string]uint32, "),
+            "Expected the result to be lifted into a Go map, got:\n{generated}"
+        );
+    }
+
+    /// Go doesn't order `bool`s, so maps keyed by them are sorted with false
+    /// first.
+    #[test]
+    fn test_export_map_bool_keys() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let map_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Map(Type::Bool, Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "count-flags".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "counts".to_string(),
+                ty: Type::Id(map_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(map_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("count-flags".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("counts map[bool]uint32,")
+                && generated.contains(") map[bool]uint32 {"),
+            "Expected `map[bool]uint32` on the Go side, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(" := slices.SortedFunc(maps.Keys(map")
+                && generated.contains("func(a, b bool) int {"),
+            "Expected the entries to be lowered with false first, got:\n{generated}"
+        );
+        // Each entry is a `bool` padded to the `u32` that follows it.
+        assert!(
+            generated.contains(" + uint64(idx) * uint64(8))"),
+            "Expected 8-byte entries, got:\n{generated}"
+        );
+    }
+
+    /// Fixed-length lists are Go arrays, lowered element by element and read
+    /// back in place from the return area.
+    #[test]
+    fn test_export_fixed_length_list() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let digest_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::FixedLengthList(Type::U8, 4),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "reverse".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "digest".to_string(),
+                ty: Type::Id(digest_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(digest_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("reverse".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("digest [4]byte,") && generated.contains(") [4]byte {"),
+            "Expected `[4]byte` on the Go side, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("array0 := arg0") && generated.contains("array0[3]"),
+            "Expected the array to be passed element by element, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(" [4]byte\n") && generated.contains(" < 4; idx"),
+            "Expected the result to be read into an array, got:\n{generated}"
+        );
+    }
+
+    /// `char` is a Go `rune`, checked to be a Unicode scalar value on the way
+    /// in and out of the guest.
+    #[test]
+    fn test_export_char_param_and_return() {
+        let func = Function {
+            name: "next-char".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "c".to_string(),
+                ty: Type::Char,
+                span: Default::default(),
+            }],
+            result: Some(Type::Char),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("next-char".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let resolve = Resolve::new();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("c rune,") && generated.contains(") rune {"),
+            "Expected `rune` on the Go side, got:\n{generated}"
+        );
+        assert_eq!(
+            generated.matches("utf8.ValidRune(").count(),
+            2,
+            "Expected the param and the result to be validated, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("raw1[0] = uint64(uint32(result0))"),
+            "Expected the rune to be passed as an `i32`, got:\n{generated}"
+        );
+    }
+
+    /// Nested records are split field by field on the way in and rebuilt
+    /// from their offsets in the return area on the way out.
+    #[test]
+    fn test_export_nested_record() {
+        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
+
+        let field = |name: &str, ty: Type| Field {
+            name: name.to_string(),
+            ty,
+            docs: Default::default(),
+            span: Default::default(),
+        };
+
+        let mut resolve = Resolve::new();
+        let point_id = resolve.types.alloc(TypeDef {
+            name: Some("point".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![field("x", Type::U32), field("y", Type::U32)],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let line_id = resolve.types.alloc(TypeDef {
+            name: Some("line".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![
+                    field("start", Type::Id(point_id)),
+                    field("end", Type::Id(point_id)),
+                ],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "reverse".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "line".to_string(),
+                ty: Type::Id(line_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(line_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("reverse".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("line Line,"),
+            "Expected the record param to be a `Line`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(":= arg0.Start") && generated.contains(":= start0.X"),
+            "Expected the nested record to be split into its fields, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("ReadUint32Le(uint32(results") && generated.contains(" + 12))"),
+            "Expected the last field to be read at its offset, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(":= Point{") && generated.contains(":= Line{"),
+            "Expected both records to be rebuilt, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains("cabi_post_reverse"),
+            "Expected no post-return for records of integers, got:\n{generated}"
+        );
+    }
+
+    /// Exports taking more than `MAX_FLAT_PARAMS` flat values get their
+    /// params written to memory the guest allocates, and a pointer to it.
+    #[test]
+    fn test_export_indirect_params() {
+        let resolve = Resolve::new();
+
+        let func = Function {
+            name: "wide".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: (0..17)
+                .map(|i| Param {
+                    name: format!("p{i}"),
+                    ty: Type::U32,
+                    span: Default::default(),
+                })
+                .collect(),
+            result: None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("wide".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let sizes = SizeAlign::default();
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("i.exports.cabiRealloc.Call(ctx, 0, 0, 4, 68)"),
+            "Expected the params to be spilled to guest memory, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("+64, uint32("),
+            "Expected the last param to be written at its offset, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("[0] = uint64(ptr")
+                && generated.contains("i.exports.wide.CallWithStack(ctx, raw"),
+            "Expected the function to be called with a pointer to its params, got:\n{generated}"
+        );
+    }
+
+    /// A returned `option` is a pointer, set only when the discriminant read
+    /// from the return area marks it present.
+    #[test]
+    fn test_export_option_return() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let option_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Option(Type::String),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "lookup".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "key".to_string(),
+                ty: Type::String,
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(option_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("lookup".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("key string,") && generated.contains(") *string {"),
+            "Expected the option to be returned as a `*string`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("ReadByte(uint32(results"),
+            "Expected the discriminant to be read from the return area, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(" != 0 {"),
+            "Expected the string to be read only when present, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.exports.cabiPostLookup; postFn != nil"),
+            "Expected the post-return function to be called, got:\n{generated}"
+        );
+    }
+
+    /// Typed `result` payloads lift into the ok value and a `ResultError[E]`,
+    /// and a `result` without an error payload still fails with an `error`.
+    #[test]
+    fn test_export_typed_result() {
+        use wit_bindgen_core::wit_parser::{
+            Enum, EnumCase, Field, Record, Result_, TypeDef, TypeDefKind, TypeId, TypeOwner,
+        };
+
+        let mut resolve = Resolve::new();
+        let point_id = resolve.types.alloc(TypeDef {
+            name: Some("point".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: ["x", "y"]
+                    .into_iter()
+                    .map(|name| Field {
+                        name: name.to_string(),
+                        ty: Type::U32,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    })
+                    .collect(),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let code_id = resolve.types.alloc(TypeDef {
+            name: Some("error-code".to_string()),
+            kind: TypeDefKind::Enum(Enum {
+                cases: ["invalid", "overflow"]
+                    .into_iter()
+                    .map(|name| EnumCase {
+                        name: name.to_string(),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    })
+                    .collect(),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let typed_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Result(Result_ {
+                ok: Some(Type::Id(point_id)),
+                err: Some(Type::Id(code_id)),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let untyped_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Result(Result_ {
+                ok: Some(Type::U32),
+                err: None,
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let function = |name: &str, result: TypeId| Function {
+            name: name.to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![],
+            result: Some(Type::Id(result)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let parse = function("parse", typed_id);
+        let count = function("count", untyped_id);
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [
+                (
+                    WorldKey::Name("parse".to_string()),
+                    WorldItem::Function(parse.clone()),
+                ),
+                (
+                    WorldKey::Name("count".to_string()),
+                    WorldItem::Function(count.clone()),
+                ),
+            ]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&parse, &mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains(") (Point, error) {") && generated.contains(":= Point{"),
+            "Expected the ok value to be lifted into a `Point`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("&ResultError[ErrorCode]{Value: "),
+            "Expected the error code to be carried in a `ResultError`, got:\n{generated}"
+        );
+
+        let mut tokens = Tokens::new();
+        generator.generate_function(&count, &mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains(") (uint32, error) {")
+                && generated.contains("errors.New(\"the result is an error\")"),
+            "Expected a result without an error payload to fail with an `error`, got:\n{generated}"
+        );
+    }
+
+    /// Heap-allocated fields of a record param are written into memory the
+    /// guest allocates through `cabi_realloc`, and are owned by it from then
+    /// on.
+    #[test]
+    fn test_export_record_param_with_string() {
+        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
+
+        let field = |name: &str, ty: Type| Field {
+            name: name.to_string(),
+            ty,
+            docs: Default::default(),
+            span: Default::default(),
+        };
+
+        let mut resolve = Resolve::new();
+        let request_id = resolve.types.alloc(TypeDef {
+            name: Some("request".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![field("path", Type::String), field("size", Type::U32)],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "handle".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "req".to_string(),
+                ty: Type::Id(request_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("handle".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.generate_function(&func, &mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("req Request,"),
+            "Expected the record param to be a `Request`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(":= arg0.Path") && generated.contains(":= arg0.Size"),
+            "Expected the record to be split into its fields, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("i.exports.cabiRealloc")
+                && generated.contains(":= writeString(ctx, path0,"),
+            "Expected the path to be written into guest memory, got:\n{generated}"
+        );
+    }
+
+    /// A resource the guest exports is wrapped in a Go struct holding its
+    /// handle, whose methods call the guest and whose `Close` drops it.
+    #[test]
+    fn test_export_resource_method() {
+        use wit_bindgen_core::wit_parser::{
+            Handle, Interface, Package, PackageName, TypeDef, TypeDefKind, TypeOwner,
+        };
+
+        let mut resolve = Resolve::new();
+        let package_id = resolve.packages.alloc(Package {
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "pkg".to_string(),
+                version: None,
+            },
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
+        let interface_id = resolve.interfaces.alloc(Interface {
+            name: Some("counters".to_string()),
+            package: Some(package_id),
+            functions: Default::default(),
+            types: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+            clone_of: None,
+        });
+        let counter_id = resolve.types.alloc(TypeDef {
+            name: Some("counter".to_string()),
+            kind: TypeDefKind::Resource,
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let borrow_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Handle(Handle::Borrow(counter_id)),
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+
+        let func = Function {
+            name: "[method]counter.increment".to_string(),
+            kind: FunctionKind::Method(counter_id),
+            params: vec![
+                Param {
+                    name: "self".to_string(),
+                    ty: Type::Id(borrow_id),
+                    span: Default::default(),
+                },
+                Param {
+                    name: "by".to_string(),
+                    ty: Type::U32,
+                    span: Default::default(),
+                },
+            ],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let own_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Handle(Handle::Own(counter_id)),
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let constructor = Function {
+            name: "[constructor]counter".to_string(),
+            kind: FunctionKind::Constructor(counter_id),
+            params: vec![Param {
+                name: "start".to_string(),
+                ty: Type::U32,
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(own_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let consume = Function {
+            name: "[static]counter.consume".to_string(),
+            kind: FunctionKind::Static(counter_id),
+            params: vec![Param {
+                name: "c".to_string(),
+                ty: Type::Id(own_id),
+                span: Default::default(),
+            }],
+            result: None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let interface = &mut resolve.interfaces[interface_id];
+        interface.types.insert("counter".to_string(), counter_id);
+        for func in [func, constructor, consume] {
+            interface.functions.insert(func.name.clone(), func);
+        }
+
+        let world_id = resolve.worlds.alloc(World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Interface(interface_id),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: Some(package_id),
+        });
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &resolve.worlds[world_id],
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let chains = generator.export_chains();
+        let handles = generator
+            .resource_handles()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let mut tokens = Tokens::new();
+        generator.format_into(&mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("type Counter struct {")
+                && generated.contains("var counterHandles = newInstanceHandles[uint32]()"),
+            "Expected a struct and handle table for the resource, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func (r *Counter) Increment(")
+                && generated.contains("by uint32,")
+                && generated.contains(":= counterHandles.of(i.module).get(arg0.handle)"),
+            "Expected the method to pass its receiver as `self`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("\"test:pkg/counters#[method]counter.increment\""),
+            "Expected the method to call the guest export, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func (e *TestWorldCounters) NewCounter(")
+                && generated.contains("\"test:pkg/counters#[constructor]counter\"")
+                && generated.contains(":= &Counter{instance: i, handle: uint32("),
+            "Expected the constructor to wrap the new handle, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("type ITestWorldCounters interface {")
+                && generated.contains("    NewCounter(ctx context.Context"),
+            "Expected the constructor to be listed by the interface's Go interface, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func (e *TestWorldCounters) CounterConsume(")
+                && generated.contains(":= arg0.handle")
+                && generated.contains("arg0.handle = 0"),
+            "Expected passing an owned resource to move it to the guest, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func (r *Counter) Close(ctx context.Context) error {")
+                && generated.contains("dtor := r.instance.exports.testPkgCountersDtorCounter;"),
+            "Expected `Close` to call the destructor, got:\n{generated}"
+        );
+        // Every function the guest exports which the bindings call is looked
+        // up once, when the guest is instantiated.
+        assert!(
+            generated.contains("type exportedFunctions struct {")
+                && generated.contains(
+                    r#"testPkgCountersDtorCounter: module.ExportedFunction("test:pkg/counters#[dtor]counter"),"#
+                )
+                && generated.contains(
+                    r#"testPkgCountersMethodCounterIncrement: module.ExportedFunction("test:pkg/counters#[method]counter.increment"),"#
+                ),
+            "Expected the guest's exports to be looked up at instantiation, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains("i.module.ExportedFunction("),
+            "Expected no exports to be looked up per call, got:\n{generated}"
+        );
+
+        let chain = chains["[export]test:pkg/counters"].to_string().unwrap();
+        for intrinsic in ["resource-new", "resource-rep", "resource-drop"] {
+            assert!(
+                chain.contains(&format!("Export(\"[{intrinsic}]counter\")")),
+                "Expected the guest to import `[{intrinsic}]counter`, got:\n{chain}"
+            );
+        }
+        // Each instance has handles of its own, which go with it.
+        assert!(
+            chain.contains("return counterHandles.of(mod).insert(rep)"),
+            "Expected the handle to go in the instance's table, got:\n{chain}"
+        );
+        assert_eq!(handles, ["counterHandles"]);
+    }
+
+    /// The functions of an exported interface are methods of a struct the
+    /// instance hands out, calling the guest by their mangled export names.
+    #[test]
+    fn test_export_interface_functions() {
+        use wit_bindgen_core::wit_parser::{Interface, Package, PackageName};
+
+        let mut resolve = Resolve::new();
+        let package_id = resolve.packages.alloc(Package {
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "pkg".to_string(),
+                version: None,
+            },
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
+        let interface_id = resolve.interfaces.alloc(Interface {
+            name: Some("processor".to_string()),
+            package: Some(package_id),
+            functions: Default::default(),
+            types: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+            clone_of: None,
+        });
+        let func = Function {
+            name: "process".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "input".to_string(),
+                ty: Type::U32,
+                span: Default::default(),
+            }],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        resolve.interfaces[interface_id]
+            .functions
+            .insert(func.name.clone(), func);
+
+        let world_id = resolve.worlds.alloc(World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Interface(interface_id),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: Some(package_id),
+        });
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &resolve.worlds[world_id],
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let mut tokens = Tokens::new();
+        generator.format_into(&mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("type TestWorldProcessor struct {")
+                && generated.contains("func (i *TestInstance) Processor() ITestWorldProcessor {"),
+            "Expected a struct for the interface, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("type ITestWorldProcessor interface {\n    Process(ctx context.Context, input uint32) uint32\n}")
+                && generated.contains("var _ ITestWorldProcessor = (*TestWorldProcessor)(nil)"),
+            "Expected an interface listing the interface's functions, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func (e *TestWorldProcessor) Process(")
+                && generated.contains("input uint32,")
+                && generated.contains("i := e.instance"),
+            "Expected the function to be a method of the struct, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("\"test:pkg/processor#process\""),
+            "Expected the function to call the mangled guest export, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("type ITestInstance interface {")
+                && generated.contains("Processor() ITestWorldProcessor\n")
+                && generated.contains("var _ ITestInstance = (*TestInstance)(nil)"),
+            "Expected an interface listing the instance's methods, got:\n{generated}"
+        );
+    }
+
+    #[test]
+    fn test_export_harnesses() {
+        use wit_bindgen_core::wit_parser::{Interface, Package, PackageName};
+
+        use crate::codegen::ir::AnalyzedImports;
+
+        let mut resolve = Resolve::new();
+        let package_id = resolve.packages.alloc(Package {
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "pkg".to_string(),
+                version: None,
+            },
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
+        let interface_id = resolve.interfaces.alloc(Interface {
+            name: Some("processor".to_string()),
+            package: Some(package_id),
+            functions: Default::default(),
+            types: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+            clone_of: None,
+        });
+        let process = Function {
+            name: "process".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "input".to_string(),
+                ty: Type::String,
+                span: Default::default(),
+            }],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        resolve.interfaces[interface_id]
+            .functions
+            .insert(process.name.clone(), process);
+        let add = Function {
+            name: "add".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![
+                Param {
+                    name: "a".to_string(),
+                    ty: Type::U32,
+                    span: Default::default(),
+                },
+                Param {
+                    name: "b".to_string(),
+                    ty: Type::U32,
+                    span: Default::default(),
+                },
+            ],
+            result: Some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world_id = resolve.worlds.alloc(World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [
+                (WorldKey::Name("add".to_string()), WorldItem::Function(add)),
+                (
+                    WorldKey::Interface(interface_id),
+                    WorldItem::Interface {
+                        id: interface_id,
+                        stability: Default::default(),
+                        span: Default::default(),
+                    },
+                ),
+            ]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: Some(package_id),
+        });
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+        let imports = AnalyzedImports {
+            instance_name: instance.clone(),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &resolve.worlds[world_id],
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let generated = generator.benchmarks(&imports).to_string().unwrap();
+
+        assert!(
+            generated.contains("func newBenchmarkInstance(b *testing.B) *TestInstance {")
+                && generated.contains("factory, err := NewTestFactory(ctx, WithOptionalImports())"),
+            "Expected a helper instantiating the guest, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func BenchmarkAdd(b *testing.B) {")
+                && generated.contains("var arg0 uint32")
+                && generated.contains("instance.Add(ctx, arg0, arg1)"),
+            "Expected a benchmark of the world's function, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("func BenchmarkProcessorProcess(b *testing.B) {")
+                && generated.contains("var arg0 string")
+                && generated.contains("instance.Processor().Process(ctx, arg0)"),
+            "Expected a benchmark of the interface's function, got:\n{generated}"
+        );
+
+        let generated = generator
+            .example(&imports, "example.com/bindings")
+            .to_string()
+            .unwrap();
+        assert!(
+            generated.contains("func main() {")
+                && generated.contains("factory, err := bindings.NewTestFactory(ctx)")
+                && generated.contains("instance, err := factory.Instantiate(ctx)"),
+            "Expected the example to instantiate the guest, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("fmt.Println(instance.Add(ctx, 1, 1))"),
+            "Expected the example to call the first function, got:\n{generated}"
+        );
+
+        let generated = generator.fuzz_targets(&imports).to_string().unwrap();
+        assert!(
+            generated.contains("func FuzzProcessorProcess(f *testing.F) {")
+                && generated.contains("f.Add(\"hello\")")
+                && generated.contains("f.Fuzz(func(t *testing.T, arg0 string) {")
+                && generated.contains("instance.Processor().Process(ctx, arg0)"),
+            "Expected a fuzz target for the function taking a string, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains("FuzzAdd"),
+            "Expected no fuzz target for the function taking integers, got:\n{generated}"
+        );
+    }
+
+    #[test]
+    fn test_export_future() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let future_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Future(Some(Type::U32)),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let func = Function {
+            name: "get-value".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![],
+            result: Some(Type::Id(future_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("get-value".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let chains = generator.export_chains();
+        let mut tokens = Tokens::new();
+        generator.format_into(&mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("func (i *TestInstance) GetValue(")
+                && generated.contains(") *Future[uint32] {"),
+            "Expected the function to return a `*Future[uint32]`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(":= futureEnds.of(i.module).remove(uint32(")
+                && generated.contains(".(*Future[uint32])"),
+            "Expected the readable end to be taken from the table, got:\n{generated}"
+        );
+
+        let chain = chains["[export]$root"].to_string().unwrap();
+        for intrinsic in [
+            "future-new",
+            "future-write",
+            "future-read",
+            "future-cancel-write",
+            "future-cancel-read",
+            "future-drop-writable",
+            "future-drop-readable",
+        ] {
+            assert!(
+                chain.contains(&format!("Export(\"[{intrinsic}-0]get-value\")")),
+                "Expected the guest to import `[{intrinsic}-0]get-value`, got:\n{chain}"
+            );
+        }
+        assert!(chain.contains("Export(\"[async-lower][future-write-0]get-value\")"));
+        assert!(chain.contains("end.(*Future[uint32]).resolve("));
+    }
+
+    #[test]
+    fn test_export_stream() {
+        use wit_bindgen_core::wit_parser::{TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::new();
+        let stream_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Stream(Some(Type::U32)),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let func = Function {
+            name: "count-up".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![],
+            result: Some(Type::Id(stream_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("count-up".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let chains = generator.export_chains();
+        let mut tokens = Tokens::new();
+        generator.format_into(&mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("func (i *TestInstance) CountUp(")
+                && generated.contains(") *Stream[uint32] {"),
+            "Expected the function to return a `*Stream[uint32]`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(":= streamEnds.of(i.module).remove(uint32(")
+                && generated.contains(".(*Stream[uint32])"),
+            "Expected the readable end to be taken from the table, got:\n{generated}"
+        );
+
+        let chain = chains["[export]$root"].to_string().unwrap();
+        for intrinsic in [
+            "stream-new",
+            "stream-write",
+            "stream-read",
+            "stream-cancel-write",
+            "stream-cancel-read",
+            "stream-drop-writable",
+            "stream-drop-readable",
+        ] {
+            assert!(
+                chain.contains(&format!("Export(\"[{intrinsic}-0]count-up\")")),
+                "Expected the guest to import `[{intrinsic}-0]count-up`, got:\n{chain}"
+            );
+        }
+        assert!(
+            chain.contains("elem := ptr + n*4") && chain.contains("if !stream.write("),
+            "Expected each value to be buffered in the stream, got:\n{chain}"
+        );
+    }
+
+    #[test]
+    fn test_export_async_lifted() {
+        let func = Function {
+            name: "fetch".to_string(),
+            kind: FunctionKind::AsyncFreestanding,
+            params: vec![Param {
+                name: "id".to_string(),
+                ty: Type::U32,
+                span: Default::default(),
+            }],
+            result: Some(Type::String),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [].into(),
+            exports: [(
+                WorldKey::Name("fetch".to_string()),
+                WorldItem::Function(func.clone()),
+            )]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        let resolve = Resolve::new();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let instance = GoIdentifier::public("TestInstance");
+
+        let config = ExportConfig {
+            instance: &instance,
+            world: &world,
+            resolve: &resolve,
+            sizes: &sizes,
+            asynchronous: true,
+            generic_containers: false,
+            tracing: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+        };
+
+        let generator = ExportGenerator::new(config);
+        let chains = generator.export_chains();
+        let mut tokens = Tokens::new();
+        generator.format_into(&mut tokens);
+
+        let generated = tokens.to_string().unwrap();
+
+        assert!(
+            generated.contains("func (i *TestInstance) Fetch(")
+                && generated.contains(
+                    "callAsync(ctx, i.exports.asyncLiftFetch, i.exports.callbackAsyncLiftFetch, \"fetch\", uint64("
+                )
+                && generated.contains(":= taskValue[string](task, \"fetch\", 0)"),
+            "Expected the function to be driven by `callAsync`, got:\n{generated}"
+        );
+        assert!(
+            generated.contains(r#"asyncLiftFetch: module.ExportedFunction("[async-lift]fetch"),"#),
+            "Expected the async-lifted function to be looked up once, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains("cabi_post_fetch"),
+            "Expected no post-return for an async-lifted function, got:\n{generated}"
+        );
+
+        let chain = chains["[export]$root"].to_string().unwrap();
+        assert!(
+            chain.contains("Export(\"[task-return]fetch\")")
+                && chain.contains("Export(\"[task-cancel]\")"),
+            "Expected the guest to import the task intrinsics, got:\n{chain}"
+        );
+        assert!(
+            chain.contains("task := asyncTaskOf(ctx)") && chain.contains("task.Return(ctx, "),
+            "Expected `[task-return]` to hand the lifted result to the task, got:\n{chain}"
+        );
+        assert!(
+            chain.contains("WithGoModuleFunction(") && !chain.contains("WithFunc("),
+            "Expected the intrinsics to be registered without reflection, got:\n{chain}"
+        );
+    }
 }
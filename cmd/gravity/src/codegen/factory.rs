@@ -1,14 +1,31 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use genco::prelude::*;
+use genco::{lang::go, prelude::*};
 
 use crate::{
-    codegen::ir::AnalyzedImports,
+    codegen::{imports::go_module_func, ir::AnalyzedImports, wasm::Compression},
     go::{
-        GoIdentifier, comment,
+        GoIdentifier, GoType, comment,
         imports::{
-            CONTEXT_CONTEXT, ERRORS_NEW, WAZERO_API_MEMORY, WAZERO_API_MODULE,
-            WAZERO_COMPILED_MODULE, WAZERO_NEW_MODULE_CONFIG, WAZERO_NEW_RUNTIME, WAZERO_RUNTIME,
+            BYTES_CLONE, BYTES_NEW_READER, CODES_ERROR, CONTEXT_CONTEXT, CONTEXT_DEADLINE_EXCEEDED,
+            CONTEXT_WITH_VALUE, CONTEXT_WITHOUT_CANCEL, ERRORS_AS, ERRORS_IS, ERRORS_JOIN,
+            ERRORS_NEW, FMT_ERRORF, FMT_SPRINTF, GZIP_NEW_READER, IO_READ_ALL, IO_READER,
+            IO_WRITER, RUNTIME_DEBUG_STACK, RUNTIME_GOMAXPROCS, SLOG_LOGGER, STRINGS_CUT,
+            SYNC_MUTEX, SYNC_ONCE, SYNC_POOL, TIME_DURATION, TIME_NOW, TIME_SINCE, TIME_TIME,
+            TRACE_NOOP_NEW_TRACER_PROVIDER, TRACE_SPAN, TRACE_TRACER, TRACE_TRACER_PROVIDER,
+            WASI_SNAPSHOT_PREVIEW1_INSTANTIATE, WASI_SNAPSHOT_PREVIEW1_MODULE_NAME,
+            WAZERO_API_FUNCTION, WAZERO_API_MEMORY, WAZERO_API_MODULE, WAZERO_API_MUTABLE_GLOBAL,
+            WAZERO_COMPILATION_CACHE, WAZERO_COMPILED_MODULE,
+            WAZERO_EXPERIMENTAL_FUNCTION_LISTENER_FACTORY, WAZERO_EXPERIMENTAL_IMPORT_RESOLVER,
+            WAZERO_EXPERIMENTAL_MEMORY_ALLOCATOR,
+            WAZERO_EXPERIMENTAL_WITH_FUNCTION_LISTENER_FACTORY,
+            WAZERO_EXPERIMENTAL_WITH_IMPORT_RESOLVER, WAZERO_EXPERIMENTAL_WITH_MEMORY_ALLOCATOR,
+            WAZERO_FS_CONFIG, WAZERO_MODULE_CONFIG, WAZERO_NEW_COMPILATION_CACHE_WITH_DIR,
+            WAZERO_NEW_MODULE_CONFIG, WAZERO_NEW_RUNTIME_CONFIG,
+            WAZERO_NEW_RUNTIME_CONFIG_INTERPRETER, WAZERO_NEW_RUNTIME_WITH_CONFIG, WAZERO_RUNTIME,
+            WAZERO_RUNTIME_CONFIG, WAZERO_SYS_CLOCK_RESOLUTION,
+            WAZERO_SYS_EXIT_CODE_CONTEXT_CANCELED, WAZERO_SYS_EXIT_CODE_DEADLINE_EXCEEDED,
+            WAZERO_SYS_NANOTIME, WAZERO_SYS_WALLTIME, ZSTD_NEW_READER,
         },
     },
 };
@@ -16,10 +33,111 @@ use crate::{
 /// Configuration for factory generation
 pub struct FactoryConfig<'a> {
     pub analyzed_imports: &'a AnalyzedImports,
+    /// The name of the world the bindings are generated for, which errors
+    /// the guest traps with name.
+    pub world: &'a str,
     pub import_chains: BTreeMap<String, Tokens<Go>>,
-    pub wasm_var_name: &'a GoIdentifier,
+    /// The variable holding the guest's Wasm, or `None` when the factory
+    /// constructor takes it as a parameter instead.
+    pub wasm_var_name: Option<&'a GoIdentifier>,
+    /// How the Wasm in `wasm_var_name` is compressed, if it is.
+    pub compression: Option<Compression>,
+    /// Whether the world passes `error-context`s, which need the
+    /// `ErrorContext` type and the `$root` intrinsics.
+    pub error_context: bool,
+    /// The tables the handles to the resources the guest exports are kept
+    /// in on the host.
+    pub resources: Vec<GoIdentifier>,
+    /// Whether the guest returns futures, which need the `Future` type and
+    /// the table of future ends.
+    pub futures: bool,
+    /// Whether the guest returns streams, which need the `Stream` type and
+    /// the table of stream ends.
+    pub streams: bool,
+    /// Whether the guest async-lifts or async-lowers functions, which need
+    /// the task and waitable intrinsics.
+    pub asynchronous: bool,
+    /// Whether the factory runs the guest in wazero's interpreter unless
+    /// told otherwise.
+    pub interpreter: bool,
+    /// Whether calls into the guest and host functions are traced with
+    /// OpenTelemetry, which needs the `WithTracerProvider` option.
+    pub tracing: bool,
+    /// Whether the guest was pre-initialized, e.g. by Wizer, so its start
+    /// functions already ran and mustn't run again.
+    pub pre_initialized: bool,
+    /// The names the guest's mutable globals are exported under, which
+    /// instance snapshots capture, or none when the guest's Wasm isn't known
+    /// when generating the bindings.
+    pub mutable_globals: Vec<String>,
+    /// Whether instances write the arguments of calls into the guest to a
+    /// reused scratch buffer, which needs the `scratchBuffer` type.
+    pub scratch_buffer: bool,
+    /// Whether the temporary copies of strings written to guest memory, and
+    /// the scratch buffer, are zeroed once done with.
+    pub zero_buffers: bool,
+    /// The import path of a runtime package, generated by
+    /// [`runtime_package`], whose copies of the helpers that don't depend on
+    /// the world the bindings use instead of their own.
+    pub runtime_package: Option<&'a str>,
 }
 
+/// The analyzed imports of a world that imports nothing, which the default
+/// [`FactoryConfig`] generates the factory for.
+static NO_IMPORTS: AnalyzedImports = AnalyzedImports {
+    interfaces: Vec::new(),
+    standalone_types: Vec::new(),
+    exported_types: Vec::new(),
+    standalone_functions: Vec::new(),
+    factory_name: GoIdentifier::Public {
+        name: String::new(),
+    },
+    instance_name: GoIdentifier::Public {
+        name: String::new(),
+    },
+    constructor_name: GoIdentifier::Public {
+        name: String::new(),
+    },
+    factory_option_name: GoIdentifier::Public {
+        name: String::new(),
+    },
+    factory_options_name: GoIdentifier::Private {
+        name: String::new(),
+    },
+};
+
+impl Default for FactoryConfig<'_> {
+    fn default() -> Self {
+        Self {
+            analyzed_imports: &NO_IMPORTS,
+            world: "",
+            import_chains: BTreeMap::new(),
+            wasm_var_name: None,
+            compression: None,
+            error_context: false,
+            resources: vec![],
+            futures: false,
+            streams: false,
+            asynchronous: false,
+            interpreter: false,
+            tracing: false,
+            pre_initialized: false,
+            mutable_globals: vec![],
+            scratch_buffer: false,
+            zero_buffers: false,
+            runtime_package: None,
+        }
+    }
+}
+
+/// The most flat parameters a synchronous export takes, which the stacks for
+/// calling them are sized for.
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// The module the guest imports canonical built-ins like the `error-context`
+/// and async intrinsics from.
+const ROOT_MODULE: &str = "$root";
+
 /// Generator for factory and instance types
 pub struct FactoryGenerator<'a> {
     config: FactoryConfig<'a>,
@@ -38,6 +156,7 @@ impl<'a> FactoryGenerator<'a> {
 
     /// Generate the `writeString` helper function.
     fn generate_write_string(&self, tokens: &mut Tokens<Go>) {
+        let write = self.write_string_bytes("s");
         // Add writeString helper function for interface string returns
         quote_in! { *tokens =>
             $(comment(&[
@@ -59,9 +178,9 @@ impl<'a> FactoryGenerator<'a> {
                     return 1, 0, err
                 }
                 ptr := results[0]
-                ok := memory.Write(uint32(ptr), []byte(s))
+                $write
                 if !ok {
-                    return 1, 0, $ERRORS_NEW("failed to write string to wasm memory")
+                    return 1, 0, &ABIError{Message: $FMT_SPRINTF("failed to write string to wasm memory at %d", ptr)}
                 }
                 return uint64(ptr), uint64(len(s)), nil
             }
@@ -69,136 +188,2714 @@ impl<'a> FactoryGenerator<'a> {
         };
     }
 
-    /// Generate the Factory struct, constructor, and methods.
-    fn generate_factory(&self, tokens: &mut Tokens<Go>) {
-        let AnalyzedImports {
-            factory_name,
-            instance_name,
-            constructor_name,
-            ..
-        } = &self.config.analyzed_imports;
-        let wasm_var_name = self.config.wasm_var_name;
-        // Build the parameter list
-        let params = self.build_parameters();
+    /// Generate the `scratchBuffer` type instances write the arguments of
+    /// calls into the guest to.
+    fn generate_scratch_buffer(&self, tokens: &mut Tokens<Go>) {
+        let write = self.write_string_bytes("str");
         quote_in! { *tokens =>
+            $(comment(&[
+                "scratchBuffer is a grow-only buffer in guest memory which the arguments of calls",
+                "into the guest are written to, rather than allocating each of them with the",
+                "realloc function. The guest must neither free its arguments nor hold on to them",
+                "once the call returns, as the buffer is reused by the next call.",
+            ]))
+            type scratchBuffer struct {
+                ptr  uint64
+                size uint64
+                used uint64
+                need uint64
+            }
             $['\n']
-            type $factory_name struct {
-                runtime $WAZERO_RUNTIME
-                module  $WAZERO_COMPILED_MODULE
+            $(comment(&[
+                "reset frees up the buffer for the next call, noting how much room the last call",
+                "needed so that the buffer can grow to fit it.",
+            ]))
+            func (s *scratchBuffer) reset() {
+                s.need = max(s.need, s.used)
+                s.used = 0
             }
             $['\n']
-            func $constructor_name(
-                $['\r']
-                $params
-                $['\r']
-            ) (*$factory_name, error) {
-                wazeroRuntime := $WAZERO_NEW_RUNTIME(ctx)
-
-                $(for chain in self.config.import_chains.values() =>
-                    $chain
-                    $['\r']
-                )
+            $(comment(&[
+                "alloc stands in for calling realloc to allocate size bytes aligned to align,",
+                "carving them out of the buffer. Only the first allocation of a call can grow the",
+                "buffer, as growing it may move it, so later ones that don't fit are allocated with",
+                "realloc, and the buffer grows to fit them on the next call.",
+            ]))
+            func (s *scratchBuffer) alloc(
+                ctx $CONTEXT_CONTEXT,
+                realloc api.Function,
+                align uint64,
+                size uint64,
+            ) ([]uint64, error) {
+                if size == 0 {
+                    return []uint64{align}, nil
+                }
+                start := (s.used + align - 1) &^ (align - 1)
+                s.used = start + size
+                if start == 0 && max(s.used, s.need) > s.size {
+                    grown := max(s.used, s.need, 2*s.size)
+                    results, err := realloc.Call(ctx, s.ptr, s.size, 8, grown)
+                    if err != nil {
+                        return nil, err
+                    }
+                    s.ptr, s.size = results[0], grown
+                }
+                if s.used > s.size {
+                    return realloc.Call(ctx, 0, 0, align, size)
+                }
+                return []uint64{s.ptr + start}, nil
+            }
+            $['\n']
+            $(comment(&["writeString is like the writeString function, but writes to the buffer."]))
+            func (s *scratchBuffer) writeString(
+                ctx $CONTEXT_CONTEXT,
+                str string,
+                memory $WAZERO_API_MEMORY,
+                realloc api.Function,
+            ) (uint64, uint64, error) {
+                if len(str) == 0 {
+                    return 1, 0, nil
+                }
 
+                results, err := s.alloc(ctx, realloc, 1, uint64(len(str)))
+                if err != nil {
+                    return 1, 0, err
+                }
+                ptr := results[0]
+                $write
+                if !ok {
+                    return 1, 0, &ABIError{Message: $FMT_SPRINTF("failed to write string to wasm memory at %d", ptr)}
+                }
+                return ptr, uint64(len(str)), nil
+            }
+            $['\n']
+            $(if self.config.zero_buffers {
                 $(comment(&[
-                    "Compiling the module takes a LONG time, so we want to do it once and hold",
-                       "onto it with the Runtime",
+                    "wipe zeroes the part of the buffer the last call used, so that its arguments",
+                    "don't linger in guest memory. Arguments that didn't fit and were allocated with",
+                    "realloc belong to the guest and are left alone.",
                 ]))
-                module, err := wazeroRuntime.CompileModule(ctx, $wasm_var_name)
+                func (s *scratchBuffer) wipe(memory $WAZERO_API_MEMORY) {
+                    if used := min(s.used, s.size); used > 0 {
+                        if view, ok := memory.Read(uint32(s.ptr), uint32(used)); ok {
+                            clear(view)
+                        }
+                    }
+                }
+                $['\n']
+            })
+        };
+    }
+
+    /// The statements writing the Go string `s` to guest memory at `ptr`,
+    /// setting `ok`. Writing copies the string's bytes first, and that copy
+    /// is zeroed afterwards when buffers are zeroed.
+    fn write_string_bytes(&self, s: &str) -> Tokens<Go> {
+        if self.config.zero_buffers {
+            quote! {
+                data := []byte($s)
+                ok := memory.Write(uint32(ptr), data)
+                clear(data)
+            }
+        } else {
+            quote!(ok := memory.Write(uint32(ptr), []byte($s)))
+        }
+    }
+
+    /// Generate the `decompressWasm` helper the factory constructor
+    /// decompresses the guest's Wasm with.
+    fn generate_decompress_wasm(&self, compression: Compression, tokens: &mut Tokens<Go>) {
+        let decompress = match compression {
+            Compression::Gzip => quote! {
+                reader, err := $GZIP_NEW_READER($BYTES_NEW_READER(compressed))
+                if err != nil {
+                    return nil, err
+                }
+                defer reader.Close()
+                return $IO_READ_ALL(reader)
+            },
+            Compression::Zstd => quote! {
+                decoder, err := $ZSTD_NEW_READER(nil)
                 if err != nil {
                     return nil, err
                 }
-                return &$factory_name{
-                    runtime: wazeroRuntime,
-                    module:  module,
-                }, nil
+                defer decoder.Close()
+                return decoder.DecodeAll(compressed, nil)
+            },
+        };
+        quote_in! { *tokens =>
+            $(comment(&[
+                "decompressWasm decompresses the guest's Wasm, which the bindings include",
+                "compressed to keep binaries small.",
+            ]))
+            func decompressWasm(compressed []byte) ([]byte, error) {
+                $decompress
             }
             $['\n']
-            func (f *$factory_name) Instantiate(ctx $CONTEXT_CONTEXT) (*$instance_name, error) {
-                if module, err := f.runtime.InstantiateModule(ctx, f.module, $WAZERO_NEW_MODULE_CONFIG()); err != nil {
-                    return nil, err
-                } else {
-                    return &$instance_name{module}, nil
+        };
+    }
+
+    /// Generate the `logDebug` helper behind the `WithLogger` option, or
+    /// refer to the runtime package's.
+    fn generate_log_debug(&self, tokens: &mut Tokens<Go>) {
+        match self.config.runtime_package {
+            Some(package) => quote_in! { *tokens =>
+                var logDebug = $(go::import(package.to_string(), "LogDebug"))
+                $['\n']
+            },
+            None => quote_log_debug("logDebug", tokens),
+        }
+    }
+
+    /// Generate the `Metrics` interface behind the `WithMetrics` option, and
+    /// the helpers reporting calls to it.
+    fn generate_metrics(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "Metrics is told about every call into the guest, named after the function",
+                "the guest exports, so that calls can be counted and timed. It must be safe",
+                "for concurrent use.",
+            ]))
+            type Metrics interface {
+                $(comment(&["OnCall is called before the guest is called into."]))
+                OnCall(name string)
+                $(comment(&[
+                    "OnReturn is called once the guest returned, after duration, with the",
+                    "error the call failed with, if any.",
+                ]))
+                OnReturn(name string, duration $TIME_DURATION, err error)
+            }
+            $['\n']
+            func callStarted(metrics Metrics, name string) $TIME_TIME {
+                if metrics != nil {
+                    metrics.OnCall(name)
                 }
+                return $TIME_NOW()
             }
             $['\n']
-            func (f *$factory_name) Close(ctx $CONTEXT_CONTEXT) {
-                f.runtime.Close(ctx)
+            func callReturned(metrics Metrics, name string, started $TIME_TIME, err error) {
+                if metrics != nil {
+                    metrics.OnReturn(name, $TIME_SINCE(started), err)
+                }
             }
             $['\n']
         };
     }
 
-    /// Generate the Instance struct, and methods.
-    fn generate_instance(&self, tokens: &mut Tokens<Go>) {
-        let instance_name = &self.config.analyzed_imports.instance_name;
+    /// Generate the `CallInterceptor` type behind the `WithCallInterceptor`
+    /// option, and the helper calling into the guest through it.
+    fn generate_call_interceptor(&self, tokens: &mut Tokens<Go>) {
         quote_in! { *tokens =>
-            type $instance_name struct {
-                module $WAZERO_API_MODULE
+            $(comment(&[
+                "CallInterceptor wraps every call into the guest, named after the function",
+                "the guest exports. It must call next, with ctx or a context derived from it,",
+                "to make the call, and return its error. Calling next again retries the call",
+                "with the same arguments, which only suits functions without strings, lists or",
+                "resources among them, as the guest owns those once called. It must be safe",
+                "for concurrent use.",
+            ]))
+            type CallInterceptor func(ctx $CONTEXT_CONTEXT, function string, next func($CONTEXT_CONTEXT) error) error
+            $['\n']
+            func interceptCall(ctx $CONTEXT_CONTEXT, interceptor CallInterceptor, function string, next func($CONTEXT_CONTEXT) error) error {
+                if interceptor == nil {
+                    return next(ctx)
+                }
+                return interceptor(ctx, function, next)
             }
             $['\n']
-            func (i *$instance_name) Close(ctx $CONTEXT_CONTEXT) error {
-                if err := i.module.Close(ctx); err != nil {
+        };
+    }
+
+    /// Generate the `ResultError` type carrying non-string `result` errors.
+    fn generate_result_error(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "ResultError carries the error payload of a WIT `result<T, E>` whose error",
+                "type isn't a string. Host functions return it (optionally wrapped) to fail",
+                "with a typed payload, and guest errors are surfaced as one.",
+            ]))
+            type ResultError[E any] struct {
+                Value E
+            }
+            $['\n']
+            func (e *ResultError[E]) Error() string {
+                return $FMT_SPRINTF("%v", e.Value)
+            }
+            $['\n']
+            $(comment(&[
+                "Unwrap exposes payloads which are errors themselves, such as enum cases",
+                "and variant cases, to `errors.Is` and `errors.As`.",
+            ]))
+            func (e *ResultError[E]) Unwrap() error {
+                if err, ok := any(e.Value).(error); ok {
                     return err
                 }
-
                 return nil
             }
             $['\n']
         };
     }
 
-    /// Build parameter list for factory constructor
-    fn build_parameters(&self) -> Tokens<Go> {
-        let interfaces = &self.config.analyzed_imports.interfaces;
-
-        quote! {
-            ctx $CONTEXT_CONTEXT,
-            $(for interface in interfaces.iter() join ($['\r']) =>
-            $(&interface.constructor_param_name) $(&interface.go_interface_name),
-            )
-        }
+    /// Generate the error types telling apart why a call into the guest or
+    /// its instantiation failed, and the `callError` helper classifying the
+    /// errors calls into the guest fail with.
+    fn generate_errors(&self, tokens: &mut Tokens<Go>) {
+        let host_errors = !self.config.analyzed_imports.interfaces.is_empty();
+        let world = self.config.world;
+        quote_in! { *tokens =>
+            $(comment(&[
+                "TrapError is what a call into the guest fails with when the guest trapped,",
+                "for example on an unreachable instruction or an out-of-bounds memory access",
+                "of its own, or was stopped, for example by its context being done. Stack",
+                "holds the guest's stack trace at the time, when wazero reported one.",
+            ]))
+            type TrapError struct {
+                World    string
+                Function string
+                Stack    string
+                Err      error
+            }
+            $['\n']
+            func (e *TrapError) Error() string {
+                return $FMT_SPRINTF("%s: guest function %s trapped: %v", e.World, e.Function, e.Err)
+            }
+            $['\n']
+            $(comment(&[
+                "Unwrap exposes the error wazero reported to `errors.Is` and `errors.As`,",
+                "such as a `sys.ExitError` when the guest exited.",
+            ]))
+            func (e *TrapError) Unwrap() error {
+                return e.Err
+            }
+            $['\n']
+            $(comment(&[
+                "ABIError is what a call fails with when a value passed between the host and",
+                "the guest breaks the canonical ABI, such as a pointer outside the guest's",
+                "memory, an invalid discriminant or an unknown handle.",
+            ]))
+            type ABIError struct {
+                Message string
+            }
+            $['\n']
+            func (e *ABIError) Error() string {
+                return e.Message
+            }
+            $['\n']
+            $(comment(&[
+                "InstantiationError is what creating a factory or an instance fails with when",
+                "the guest or the host modules it imports can't be compiled or instantiated.",
+            ]))
+            type InstantiationError struct {
+                Err error
+            }
+            $['\n']
+            func (e *InstantiationError) Error() string {
+                return $FMT_SPRINTF("failed to instantiate guest: %v", e.Err)
+            }
+            $['\n']
+            $(comment(&["Unwrap exposes the error wazero reported to `errors.Is` and `errors.As`."]))
+            func (e *InstantiationError) Unwrap() error {
+                return e.Err
+            }
+            $['\n']
+            $(comment(&[
+                "callError classifies the error a call into the guest failed with, which is a",
+                "TrapError unless a host function or the ABI is to blame. When the call's",
+                "context is done, the guest may have been stopped partway through the call,",
+                "so its instance is closed with the exit code wazero uses for that, and the",
+                "context's error is wrapped too.",
+            ]))
+            func callError(ctx $CONTEXT_CONTEXT, module $WAZERO_API_MODULE, function string, err error) error {
+                if err == nil {
+                    return nil
+                }
+                if ctxErr := ctx.Err(); ctxErr != nil {
+                    exitCode := $WAZERO_SYS_EXIT_CODE_CONTEXT_CANCELED
+                    if $ERRORS_IS(ctxErr, $CONTEXT_DEADLINE_EXCEEDED) {
+                        exitCode = $WAZERO_SYS_EXIT_CODE_DEADLINE_EXCEEDED
+                    }
+                    module.CloseWithExitCode(ctx, exitCode)
+                    err = $FMT_ERRORF("%w: %w", ctxErr, err)
+                }
+                var abiErr *ABIError
+                if $ERRORS_AS(err, &abiErr) {
+                    return err
+                }
+                $(if host_errors {
+                    var panicErr *HostPanicError
+                    if $ERRORS_AS(err, &panicErr) {
+                        return err
+                    }
+                    var notProvidedErr *ImportNotProvidedError
+                    if $ERRORS_AS(err, &notProvidedErr) {
+                        return err
+                    }
+                })
+                $(comment(&["wazero appends the guest's stack trace to the errors it traps with"]))
+                _, stack, _ := $STRINGS_CUT(err.Error(), "wasm stack trace:\n")
+                return &TrapError{World: $(quoted(world)), Function: function, Stack: stack, Err: err}
+            }
+            $['\n']
+        };
     }
-}
 
-impl<'a> FormatInto<Go> for &FactoryGenerator<'a> {
-    fn format_into(self, tokens: &mut Tokens<Go>) {
-        self.generate_factory(tokens);
-        tokens.push();
-        self.generate_instance(tokens);
-        tokens.push();
-        self.generate_write_string(tokens);
-        tokens.push();
+    /// Generate the helpers marking the spans of failed calls, into the guest
+    /// and to host functions.
+    fn generate_tracing(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "failSpan marks span as failed with err, unless err is nil.",
+            ]))
+            func failSpan(span $TRACE_SPAN, err error) {
+                if err == nil {
+                    return
+                }
+                span.RecordError(err)
+                span.SetStatus($CODES_ERROR, err.Error())
+            }
+            $['\n']
+            $(if !self.config.analyzed_imports.interfaces.is_empty() {
+                $(comment(&[
+                    "traceHostPanic marks span as failed when the host function deferring it",
+                    "panics, and lets the panic carry on to recoverHostPanic.",
+                ]))
+                func traceHostPanic(span $TRACE_SPAN, function string) {
+                    if r := recover(); r != nil {
+                        err, ok := r.(error)
+                        if !ok {
+                            err = $FMT_ERRORF("host function %s panicked: %v", function, r)
+                        }
+                        failSpan(span, err)
+                        panic(r)
+                    }
+                }
+                $['\n']
+            })
+        };
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use genco::lang::go::Tokens;
-
-    use crate::{
-        codegen::{FactoryGenerator, factory::FactoryConfig, ir::AnalyzedImports},
-        go::GoIdentifier,
-    };
 
-    #[test]
-    fn test_generate_write_string() {
-        let analyzed_imports = &AnalyzedImports {
-            interfaces: vec![],
-            standalone_types: vec![],
-            standalone_functions: vec![],
-            factory_name: GoIdentifier::public("test-factory"),
-            instance_name: GoIdentifier::public("test-instance"),
-            constructor_name: GoIdentifier::public("test-constructor"),
-        };
-        let config = FactoryConfig {
-            analyzed_imports,
-            import_chains: Default::default(),
-            wasm_var_name: &GoIdentifier::public("test-wasm"),
+    /// Generate the `HostPanicError` type and the `recoverHostPanic` helper
+    /// deferred by every host function.
+    fn generate_host_panic(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "HostPanicError is what a call into the guest fails with when the host",
+                "implementation of one of its imports panicked. The guest is trapped rather",
+                "than the panic unwinding through wazero, and Stack records where it happened.",
+            ]))
+            type HostPanicError struct {
+                Function string
+                Value    any
+                Stack    []byte
+            }
+            $['\n']
+            func (e *HostPanicError) Error() string {
+                return $FMT_SPRINTF("host function %s panicked: %v\n%s", e.Function, e.Value, e.Stack)
+            }
+            $['\n']
+            $(comment(&[
+                "Unwrap exposes panics with an error to `errors.Is` and `errors.As`.",
+            ]))
+            func (e *HostPanicError) Unwrap() error {
+                if err, ok := e.Value.(error); ok {
+                    return err
+                }
+                return nil
+            }
+            $['\n']
+            $(comment(&[
+                "recoverHostPanic traps the guest with a HostPanicError when the host function",
+                "deferring it panics, unless it panicked with one of the errors already",
+                "telling why.",
+            ]))
+            func recoverHostPanic(function string) {
+                if r := recover(); r != nil {
+                    switch r.(type) {
+                    case *ABIError, *HostPanicError, *ImportNotProvidedError:
+                        panic(r)
+                    }
+                    panic(&HostPanicError{Function: function, Value: r, Stack: $RUNTIME_DEBUG_STACK()})
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "ImportNotProvidedError is what a call into the guest fails with when the",
+                "guest calls a function of an import that was left nil, which",
+                "WithOptionalImports allows.",
+            ]))
+            type ImportNotProvidedError struct {
+                Import   string
+                Function string
+            }
+            $['\n']
+            func (e *ImportNotProvidedError) Error() string {
+                return $FMT_SPRINTF("host function %s was called, but %s was not provided", e.Function, e.Import)
+            }
+            $['\n']
+            $(comment(&[
+                "HostInterceptor wraps the calls the guest makes to the host functions of",
+                "iface, the constructor parameter implementing them. It must call next, with",
+                "ctx or a context derived from it, to run method, or panic to trap the guest",
+                "instead: returning without calling next leaves the guest with no results.",
+                "Panics are reported as a HostPanicError.",
+            ]))
+            type HostInterceptor func(ctx $CONTEXT_CONTEXT, iface, method string, next func($CONTEXT_CONTEXT))
+            $['\n']
+            $(comment(&[
+                "interceptHost runs next through interceptor, if there is one.",
+            ]))
+            func interceptHost(ctx $CONTEXT_CONTEXT, interceptor HostInterceptor, iface, method string, next func($CONTEXT_CONTEXT)) {
+                if interceptor == nil {
+                    next(ctx)
+                    return
+                }
+                defer recoverHostPanic(method)
+                interceptor(ctx, iface, method, next)
+            }
+            $['\n']
         };
-        let generator = FactoryGenerator::new(config);
-        let mut tokens = Tokens::new();
-        generator.generate_write_string(&mut tokens);
+    }
 
-        assert!(tokens.to_string().unwrap().contains("func writeString"));
+    /// Generate the `handleTable` type mapping the handles shared with guests
+    /// to what they refer to.
+    fn generate_handle_table(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "HandleChecks enables assertions catching resources that are used after",
+                "their ownership was transferred. They're meant for debugging.",
+            ]))
+            var HandleChecks = false
+            $['\n']
+            $(comment(&[
+                "handleTable maps the handles a guest holds to the values they refer to.",
+                "Borrowed handles are only lent to the guest for the duration of a call.",
+            ]))
+            type handleTable[T any] struct {
+                mu       $SYNC_MUTEX
+                next     uint32
+                entries  map[uint32]T
+                borrowed map[uint32]bool
+            }
+            $['\n']
+            func newHandleTable[T any]() *handleTable[T] {
+                return &handleTable[T]{entries: map[uint32]T{}, borrowed: map[uint32]bool{}}
+            }
+            $['\n']
+            func (t *handleTable[T]) insert(value T) uint32 {
+                t.mu.Lock()
+                defer t.mu.Unlock()
+                $(comment(&["Handles start at 1 so that 0 is never valid"]))
+                t.next++
+                t.entries[t.next] = value
+                return t.next
+            }
+            $['\n']
+            func (t *handleTable[T]) lend(value T) uint32 {
+                handle := t.insert(value)
+                t.mu.Lock()
+                defer t.mu.Unlock()
+                t.borrowed[handle] = true
+                return handle
+            }
+            $['\n']
+            func (t *handleTable[T]) isBorrowed(handle uint32) bool {
+                t.mu.Lock()
+                defer t.mu.Unlock()
+                return t.borrowed[handle]
+            }
+            $['\n']
+            func (t *handleTable[T]) get(handle uint32) (T, bool) {
+                t.mu.Lock()
+                defer t.mu.Unlock()
+                value, ok := t.entries[handle]
+                return value, ok
+            }
+            $['\n']
+            func (t *handleTable[T]) remove(handle uint32) (T, bool) {
+                t.mu.Lock()
+                defer t.mu.Unlock()
+                value, ok := t.entries[handle]
+                delete(t.entries, handle)
+                delete(t.borrowed, handle)
+                return value, ok
+            }
+            $['\n']
+        };
+    }
+
+    /// Generate the `instanceHandles` type keeping a `handleTable` per
+    /// instance, for the resources the host implements.
+    fn generate_instance_handles(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "instanceHandles keeps a separate handleTable for every instance, so that",
+                "guests can't use each other's handles.",
+            ]))
+            type instanceHandles[T any] struct {
+                mu     $SYNC_MUTEX
+                tables map[$WAZERO_API_MODULE]*handleTable[T]
+            }
+            $['\n']
+            func newInstanceHandles[T any]() *instanceHandles[T] {
+                return &instanceHandles[T]{tables: map[$WAZERO_API_MODULE]*handleTable[T]{}}
+            }
+            $['\n']
+            func (h *instanceHandles[T]) of(mod $WAZERO_API_MODULE) *handleTable[T] {
+                h.mu.Lock()
+                defer h.mu.Unlock()
+                table, ok := h.tables[mod]
+                if !ok {
+                    table = newHandleTable[T]()
+                    h.tables[mod] = table
+                }
+                return table
+            }
+            $['\n']
+            $(comment(&[
+                "release forgets the table of an instance, dropping the values the guest",
+                "still had handles to.",
+            ]))
+            func (h *instanceHandles[T]) release(ctx $CONTEXT_CONTEXT, mod $WAZERO_API_MODULE) {
+                h.mu.Lock()
+                table := h.tables[mod]
+                delete(h.tables, mod)
+                h.mu.Unlock()
+                if table == nil {
+                    return
+                }
+                for handle, value := range table.entries {
+                    if table.borrowed[handle] {
+                        continue
+                    }
+                    if dropper, ok := any(value).(interface{ Drop($CONTEXT_CONTEXT) }); ok {
+                        dropper.Drop(ctx)
+                    }
+                }
+            }
+            $['\n']
+        };
+    }
+
+    /// The tables of the resources the host implements, which have to be
+    /// released along with an instance.
+    fn host_resource_handles(&self) -> Vec<&GoIdentifier> {
+        self.config
+            .analyzed_imports
+            .interfaces
+            .iter()
+            .flat_map(|interface| &interface.resources)
+            .map(|resource| &resource.handles)
+            .collect()
+    }
+
+    /// The tables keeping the handles of each instance apart, which have to
+    /// be released along with it.
+    fn instance_handles(&self) -> Vec<GoIdentifier> {
+        let mut handles = self
+            .host_resource_handles()
+            .into_iter()
+            .chain(&self.config.resources)
+            .cloned()
+            .collect::<Vec<_>>();
+        if self.config.error_context {
+            handles.push(GoIdentifier::private("error-contexts"));
+        }
+        if self.config.futures {
+            handles.push(GoIdentifier::private("future-ends"));
+        }
+        if self.config.streams {
+            handles.push(GoIdentifier::private("stream-ends"));
+        }
+        if self.config.asynchronous {
+            handles.push(GoIdentifier::private("waitable-sets"));
+        }
+        handles
+    }
+
+    /// Generate the `ErrorContext` type and the table of handles shared with
+    /// guests.
+    fn generate_error_context(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "ErrorContext is a WIT `error-context`. Guests refer to it by handle, while",
+                "the debug message stays on the host.",
+            ]))
+            type ErrorContext struct {
+                DebugMessage string
+            }
+            $['\n']
+            func (e *ErrorContext) Error() string {
+                return e.DebugMessage
+            }
+            $['\n']
+            $(comment(&[
+                "errorContexts holds every error context a guest has a handle to, apart",
+                "for every instance.",
+            ]))
+            var errorContexts = newInstanceHandles[*ErrorContext]()
+            $['\n']
+        };
+    }
+
+    /// Generate the `Future` type and the table of the future ends guests
+    /// have handles to.
+    fn generate_future(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "Future is a WIT `future` returned by a guest, which resolves once the guest",
+                "writes its value.",
+            ]))
+            type Future[T any] struct {
+                done  chan struct{}
+                once  $SYNC_ONCE
+                value T
+                err   error
+            }
+            $['\n']
+            func newFuture[T any]() *Future[T] {
+                return &Future[T]{done: make(chan struct{})}
+            }
+            $['\n']
+            $(comment(&[
+                "Await blocks until the guest writes the future's value or ctx is done.",
+                "It returns an error if the guest dropped the future without writing it.",
+            ]))
+            func (f *Future[T]) Await(ctx $CONTEXT_CONTEXT) (T, error) {
+                select {
+                case <-f.done:
+                    return f.value, f.err
+                case <-ctx.Done():
+                    var value T
+                    return value, ctx.Err()
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "Done returns a channel that's closed once the future resolves.",
+            ]))
+            func (f *Future[T]) Done() <-chan struct{} {
+                return f.done
+            }
+            $['\n']
+            func (f *Future[T]) resolve(value T, err error) {
+                f.once.Do(func() {
+                    f.value = value
+                    f.err = err
+                    close(f.done)
+                })
+            }
+            $['\n']
+            $(comment(&[
+                "futureEnds holds both ends of every future a guest has a handle to, apart",
+                "for every instance.",
+            ]))
+            var futureEnds = newInstanceHandles[any]()
+            $['\n']
+        };
+    }
+
+    /// Generate the `Stream` type and the table of the stream ends guests
+    /// have handles to.
+    fn generate_stream(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "Stream is a WIT `stream` returned by a guest, which delivers the values the",
+                "guest writes. The guest may write them before the call returning the stream",
+                "does, so they're buffered until they're received rather than blocking it.",
+            ]))
+            type Stream[T any] struct {
+                mu          $SYNC_MUTEX
+                pending     []T
+                finished    bool
+                wake        chan struct{}
+                values      chan T
+                closed      chan struct{}
+                deliverOnce $SYNC_ONCE
+                closeOnce   $SYNC_ONCE
+            }
+            $['\n']
+            func newStream[T any]() *Stream[T] {
+                return &Stream[T]{
+                    wake:   make(chan struct{}, 1),
+                    values: make(chan T),
+                    closed: make(chan struct{}),
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "Values returns the channel the guest's values are delivered on, in the order",
+                "they were written. The channel is closed once the guest is done writing and",
+                "every value is received, or once the stream is closed.",
+            ]))
+            func (s *Stream[T]) Values() <-chan T {
+                s.deliverOnce.Do(func() {
+                    go s.deliver()
+                })
+                return s.values
+            }
+            $['\n']
+            $(comment(&[
+                "Close stops receiving values, so the buffered ones and the guest's later",
+                "writes are dropped.",
+            ]))
+            func (s *Stream[T]) Close() {
+                s.closeOnce.Do(func() {
+                    s.mu.Lock()
+                    defer s.mu.Unlock()
+                    close(s.closed)
+                    s.pending = nil
+                })
+            }
+            $['\n']
+            $(comment(&[
+                "write buffers a value the guest wrote, reporting false if the stream is",
+                "closed.",
+            ]))
+            func (s *Stream[T]) write(value T) bool {
+                s.mu.Lock()
+                defer s.mu.Unlock()
+                select {
+                case <-s.closed:
+                    return false
+                default:
+                }
+                s.pending = append(s.pending, value)
+                s.notify()
+                return true
+            }
+            $['\n']
+            $(comment(&["finish records that the guest is done writing."]))
+            func (s *Stream[T]) finish() {
+                s.mu.Lock()
+                defer s.mu.Unlock()
+                s.finished = true
+                s.notify()
+            }
+            $['\n']
+            func (s *Stream[T]) notify() {
+                select {
+                case s.wake <- struct{}{}:
+                default:
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "deliver sends the buffered values on the channel as they're received, until",
+                "the guest is done writing or the stream is closed.",
+            ]))
+            func (s *Stream[T]) deliver() {
+                defer close(s.values)
+                for {
+                    s.mu.Lock()
+                    pending, finished := s.pending, s.finished
+                    s.pending = nil
+                    s.mu.Unlock()
+                    for _, value := range pending {
+                        select {
+                        case s.values <- value:
+                        case <-s.closed:
+                            return
+                        }
+                    }
+                    if len(pending) > 0 {
+                        continue
+                    }
+                    if finished {
+                        return
+                    }
+                    select {
+                    case <-s.wake:
+                    case <-s.closed:
+                        return
+                    }
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "streamEnds holds both ends of every stream a guest has a handle to, apart",
+                "for every instance.",
+            ]))
+            var streamEnds = newInstanceHandles[any]()
+            $['\n']
+        };
+    }
+
+    /// Generate the host module providing the canonical built-ins the guest
+    /// imports from `$root`.
+    fn generate_root_intrinsics(&self) -> Tokens<Go> {
+        quote! {
+            rootModule, err := wazeroRuntime.NewHostModuleBuilder(options.namespace + $(quoted(ROOT_MODULE))).
+                $(if self.config.error_context => $(self.generate_error_context_intrinsics()))
+                $(if self.config.asynchronous => $(self.generate_async_intrinsics()))
+                Instantiate(ctx)
+            if err != nil {
+                return nil, &InstantiationError{Err: err}
+            }
+            hostModules = append(hostModules, rootModule)
+        }
+    }
+
+    /// Generate the `error-context` intrinsics.
+    fn generate_error_context_intrinsics(&self) -> Tokens<Go> {
+        let new = go_module_func(
+            &[("ptr", GoType::Uint32), ("length", GoType::Uint32)],
+            Some(GoType::Uint32),
+            quote! {
+                buf, ok := mod.Memory().Read(ptr, length)
+                if !ok {
+                    panic(&ABIError{Message: $FMT_SPRINTF("[error-context-new]: failed to read bytes from memory at %d", ptr)})
+                }
+                return errorContexts.of(mod).insert(&ErrorContext{DebugMessage: string(buf)})
+            },
+        );
+        let debug_message = go_module_func(
+            &[("handle", GoType::Uint32), ("retptr", GoType::Uint32)],
+            None,
+            quote! {
+                errorContext, ok := errorContexts.of(mod).get(handle)
+                if !ok {
+                    panic($ERRORS_NEW("invalid error-context handle"))
+                }
+                ptr, length, err := writeString(ctx, errorContext.DebugMessage, mod.Memory(), mod.ExportedFunction("cabi_realloc"))
+                if err != nil {
+                    panic(err)
+                }
+                if !mod.Memory().WriteUint32Le(retptr, uint32(ptr)) || !mod.Memory().WriteUint32Le(retptr+4, uint32(length)) {
+                    panic(&ABIError{Message: $FMT_SPRINTF("[error-context-debug-message]: failed to write result to memory at %d", retptr)})
+                }
+            },
+        );
+        let drop = go_module_func(
+            &[("handle", GoType::Uint32)],
+            None,
+            quote! {
+                errorContexts.of(mod).remove(handle)
+            },
+        );
+        quote! {
+                NewFunctionBuilder().
+                $new
+                Export("[error-context-new;encoding=utf8]").
+                NewFunctionBuilder().
+                $debug_message
+                Export("[error-context-debug-message;encoding=utf8;realloc=cabi_realloc]").
+                NewFunctionBuilder().
+                $drop
+                Export("[error-context-drop]").
+        }
+    }
+
+    /// Generate the intrinsics an async task uses to keep its state and wait
+    /// on events.
+    ///
+    /// The host finishes everything it's asked to before returning, so
+    /// there's never anything to wait on: a waitable set never has an event,
+    /// and waiting on one would never end.
+    fn generate_async_intrinsics(&self) -> Tokens<Go> {
+        let set_and_ptr = &[("set", GoType::Uint32), ("ptr", GoType::Uint32)];
+        let intrinsics = [
+            (
+                "[context-get-0]",
+                go_module_func(
+                    &[],
+                    Some(GoType::Uint32),
+                    quote! {
+                        return asyncTaskOf(ctx).context
+                    },
+                ),
+            ),
+            (
+                "[context-set-0]",
+                go_module_func(
+                    &[("value", GoType::Uint32)],
+                    None,
+                    quote! {
+                        asyncTaskOf(ctx).context = value
+                    },
+                ),
+            ),
+            (
+                "[waitable-set-new]",
+                go_module_func(
+                    &[],
+                    Some(GoType::Uint32),
+                    quote! {
+                        return waitableSets.of(mod).insert(struct{}{})
+                    },
+                ),
+            ),
+            (
+                "[waitable-set-drop]",
+                go_module_func(
+                    &[("set", GoType::Uint32)],
+                    None,
+                    quote! {
+                        if _, ok := waitableSets.of(mod).remove(set); !ok {
+                            panic($ERRORS_NEW("invalid waitable set handle"))
+                        }
+                    },
+                ),
+            ),
+            (
+                "[waitable-join]",
+                go_module_func(
+                    &[("waitable", GoType::Uint32), ("set", GoType::Uint32)],
+                    None,
+                    quote! {
+                        if _, ok := waitableSets.of(mod).get(set); set != 0 && !ok {
+                            panic($ERRORS_NEW("invalid waitable set handle"))
+                        }
+                    },
+                ),
+            ),
+            (
+                "[waitable-set-poll]",
+                go_module_func(
+                    set_and_ptr,
+                    Some(GoType::Uint32),
+                    quote! {
+                        $(comment(&["There's never an event, so the guest is told there's none"]))
+                        return 0
+                    },
+                ),
+            ),
+            (
+                "[waitable-set-wait]",
+                go_module_func(
+                    set_and_ptr,
+                    Some(GoType::Uint32),
+                    quote! {
+                        panic($ERRORS_NEW("waiting on a waitable set that never has an event"))
+                    },
+                ),
+            ),
+            (
+                "[yield]",
+                go_module_func(
+                    &[],
+                    Some(GoType::Uint32),
+                    quote! {
+                        $(comment(&["Report whether the task was cancelled while it yielded"]))
+                        if ctx.Err() != nil {
+                            return 1
+                        }
+                        return 0
+                    },
+                ),
+            ),
+            (
+                "[subtask-drop]",
+                go_module_func(
+                    &[("subtask", GoType::Uint32)],
+                    None,
+                    quote! {
+                        $(comment(&["Async-lowered calls return before they start a subtask"]))
+                        panic($ERRORS_NEW("invalid subtask handle"))
+                    },
+                ),
+            ),
+        ];
+        quote! {
+                $(for (name, intrinsic) in intrinsics join ($['\r']) =>
+                    NewFunctionBuilder().
+                    $intrinsic
+                    Export($(quoted(name))).
+                )
+        }
+    }
+
+    /// Generate the state of the calls to async-lifted functions, and the
+    /// loop driving them to completion.
+    fn generate_async(&self, tokens: &mut Tokens<Go>) {
+        quote_in! { *tokens =>
+            $(comment(&[
+                "asyncTask is a call to an async-lifted function, which it hands its",
+                "results over to through `[task-return]`.",
+            ]))
+            type asyncTask struct {
+                values    []any
+                returned  bool
+                cancelled bool
+                context   uint32
+            }
+            $['\n']
+            type asyncTaskKey struct{}
+            $['\n']
+            func asyncTaskOf(ctx $CONTEXT_CONTEXT) *asyncTask {
+                task, ok := ctx.Value(asyncTaskKey{}).(*asyncTask)
+                if !ok {
+                    panic($ERRORS_NEW("async intrinsic called outside of an async call"))
+                }
+                return task
+            }
+            $['\n']
+            func (t *asyncTask) Return(ctx $CONTEXT_CONTEXT, values ...any) {
+                if t.returned {
+                    panic($ERRORS_NEW("task returned more than once"))
+                }
+                t.values = values
+                t.returned = true
+            }
+            $['\n']
+            $(comment(&[
+                "taskValue is the nth value the task of the async-lifted function name handed",
+                "over, which the bindings expect to be a T.",
+            ]))
+            func taskValue[T any](task *asyncTask, name string, nth int) (T, error) {
+                var zero T
+                if nth >= len(task.values) {
+                    return zero, &ABIError{Message: $FMT_SPRINTF("%s returned %d values", name, len(task.values))}
+                }
+                $(comment(&["A nil error or variant comes back as a nil interface"]))
+                if task.values[nth] == nil {
+                    return zero, nil
+                }
+                value, ok := task.values[nth].(T)
+                if !ok {
+                    return zero, &ABIError{Message: $FMT_SPRINTF("%s returned an unexpected %T as its value %d", name, task.values[nth], nth)}
+                }
+                return value, nil
+            }
+            $['\n']
+            $(comment(&[
+                "callAsync calls an async-lifted function, lift, and drives it through its",
+                "callback until it returns. If ctx is done before then, the task is cancelled.",
+            ]))
+            func callAsync(ctx $CONTEXT_CONTEXT, lift, callback $WAZERO_API_FUNCTION, name string, params ...uint64) (*asyncTask, error) {
+                if lift == nil || callback == nil {
+                    return nil, &ABIError{Message: $FMT_SPRINTF("%s isn't exported with its callback", "[async-lift]"+name)}
+                }
+                task := &asyncTask{}
+                ctx = $CONTEXT_WITH_VALUE(ctx, asyncTaskKey{}, task)
+                results, err := lift.Call(ctx, params...)
+                if err != nil {
+                    return nil, err
+                }
+                for {
+                    code := uint32(results[0])
+                    switch code & 0xf {
+                    case 0:
+                        $(comment(&["EXIT: the task is done"]))
+                        if task.cancelled {
+                            return nil, ctx.Err()
+                        }
+                        if !task.returned {
+                            return nil, $FMT_ERRORF("%s exited without returning", name)
+                        }
+                        return task, nil
+                    case 1:
+                        $(comment(&["YIELD: the task can go on whenever, unless it's been cancelled"]))
+                        event := uint64(0)
+                        if ctx.Err() != nil && !task.returned {
+                            event = 6
+                        }
+                        results, err = callback.Call(ctx, event, 0, 0)
+                        if err != nil {
+                            return nil, err
+                        }
+                    case 2:
+                        $(comment(&["WAIT: the task waits on a waitable set, which never has an event"]))
+                        return nil, $FMT_ERRORF("%s waits on waitable set %d, which never has an event", name, code>>4)
+                    default:
+                        return nil, &ABIError{Message: $FMT_SPRINTF("%s returned invalid callback code %d", name, code)}
+                    }
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "waitableSets holds every waitable set a guest has a handle to, apart for",
+                "every instance.",
+            ]))
+            var waitableSets = newInstanceHandles[struct{}]()
+            $['\n']
+        };
+    }
+
+    /// Generate the functional options accepted by the factory constructor.
+    fn generate_factory_options(&self, tokens: &mut Tokens<Go>) {
+        let AnalyzedImports {
+            factory_option_name,
+            factory_options_name,
+            interfaces,
+            ..
+        } = &self.config.analyzed_imports;
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(&[
+                "The configuration collected from the options passed to the factory",
+                "constructor.",
+            ]))
+            type $factory_options_name struct {
+                runtime             $WAZERO_RUNTIME
+                compilationCache    $WAZERO_COMPILATION_CACHE
+                compilationCacheDir string
+                namespace           string
+                interpreter         bool
+                poolSize            int
+                closeOnContextDone  bool
+                callTimeout         $TIME_DURATION
+                maxConcurrentCalls  int
+                maxLength           uint32
+                memoryLimitPages    uint32
+                configureRuntime    func($WAZERO_RUNTIME_CONFIG) $WAZERO_RUNTIME_CONFIG
+                wasiPreview1        bool
+                stdout              $IO_WRITER
+                stderr              $IO_WRITER
+                env                 [][2]string
+                args                []string
+                fsConfig            $WAZERO_FS_CONFIG
+                walltime            $WAZERO_SYS_WALLTIME
+                walltimeResolution  $WAZERO_SYS_CLOCK_RESOLUTION
+                nanotime            $WAZERO_SYS_NANOTIME
+                nanotimeResolution  $WAZERO_SYS_CLOCK_RESOLUTION
+                randSource          $IO_READER
+                logger              *$SLOG_LOGGER
+                metrics             Metrics
+                callInterceptor     CallInterceptor
+                listenerFactory     $WAZERO_EXPERIMENTAL_FUNCTION_LISTENER_FACTORY
+                memoryAllocator     $WAZERO_EXPERIMENTAL_MEMORY_ALLOCATOR
+                optionalImports     bool
+                lazyCompilation     bool
+                $(if !interfaces.is_empty() => hostInterceptor HostInterceptor)
+                $(if self.config.tracing => tracerProvider $TRACE_TRACER_PROVIDER)
+            }
+            $['\n']
+            $(comment(&[
+                "An option configuring how the factory sets up its runtime.",
+            ]))
+            type $factory_option_name func(*$factory_options_name)
+            $['\n']
+            $(comment(&[
+                "WithRuntime attaches the factory to an existing runtime instead of",
+                "creating its own, so several factories can share compiled code and memory.",
+                "The factory never closes a runtime it doesn't own, and leaves its config as",
+                "it is, so WithCloseOnContextDone and the options configuring the runtime are",
+                "ignored. Host modules whose names another factory on the runtime took are",
+                "moved under a namespace, see WithHostModuleNamespace.",
+            ]))
+            func WithRuntime(runtime $WAZERO_RUNTIME) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.runtime = runtime
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCompilationCache shares compiled code between runtimes created by",
+                "different factories. It has no effect when combined with WithRuntime, as",
+                "the provided runtime already carries its own cache.",
+            ]))
+            func WithCompilationCache(cache $WAZERO_COMPILATION_CACHE) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.compilationCache = cache
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCompilationCacheDir persists compiled code in dir, so that later",
+                "processes skip compiling the guest again. The cache is shared with any",
+                "other runtime using the same directory. It has no effect when combined with",
+                "WithRuntime or WithCompilationCache.",
+            ]))
+            func WithCompilationCacheDir(dir string) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.compilationCacheDir = dir
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithHostModuleNamespace registers the host modules under names prefixed",
+                "with namespace, and links the guest against those names instead. Without",
+                "it, a factory sharing a runtime with another that registered host modules",
+                "under the same names picks a namespace of the form \"gravity-N/\" itself.",
+            ]))
+            func WithHostModuleNamespace(namespace string) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.namespace = namespace
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithInterpreter forces the factory to run the guest in wazero's interpreter.",
+                "Without it, the compiler is used on platforms that support it and the",
+                "interpreter everywhere else. It has no effect when combined with WithRuntime.",
+            ]))
+            func WithInterpreter() $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.interpreter = true
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCompiler undoes WithInterpreter, and the interpreter default the bindings",
+                "may have been generated with, so the compiler is used on platforms that",
+                "support it. It has no effect when combined with WithRuntime.",
+            ]))
+            func WithCompiler() $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.interpreter = false
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithInstancePoolSize sets how many idle instances the factory keeps around",
+                "for WithInstance to reuse, which defaults to GOMAXPROCS. Zero disables",
+                "pooling, so that each call instantiates the guest anew.",
+            ]))
+            func WithInstancePoolSize(size int) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.poolSize = size
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCloseOnContextDone sets whether a call whose context is done aborts the",
+                "guest, closing its instance, which is the default. Without it, a guest that",
+                "never returns blocks its caller forever. wazero only supports this for a",
+                "whole runtime, so it's ignored when combined with WithRuntime, including the",
+                "default: calls are then only aborted if the runtime was configured with",
+                "wazero.RuntimeConfig.WithCloseOnContextDone.",
+            ]))
+            func WithCloseOnContextDone(enabled bool) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.closeOnContextDone = enabled
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCallTimeout bounds every call into the guest to the given duration, on",
+                "top of any deadline of the call's context, so a misbehaving guest can't stall",
+                "its caller. A call running past it is aborted, closing its instance, unless",
+                "WithCloseOnContextDone disabled that or WithRuntime is given a runtime that",
+                "doesn't close modules when their context is done.",
+            ]))
+            func WithCallTimeout(timeout $TIME_DURATION) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.callTimeout = timeout
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithMaxConcurrentCalls caps the calls into the guest running at once, across",
+                "all instances of the factory, at n. Further calls wait for one to return, or",
+                "fail with the error of their context if it's done first. A host function",
+                "calling back into a guest of the same factory can deadlock once the cap is",
+                "reached.",
+            ]))
+            func WithMaxConcurrentCalls(n int) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.maxConcurrentCalls = n
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithMaxLength caps the length of the strings, lists and maps the guest hands",
+                "to the host, in bytes for strings and in elements otherwise. Longer ones fail",
+                "with an ABIError rather than being allocated. Lengths are always checked",
+                "against the size of the guest's memory.",
+            ]))
+            func WithMaxLength(n uint32) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.maxLength = n
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithMemoryLimitPages caps the memory of each instance at the given number of",
+                "64 KiB pages, so that guests fail to grow their memory past it. It has no",
+                "effect when combined with WithRuntime.",
+            ]))
+            func WithMemoryLimitPages(pages uint32) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.memoryLimitPages = pages
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithRuntimeConfig lets configure adjust the runtime's config once the other",
+                "options have been applied to it, for settings the factory has no option for,",
+                "such as core features or debug info. It has no effect when combined with",
+                "WithRuntime.",
+            ]))
+            func WithRuntimeConfig(configure func($WAZERO_RUNTIME_CONFIG) $WAZERO_RUNTIME_CONFIG) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.configureRuntime = configure
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithWASIPreview1 provides wazero's WASI preview 1 host module to guests",
+                "importing it, such as those built for `wasm32-wasip1`. Without it, such",
+                "guests are rejected. A runtime shared with WithRuntime that already has the",
+                "module keeps using it. Otherwise the module is added to it for good, as other",
+                "factories on the runtime may come to depend on it, so closing the factory",
+                "leaves it there.",
+            ]))
+            func WithWASIPreview1() $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.wasiPreview1 = true
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithStdout sends whatever the guest writes to its standard output to w,",
+                "which is discarded by default.",
+            ]))
+            func WithStdout(w $IO_WRITER) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.stdout = w
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithStderr sends whatever the guest writes to its standard error to w,",
+                "which is discarded by default.",
+            ]))
+            func WithStderr(w $IO_WRITER) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.stderr = w
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithEnv sets the environment variable key to value for the guest, which",
+                "sees no environment variables by default. Setting the same key again",
+                "replaces its value.",
+            ]))
+            func WithEnv(key, value string) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.env = append(o.env, [2]string{key, value})
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithArgs sets the command-line arguments the guest sees, starting with the",
+                "program name. The guest sees no arguments by default.",
+            ]))
+            func WithArgs(args ...string) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.args = args
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithFSConfig mounts the directories and filesystems in config for the",
+                "guest, which has no filesystem access by default.",
+            ]))
+            func WithFSConfig(config $WAZERO_FS_CONFIG) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.fsConfig = config
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithWalltime sets the wall clock the guest reads, along with its resolution.",
+                "It defaults to wazero's fake clock, so a fixed clock here keeps guests",
+                "reproducible in tests while the real one can be passed in production.",
+            ]))
+            func WithWalltime(walltime $WAZERO_SYS_WALLTIME, resolution $WAZERO_SYS_CLOCK_RESOLUTION) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.walltime = walltime
+                    o.walltimeResolution = resolution
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithNanotime sets the monotonic clock the guest reads, along with its",
+                "resolution. It defaults to wazero's fake clock.",
+            ]))
+            func WithNanotime(nanotime $WAZERO_SYS_NANOTIME, resolution $WAZERO_SYS_CLOCK_RESOLUTION) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.nanotime = nanotime
+                    o.nanotimeResolution = resolution
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithRandSource sets where the guest's random bytes come from, which is a",
+                "deterministic source by default. Pass a seeded reader for reproducible",
+                "tests, or crypto/rand.Reader when the guest needs real randomness.",
+            ]))
+            func WithRandSource(source $IO_READER) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.randSource = source
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithLogger sends debug logs about instantiating the guest, calling its",
+                "exports and its calls to host functions to logger. Nothing is logged by",
+                "default.",
+            ]))
+            func WithLogger(logger *$SLOG_LOGGER) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.logger = logger
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithMetrics reports every call into the guest to metrics, so they can be",
+                "counted and timed.",
+            ]))
+            func WithMetrics(metrics Metrics) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.metrics = metrics
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithCallInterceptor passes every call into the guest through interceptor, for",
+                "concerns such as retries, metrics or enriching the context that apply to",
+                "every export alike.",
+            ]))
+            func WithCallInterceptor(interceptor CallInterceptor) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.callInterceptor = interceptor
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithFunctionListenerFactory attaches wazero's experimental function listeners",
+                "to the guest and the host modules, for tracing or profiling the calls between",
+                "every function. Listeners slow every call down, so they're meant for",
+                "debugging.",
+            ]))
+            func WithFunctionListenerFactory(factory $WAZERO_EXPERIMENTAL_FUNCTION_LISTENER_FACTORY) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.listenerFactory = factory
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithMemoryAllocator has wazero's experimental allocator back the linear memory",
+                "of every instance, such as with mmap'd or pooled memory, so hosts can account",
+                "for the memory of each guest.",
+            ]))
+            func WithMemoryAllocator(allocator $WAZERO_EXPERIMENTAL_MEMORY_ALLOCATOR) $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.memoryAllocator = allocator
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithOptionalImports lets the constructor take nil for any of the imported",
+                "interfaces. The guest is only trapped, with an ImportNotProvidedError, if it",
+                "calls one of their functions.",
+            ]))
+            func WithOptionalImports() $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.optionalImports = true
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "WithLazyCompilation defers compiling the guest from the constructor to the",
+                "first Instantiate, so programs that rarely use the factory don't pay for it",
+                "at startup. Instantiate then returns the errors compiling the guest.",
+            ]))
+            func WithLazyCompilation() $factory_option_name {
+                return func(o *$factory_options_name) {
+                    o.lazyCompilation = true
+                }
+            }
+            $['\n']
+            $(if !interfaces.is_empty() {
+                $(comment(&[
+                    "WithHostInterceptor passes every call the guest makes to a host function",
+                    "through interceptor, for concerns such as authorization, logging or",
+                    "containing panics that apply to every import alike.",
+                ]))
+                func WithHostInterceptor(interceptor HostInterceptor) $factory_option_name {
+                    return func(o *$factory_options_name) {
+                        o.hostInterceptor = interceptor
+                    }
+                }
+                $['\n']
+            })
+            $(if self.config.tracing {
+                $(comment(&[
+                    "WithTracerProvider records a span from provider around every call into the",
+                    "guest, and a child span around each of its calls to host functions, named",
+                    "after the WIT function. The spans of calls which fail, with an error or a",
+                    "panic, record it and have an error status. No spans are recorded by default.",
+                ]))
+                func WithTracerProvider(provider $TRACE_TRACER_PROVIDER) $factory_option_name {
+                    return func(o *$factory_options_name) {
+                        o.tracerProvider = provider
+                    }
+                }
+                $['\n']
+            })
+        };
+    }
+
+    /// Generate the Factory struct, constructor, and methods.
+    fn generate_factory(&self, tokens: &mut Tokens<Go>) {
+        let AnalyzedImports {
+            factory_name,
+            instance_name,
+            constructor_name,
+            factory_options_name,
+            interfaces,
+            ..
+        } = &self.config.analyzed_imports;
+        let wasm = match (self.config.wasm_var_name, self.config.compression) {
+            (Some(wasm_var_name), Some(_)) => quote! {
+                wasm, err := decompressWasm($wasm_var_name)
+                if err != nil {
+                    return nil, err
+                }
+            },
+            (Some(wasm_var_name), None) => quote!(wasm := $wasm_var_name),
+            // The constructor takes the Wasm as its `wasm` parameter
+            (None, _) => quote!(),
+        };
+        // Build the parameter list
+        let params = self.build_parameters();
+        let host_modules = self.host_modules();
+        let mut instance_fields = vec![
+            quote!(module: module),
+            quote!(exports: newExportedFunctions(module)),
+            quote!(logger: f.logger),
+            quote!(metrics: f.metrics),
+            quote!(callInterceptor: f.callInterceptor),
+            quote!(callTimeout: f.callTimeout),
+            quote!(calls: f.calls),
+            quote!(maxLength: f.maxLength),
+        ];
+        if self.config.tracing {
+            instance_fields.push(quote!(tracer: f.tracer));
+        }
+        quote_in! { *tokens =>
+            $['\n']
+            type $factory_name struct {
+                runtime      $WAZERO_RUNTIME
+                wasm         []byte
+                wasiPreview1 bool
+                compileOnce  $SYNC_ONCE
+                compileErr   error
+                module       $WAZERO_COMPILED_MODULE
+                moduleConfig $WAZERO_MODULE_CONFIG
+                memoryAllocator $WAZERO_EXPERIMENTAL_MEMORY_ALLOCATOR
+                listenerFactory $WAZERO_EXPERIMENTAL_FUNCTION_LISTENER_FACTORY
+                importResolver $WAZERO_EXPERIMENTAL_IMPORT_RESOLVER
+                callTimeout  $TIME_DURATION
+                calls        chan struct{}
+                maxLength    uint32
+                logger       *$SLOG_LOGGER
+                metrics      Metrics
+                callInterceptor CallInterceptor
+                $(if self.config.tracing => tracer $TRACE_TRACER)
+                hostModules  []$WAZERO_API_MODULE
+                ownsRuntime  bool
+                pool         chan *$instance_name
+            }
+            $['\n']
+            func $constructor_name(
+                $['\r']
+                $params
+                $['\r']
+            ) (*$factory_name, error) {
+                options := $factory_options_name{
+                    poolSize:           $RUNTIME_GOMAXPROCS(0),
+                    closeOnContextDone: true,
+                    $(if self.config.interpreter => interpreter: true,)
+                }
+                for _, opt := range opts {
+                    opt(&options)
+                }
+                $(for interface in interfaces {
+                    if $(&interface.constructor_param_name) == nil && !options.optionalImports {
+                        return nil, $ERRORS_NEW($(quoted(format!(
+                            "{} is nil, which needs WithOptionalImports",
+                            String::from(&interface.constructor_param_name)
+                        ))))
+                    }
+                    $['\r']
+                })
+                if options.listenerFactory != nil {
+                    $(comment(&["wazero picks the listeners up from the context modules are compiled with"]))
+                    ctx = $WAZERO_EXPERIMENTAL_WITH_FUNCTION_LISTENER_FACTORY(ctx, options.listenerFactory)
+                }
+                $(if self.config.tracing {
+                    if options.tracerProvider == nil {
+                        options.tracerProvider = $TRACE_NOOP_NEW_TRACER_PROVIDER()
+                    }
+                    tracer := options.tracerProvider.Tracer("github.com/arcjet/gravity")
+                })
+
+                $(if self.config.compression.is_some() {
+                    $(comment(&["Decompressed first, so failing to doesn't leave a runtime to close"]))
+                })
+                $wasm
+
+                wazeroRuntime := options.runtime
+                ownsRuntime := wazeroRuntime == nil
+                if ownsRuntime {
+                    $(comment(&[
+                        "The default config selects the compiler where the platform supports it",
+                        "and falls back to the interpreter otherwise",
+                    ]))
+                    runtimeConfig := $WAZERO_NEW_RUNTIME_CONFIG()
+                    if options.interpreter {
+                        runtimeConfig = $WAZERO_NEW_RUNTIME_CONFIG_INTERPRETER()
+                    }
+                    if options.compilationCache == nil && options.compilationCacheDir != "" {
+                        cache, err := $WAZERO_NEW_COMPILATION_CACHE_WITH_DIR(options.compilationCacheDir)
+                        if err != nil {
+                            return nil, err
+                        }
+                        options.compilationCache = cache
+                    }
+                    if options.compilationCache != nil {
+                        runtimeConfig = runtimeConfig.WithCompilationCache(options.compilationCache)
+                    }
+                    runtimeConfig = runtimeConfig.WithCloseOnContextDone(options.closeOnContextDone)
+                    if options.memoryLimitPages != 0 {
+                        runtimeConfig = runtimeConfig.WithMemoryLimitPages(options.memoryLimitPages)
+                    }
+                    if options.configureRuntime != nil {
+                        runtimeConfig = options.configureRuntime(runtimeConfig)
+                    }
+                    wazeroRuntime = $WAZERO_NEW_RUNTIME_WITH_CONFIG(ctx, runtimeConfig)
+                }
+                $(if !host_modules.is_empty() {
+                    hostModuleNames := []string{
+                        $(for module in &host_modules join ($['\r']) => $(quoted(*module)),)
+                    }
+                    if !ownsRuntime && options.namespace == "" && hostModulesRegistered(wazeroRuntime, "", hostModuleNames) {
+                        $(comment(&[
+                            "Another factory already registered host modules under these names on",
+                            "the shared runtime, so this one's go under the first free namespace",
+                        ]))
+                        for n := 1; options.namespace == ""; n++ {
+                            namespace := $FMT_SPRINTF("gravity-%d/", n)
+                            if !hostModulesRegistered(wazeroRuntime, namespace, hostModuleNames) {
+                                options.namespace = namespace
+                            }
+                        }
+                    }
+                })
+                var hostModules []$WAZERO_API_MODULE
+
+                $(for chain in self.config.import_chains.values() =>
+                    $chain
+                    $['\r']
+                )
+                $(if self.config.error_context || self.config.asynchronous {
+                    $(self.generate_root_intrinsics())
+                    $['\r']
+                })
+
+                $(comment(&[
+                    "Instances are left anonymous, as wazero refuses to instantiate a module",
+                    "under a name that's in use, which would be the name the guest gives",
+                    "itself otherwise",
+                ]))
+                moduleConfig := $WAZERO_NEW_MODULE_CONFIG().WithName("")
+                $(if self.config.pre_initialized {
+                    $(comment(&["The guest's initialization was snapshotted, so it mustn't run again"]))
+                    moduleConfig = moduleConfig.WithStartFunctions()
+                })
+                if options.stdout != nil {
+                    moduleConfig = moduleConfig.WithStdout(options.stdout)
+                }
+                if options.stderr != nil {
+                    moduleConfig = moduleConfig.WithStderr(options.stderr)
+                }
+                for _, env := range options.env {
+                    moduleConfig = moduleConfig.WithEnv(env[0], env[1])
+                }
+                if options.args != nil {
+                    moduleConfig = moduleConfig.WithArgs(options.args...)
+                }
+                if options.fsConfig != nil {
+                    moduleConfig = moduleConfig.WithFSConfig(options.fsConfig)
+                }
+                if options.walltime != nil {
+                    moduleConfig = moduleConfig.WithWalltime(options.walltime, options.walltimeResolution)
+                }
+                if options.nanotime != nil {
+                    moduleConfig = moduleConfig.WithNanotime(options.nanotime, options.nanotimeResolution)
+                }
+                if options.randSource != nil {
+                    moduleConfig = moduleConfig.WithRandSource(options.randSource)
+                }
+                f := &$factory_name{
+                    runtime:      wazeroRuntime,
+                    wasm:         wasm,
+                    wasiPreview1: options.wasiPreview1,
+                    moduleConfig: moduleConfig,
+                    memoryAllocator: options.memoryAllocator,
+                    listenerFactory: options.listenerFactory,
+                    callTimeout:  options.callTimeout,
+                    maxLength:    options.maxLength,
+                    logger:       options.logger,
+                    metrics:      options.metrics,
+                    callInterceptor: options.callInterceptor,
+                    $(if self.config.tracing => tracer: tracer,)
+                    hostModules:  hostModules,
+                    ownsRuntime:  ownsRuntime,
+                    pool:         make(chan *$instance_name, max(options.poolSize, 0)),
+                }
+                $(if !host_modules.is_empty() {
+                    if options.namespace != "" {
+                        $(comment(&[
+                            "The guest imports the host modules under their own names, so its imports",
+                            "are resolved against the namespaced ones instead",
+                        ]))
+                        namespaced := make(map[string]$WAZERO_API_MODULE, len(hostModuleNames))
+                        for _, name := range hostModuleNames {
+                            namespaced[name] = wazeroRuntime.Module(options.namespace + name)
+                        }
+                        f.importResolver = func(name string) $WAZERO_API_MODULE {
+                            return namespaced[name]
+                        }
+                    }
+                })
+                if options.maxConcurrentCalls > 0 {
+                    f.calls = make(chan struct{}, options.maxConcurrentCalls)
+                }
+                if !options.lazyCompilation {
+                    if err := f.compile(ctx); err != nil {
+                        return nil, err
+                    }
+                }
+                return f, nil
+            }
+            $['\n']
+            $(comment(&[
+                "compile compiles the guest the first time it's called, and returns the",
+                "outcome of that on every call. The outcome is shared by every caller, so",
+                "it doesn't depend on the first caller's context being cancelled.",
+            ]))
+            func (f *$factory_name) compile(ctx $CONTEXT_CONTEXT) error {
+                f.compileOnce.Do(func() {
+                    ctx := $CONTEXT_WITHOUT_CANCEL(ctx)
+                    if f.listenerFactory != nil {
+                        $(comment(&["wazero picks the listeners up from the context modules are compiled with"]))
+                        ctx = $WAZERO_EXPERIMENTAL_WITH_FUNCTION_LISTENER_FACTORY(ctx, f.listenerFactory)
+                    }
+                    $(comment(&[
+                        "Compiling the module takes a LONG time, so we want to do it once and hold",
+                        "onto it with the Runtime",
+                    ]))
+                    module, err := f.runtime.CompileModule(ctx, f.wasm)
+                    if err != nil {
+                        f.compileErr = &InstantiationError{Err: err}
+                        return
+                    }
+                    for _, fn := range module.ImportedFunctions() {
+                        if moduleName, _, _ := fn.Import(); moduleName != $WASI_SNAPSHOT_PREVIEW1_MODULE_NAME {
+                            continue
+                        }
+                        if !f.wasiPreview1 {
+                            f.compileErr = &InstantiationError{Err: $ERRORS_NEW("the guest imports WASI preview 1, which needs WithWASIPreview1")}
+                            return
+                        }
+                        if f.runtime.Module($WASI_SNAPSHOT_PREVIEW1_MODULE_NAME) == nil {
+                            $(comment(&[
+                                "Later factories on a shared runtime use this module rather than",
+                                "instantiating their own, so it's only closed along with the runtime",
+                            ]))
+                            if _, err := $WASI_SNAPSHOT_PREVIEW1_INSTANTIATE(ctx, f.runtime); err != nil {
+                                f.compileErr = &InstantiationError{Err: err}
+                                return
+                            }
+                        }
+                        break
+                    }
+                    f.module = module
+                    $(comment(&["The Wasm isn't needed anymore, so let it be collected"]))
+                    f.wasm = nil
+                })
+                return f.compileErr
+            }
+            $['\n']
+            func (f *$factory_name) Instantiate(ctx $CONTEXT_CONTEXT) (*$instance_name, error) {
+                if err := f.compile(ctx); err != nil {
+                    return nil, err
+                }
+                if f.memoryAllocator != nil {
+                    $(comment(&["wazero picks the allocator up from the context modules are instantiated with"]))
+                    ctx = $WAZERO_EXPERIMENTAL_WITH_MEMORY_ALLOCATOR(ctx, f.memoryAllocator)
+                }
+                if f.importResolver != nil {
+                    ctx = $WAZERO_EXPERIMENTAL_WITH_IMPORT_RESOLVER(ctx, f.importResolver)
+                }
+                if module, err := f.runtime.InstantiateModule(ctx, f.module, f.moduleConfig); err != nil {
+                    return nil, &InstantiationError{Err: err}
+                } else {
+                    logDebug(ctx, f.logger, "instantiated guest", "module", module.Name())
+                    return &$instance_name{$(for field in &instance_fields join (, ) => $field)}, nil
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "Warmup compiles the guest if WithLazyCompilation put that off, so services",
+                "can pay for it during boot, before taking traffic. With instantiate, it also",
+                "instantiates the guest once and closes the instance again.",
+            ]))
+            func (f *$factory_name) Warmup(ctx $CONTEXT_CONTEXT, instantiate bool) error {
+                if err := f.compile(ctx); err != nil {
+                    return err
+                }
+                if !instantiate {
+                    return nil
+                }
+                instance, err := f.Instantiate(ctx)
+                if err != nil {
+                    return err
+                }
+                return instance.Close(ctx)
+            }
+            $['\n']
+            $(comment(&[
+                "Compiled returns the compiled guest, compiling it first if WithLazyCompilation",
+                "put that off, so hosts can inspect its imports, exports and memory limits, or",
+                "instantiate it themselves. The module belongs to the factory and must not be",
+                "closed.",
+            ]))
+            func (f *$factory_name) Compiled(ctx $CONTEXT_CONTEXT) ($WAZERO_COMPILED_MODULE, error) {
+                if err := f.compile(ctx); err != nil {
+                    return nil, err
+                }
+                return f.module, nil
+            }
+            $['\n']
+            $(comment(&[
+                "WithInstance calls fn with an idle instance from the factory's pool, or a",
+                "new one if there is none, so concurrent callers each get an instance of",
+                "their own. The instance goes back to the pool once fn returns, unless fn",
+                "failed, as the guest may have been left in a bad state. fn must not keep",
+                "the instance around.",
+            ]))
+            func (f *$factory_name) WithInstance(ctx $CONTEXT_CONTEXT, fn func(*$instance_name) error) error {
+                var instance *$instance_name
+                select {
+                case instance = <-f.pool:
+                default:
+                    var err error
+                    instance, err = f.Instantiate(ctx)
+                    if err != nil {
+                        return err
+                    }
+                }
+                if err := fn(instance); err != nil {
+                    return $ERRORS_JOIN(err, instance.Close(ctx))
+                }
+                select {
+                case f.pool <- instance:
+                    return nil
+                default:
+                    return instance.Close(ctx)
+                }
+            }
+            $['\n']
+            $(comment(&[
+                "Close releases the compiled guest and the host modules, along with the",
+                "runtime if the factory created it. Instances of the factory must not be",
+                "used afterwards.",
+            ]))
+            func (f *$factory_name) Close(ctx $CONTEXT_CONTEXT) error {
+                $(comment(&["Everything is closed even if something fails to, so nothing leaks"]))
+                var errs []error
+                for len(f.pool) > 0 {
+                    errs = append(errs, (<-f.pool).Close(ctx))
+                }
+                if f.ownsRuntime {
+                    errs = append(errs, f.runtime.Close(ctx))
+                    return $ERRORS_JOIN(errs...)
+                }
+                $(comment(&[
+                    "The runtime is shared, so only release what this factory added to it",
+                ]))
+                if f.module != nil {
+                    errs = append(errs, f.module.Close(ctx))
+                }
+                for _, hostModule := range f.hostModules {
+                    errs = append(errs, hostModule.Close(ctx))
+                }
+                return $ERRORS_JOIN(errs...)
+            }
+            $['\n']
+        };
+    }
+
+    /// Every host module the guest imports from, which have to be renamed to
+    /// match when the factory is namespaced.
+    fn host_modules(&self) -> BTreeSet<&str> {
+        let mut host_modules = self
+            .config
+            .analyzed_imports
+            .interfaces
+            .iter()
+            .map(|interface| interface.wazero_module_name.as_str())
+            .chain(self.config.import_chains.keys().map(String::as_str))
+            .collect::<BTreeSet<_>>();
+        if self.config.error_context || self.config.asynchronous {
+            host_modules.insert(ROOT_MODULE);
+        }
+        host_modules
+    }
+
+    /// Generate the `hostModulesRegistered` helper used to pick a free
+    /// namespace, or refer to the runtime package's.
+    fn generate_host_modules_registered(&self, tokens: &mut Tokens<Go>) {
+        match self.config.runtime_package {
+            Some(package) => quote_in! { *tokens =>
+                var hostModulesRegistered = $(go::import(package.to_string(), "HostModulesRegistered"))
+                $['\n']
+            },
+            None => quote_host_modules_registered("hostModulesRegistered", tokens),
+        }
+    }
+
+    /// Generate the Instance struct, and methods.
+    fn generate_instance(&self, tokens: &mut Tokens<Go>) {
+        let instance_name = &self.config.analyzed_imports.instance_name;
+        quote_in! { *tokens =>
+            $(comment(&[
+                "callStack holds the flat arguments and result of a call into the guest.",
+                "They're pooled rather than kept on the instance, so that concurrent calls",
+                "each have their own without allocating.",
+            ]))
+            type callStack [$MAX_FLAT_PARAMS]uint64
+            $['\n']
+            var callStacks = $SYNC_POOL{New: func() any { return new(callStack) }}
+            $['\n']
+            type $instance_name struct {
+                module $WAZERO_API_MODULE
+                exports exportedFunctions
+                logger *$SLOG_LOGGER
+                metrics Metrics
+                callInterceptor CallInterceptor
+                callTimeout $TIME_DURATION
+                calls chan struct{}
+                maxLength uint32
+                $(if self.config.tracing => tracer $TRACE_TRACER)
+                $(if self.config.scratch_buffer => scratch scratchBuffer)
+            }
+            $['\n']
+            $(comment(&[
+                "Close releases the guest's memory and the host resources it holds handles",
+                "to. The instance must not be used afterwards.",
+            ]))
+            func (i *$instance_name) Close(ctx $CONTEXT_CONTEXT) error {
+                $(for handles in self.instance_handles() join ($['\r']) => $handles.release(ctx, i.module))
+                if err := i.module.Close(ctx); err != nil {
+                    return err
+                }
+
+                return nil
+            }
+            $['\n']
+            $(comment(&[
+                "Module returns the wazero module the instance runs the guest in, for calling",
+                "exports or inspecting memory the bindings don't cover. Calls made through it",
+                "bypass the bindings' logging, metrics and resource handling.",
+            ]))
+            func (i *$instance_name) Module() $WAZERO_API_MODULE {
+                return i.module
+            }
+            $['\n']
+            $(comment(&[
+                "MemorySize returns the size of the guest's memory in bytes. Wasm memory never",
+                "shrinks, so this is also the most the guest has used, and an instance whose",
+                "memory keeps growing across calls is likely leaking.",
+            ]))
+            func (i *$instance_name) MemorySize() uint32 {
+                if memory := i.module.Memory(); memory != nil {
+                    return memory.Size()
+                }
+                return 0
+            }
+            $['\n']
+            $(comment(&["MemoryPages returns the size of the guest's memory in 64 KiB pages."]))
+            func (i *$instance_name) MemoryPages() uint32 {
+                return i.MemorySize() / 65536
+            }
+            $['\n']
+        };
+    }
+
+    /// Generate the `InstanceSnapshot` type and the instance methods taking
+    /// and restoring one.
+    fn generate_instance_snapshot(&self, tokens: &mut Tokens<Go>) {
+        let instance_name = &self.config.analyzed_imports.instance_name;
+        let mutable_globals = &self.config.mutable_globals;
+        let globals = !mutable_globals.is_empty();
+        let snapshot_doc: &[&str] = if globals {
+            &[
+                "Snapshot copies the instance's memory and mutable globals, typically once",
+                "it's initialized, so that Restore can reset it between requests for a",
+                "fraction of the cost of instantiating the guest again. It must not be called",
+                "during a call into the guest.",
+            ]
+        } else {
+            // The guest's Wasm wasn't known when generating the bindings, so
+            // its mutable globals couldn't be exported.
+            &[
+                "Snapshot copies the instance's memory, typically once it's initialized, so",
+                "that Restore can reset it between requests for a fraction of the cost of",
+                "instantiating the guest again. It must not be called during a call into the",
+                "guest. The guest's mutable globals, like the stack pointer, aren't captured,",
+                "so only guests whose globals are back to the same values between calls can",
+                "be reset this way.",
+            ]
+        };
+        quote_in! { *tokens =>
+            $(if globals {
+                $(comment(&[
+                    "mutableGlobals are the names the guest's mutable globals are exported",
+                    "under, which snapshots capture.",
+                ]))
+                var mutableGlobals = [...]string{
+                    $(for name in mutable_globals join ($['\r']) => $(quoted(name)),)
+                }
+                $['\n']
+            })
+            $(comment(&[
+                "InstanceSnapshot is a copy of an instance's memory and mutable globals taken",
+                "by Snapshot, which Restore resets the instance to.",
+            ]))
+            type InstanceSnapshot struct {
+                memory []byte
+                $(if globals => globals [len(mutableGlobals)]uint64)
+                $(if self.config.scratch_buffer => scratch scratchBuffer)
+            }
+            $['\n']
+            $(comment(snapshot_doc))
+            func (i *$instance_name) Snapshot() (*InstanceSnapshot, error) {
+                snapshot := &InstanceSnapshot{
+                    $(if self.config.scratch_buffer => scratch: i.scratch,)
+                }
+                $(if globals {
+                    for j, name := range mutableGlobals {
+                        snapshot.globals[j] = i.module.ExportedGlobal(name).Get()
+                    }
+                })
+                if memory := i.module.Memory(); memory != nil {
+                    view, ok := memory.Read(0, memory.Size())
+                    if !ok {
+                        return nil, $FMT_ERRORF("failed to snapshot %d bytes of memory", memory.Size())
+                    }
+                    snapshot.memory = $BYTES_CLONE(view)
+                }
+                return snapshot, nil
+            }
+            $['\n']
+            $(comment(&[
+                "Restore resets the instance's memory and mutable globals to a snapshot",
+                "Snapshot took of it, zeroing whatever memory the guest grew since, and drops",
+                "the host resources the guest holds handles to. Resources the guest handed",
+                "out since the snapshot must not be used afterwards.",
+            ]))
+            func (i *$instance_name) Restore(ctx $CONTEXT_CONTEXT, snapshot *InstanceSnapshot) error {
+                if memory := i.module.Memory(); memory != nil {
+                    if !memory.Write(0, snapshot.memory) {
+                        return $FMT_ERRORF("failed to restore %d bytes of memory", len(snapshot.memory))
+                    }
+                    size := uint32(len(snapshot.memory))
+                    if grown, ok := memory.Read(size, memory.Size()-size); ok {
+                        clear(grown)
+                    }
+                }
+                $(if globals {
+                    for j, name := range mutableGlobals {
+                        i.module.ExportedGlobal(name).($WAZERO_API_MUTABLE_GLOBAL).Set(snapshot.globals[j])
+                    }
+                })
+                $(for handles in self.instance_handles() join ($['\r']) => $handles.release(ctx, i.module))
+                $(if self.config.scratch_buffer => i.scratch = snapshot.scratch)
+                return nil
+            }
+            $['\n']
+        };
+    }
+
+    /// Build parameter list for factory constructor
+    fn build_parameters(&self) -> Tokens<Go> {
+        let interfaces = &self.config.analyzed_imports.interfaces;
+
+        let option_name = &self.config.analyzed_imports.factory_option_name;
+
+        quote! {
+            ctx $CONTEXT_CONTEXT,
+            $(if self.config.wasm_var_name.is_none() => wasm []byte,)
+            $(for interface in interfaces.iter() join ($['\r']) =>
+            $(&interface.constructor_param_name) $(&interface.go_interface_name),
+            )
+            opts ...$option_name,
+        }
+    }
+}
+
+/// Generate the runtime package holding the helpers the bindings of every
+/// world would otherwise each have a copy of, exported for the bindings
+/// given its import path as `runtime_package` to refer to.
+pub fn runtime_package() -> Tokens<Go> {
+    let mut tokens = Tokens::new();
+    quote_log_debug("LogDebug", &mut tokens);
+    tokens.push();
+    quote_host_modules_registered("HostModulesRegistered", &mut tokens);
+    tokens
+}
+
+/// Quote the helper logging at debug level, named `name`.
+fn quote_log_debug(name: &str, tokens: &mut Tokens<Go>) {
+    quote_in! { *tokens =>
+        $(comment([format!("{name} logs msg at debug level if logger isn't nil.")]))
+        func $name(ctx $CONTEXT_CONTEXT, logger *$SLOG_LOGGER, msg string, args ...any) {
+            if logger != nil {
+                logger.DebugContext(ctx, msg, args...)
+            }
+        }
+        $['\n']
+    };
+}
+/// Quote the helper picking a free namespace, named `name`.
+fn quote_host_modules_registered(name: &str, tokens: &mut Tokens<Go>) {
+    quote_in! { *tokens =>
+        $(comment([
+            format!("{name} reports whether any of the given host modules is"),
+            "already registered on runtime under `namespace + module`".to_string(),
+        ]))
+        func $name(runtime $WAZERO_RUNTIME, namespace string, modules []string) bool {
+            for _, module := range modules {
+                if runtime.Module(namespace+module) != nil {
+                    return true
+                }
+            }
+            return false
+        }
+        $['\n']
+    };
+}
+
+impl<'a> FormatInto<Go> for &FactoryGenerator<'a> {
+    fn format_into(self, tokens: &mut Tokens<Go>) {
+        self.generate_factory_options(tokens);
+        tokens.push();
+        self.generate_factory(tokens);
+        tokens.push();
+        self.generate_instance(tokens);
+        tokens.push();
+        self.generate_instance_snapshot(tokens);
+        tokens.push();
+        self.generate_write_string(tokens);
+        tokens.push();
+        if self.config.scratch_buffer {
+            self.generate_scratch_buffer(tokens);
+            tokens.push();
+        }
+        self.generate_log_debug(tokens);
+        tokens.push();
+        if let (Some(_), Some(compression)) = (self.config.wasm_var_name, self.config.compression) {
+            self.generate_decompress_wasm(compression, tokens);
+            tokens.push();
+        }
+        self.generate_metrics(tokens);
+        tokens.push();
+        self.generate_call_interceptor(tokens);
+        tokens.push();
+        if self.config.tracing {
+            self.generate_tracing(tokens);
+            tokens.push();
+        }
+        self.generate_result_error(tokens);
+        tokens.push();
+        self.generate_errors(tokens);
+        tokens.push();
+        if !self.config.analyzed_imports.interfaces.is_empty() {
+            self.generate_host_panic(tokens);
+            tokens.push();
+        }
+        let host_resources = !self.host_resource_handles().is_empty();
+        if self.config.error_context
+            || !self.config.resources.is_empty()
+            || self.config.futures
+            || self.config.streams
+            || self.config.asynchronous
+            || host_resources
+        {
+            self.generate_handle_table(tokens);
+            tokens.push();
+        }
+        if !self.instance_handles().is_empty() {
+            self.generate_instance_handles(tokens);
+            tokens.push();
+        }
+        if self.config.error_context {
+            self.generate_error_context(tokens);
+            tokens.push();
+        }
+        if self.config.futures {
+            self.generate_future(tokens);
+            tokens.push();
+        }
+        if self.config.streams {
+            self.generate_stream(tokens);
+            tokens.push();
+        }
+        if self.config.asynchronous {
+            self.generate_async(tokens);
+            tokens.push();
+        }
+        if !self.host_modules().is_empty() {
+            self.generate_host_modules_registered(tokens);
+            tokens.push();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use genco::{prelude::*, tokens::Tokens};
+
+    use crate::{
+        codegen::{
+            FactoryGenerator,
+            factory::{FactoryConfig, runtime_package},
+            ir::{AnalyzedImports, AnalyzedInterface, AnalyzedResource},
+            wasm::Compression,
+        },
+        go::GoIdentifier,
+    };
+
+    fn analyzed_imports(interfaces: Vec<AnalyzedInterface>) -> AnalyzedImports {
+        AnalyzedImports {
+            interfaces,
+            standalone_types: vec![],
+            exported_types: vec![],
+            standalone_functions: vec![],
+            factory_name: GoIdentifier::public("test-factory"),
+            instance_name: GoIdentifier::public("test-instance"),
+            constructor_name: GoIdentifier::public("test-constructor"),
+            factory_option_name: GoIdentifier::public("test-factory-option"),
+            factory_options_name: GoIdentifier::private("test-factory-options"),
+        }
+    }
+
+    fn logger_interface() -> AnalyzedInterface {
+        AnalyzedInterface {
+            name: "logger".to_string(),
+            methods: vec![],
+            types: vec![],
+            resources: vec![],
+            go_interface_name: GoIdentifier::public("i-test-logger"),
+            constructor_param_name: GoIdentifier::private("logger"),
+            wazero_module_name: "test:pkg/logger".to_string(),
+        }
+    }
+
+    fn generate(analyzed_imports: &AnalyzedImports) -> String {
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        tokens.to_string().unwrap()
+    }
+
+    #[test]
+    fn test_generate_write_string() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        generator.generate_write_string(&mut tokens);
+
+        assert!(tokens.to_string().unwrap().contains("func writeString"));
+    }
+
+    #[test]
+    fn test_external_wasm() {
+        let analyzed_imports = analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports: &analyzed_imports,
+            world: "test-world",
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("wasm []byte,"));
+        assert!(!generated.contains("wasm := "));
+        assert!(generated.contains("module, err := f.runtime.CompileModule(ctx, f.wasm)"));
+    }
+
+    #[test]
+    fn test_compressed_wasm() {
+        let analyzed_imports = analyzed_imports(vec![]);
+        for (compression, decompress) in [
+            (
+                Compression::Gzip,
+                "gzip.NewReader(bytes.NewReader(compressed))",
+            ),
+            (Compression::Zstd, "zstd.NewReader(nil)"),
+        ] {
+            let config = FactoryConfig {
+                analyzed_imports: &analyzed_imports,
+                world: "test-world",
+                wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+                compression: Some(compression),
+                ..Default::default()
+            };
+            let generator = FactoryGenerator::new(config);
+            let mut tokens = Tokens::<Go>::new();
+            (&generator).format_into(&mut tokens);
+            let generated = tokens.to_string().unwrap();
+
+            // Failing to decompress mustn't leave a runtime behind.
+            let decompressed = generated
+                .find("wasm, err := decompressWasm(TestWasm)")
+                .unwrap();
+            assert!(decompressed < generated.find("wazeroRuntime := options.runtime").unwrap());
+            assert!(generated.contains("func decompressWasm(compressed []byte) ([]byte, error) {"));
+            assert!(generated.contains(decompress));
+        }
+
+        let generated = generate(&analyzed_imports);
+        assert!(!generated.contains("decompressWasm"));
+    }
+
+    /// Snapshots capture the guest's mutable globals only when they were
+    /// exported when generating the bindings.
+    #[test]
+    fn test_instance_snapshot() {
+        let generated = generate(&analyzed_imports(vec![]));
+        assert!(
+            generated
+                .contains("The guest's mutable globals, like the stack pointer, aren't captured")
+        );
+        assert!(!generated.contains("mutableGlobals"));
+
+        let analyzed_imports = analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports: &analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            mutable_globals: vec!["__stack_pointer".into(), "gravity-global-1".into()],
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains(
+            "var mutableGlobals = [...]string{\n    \"__stack_pointer\",\n    \"gravity-global-1\",\n}"
+        ));
+        assert!(!generated.contains("aren't captured"));
+    }
+
+    #[test]
+    fn test_pre_initialized() {
+        let generated = generate(&analyzed_imports(vec![]));
+        assert!(!generated.contains("WithStartFunctions"));
+
+        let analyzed_imports = analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports: &analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            pre_initialized: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("moduleConfig = moduleConfig.WithStartFunctions()"));
+    }
+
+    #[test]
+    fn test_scratch_buffer() {
+        let generated = generate(&analyzed_imports(vec![]));
+        assert!(!generated.contains("scratchBuffer"));
+
+        let analyzed_imports = analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports: &analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            scratch_buffer: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("type scratchBuffer struct {"));
+        assert!(generated.contains("scratch scratchBuffer"));
+        assert!(generated.contains(
+            "return &TestInstance{module: module, exports: newExportedFunctions(module), logger: f.logger, metrics: f.metrics, callInterceptor: f.callInterceptor, callTimeout: f.callTimeout, calls: f.calls, maxLength: f.maxLength}, nil"
+        ));
+        assert!(generated.contains("func (s *scratchBuffer) alloc("));
+        assert!(generated.contains("results, err := realloc.Call(ctx, s.ptr, s.size, 8, grown)"));
+        assert!(generated.contains("func (s *scratchBuffer) writeString("));
+    }
+
+    #[test]
+    fn test_tracing() {
+        let generated = generate(&analyzed_imports(vec![]));
+        assert!(!generated.contains("WithTracerProvider"));
+        assert!(!generated.contains("go.opentelemetry.io"));
+
+        let analyzed_imports = analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports: &analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            tracing: true,
+            ..Default::default()
+        };
+        let mut tokens = Tokens::<Go>::new();
+        FactoryGenerator::new(config).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains(
+            "func WithTracerProvider(provider trace.TracerProvider) TestFactoryOption {"
+        ));
+        assert!(generated.contains("options.tracerProvider = noop.NewTracerProvider()"));
+        assert!(generated.contains("span.SetStatus(codes.Error, err.Error())"));
+        assert!(
+            generated.contains(
+                r#"tracer := options.tracerProvider.Tracer("github.com/arcjet/gravity")"#
+            )
+        );
+        assert!(generated.contains(
+            "return &TestInstance{module: module, exports: newExportedFunctions(module), logger: f.logger, metrics: f.metrics, callInterceptor: f.callInterceptor, callTimeout: f.callTimeout, calls: f.calls, maxLength: f.maxLength, tracer: f.tracer}, nil"
+        ));
+    }
+
+    #[test]
+    fn test_zero_buffers() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            scratch_buffer: true,
+            zero_buffers: true,
+            ..Default::default()
+        };
+        let mut tokens = Tokens::new();
+        FactoryGenerator::new(config).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert_eq!(generated.matches("clear(data)").count(), 2);
+        assert!(generated.contains("func (s *scratchBuffer) wipe(memory api.Memory) {"));
+
+        let generated = generate(analyzed_imports);
+        assert!(!generated.contains("clear(data)"));
+        assert!(!generated.contains("wipe("));
+    }
+
+    #[test]
+    fn test_interpreter_default() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            interpreter: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains(" interpreter: true,\n"));
+    }
+
+    #[test]
+    fn test_namespace_skipped_without_host_modules() {
+        let generated = generate(&analyzed_imports(vec![]));
+
+        assert!(!generated.contains("hostModuleNames"));
+        assert!(!generated.contains("f.importResolver = "));
+        assert!(generated.contains("module, err := f.runtime.CompileModule(ctx, f.wasm)"));
+    }
+
+    #[test]
+    fn test_runtime_package() {
+        let analyzed_imports = &analyzed_imports(vec![logger_interface()]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            runtime_package: Some("example.com/gravityruntime"),
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("var logDebug = gravityruntime.LogDebug"));
+        assert!(
+            generated.contains("var hostModulesRegistered = gravityruntime.HostModulesRegistered")
+        );
+        assert!(!generated.contains("func logDebug("));
+        assert!(!generated.contains("func hostModulesRegistered("));
+
+        let runtime = runtime_package().to_string().unwrap();
+        assert!(runtime.contains(
+            "func LogDebug(ctx context.Context, logger *slog.Logger, msg string, args ...any) {"
+        ));
+        assert!(runtime.contains(
+            "func HostModulesRegistered(runtime wazero.Runtime, namespace string, modules []string) bool {"
+        ));
+    }
+
+    #[test]
+    fn test_error_context_intrinsics() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            error_context: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("type ErrorContext struct {"));
+        assert!(generated.contains("func (e *ErrorContext) Error() string {"));
+        assert!(generated.contains("NewHostModuleBuilder(options.namespace + \"$root\")"));
+        assert!(generated.contains("Export(\"[error-context-new;encoding=utf8]\")"));
+        assert!(generated.contains(
+            "Export(\"[error-context-debug-message;encoding=utf8;realloc=cabi_realloc]\")"
+        ));
+        assert!(generated.contains("Export(\"[error-context-drop]\")"));
+        // Guests mustn't be able to use each other's error contexts.
+        assert!(generated.contains("var errorContexts = newInstanceHandles[*ErrorContext]()"));
+        assert!(generated.contains("errorContexts.of(mod).insert(&ErrorContext{"));
+        assert!(generated.contains("errorContexts.release(ctx, i.module)"));
+        // The intrinsics are a host module like any other, so a namespaced
+        // factory has to rename the guest's imports of them too.
+        assert!(generated.contains("\"$root\","));
+    }
+
+    #[test]
+    fn test_guest_resources_released_with_instance() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            resources: vec![GoIdentifier::private("counter-handles")],
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("type instanceHandles[T any] struct {"));
+        assert!(generated.contains("counterHandles.release(ctx, i.module)"));
+    }
+
+    #[test]
+    fn test_host_resources_released_with_instance() {
+        let mut interface = logger_interface();
+        interface.resources.push(AnalyzedResource {
+            name: "file".to_string(),
+            go_interface_name: GoIdentifier::public("file"),
+            handles: GoIdentifier::private("file-handles"),
+            methods: vec![],
+        });
+        let generated = generate(&analyzed_imports(vec![interface]));
+
+        assert!(generated.contains("type handleTable[T any] struct {"));
+        assert!(generated.contains("func (t *handleTable[T]) lend(value T) uint32 {"));
+        assert!(generated.contains("type instanceHandles[T any] struct {"));
+        assert!(generated.contains("fileHandles.release(ctx, i.module)"));
+    }
+
+    #[test]
+    fn test_futures() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            futures: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("type Future[T any] struct {"));
+        assert!(generated.contains("func (f *Future[T]) Await(ctx context.Context) (T, error) {"));
+        assert!(generated.contains("func (f *Future[T]) Done() <-chan struct{} {"));
+        assert!(generated.contains("var futureEnds = newInstanceHandles[any]()"));
+        assert!(generated.contains("futureEnds.release(ctx, i.module)"));
+        assert!(generated.contains("type handleTable[T any] struct {"));
+    }
+
+    #[test]
+    fn test_async() {
+        let analyzed_imports = &analyzed_imports(vec![]);
+        let config = FactoryConfig {
+            analyzed_imports,
+            world: "test-world",
+            wasm_var_name: Some(&GoIdentifier::public("test-wasm")),
+            asynchronous: true,
+            ..Default::default()
+        };
+        let generator = FactoryGenerator::new(config);
+        let mut tokens = Tokens::<Go>::new();
+        (&generator).format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
+
+        assert!(generated.contains("type asyncTask struct {"));
+        assert!(generated.contains("func callAsync("));
+        assert!(generated.contains("results, err := lift.Call(ctx, params...)"));
+        assert!(
+            generated.contains(
+                "func taskValue[T any](task *asyncTask, name string, nth int) (T, error) {"
+            )
+        );
+        assert!(generated.contains("NewHostModuleBuilder(options.namespace + \"$root\")"));
+        for intrinsic in [
+            "context-get-0",
+            "context-set-0",
+            "waitable-set-new",
+            "waitable-set-drop",
+            "waitable-join",
+            "waitable-set-poll",
+            "waitable-set-wait",
+            "yield",
+            "subtask-drop",
+        ] {
+            assert!(
+                generated.contains(&format!("Export(\"[{intrinsic}]\")")),
+                "Expected the guest to import `[{intrinsic}]`, got:\n{generated}"
+            );
+        }
+        assert!(!generated.contains("[error-context-new;encoding=utf8]"));
+        assert!(generated.contains("var waitableSets = newInstanceHandles[struct{}]()"));
+        assert!(generated.contains("waitableSets.release(ctx, i.module)"));
     }
 }
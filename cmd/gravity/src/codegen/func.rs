@@ -1,22 +1,27 @@
-use std::mem;
+use std::{collections::BTreeSet, mem};
 
 use genco::prelude::*;
 use wit_bindgen_core::{
-    abi::{Bindgen, Instruction},
-    wit_parser::{Alignment, ArchitectureSize, Resolve, Result_, SizeAlign, Type},
+    abi::{self, Bindgen, Bitcast, Instruction, WasmType},
+    wit_parser::{
+        Alignment, ArchitectureSize, Function, FunctionKind, Handle, Resolve, Result_, SizeAlign,
+        Type, TypeId,
+    },
 };
 
 use crate::{
     go::{
         comment,
         imports::{
-            ERRORS_NEW, WAZERO_API_DECODE_F32, WAZERO_API_DECODE_F64, WAZERO_API_DECODE_I32,
-            WAZERO_API_DECODE_U32, WAZERO_API_ENCODE_F32, WAZERO_API_ENCODE_F64,
-            WAZERO_API_ENCODE_I32,
+            CONTEXT_CONTEXT, CONTEXT_WITH_TIMEOUT, ERRORS_AS, ERRORS_NEW, FMT_SPRINTF, MAPS_KEYS,
+            MATH_FLOAT32_BITS, MATH_FLOAT32_FROM_BITS, MATH_FLOAT64_BITS, MATH_FLOAT64_FROM_BITS,
+            SLICES_SORTED, SLICES_SORTED_FUNC, UNSAFE_SLICE_DATA, UNSAFE_STRING, UTF8_VALID_RUNE,
+            WAZERO_API_DECODE_F32, WAZERO_API_DECODE_F64, WAZERO_API_ENCODE_F32,
+            WAZERO_API_ENCODE_F64,
         },
-        GoIdentifier, GoResult, GoType, Operand,
+        GoIdentifier, GoResult, GoType, Operand, container_payload,
     },
-    resolve_type, resolve_wasm_type,
+    resolve_flags_repr, resolve_return_type_with, resolve_type, resolve_type_with,
 };
 
 /// The direction of a function.
@@ -37,6 +42,40 @@ enum Direction<'a> {
 
 pub struct Func<'a> {
     direction: Direction<'a>,
+    /// The name the function's failures to access guest memory are reported
+    /// under.
+    name: Option<String>,
+    /// The name the function is exported from the core module under, if it
+    /// isn't the function's WIT name.
+    wasm_name: Option<String>,
+    /// Whether the function is called with the component model's async ABI:
+    /// an export is async-lifted and hands its results over through
+    /// `[task-return]`, and an import is async-lowered and writes its
+    /// results through an out pointer.
+    asynchronous: bool,
+    /// Whether `option` and `result` values are carried in the generated
+    /// generic `Option[T]` and `Result[T, E]`.
+    generic_containers: bool,
+    /// The WIT result of the function, which is returned as a Go `error`
+    /// rather than a generic `Result[T, E]` if it's a `result`.
+    wit_result: Option<Type>,
+    /// Whether calls into the guest record a span with the instance's
+    /// tracer, or, for an import, whether the host function's span is marked
+    /// failed when its implementation returns an error.
+    tracing: bool,
+    /// Whether the strings and `list<u8>`s an import is called with are
+    /// views of guest memory rather than copies, for hosts which don't keep
+    /// them past the call.
+    zero_copy: bool,
+    /// Whether an export's arguments are written to the instance's scratch
+    /// buffer rather than to memory allocated for each of them.
+    scratch_buffer: bool,
+    /// Whether the host's copies of the guest's data, and the scratch
+    /// buffer, are zeroed once the call is done with them.
+    zero_buffers: bool,
+    /// The functions the core module exports which an export's body calls.
+    /// See [`Self::exported_function`].
+    exported_functions: BTreeSet<String>,
     args: Vec<String>,
     result: GoResult,
     tmp: usize,
@@ -52,6 +91,16 @@ impl<'a> Func<'a> {
     pub fn export(result: GoResult, sizes: &'a SizeAlign) -> Self {
         Self {
             direction: Direction::Export,
+            name: None,
+            wasm_name: None,
+            asynchronous: false,
+            generic_containers: false,
+            wit_result: None,
+            tracing: false,
+            zero_copy: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+            exported_functions: BTreeSet::new(),
             args: Vec::new(),
             result,
             tmp: 0,
@@ -66,6 +115,16 @@ impl<'a> Func<'a> {
     pub fn import(param_name: &'a GoIdentifier, result: GoResult, sizes: &'a SizeAlign) -> Self {
         Self {
             direction: Direction::Import { param_name },
+            name: None,
+            wasm_name: None,
+            asynchronous: false,
+            generic_containers: false,
+            wit_result: None,
+            tracing: false,
+            zero_copy: false,
+            scratch_buffer: false,
+            zero_buffers: false,
+            exported_functions: BTreeSet::new(),
             args: Vec::new(),
             result,
             tmp: 0,
@@ -76,6 +135,76 @@ impl<'a> Func<'a> {
         }
     }
 
+    /// Report failures to access guest memory under `name`. See
+    /// [`Self::name`].
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Call the function under `name` instead of its WIT name, as the
+    /// functions of exported interfaces are.
+    pub fn with_wasm_name(mut self, name: String) -> Self {
+        self.wasm_name = Some(name);
+        self
+    }
+
+    /// Call the function with the async ABI. See [`Self::asynchronous`].
+    pub fn with_async(mut self) -> Self {
+        self.asynchronous = true;
+        self
+    }
+
+    /// Carry `option` and `result` values in the generated generic
+    /// containers. See [`Self::generic_containers`].
+    pub fn with_generic_containers(mut self, generic_containers: bool) -> Self {
+        self.generic_containers = generic_containers;
+        self
+    }
+
+    /// Trace calls into the guest, or the host function's failures. See
+    /// [`Self::tracing`].
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
+    /// Hand the host views of guest memory. See [`Self::zero_copy`].
+    pub fn with_zero_copy(mut self, zero_copy: bool) -> Self {
+        self.zero_copy = zero_copy;
+        self
+    }
+
+    /// Write arguments to the scratch buffer. See [`Self::scratch_buffer`].
+    pub fn with_scratch_buffer(mut self, scratch_buffer: bool) -> Self {
+        self.scratch_buffer = scratch_buffer;
+        self
+    }
+
+    /// Zero copies of guest data once done with them. See
+    /// [`Self::zero_buffers`].
+    pub fn with_zero_buffers(mut self, zero_buffers: bool) -> Self {
+        self.zero_buffers = zero_buffers;
+        self
+    }
+
+    /// Set the WIT result of the function. See [`Self::wit_result`].
+    pub fn with_wit_result(mut self, wit_result: Option<Type>) -> Self {
+        self.wit_result = wit_result;
+        self
+    }
+
+    /// Whether the `result` type `ty` is carried in a generic `Result[T, E]`,
+    /// rather than being the result the function returns as a Go `error`.
+    fn generic_result(&self, ty: TypeId) -> bool {
+        self.generic_containers || self.wit_result != Some(Type::Id(ty))
+    }
+
+    /// Resolves a WIT type to the Go type the function carries it in.
+    fn resolve_type(&self, typ: &Type, resolve: &Resolve) -> GoType {
+        resolve_type_with(typ, resolve, self.generic_containers)
+    }
+
     fn tmp(&mut self) -> usize {
         let ret = self.tmp;
         self.tmp += 1;
@@ -93,6 +222,248 @@ impl<'a> Func<'a> {
         }
     }
 
+    /// The functions the core module exports which the body calls through
+    /// the instance's `exportedFunctions`.
+    pub fn exported_functions(&self) -> &BTreeSet<String> {
+        &self.exported_functions
+    }
+
+    /// The Go expression for the function the core module exports as `name`.
+    /// Exports take it from the instance's `exportedFunctions`, which looked
+    /// it up when the guest was instantiated, while imports look it up on
+    /// the module calling them.
+    fn exported_function(&mut self, name: &str) -> Tokens<Go> {
+        match self.direction {
+            Direction::Export => {
+                self.exported_functions.insert(name.to_string());
+                let field = crate::exported_function_field(name);
+                quote!(i.exports.$field)
+            }
+            Direction::Import { .. } => quote!(mod.ExportedFunction($(quoted(name.to_string())))),
+        }
+    }
+
+    /// The call allocating `size` bytes aligned to `align` in guest memory
+    /// with the `realloc` export, evaluating to the call's results. An
+    /// export's arguments are carved out of the instance's scratch buffer
+    /// instead, if it has one.
+    fn allocate(&mut self, realloc: &str, align: usize, size: Tokens<Go>) -> Tokens<Go> {
+        let realloc = self.exported_function(realloc);
+        if self.scratch_buffer && matches!(self.direction, Direction::Export) {
+            quote!(i.scratch.alloc(ctx, $realloc, $align, $size))
+        } else {
+            quote!($realloc.Call(ctx, 0, 0, $align, $size))
+        }
+    }
+
+    /// Check that the guest memory access which `failed` reports on went
+    /// through, failing with an error naming the function and the `address`
+    /// otherwise. `access` says what was done, e.g. "read i32 from".
+    fn check_memory(&self, failed: Tokens<Go>, access: &str, address: Tokens<Go>) -> Tokens<Go> {
+        self.check_abi(failed, &format!("failed to {access} memory at %d"), address)
+    }
+
+    /// Check that the string, list or map of `len` elements of `size` bytes
+    /// the guest hands over fits in its memory and within the factory's
+    /// maximum length, before anything is allocated for it.
+    fn check_length(&self, len: &Operand, size: usize) -> Tokens<Go> {
+        let module = self.module_handle();
+        let max = match self.direction {
+            Direction::Export => "i.maxLength",
+            // Host functions are defined in the factory constructor
+            Direction::Import { .. } => "options.maxLength",
+        };
+        self.check_abi(
+            quote!(($max > 0 && $len > $max) || uint64($len)*$size > uint64($module.Memory().Size())),
+            "length %d is out of bounds",
+            quote!($len),
+        )
+    }
+
+    /// Check that `failed` doesn't hold, failing with an `ABIError` whose
+    /// `message`, prefixed with the function's name, is formatted with `arg`
+    /// otherwise.
+    fn check_abi(&self, failed: Tokens<Go>, message: &str, arg: Tokens<Go>) -> Tokens<Go> {
+        let message = match &self.name {
+            Some(name) => format!("{name}: {message}"),
+            None => message.to_string(),
+        };
+        let err = quote!(&ABIError{Message: $FMT_SPRINTF($(quoted(message)), $arg)});
+        match &self.result {
+            GoResult::Anon(GoType::ValueOrError(typ)) => quote! {
+                if $failed {
+                    var zero $(typ.as_ref())
+                    return zero, $err
+                }
+            },
+            GoResult::Anon(GoType::Error) => quote! {
+                if $failed {
+                    return $err
+                }
+            },
+            GoResult::Anon(_) | GoResult::Empty => quote! {
+                $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                if $failed {
+                    panic($err)
+                }
+            },
+        }
+    }
+
+    /// Bound a call into the guest by the instance's call timeout and, when
+    /// the factory limits concurrent calls, wait for a slot. It's emitted
+    /// before anything else, as lowering the arguments already runs the
+    /// guest's `cabi_realloc`, which mustn't outlive the timeout or share the
+    /// instance with another call either.
+    pub fn begin_call(&mut self) {
+        let enter_call = self.enter_call("zero");
+        quote_in! { self.body =>
+            $['\r']
+            if i.callTimeout > 0 {
+                timeoutCtx, cancel := $CONTEXT_WITH_TIMEOUT(ctx, i.callTimeout)
+                defer cancel()
+                ctx = timeoutCtx
+            }
+            $enter_call
+        };
+    }
+
+    /// Wait for a slot when the factory limits concurrent calls into the
+    /// guest, failing with the context's error if it's done first. `default`
+    /// names the zero value returned along with the error.
+    fn enter_call(&self, default: &str) -> Tokens<Go> {
+        let fail = match &self.result {
+            GoResult::Anon(GoType::ValueOrError(typ)) => quote! {
+                var $default $(typ.as_ref())
+                return $default, ctx.Err()
+            },
+            GoResult::Anon(GoType::Error) => quote!(return ctx.Err()),
+            GoResult::Anon(_) | GoResult::Empty => quote! {
+                $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                panic(ctx.Err())
+            },
+        };
+        quote! {
+            if i.calls != nil {
+                select {
+                case i.calls <- struct{}{}:
+                    defer func() { <-i.calls }()
+                case <-ctx.Done():
+                    $fail
+                }
+            }
+        }
+    }
+
+    /// Emit the guest memory write `write`, checking that it went through.
+    fn write_memory(&mut self, write: Tokens<Go>, access: &str, address: Tokens<Go>) {
+        let check = self.check_memory(quote!(!$write), access, address);
+        quote_in! { self.body =>
+            $['\r']
+            $check
+        };
+    }
+
+    /// The Go type a flattened core value is carried in.
+    ///
+    /// Exports pass values to wazero as a `[]uint64`, so floats travel as
+    /// their bits, as produced by `api.EncodeF32` and `api.EncodeF64`, and
+    /// share a type with the 64-bit integers they may be joined with in a
+    /// variant. Host functions are called with native `float32` and
+    /// `float64` values instead.
+    fn core_type(&self, typ: &WasmType) -> GoType {
+        match (&self.direction, typ) {
+            (Direction::Import { .. }, WasmType::F32) => GoType::Float32,
+            (Direction::Import { .. }, WasmType::F64) => GoType::Float64,
+            (_, WasmType::I32) => GoType::Uint32,
+            (
+                _,
+                WasmType::I64
+                | WasmType::F32
+                | WasmType::F64
+                | WasmType::Pointer
+                | WasmType::PointerOrI64
+                | WasmType::Length,
+            ) => GoType::Uint64,
+        }
+    }
+
+    /// Whether floats are carried as native Go floats rather than as bits.
+    fn native_floats(&self) -> bool {
+        matches!(self.direction, Direction::Import { .. })
+    }
+
+    /// The Go expression reinterpreting `operand` as the target of `cast`.
+    fn bitcast(&self, cast: &Bitcast, operand: Tokens<Go>) -> Tokens<Go> {
+        if let Bitcast::Sequence(casts) = cast {
+            return casts
+                .iter()
+                .fold(operand, |operand, cast| self.bitcast(cast, operand));
+        }
+        let Some(target) = bitcast_target(cast) else {
+            return operand;
+        };
+        if self.native_floats() {
+            match cast {
+                Bitcast::F32ToI32 => return quote!($MATH_FLOAT32_BITS($operand)),
+                Bitcast::F32ToI64 => return quote!(uint64($MATH_FLOAT32_BITS($operand))),
+                Bitcast::F64ToI64 => return quote!($MATH_FLOAT64_BITS($operand)),
+                Bitcast::I32ToF32 | Bitcast::I64ToF32 => {
+                    return quote!($MATH_FLOAT32_FROM_BITS(uint32($operand)));
+                }
+                Bitcast::I64ToF64 => return quote!($MATH_FLOAT64_FROM_BITS($operand)),
+                _ => {}
+            }
+        }
+        let typ = self.core_type(&target);
+        quote!($typ($operand))
+    }
+
+    /// Generates an async-lowered import whose parameters the async ABI
+    /// passes through the `params` pointer while the sync one would have
+    /// flattened them, which [`wit_bindgen_core::abi::call`] can't express.
+    /// The result, if any, is written through the `retptr` out pointer.
+    pub fn call_spilled(&mut self, resolve: &Resolve, func: &Function) {
+        self.push_arg("params");
+        let offsets = self.sizes.field_offsets(func.params.iter().map(|p| &p.ty));
+        let mut operands = offsets
+            .into_iter()
+            .map(|(offset, typ)| {
+                let address = match offset.size_wasm32() {
+                    0 => "params".to_string(),
+                    offset => format!("params + {offset}"),
+                };
+                abi::lift_from_memory(resolve, self, Operand::SingleValue(address), typ)
+            })
+            .collect();
+        let mut results = Vec::new();
+        self.emit(
+            resolve,
+            &Instruction::CallInterface {
+                func,
+                async_: false,
+            },
+            &mut operands,
+            &mut results,
+        );
+        if let (Some(typ), Some(value)) = (&func.result, results.pop()) {
+            self.push_arg("retptr");
+            abi::lower_to_memory(
+                resolve,
+                self,
+                Operand::SingleValue("retptr".into()),
+                value,
+                typ,
+            );
+        }
+        self.emit(
+            resolve,
+            &Instruction::Return { func, amt: 0 },
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+    }
+
     pub fn args(&self) -> &[String] {
         &self.args
     }
@@ -105,6 +476,38 @@ impl<'a> Func<'a> {
         &self.body
     }
 
+    /// Declares a variable for each core value a lowered case-based value
+    /// (an option, result or variant) is flattened to, and assigns each case's
+    /// values to them at the end of the case's block.
+    fn join_case_results(
+        &self,
+        tmp: usize,
+        types: &[WasmType],
+        cases: &mut [(&mut Tokens<Go>, &[Operand])],
+        results: &mut Vec<Operand>,
+    ) -> Tokens<Go> {
+        let mut vars: Tokens<Go> = Tokens::new();
+        for (i, typ) in types.iter().enumerate() {
+            let variant = &format!("variant{tmp}_{i}");
+            let typ = self.core_type(typ);
+            results.push(Operand::SingleValue(variant.into()));
+
+            quote_in! { vars =>
+                $['\r']
+                var $variant $typ
+            }
+
+            for (block, case_results) in cases.iter_mut() {
+                let case_result = &case_results[i];
+                quote_in! { **block =>
+                    $['\r']
+                    $variant = $case_result
+                };
+            }
+        }
+        vars
+    }
+
     fn push_arg(&mut self, value: &str) {
         self.args.push(value.into())
     }
@@ -114,6 +517,67 @@ impl<'a> Func<'a> {
     }
 }
 
+/// Builds the Go `error` for the `err` case of a lifted `result<T, E>`.
+///
+/// String payloads become plain errors; any other payload is carried in a
+/// `ResultError[E]` so callers can recover it with `errors.As`.
+fn lift_result_error(err_typ: &Type, err_op: &Operand, resolve: &Resolve) -> Tokens<Go> {
+    match err_typ {
+        Type::String => quote!($ERRORS_NEW($err_op)),
+        typ => {
+            let typ = resolve_type(typ, resolve);
+            quote!(&ResultError[$typ]{Value: $err_op})
+        }
+    }
+}
+
+/// Binds `variantPayload` to the payload of the host's error when lowering
+/// the `err` case of a `result<T, E>`.
+///
+/// String payloads are the error's message. Any other payload has to be
+/// recovered from a `ResultError[E]` in the error's chain, since there is no
+/// way to derive an `E` from an arbitrary Go error.
+fn lower_result_error(err_typ: &Type, err: &str, resolve: &Resolve) -> Tokens<Go> {
+    match err_typ {
+        Type::String => quote!(variantPayload := $err.Error()),
+        typ => {
+            let typ = resolve_type(typ, resolve);
+            quote! {
+                var resultErr *ResultError[$(&typ)]
+                if !$ERRORS_AS($err, &resultErr) {
+                    $(comment(&[
+                        "The error doesn't carry a payload of the WIT error type, so there",
+                        "is nothing to hand back to the guest",
+                    ]))
+                    panic($err)
+                }
+                variantPayload := resultErr.Value
+            }
+        }
+    }
+}
+
+/// The core type a bitcast produces, or `None` if it leaves the value alone.
+fn bitcast_target(cast: &Bitcast) -> Option<WasmType> {
+    match cast {
+        Bitcast::None => None,
+        Bitcast::Sequence(casts) => casts.iter().rev().find_map(bitcast_target),
+        Bitcast::F32ToI32 | Bitcast::I64ToI32 | Bitcast::PToI32 | Bitcast::LToI32 => {
+            Some(WasmType::I32)
+        }
+        Bitcast::I32ToF32 | Bitcast::I64ToF32 => Some(WasmType::F32),
+        Bitcast::I64ToF64 => Some(WasmType::F64),
+        Bitcast::F64ToI64
+        | Bitcast::I32ToI64
+        | Bitcast::F32ToI64
+        | Bitcast::P64ToI64
+        | Bitcast::LToI64 => Some(WasmType::I64),
+        Bitcast::I64ToP64 | Bitcast::PToP64 => Some(WasmType::PointerOrI64),
+        Bitcast::P64ToP | Bitcast::I32ToP | Bitcast::LToP => Some(WasmType::Pointer),
+        Bitcast::PToL | Bitcast::I32ToL | Bitcast::I64ToL => Some(WasmType::Length),
+    }
+}
+
 impl Bindgen for Func<'_> {
     type Operand = Operand;
 
@@ -126,6 +590,8 @@ impl Bindgen for Func<'_> {
     ) {
         let iter_element = "e";
         let iter_base = "base";
+        let map_key = "mapKey";
+        let map_value = "mapValue";
         // Hoist to avoid borrow-checker conflict with `quote_in! { self.body => ... }`.
         let module_handle = self.module_handle();
 
@@ -154,11 +620,17 @@ impl Bindgen for Func<'_> {
                 let operand = &operands[0];
                 match self.direction {
                     Direction::Export => {
+                        let realloc_function = self.exported_function(realloc_name);
+                        let write_string = if self.scratch_buffer {
+                            "i.scratch.writeString"
+                        } else {
+                            "writeString"
+                        };
                         quote_in! { self.body =>
                             $['\r']
                             $memory := i.module.Memory()
-                            $realloc := i.module.ExportedFunction($(quoted(*realloc_name)))
-                            $ptr, $len, $err := writeString(ctx, $operand, $memory, $realloc)
+                            $realloc := $realloc_function
+                            $ptr, $len, $err := $write_string(ctx, $operand, $memory, $realloc)
                             $(match &self.result {
                                 GoResult::Anon(GoType::ValueOrError(typ)) => {
                                     if $err != nil {
@@ -195,39 +667,141 @@ impl Bindgen for Func<'_> {
                 results.push(Operand::SingleValue(ptr.into()));
                 results.push(Operand::SingleValue(len.into()));
             }
+            // An async-lifted export is driven to completion by `callAsync`,
+            // which collects the values it hands to `[task-return]`.
+            Instruction::CallWasm { name, .. } if self.asynchronous => {
+                let name: &str = &self.wasm_name.clone().unwrap_or_else(|| name.to_string());
+                let tmp = self.tmp();
+                let span = &format!("span{tmp}");
+                let started = &format!("started{tmp}");
+                let err = &format!("err{tmp}");
+                let default = &format!("default{tmp}");
+                let lift = &self.exported_function(&format!("[async-lift]{name}"));
+                let callback = &self.exported_function(&format!("[callback][async-lift]{name}"));
+                quote_in! { self.body =>
+                    $['\r']
+                    logDebug(ctx, i.logger, "calling guest function", "function", $(quoted(name)))
+                    $(if self.tracing {
+                        ctx, $span := i.tracer.Start(ctx, $(quoted(name)))
+                        defer $span.End()
+                    })
+                    $started := callStarted(i.metrics, $(quoted(name)))
+                    var task *asyncTask
+                    $err := interceptCall(ctx, i.callInterceptor, $(quoted(name)), func(ctx $CONTEXT_CONTEXT) error {
+                        var err error
+                        task, err = callAsync(ctx, $lift, $callback, $(quoted(name)), $(for op in operands.iter() join (, ) => uint64($op)))
+                        return err
+                    })
+                    $err = callError(ctx, $module_handle, $(quoted(name)), $err)
+                    callReturned(i.metrics, $(quoted(name)), $started, $err)
+                    $(if self.tracing => failSpan($span, $err))
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if $err != nil {
+                                var $default $(typ.as_ref())
+                                return $default, $err
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if $err != nil {
+                                return $err
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if $err != nil {
+                                panic($err)
+                            }
+                        }
+                    })
+                };
+            }
             Instruction::CallWasm { name, .. } => {
+                let name: &str = &self.wasm_name.clone().unwrap_or_else(|| name.to_string());
                 let tmp = self.tmp();
+                let span = &format!("span{tmp}");
+                let started = &format!("started{tmp}");
+                let stack = &format!("stack{tmp}");
                 let raw = &format!("raw{tmp}");
                 let ret = &format!("results{tmp}");
                 let err = &format!("err{tmp}");
                 let default = &format!("default{tmp}");
+                // Without its WIT result, err on the side of calling the
+                // post-return function, which is only looked up.
+                let post_return = match &self.wit_result {
+                    Some(typ) => crate::needs_post_return(typ, resolve),
+                    None => self.result.needs_cleanup(),
+                };
+                let function = &self.exported_function(name);
+                let post_function =
+                    post_return.then(|| self.exported_function(&format!("cabi_post_{name}")));
+                // The arguments and result are passed on a pooled stack, which
+                // fits the at most `MAX_FLAT_PARAMS` flat parameters and one
+                // flat result of a synchronous export, sparing the slices
+                // `Call` allocates. The result stays on it until post-return,
+                // and it goes back to the pool only after that.
+                let stack_size = match self.result {
+                    GoResult::Anon(_) => operands.len().max(1),
+                    GoResult::Empty => operands.len(),
+                };
                 // TODO(#17): Wrapping every argument in `uint64` is bad and we should instead be looking
                 // at the types and converting with proper guards in place
+                let wipe_scratch = self.zero_buffers && self.scratch_buffer;
+                // The arguments are put on the stack by the call itself, as
+                // the results overwrite them, so that retrying it passes the
+                // guest the same ones.
+                let call = quote! {
+                    interceptCall(ctx, i.callInterceptor, $(quoted(name)), func(ctx $CONTEXT_CONTEXT) error {
+                        $(for (idx, op) in operands.iter().enumerate() join ($['\r']) => $raw[$idx] = uint64($op))
+                        return $function.CallWithStack(ctx, $raw)
+                    })
+                };
                 quote_in! { self.body =>
                     $['\r']
+                    $(if wipe_scratch {
+                        $(comment(&["The arguments aren't needed once the call returns"]))
+                        defer i.scratch.wipe(i.module.Memory())
+                    })
+                    $stack := callStacks.Get().(*callStack)
+                    defer callStacks.Put($stack)
+                    $raw := $stack[:$stack_size]
+                    logDebug(ctx, i.logger, "calling guest function", "function", $(quoted(name)))
+                    $(if self.tracing {
+                        ctx, $span := i.tracer.Start(ctx, $(quoted(name)))
+                        defer $span.End()
+                    })
+                    $started := callStarted(i.metrics, $(quoted(name)))
                     $(match &self.result {
                         GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            $raw, $err := $module_handle.ExportedFunction($(quoted(*name))).Call(ctx, $(for op in operands.iter() join (, ) => uint64($op)))
+                            $err := callError(ctx, i.module, $(quoted(name)), $(&call))
+                            callReturned(i.metrics, $(quoted(name)), $started, $err)
+                            $(if self.tracing => failSpan($span, $err))
                             if $err != nil {
                                 var $default $(typ.as_ref())
                                 return $default, $err
                             }
                         }
                         GoResult::Anon(GoType::Error) => {
-                            $raw, $err := $module_handle.ExportedFunction($(quoted(*name))).Call(ctx, $(for op in operands.iter() join (, ) => uint64($op)))
+                            $err := callError(ctx, i.module, $(quoted(name)), $(&call))
+                            callReturned(i.metrics, $(quoted(name)), $started, $err)
+                            $(if self.tracing => failSpan($span, $err))
                             if $err != nil {
                                 return $err
                             }
                         }
                         GoResult::Anon(_) => {
-                            $raw, $err := $module_handle.ExportedFunction($(quoted(*name))).Call(ctx, $(for op in operands.iter() join (, ) => uint64($op)))
+                            $err := callError(ctx, i.module, $(quoted(name)), $(&call))
+                            callReturned(i.metrics, $(quoted(name)), $started, $err)
+                            $(if self.tracing => failSpan($span, $err))
                             $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
                             if $err != nil {
                                 panic($err)
                             }
                         }
                         GoResult::Empty => {
-                            _, $err := $module_handle.ExportedFunction($(quoted(*name))).Call(ctx, $(for op in operands.iter() join (, ) => uint64($op)))
+                            $err := callError(ctx, i.module, $(quoted(name)), $(&call))
+                            callReturned(i.metrics, $(quoted(name)), $started, $err)
+                            $(if self.tracing => failSpan($span, $err))
                             $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
                             if $err != nil {
                                 panic($err)
@@ -235,15 +809,15 @@ impl Bindgen for Func<'_> {
                         }
                     })
 
-                    $(if self.result.needs_cleanup() {
+                    $(if let Some(post_function) = post_function {
                         $(comment(&[
                             "The cleanup via `cabi_post_*` cleans up the memory in the guest. By",
                             "deferring this, we ensure that no memory is corrupted before the function",
                             "is done accessing it."
                         ]))
                         defer func() {
-                            if postFn := $module_handle.ExportedFunction($(quoted(format!("cabi_post_{name}")))); postFn != nil {
-                                if _, err := postFn.Call(ctx, $raw...); err != nil {
+                            if postFn := $post_function; postFn != nil {
+                                if err := postFn.CallWithStack(ctx, $raw[:1]); err != nil {
                                     $(comment(&[
                                         "If we get an error during cleanup, something really bad is",
                                         "going on, so we panic. Also, you can't return the error from",
@@ -265,36 +839,24 @@ impl Bindgen for Func<'_> {
                     GoResult::Anon(_) => results.push(Operand::SingleValue(ret.into())),
                 }
             }
-            Instruction::I32Load8U { offset } => {
+            // The signed load doesn't sign-extend: the `S8FromI32` that follows
+            // narrows the value back down, which yields the same bits.
+            Instruction::I32Load8U { offset } | Instruction::I32Load8S { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read byte from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
                     $value, $ok := $module_handle.Memory().ReadByte(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read byte from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read byte from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read byte from memory"))
-                            }
-                        }
-                    })
+                    $check
                 };
                 results.push(Operand::SingleValue(value.into()));
             }
@@ -357,30 +919,16 @@ impl Bindgen for Func<'_> {
                 let tmp = self.tmp();
                 let ptr = &format!("ptr{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read pointer from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
                     $ptr, $ok := $module_handle.Memory().ReadUint32Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read pointer from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read pointer from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read pointer from memory"))
-                            }
-                        }
-                    })
+                    $check
                 };
                 results.push(Operand::SingleValue(ptr.into()));
             }
@@ -390,30 +938,16 @@ impl Bindgen for Func<'_> {
                 let tmp = self.tmp();
                 let len = &format!("len{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read length from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
                     $len, $ok := $module_handle.Memory().ReadUint32Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read length from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read length from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read length from memory"))
-                            }
-                        }
-                    })
+                    $check
                 };
                 results.push(Operand::SingleValue(len.into()));
             }
@@ -423,30 +957,16 @@ impl Bindgen for Func<'_> {
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read i32 from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
                     $value, $ok := $module_handle.Memory().ReadUint32Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read i32 from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read i32 from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read i32 from memory"))
-                            }
-                        }
-                    })
+                    $check
                 };
                 results.push(Operand::SingleValue(value.into()));
             }
@@ -454,61 +974,101 @@ impl Bindgen for Func<'_> {
                 let tmp = self.tmp();
                 let buf = &format!("buf{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let str = &format!("str{tmp}");
                 let ptr = &operands[0];
                 let len = &operands[1];
                 match self.direction {
                     Direction::Export { .. } => {
+                        let check_length = self.check_length(len, 1);
+                        let check =
+                            self.check_memory(quote!(!$ok), "read bytes from", quote!($ptr));
                         quote_in! { self.body =>
                             $['\r']
+                            $check_length
                             $buf, $ok := i.module.Memory().Read($ptr, $len)
-                            $(match &self.result {
-                                GoResult::Anon(GoType::ValueOrError(typ)) => {
-                                    if !$ok {
-                                        var $default $(typ.as_ref())
-                                        return $default, $ERRORS_NEW("failed to read bytes from memory")
-                                    }
-                                }
-                                GoResult::Anon(GoType::Error) => {
-                                    if !$ok {
-                                        return $ERRORS_NEW("failed to read bytes from memory")
-                                    }
-                                }
-                                GoResult::Anon(_) | GoResult::Empty => {
-                                    $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                                    if !$ok {
-                                        panic($ERRORS_NEW("failed to read bytes from memory"))
-                                    }
-                                }
-                            })
+                            $check
                             $str := string($buf)
                         };
                     }
                     Direction::Import { .. } => {
+                        let check_length = self.check_length(len, 1);
+                        let check =
+                            self.check_memory(quote!(!$ok), "read bytes from", quote!($ptr));
+                        let string = if self.zero_copy {
+                            quote!($UNSAFE_STRING($UNSAFE_SLICE_DATA($buf), len($buf)))
+                        } else {
+                            quote!(string($buf))
+                        };
                         quote_in! { self.body =>
                             $['\r']
+                            $check_length
                             $buf, $ok := mod.Memory().Read($ptr, $len)
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read bytes from memory"))
-                            }
-                            $str := string($buf)
+                            $check
+                            $str := $string
                         };
                     }
                 }
                 results.push(Operand::SingleValue(str.into()));
             }
+            // Every shape of `result` other than the function's own lifts
+            // into a `Result[T, E]`, with a missing payload as an empty struct.
+            Instruction::ResultLift { ty, .. } if self.generic_result(*ty) => {
+                let (err_block, err_results) = self.pop_block();
+                let (ok_block, ok_results) = self.pop_block();
+
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let typ = self.resolve_type(&Type::Id(*ty), resolve);
+                let GoType::GenericResult(ok_typ, err_typ) = &typ else {
+                    unreachable!("generic containers resolve `result` to `Result[T, E]`");
+                };
+                let payload = |results: &[Operand]| match results {
+                    [payload] => quote!($payload),
+                    _ => quote!($("struct{}{}")),
+                };
+                let ok_payload = payload(&ok_results);
+                let err_payload = payload(&err_results);
+                let type_args = quote!($(container_payload(ok_typ)), $(container_payload(err_typ)));
+                let tag = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    var $value $(&typ)
+                    switch $tag {
+                    case 0:
+                        $ok_block
+                        $value = ResultOk[$(&type_args)]($ok_payload)
+                    case 1:
+                        $err_block
+                        $value = ResultErr[$(&type_args)]($err_payload)
+                    default:
+                        $(match &self.result {
+                            GoResult::Anon(GoType::ValueOrError(typ)) => {
+                                var default0 $(typ.as_ref())
+                                return default0, &ABIError{Message: "invalid variant discriminant for expected"}
+                            }
+                            GoResult::Anon(GoType::Error) => {
+                                return &ABIError{Message: "invalid variant discriminant for expected"}
+                            }
+                            GoResult::Anon(_) | GoResult::Empty => {
+                                $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                                panic(&ABIError{Message: "invalid variant discriminant for expected"})
+                            }
+                        })
+                    }
+                };
+
+                results.push(Operand::SingleValue(value.into()));
+            }
             Instruction::ResultLift {
-                result:
-                    Result_ {
-                        ok: Some(typ),
-                        err: Some(Type::String),
-                    },
+                result: Result_ { ok: Some(typ), err },
                 ..
             } => {
                 let (err_block, err_results) = self.pop_block();
-                assert_eq!(err_results.len(), 1);
-                let err_op = &err_results[0];
+                let error = match (err, err_results.as_slice()) {
+                    (Some(err_typ), [err_op]) => lift_result_error(err_typ, err_op, resolve),
+                    (None, []) => quote!($ERRORS_NEW("the result is an error")),
+                    (_, results) => unreachable!("a result's error lifts to {results:?}"),
+                };
 
                 let (ok_block, ok_results) = self.pop_block();
                 assert_eq!(ok_results.len(), 1);
@@ -517,7 +1077,7 @@ impl Bindgen for Func<'_> {
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let err = &format!("err{tmp}");
-                let typ = resolve_type(typ, resolve);
+                let typ = self.resolve_type(typ, resolve);
                 let tag = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
@@ -529,25 +1089,26 @@ impl Bindgen for Func<'_> {
                         $value = $ok_op
                     case 1:
                         $err_block
-                        $err = $ERRORS_NEW($err_op)
+                        $err = $error
                     default:
-                        $err = $ERRORS_NEW("invalid variant discriminant for expected")
+                        $err = &ABIError{Message: "invalid variant discriminant for expected"}
                     }
                 };
 
                 results.push(Operand::MultiValue((value.into(), err.into())));
             }
+            // Without an ok payload only the error is returned, and without
+            // an error payload either the error has nothing to say.
             Instruction::ResultLift {
-                result:
-                    Result_ {
-                        ok: None,
-                        err: Some(Type::String),
-                    },
+                result: Result_ { ok: None, err },
                 ..
             } => {
                 let (err_block, err_results) = self.pop_block();
-                assert_eq!(err_results.len(), 1);
-                let err_op = &err_results[0];
+                let error = match (err, err_results.as_slice()) {
+                    (Some(err_typ), [err_op]) => lift_result_error(err_typ, err_op, resolve),
+                    (None, []) => quote!($ERRORS_NEW("the result is an error")),
+                    (_, results) => unreachable!("a result's error lifts to {results:?}"),
+                };
 
                 let (ok_block, ok_results) = self.pop_block();
                 assert_eq!(ok_results.len(), 0);
@@ -563,46 +1124,161 @@ impl Bindgen for Func<'_> {
                         $ok_block
                     case 1:
                         $err_block
-                        $err = $ERRORS_NEW($err_op)
+                        $err = $error
                     default:
-                        $err = $ERRORS_NEW("invalid variant discriminant for expected")
+                        $err = &ABIError{Message: "invalid variant discriminant for expected"}
                     }
                 };
 
                 results.push(Operand::SingleValue(err.into()));
             }
-            Instruction::ResultLift { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::Return { amt, .. } => {
-                if *amt != 0 {
-                    let operand = &operands[0];
-                    quote_in! { self.body =>
+            // An async-lifted export returns the values its task handed over,
+            // which were lifted as they were, failing with an `ABIError` if
+            // they aren't what it returns.
+            Instruction::Return { func, .. }
+                if self.asynchronous && matches!(self.direction, Direction::Export) =>
+            {
+                let name = &self.wasm_name.clone().unwrap_or_else(|| func.name.clone());
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let result = &format!("result{tmp}");
+                let err = &format!("err{tmp}");
+                let value_of =
+                    |typ: &GoType, nth: usize| quote!(taskValue[$typ](task, $(quoted(name)), $nth));
+                match &self.result {
+                    GoResult::Empty => (),
+                    GoResult::Anon(GoType::ValueOrError(typ)) => quote_in! { self.body =>
+                        $['\r']
+                        $value, $err := $(value_of(typ, 0))
+                        if $err != nil {
+                            return $value, $err
+                        }
+                        $result, $err := $(value_of(&GoType::Error, 1))
+                        if $err != nil {
+                            return $value, $err
+                        }
+                        return $value, $result
+                    },
+                    GoResult::Anon(GoType::Error) => quote_in! { self.body =>
+                        $['\r']
+                        $result, $err := $(value_of(&GoType::Error, 0))
+                        if $err != nil {
+                            return $err
+                        }
+                        return $result
+                    },
+                    GoResult::Anon(GoType::MultiReturn(typs)) => {
+                        let fields = (0..typs.len()).map(|nth| format!("F{nth}"));
+                        quote_in! { self.body =>
+                            $['\r']
+                            $value, $err := $(value_of(&GoType::Tuple(typs.clone()), 0))
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if $err != nil {
+                                panic($err)
+                            }
+                            return $(for field in fields join (, ) => $value.$field)
+                        };
+                    }
+                    GoResult::Anon(typ) => quote_in! { self.body =>
                         $['\r']
-                        return $operand
+                        $value, $err := $(value_of(typ, 0))
+                        $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                        if $err != nil {
+                            panic($err)
+                        }
+                        return $value
+                    },
+                }
+            }
+            // An async-lowered import completes before it returns, so it
+            // writes its results through the out pointer and reports that
+            // it's done instead.
+            Instruction::Return { amt, .. } if self.asynchronous => {
+                if *amt != 0 {
+                    let operand = &operands[0];
+                    let write = match &self.result {
+                        GoResult::Anon(GoType::Uint32) => "WriteUint32Le",
+                        GoResult::Anon(GoType::Uint64) => "WriteUint64Le",
+                        GoResult::Anon(GoType::Float32) => "WriteFloat32Le",
+                        GoResult::Anon(GoType::Float64) => "WriteFloat64Le",
+                        result => unreachable!("core results are numbers, not {result:?}"),
                     };
+                    self.write_memory(
+                        quote!($module_handle.Memory().$write(retptr, $operand)),
+                        "write result to",
+                        quote!(retptr),
+                    );
+                }
+                quote_in! { self.body =>
+                    $['\r']
+                    $(comment(&["The subtask returned, so there's nothing for the guest to wait on"]))
+                    return 2
+                };
+            }
+            // A host function whose core signature has several results
+            // returns each of them.
+            Instruction::Return { amt, .. } if *amt > 1 => {
+                quote_in! { self.body =>
+                    $['\r']
+                    return $(for op in operands.iter() join (, ) => $op)
+                };
+            }
+            Instruction::Return { amt, .. } => {
+                if *amt != 0 {
+                    let operand = &operands[0];
+                    match &self.result {
+                        // A tuple result is lifted into its `TupleN` struct and
+                        // handed back as one Go return value per element.
+                        GoResult::Anon(GoType::MultiReturn(typs)) => {
+                            let fields = (0..typs.len()).map(|nth| format!("F{nth}"));
+                            quote_in! { self.body =>
+                                $['\r']
+                                return $(for field in fields join (, ) => $operand.$field)
+                            };
+                        }
+                        _ => quote_in! { self.body =>
+                            $['\r']
+                            return $operand
+                        },
+                    }
                 }
             }
             Instruction::CallInterface { func, .. } => {
-                let ident = GoIdentifier::public(&func.name);
+                let ident = crate::function_go_name(func);
                 let tmp = self.tmp();
+                // A resource method is called on the value behind its `self`
+                // handle rather than on the interface implementation.
+                let (receiver, operands) = match func.kind {
+                    FunctionKind::Method(_) | FunctionKind::AsyncMethod(_) => {
+                        (Some(&operands[0]), &operands[1..])
+                    }
+                    _ => (None, &operands[..]),
+                };
                 let args = quote!($(for op in operands.iter() join (, ) => $op));
                 let returns = match &func.result {
                     None => GoType::Nothing,
-                    Some(typ) => resolve_type(typ, resolve),
+                    Some(typ) => resolve_return_type_with(typ, resolve, self.generic_containers),
                 };
                 let value = &format!("value{tmp}");
                 let err = &format!("err{tmp}");
                 let ok = &format!("ok{tmp}");
-                // `ValueOrError`/`ValueOrOk` are the only two Go shapes that
-                // come back as multiple return values — everything else (a
-                // primitive, string, slice, pointer-to-T, interface, or a
-                // user-defined record/enum/alias) lands in a single
-                // identifier that subsequent ABI instructions will lower.
+                // `ValueOrError`/`ValueOrOk` come back as a value plus an
+                // error or flag. A tuple's values are gathered into its
+                // `TupleN` struct so `TupleLower` can split them again.
+                // Everything else (a primitive, string, slice, pointer-to-T,
+                // interface, or a user-defined record/enum/alias) lands in a
+                // single identifier that subsequent ABI instructions will
+                // lower.
                 match self.direction {
                     Direction::Export { .. } => todo!("TODO(#10): handle export direction"),
                     Direction::Import { param_name, .. } => {
+                        let param_name = &match receiver {
+                            Some(receiver) => quote!($receiver),
+                            None => quote!($param_name),
+                        };
                         quote_in! { self.body =>
                             $['\r']
-                            $(match returns {
+                            $(match &returns {
                                 GoType::Nothing => $param_name.$ident(ctx, $args),
                                 GoType::Error => $err := $param_name.$ident(ctx, $args),
                                 GoType::ValueOrError(_) => {
@@ -611,11 +1287,23 @@ impl Bindgen for Func<'_> {
                                 GoType::ValueOrOk(_) => {
                                     $value, $ok := $param_name.$ident(ctx, $args)
                                 }
+                                GoType::MultiReturn(typs) => {
+                                    $(for nth in 0..typs.len() join (, ) => $(format!("{value}_{nth}"))) := $param_name.$ident(ctx, $args)
+                                    $value := $(GoType::Tuple(typs.clone())){
+                                        $(for nth in 0..typs.len() join ($['\r']) => $(format!("F{nth}")): $(format!("{value}_{nth}")),)
+                                    }
+                                }
                                 _ => $value := $param_name.$ident(ctx, $args),
                             })
                         }
                     }
                 }
+                if self.tracing && matches!(returns, GoType::Error | GoType::ValueOrError(_)) {
+                    quote_in! { self.body =>
+                        $['\r']
+                        failSpan(span, $err)
+                    };
+                }
                 match returns {
                     GoType::Nothing => (),
                     GoType::Error => {
@@ -643,65 +1331,98 @@ impl Bindgen for Func<'_> {
                 let offset = offset.size_wasm32();
                 let tag = &operands[0];
                 let ptr = &operands[1];
-                if let Operand::Literal(byte) = tag {
-                    quote_in! { self.body =>
-                        $['\r']
-                        $module_handle.Memory().WriteByte($ptr+$offset, $byte)
-                    }
+                let write = if let Operand::Literal(byte) = tag {
+                    quote!($module_handle.Memory().WriteByte($ptr+$offset, $byte))
                 } else {
-                    let tmp = self.tmp();
-                    let byte = format!("byte{tmp}");
-                    quote_in! { self.body =>
-                        $['\r']
-                        var $(&byte) uint8
-                        switch $tag {
-                        case 0:
-                            $(&byte) = 0
-                        case 1:
-                            $(&byte) = 1
-                        default:
-                            $(comment(["TODO(#8): Return an error if the return type allows it"]))
-                            panic($ERRORS_NEW("invalid int8 value encountered"))
-                        }
-                        $module_handle.Memory().WriteByte($ptr+$offset, $byte)
-                    }
-                }
+                    // Bytes come from bools, discriminants and `u8`/`s8` values
+                    // alike, so keep the low eight bits rather than checking for
+                    // 0 or 1.
+                    quote!($module_handle.Memory().WriteByte($ptr+$offset, uint8($tag)))
+                };
+                self.write_memory(write, "write byte to", quote!($ptr+$offset));
             }
             Instruction::I32Store { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let tag = &operands[0];
                 let ptr = &operands[1];
-                quote_in! { self.body =>
-                    $['\r']
-                    $module_handle.Memory().WriteUint32Le($ptr+$offset, $tag)
-                }
+                self.write_memory(
+                    quote!($module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($tag))),
+                    "write i32 to",
+                    quote!($ptr+$offset),
+                );
             }
             Instruction::LengthStore { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let len = &operands[0];
                 let ptr = &operands[1];
-                quote_in! { self.body =>
-                    $['\r']
-                    $module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($len))
-                }
+                self.write_memory(
+                    quote!($module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($len))),
+                    "write length to",
+                    quote!($ptr+$offset),
+                );
             }
             Instruction::PointerStore { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let value = &operands[0];
                 let ptr = &operands[1];
+                self.write_memory(
+                    quote!($module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($value))),
+                    "write pointer to",
+                    quote!($ptr+$offset),
+                );
+            }
+            Instruction::ResultLower {
+                result,
+                ty,
+                results: result_types,
+            } if self.generic_result(*ty) => {
+                let (mut err_block, err_results) = self.pop_block();
+                let (mut ok_block, ok_results) = self.pop_block();
+
+                let tmp = self.tmp();
+                let vars = self.join_case_results(
+                    tmp,
+                    result_types,
+                    &mut [
+                        (&mut ok_block, ok_results.as_slice()),
+                        (&mut err_block, err_results.as_slice()),
+                    ],
+                    results,
+                );
+
+                let Operand::SingleValue(value) = &operands[0] else {
+                    unreachable!("ResultLower expects a single `Result[T, E]` operand");
+                };
+                // Only a case with a payload reads `variantPayload`.
+                let bind = |has_payload: bool, getter: &str| {
+                    if has_payload {
+                        quote!(variantPayload, _ := $value.$getter())
+                    } else {
+                        quote!()
+                    }
+                };
+                let ok_payload = bind(result.ok.is_some(), "Ok");
+                let err_payload = bind(result.err.is_some(), "Err");
                 quote_in! { self.body =>
                     $['\r']
-                    $module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($value))
-                }
+                    $vars
+                    if $value.IsErr() {
+                        $err_payload
+                        $err_block
+                    } else {
+                        $ok_payload
+                        $ok_block
+                    }
+                };
             }
             Instruction::ResultLower {
                 result:
                     Result_ {
                         ok: Some(_),
-                        err: Some(Type::String),
+                        err: err_typ,
                     },
                 ..
             } => {
@@ -717,10 +1438,14 @@ impl Bindgen for Func<'_> {
                     ),
                     Operand::MultiValue(bindings) => bindings,
                 };
+                let err_payload = match err_typ {
+                    Some(err_typ) => lower_result_error(err_typ, err, resolve),
+                    None => quote!(),
+                };
                 quote_in! { self.body =>
                     $['\r']
                     if $err != nil {
-                        variantPayload := $err.Error()
+                        $err_payload
                         $err_block
                     } else {
                         variantPayload := $ok
@@ -729,27 +1454,39 @@ impl Bindgen for Func<'_> {
                 };
             }
             Instruction::ResultLower {
-                result:
-                    Result_ {
-                        ok: None,
-                        err: Some(Type::String),
-                    },
+                result: Result_ { ok: None, err },
+                results: result_types,
                 ..
             } => {
-                let (err, _) = self.pop_block();
-                let (ok, _) = self.pop_block();
+                let (mut err_block, err_results) = self.pop_block();
+                let (mut ok_block, ok_results) = self.pop_block();
+                let tmp = self.tmp();
+                let vars = self.join_case_results(
+                    tmp,
+                    result_types,
+                    &mut [
+                        (&mut ok_block, ok_results.as_slice()),
+                        (&mut err_block, err_results.as_slice()),
+                    ],
+                    results,
+                );
+
                 let err_result = &operands[0];
+                let err_payload = match err {
+                    Some(err_typ) => lower_result_error(err_typ, &err_result.as_string(), resolve),
+                    None => quote!(),
+                };
                 quote_in! { self.body =>
                     $['\r']
+                    $vars
                     if $err_result != nil {
-                        variantPayload := $err_result.Error()
-                        $err
+                        $err_payload
+                        $err_block
                     } else {
-                        $ok
+                        $ok_block
                     }
                 };
             }
-            Instruction::ResultLower { .. } => todo!("implement instruction: {inst:?}"),
             Instruction::OptionLift { payload, .. } => {
                 let (some, some_results) = self.blocks.pop().unwrap();
                 let (_none, _) = self.blocks.pop().unwrap();
@@ -757,18 +1494,29 @@ impl Bindgen for Func<'_> {
 
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
-                let inner_typ = resolve_type(payload, resolve);
+                let inner_typ = self.resolve_type(payload, resolve);
                 let op = &operands[0];
 
-                quote_in! { self.body =>
-                    $['\r']
-                    var $result *$inner_typ
-                    if $op != 0 {
-                        $some
-                        someValue$tmp := $some_result
-                        $result = &someValue$tmp
-                    }
-                };
+                if self.generic_containers {
+                    quote_in! { self.body =>
+                        $['\r']
+                        var $result Option[$(container_payload(&inner_typ))]
+                        if $op != 0 {
+                            $some
+                            $result = OptionSome[$(container_payload(&inner_typ))]($some_result)
+                        }
+                    };
+                } else {
+                    quote_in! { self.body =>
+                        $['\r']
+                        var $result *$inner_typ
+                        if $op != 0 {
+                            $some
+                            someValue$tmp := $some_result
+                            $result = &someValue$tmp
+                        }
+                    };
+                }
 
                 results.push(Operand::SingleValue(result.into()));
             }
@@ -780,43 +1528,42 @@ impl Bindgen for Func<'_> {
                 let (mut none_block, none_results) = self.pop_block();
 
                 let tmp = self.tmp();
+                let vars = self.join_case_results(
+                    tmp,
+                    result_types,
+                    &mut [
+                        (&mut some_block, some_results.as_slice()),
+                        (&mut none_block, none_results.as_slice()),
+                    ],
+                    results,
+                );
 
-                let mut vars: Tokens<Go> = Tokens::new();
-                for i in 0..result_types.len() {
-                    let variant = &format!("variant{tmp}_{i}");
-                    let typ = resolve_wasm_type(&result_types[i]);
-                    results.push(Operand::SingleValue(variant.into()));
-
-                    quote_in! { vars =>
-                        $['\r']
-                        var $variant $typ
-                    }
-
-                    let some_result = &some_results[i];
-                    let none_result = &none_results[i];
-                    quote_in! { some_block =>
+                let Operand::SingleValue(value) = &operands[0] else {
+                    unreachable!("OptionLower expects a single `*T` or `Option[T]` operand");
+                };
+                if self.generic_containers {
+                    let is_some = &format!("isSome{tmp}");
+                    quote_in! { self.body =>
                         $['\r']
-                        $variant = $some_result
+                        $vars
+                        if variantPayload, $is_some := $value.Get(); $is_some {
+                            $some_block
+                        } else {
+                            $none_block
+                        }
                     };
-                    quote_in! { none_block =>
+                } else {
+                    quote_in! { self.body =>
                         $['\r']
-                        $variant = $none_result
+                        $vars
+                        if $value == nil {
+                            $none_block
+                        } else {
+                            variantPayload := *$value
+                            $some_block
+                        }
                     };
                 }
-
-                let Operand::SingleValue(value) = &operands[0] else {
-                    unreachable!("OptionLower expects a single `*T` operand");
-                };
-                quote_in! { self.body =>
-                    $['\r']
-                    $vars
-                    if $value == nil {
-                        $none_block
-                    } else {
-                        variantPayload := *$value
-                        $some_block
-                    }
-                };
             }
             Instruction::RecordLower { record, .. } => {
                 let tmp = self.tmp();
@@ -831,9 +1578,12 @@ impl Bindgen for Func<'_> {
                     results.push(Operand::SingleValue(var.into()))
                 }
             }
-            Instruction::RecordLift { record, name, .. } => {
+            Instruction::RecordLift { record, ty, .. } => {
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
+                // The WIT name may collide with a type from another
+                // interface, in which case the Go type is qualified.
+                let name = crate::qualified_type_name(*ty, resolve);
                 let fields = record
                     .fields
                     .iter()
@@ -842,7 +1592,7 @@ impl Bindgen for Func<'_> {
 
                 quote_in! {self.body =>
                     $['\r']
-                    $value := $(GoIdentifier::public(*name)){
+                    $value := $(GoIdentifier::public(&name)){
                         $(for (name, op) in fields join ($['\r']) => $name: $op,)
                     }
                 };
@@ -868,12 +1618,198 @@ impl Bindgen for Func<'_> {
                 let operand = &operands[0];
                 let size = self.sizes.size(element).size_wasm32();
                 let align = self.sizes.align(element).align_wasm32();
+                let allocate = self.allocate(realloc_name, align, quote!($len * $size));
+
+                // Bytes have the same layout in Go and in guest memory, so they
+                // are copied over in one go instead of element by element.
+                let copy_elements = if matches!(element, Type::U8) {
+                    self.check_memory(
+                        quote!(!$module_handle.Memory().Write(uint32($ptr), $vec)),
+                        "write bytes to",
+                        quote!($ptr),
+                    )
+                } else {
+                    // A nested list lowers its own loop inside `body`, which
+                    // re-declares `idx`, `e` and `base` in the inner scope, so
+                    // each level only ever sees its own element.
+                    quote! {
+                        for idx := uint64(0); idx < $len; idx++ {
+                            $iter_element := $vec[idx]
+                            $iter_base := uint32($ptr + uint64(idx) * uint64($size))
+                            $body
+                        }
+                    }
+                };
 
                 quote_in! { self.body =>
                     $['\r']
                     $vec := $operand
                     $len := uint64(len($vec))
-                    $result, $err := $module_handle.ExportedFunction($(quoted(*realloc_name))).Call(ctx, 0, 0, $align, $len * $size)
+                    $result, $err := $allocate
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if $err != nil {
+                                var $default $(typ.as_ref())
+                                return $default, $err
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if $err != nil {
+                                return $err
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if $err != nil {
+                                panic($err)
+                            }
+                        }
+                    })
+                    $ptr := $result[0]
+                    $copy_elements
+                };
+                results.push(Operand::SingleValue(ptr.into()));
+                results.push(Operand::SingleValue(len.into()));
+            }
+            Instruction::ListLift { element, .. } => {
+                let (body, body_results) = self.pop_block();
+                let tmp = self.tmp();
+                let size = self.sizes.size(element).size_wasm32();
+                let len = &format!("len{tmp}");
+                let base = &format!("base{tmp}");
+                let result = &format!("result{tmp}");
+                let idx = &format!("idx{tmp}");
+
+                let base_operand = &operands[0];
+                let len_operand = &operands[1];
+                let body_result = &body_results[0];
+
+                let typ = self.resolve_type(element, resolve);
+                let check_length = self.check_length(len_operand, size);
+
+                if matches!(element, Type::U8) {
+                    let buf = &format!("buf{tmp}");
+                    let ok = &format!("ok{tmp}");
+                    let check =
+                        self.check_memory(quote!(!$ok), "read bytes from", quote!($base_operand));
+                    quote_in! { self.body =>
+                        $['\r']
+                        $check_length
+                        $buf, $ok := $module_handle.Memory().Read($base_operand, $len_operand)
+                        $check
+                    };
+                    if self.zero_copy && matches!(self.direction, Direction::Import { .. }) {
+                        quote_in! { self.body =>
+                            $['\r']
+                            $result := $buf
+                        };
+                    } else {
+                        // `Read` returns a view of guest memory, which the guest
+                        // is free to reuse, so the bytes are copied out in one go.
+                        quote_in! { self.body =>
+                            $['\r']
+                            $result := make([]byte, len($buf))
+                            copy($result, $buf)
+                        };
+                        // A host function is done with its arguments once it
+                        // returns, where an export's result is the caller's.
+                        if self.zero_buffers && matches!(self.direction, Direction::Import { .. }) {
+                            quote_in! { self.body =>
+                                $['\r']
+                                defer clear($result)
+                            };
+                        }
+                    }
+                } else {
+                    quote_in! { self.body =>
+                        $['\r']
+                        $check_length
+                        $base := $base_operand
+                        $len := $len_operand
+                        $result := make([]$typ, $len)
+                        for $idx := uint32(0); $idx < $len; $idx++ {
+                            base := $base + $idx * $size
+                            $body
+                            $result[$idx] = $body_result
+                        }
+                    }
+                }
+                results.push(Operand::SingleValue(result.into()));
+            }
+            Instruction::IterMapKey { .. } => results.push(Operand::SingleValue(map_key.into())),
+            Instruction::IterMapValue { .. } => {
+                results.push(Operand::SingleValue(map_value.into()))
+            }
+            // Without `realloc` the map would be lent to the guest out of the
+            // caller's memory, which the host's isn't, so only an empty map,
+            // which takes none, can be passed.
+            Instruction::MapLower { realloc: None, .. } => {
+                self.pop_block();
+                let tmp = self.tmp();
+                let ptr = &format!("ptr{tmp}");
+                let len = &format!("len{tmp}");
+                let operand = &operands[0];
+                let check = self.check_abi(
+                    quote!($len > 0),
+                    "cannot lend a map of %d entries to the guest",
+                    quote!($len),
+                );
+                quote_in! { self.body =>
+                    $['\r']
+                    $ptr := uint64(0)
+                    $len := uint64(len($operand))
+                    $check
+                };
+                results.push(Operand::SingleValue(ptr.into()));
+                results.push(Operand::SingleValue(len.into()));
+            }
+            Instruction::MapLower {
+                key,
+                value,
+                realloc: Some(realloc_name),
+                ..
+            } => {
+                let (body, _) = self.pop_block();
+                let tmp = self.tmp();
+                let map = &format!("map{tmp}");
+                let keys = &format!("keys{tmp}");
+                let result = &format!("result{tmp}");
+                let err = &format!("err{tmp}");
+                let default = &format!("default{tmp}");
+                let ptr = &format!("ptr{tmp}");
+                let len = &format!("len{tmp}");
+                let operand = &operands[0];
+                let entry = self.sizes.record([*key, *value]);
+                let size = entry.size.size_wasm32();
+                let align = entry.align.align_wasm32();
+                // Go randomises map iteration, so entries are written in key
+                // order to give the guest the same list for the same map. Map
+                // keys are primitive types, which all are ordered in Go but
+                // `bool`.
+                let keys_sorted = match key {
+                    Type::Bool => quote! {
+                        $SLICES_SORTED_FUNC($MAPS_KEYS($map), func(a, b bool) int {
+                            $(comment(&["false goes before true"]))
+                            switch {
+                            case a == b:
+                                return 0
+                            case b:
+                                return -1
+                            default:
+                                return 1
+                            }
+                        })
+                    },
+                    _ => quote!($SLICES_SORTED($MAPS_KEYS($map))),
+                };
+                let allocate = self.allocate(realloc_name, align, quote!($len * $size));
+
+                quote_in! { self.body =>
+                    $['\r']
+                    $map := $operand
+                    $keys := $keys_sorted
+                    $len := uint64(len($keys))
+                    $result, $err := $allocate
                     $(match &self.result {
                         GoResult::Anon(GoType::ValueOrError(typ)) => {
                             if $err != nil {
@@ -895,7 +1831,8 @@ impl Bindgen for Func<'_> {
                     })
                     $ptr := $result[0]
                     for idx := uint64(0); idx < $len; idx++ {
-                        $iter_element := $vec[idx]
+                        $map_key := $keys[idx]
+                        $map_value := $map[$map_key]
                         $iter_base := uint32($ptr + uint64(idx) * uint64($size))
                         $body
                     }
@@ -903,10 +1840,10 @@ impl Bindgen for Func<'_> {
                 results.push(Operand::SingleValue(ptr.into()));
                 results.push(Operand::SingleValue(len.into()));
             }
-            Instruction::ListLift { element, .. } => {
+            Instruction::MapLift { key, value, .. } => {
                 let (body, body_results) = self.pop_block();
                 let tmp = self.tmp();
-                let size = self.sizes.size(element).size_wasm32();
+                let size = self.sizes.record([*key, *value]).size.size_wasm32();
                 let len = &format!("len{tmp}");
                 let base = &format!("base{tmp}");
                 let result = &format!("result{tmp}");
@@ -914,19 +1851,25 @@ impl Bindgen for Func<'_> {
 
                 let base_operand = &operands[0];
                 let len_operand = &operands[1];
-                let body_result = &body_results[0];
+                let key_result = &body_results[0];
+                let value_result = &body_results[1];
 
-                let typ = resolve_type(element, resolve);
+                let typ = GoType::Map(
+                    Box::new(self.resolve_type(key, resolve)),
+                    Box::new(self.resolve_type(value, resolve)),
+                );
+                let check_length = self.check_length(len_operand, size);
 
                 quote_in! { self.body =>
                     $['\r']
+                    $check_length
                     $base := $base_operand
                     $len := $len_operand
-                    $result := make([]$typ, $len)
+                    $result := make($typ, $len)
                     for $idx := uint32(0); $idx < $len; $idx++ {
                         base := $base + $idx * $size
                         $body
-                        $result[$idx] = $body_result
+                        $result[$key_result] = $value_result
                     }
                 }
                 results.push(Operand::SingleValue(result.into()));
@@ -948,7 +1891,7 @@ impl Bindgen for Func<'_> {
 
                 for (i, typ) in result_types.iter().enumerate() {
                     let variant_item = &format!("variant{tmp}_{i}");
-                    let typ = resolve_wasm_type(typ);
+                    let typ = self.core_type(typ);
                     quote_in! { self.body =>
                         $['\r']
                         var $variant_item $typ
@@ -1018,14 +1961,14 @@ impl Bindgen for Func<'_> {
                             $(match &self.result {
                                 GoResult::Anon(GoType::ValueOrError(typ)) => {
                                     var $default $(typ.as_ref())
-                                    return $default, $ERRORS_NEW("invalid variant type provided")
+                                    return $default, &ABIError{Message: "invalid variant type provided"}
                                 }
                                 GoResult::Anon(GoType::Error) => {
-                                    return $ERRORS_NEW("invalid variant type provided")
+                                    return &ABIError{Message: "invalid variant type provided"}
                                 }
                                 GoResult::Anon(_) | GoResult::Empty => {
                                     $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                                    panic($ERRORS_NEW("invalid variant type provided"))
+                                    panic(&ABIError{Message: "invalid variant type provided"})
                                 }
                             })
                     }
@@ -1052,138 +1995,195 @@ impl Bindgen for Func<'_> {
                     switch $value {
                     $cases
                     default:
-                        panic($ERRORS_NEW("invalid enum type provided"))
+                        panic(&ABIError{Message: "invalid enum type provided"})
                     }
                 };
 
                 results.push(Operand::SingleValue(enum_tmp.to_string()));
             }
-            Instruction::Bitcasts { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::I32Load8S { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::I32Load16U { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::I32Load16S { .. } => todo!("implement instruction: {inst:?}"),
+            // Variant cases with differing payloads share joined core slots.
+            // Since floats are carried as their bits, every cast is a plain
+            // integer conversion; narrowing keeps the low bits as required.
+            Instruction::Bitcasts { casts } => {
+                for (cast, operand) in casts.iter().zip(operands.iter()) {
+                    if bitcast_target(cast).is_none() {
+                        results.push(operand.clone());
+                        continue;
+                    }
+                    let tmp = self.tmp();
+                    let value = &format!("value{tmp}");
+                    let expr = self.bitcast(cast, quote!($operand));
+                    quote_in! { self.body =>
+                        $['\r']
+                        $value := $expr
+                    };
+                    results.push(Operand::SingleValue(value.into()));
+                }
+            }
+            // As with the 8-bit loads, `S16FromI32` narrows the value so there's
+            // no need to sign-extend here.
+            Instruction::I32Load16U { offset } | Instruction::I32Load16S { offset } => {
+                // TODO(#58): Support additional ArchitectureSize
+                let offset = offset.size_wasm32();
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let ok = &format!("ok{tmp}");
+                let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read i16 from",
+                    quote!(uint32($operand + $offset)),
+                );
+                quote_in! { self.body =>
+                    $['\r']
+                    $value, $ok := $module_handle.Memory().ReadUint16Le(uint32($operand + $offset))
+                    $check
+                };
+                results.push(Operand::SingleValue(value.into()));
+            }
             Instruction::I64Load { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read i64 from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
                     $value, $ok := $module_handle.Memory().ReadUint64Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read i64 from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read i64 from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read i64 from memory"))
-                            }
-                        }
-                    })
+                    $check
                 };
                 results.push(Operand::SingleValue(value.into()));
             }
             Instruction::F32Load { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
+                let read = if self.native_floats() {
+                    "ReadFloat32Le"
+                } else {
+                    "ReadUint32Le"
+                };
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read f32 from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
-                    $value, $ok := $module_handle.Memory().ReadUint64Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read f32 from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read f32 from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read f32 from memory"))
-                            }
-                        }
-                    })
+                    $value, $ok := $module_handle.Memory().$read(uint32($operand + $offset))
+                    $check
                 };
                 results.push(Operand::SingleValue(value.into()));
             }
             Instruction::F64Load { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
+                let read = if self.native_floats() {
+                    "ReadFloat64Le"
+                } else {
+                    "ReadUint64Le"
+                };
                 let tmp = self.tmp();
                 let value = &format!("value{tmp}");
                 let ok = &format!("ok{tmp}");
-                let default = &format!("default{tmp}");
                 let operand = &operands[0];
+                let check = self.check_memory(
+                    quote!(!$ok),
+                    "read f64 from",
+                    quote!(uint32($operand + $offset)),
+                );
                 quote_in! { self.body =>
                     $['\r']
-                    $value, $ok := $module_handle.Memory().ReadUint64Le(uint32($operand + $offset))
-                    $(match &self.result {
-                        GoResult::Anon(GoType::ValueOrError(typ)) => {
-                            if !$ok {
-                                var $default $(typ.as_ref())
-                                return $default, $ERRORS_NEW("failed to read f64 from memory")
-                            }
-                        }
-                        GoResult::Anon(GoType::Error) => {
-                            if !$ok {
-                                return $ERRORS_NEW("failed to read f64 from memory")
-                            }
-                        }
-                        GoResult::Anon(_) | GoResult::Empty => {
-                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                            if !$ok {
-                                panic($ERRORS_NEW("failed to read f64 from memory"))
-                            }
-                        }
-                    })
+                    $value, $ok := $module_handle.Memory().$read(uint32($operand + $offset))
+                    $check
                 };
                 results.push(Operand::SingleValue(value.into()));
             }
-            Instruction::I32Store16 { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::I64Store { .. } => todo!("implement instruction: {inst:?}"),
+            Instruction::I32Store16 { offset } => {
+                // TODO(#58): Support additional ArchitectureSize
+                let offset = offset.size_wasm32();
+                let tag = &operands[0];
+                let ptr = &operands[1];
+                self.write_memory(
+                    quote!($module_handle.Memory().WriteUint16Le($ptr+$offset, uint16($tag))),
+                    "write i16 to",
+                    quote!($ptr+$offset),
+                );
+            }
+            Instruction::I64Store { offset } => {
+                // TODO(#58): Support additional ArchitectureSize
+                let offset = offset.size_wasm32();
+                let tag = &operands[0];
+                let ptr = &operands[1];
+                self.write_memory(
+                    quote!($module_handle.Memory().WriteUint64Le($ptr+$offset, uint64($tag))),
+                    "write i64 to",
+                    quote!($ptr+$offset),
+                );
+            }
             Instruction::F32Store { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let tag = &operands[0];
                 let ptr = &operands[1];
-                quote_in! { self.body =>
-                    $['\r']
-                    $module_handle.Memory().WriteUint64Le($ptr+$offset, $tag)
-                }
+                // `api.EncodeF32` widens to a uint64, but an f32 only occupies
+                // four bytes; writing eight would clobber the next value.
+                let write = if self.native_floats() {
+                    quote!($module_handle.Memory().WriteFloat32Le($ptr+$offset, $tag))
+                } else {
+                    quote!($module_handle.Memory().WriteUint32Le($ptr+$offset, uint32($tag)))
+                };
+                self.write_memory(write, "write f32 to", quote!($ptr+$offset));
             }
             Instruction::F64Store { offset } => {
                 // TODO(#58): Support additional ArchitectureSize
                 let offset = offset.size_wasm32();
                 let tag = &operands[0];
                 let ptr = &operands[1];
+                let write = if self.native_floats() {
+                    quote!($module_handle.Memory().WriteFloat64Le($ptr+$offset, $tag))
+                } else {
+                    quote!($module_handle.Memory().WriteUint64Le($ptr+$offset, $tag))
+                };
+                self.write_memory(write, "write f64 to", quote!($ptr+$offset));
+            }
+            // A Go `rune` can hold surrogates and values past U+10FFFF, neither
+            // of which is a valid `char`.
+            Instruction::I32FromChar => {
+                let tmp = self.tmp();
+                let result = &format!("result{tmp}");
+                let default = &format!("default{tmp}");
+                let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $module_handle.Memory().WriteUint64Le($ptr+$offset, $tag)
-                }
+                    $result := $operand
+                    if !$UTF8_VALID_RUNE($result) {
+                        $(match &self.result {
+                            GoResult::Anon(GoType::ValueOrError(typ)) => {
+                                var $default $(typ.as_ref())
+                                return $default, &ABIError{Message: "invalid char: not a Unicode scalar value"}
+                            }
+                            GoResult::Anon(GoType::Error) => {
+                                return &ABIError{Message: "invalid char: not a Unicode scalar value"}
+                            }
+                            GoResult::Anon(_) | GoResult::Empty => {
+                                $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                                panic(&ABIError{Message: "invalid char: not a Unicode scalar value"})
+                            }
+                        })
+                    }
+                };
+                results.push(Operand::SingleValue(format!("uint32({result})")));
             }
-            Instruction::I32FromChar => todo!("implement instruction: {inst:?}"),
             Instruction::I64FromU64 => {
                 // I64FromU64 is a no-op reinterpretation (same 64-bit value,
                 // different signedness). Use uint64() identity cast — int64()
@@ -1198,28 +2198,23 @@ impl Bindgen for Func<'_> {
                 }
                 results.push(Operand::SingleValue(value.into()));
             }
+            // Core values are carried unsigned (`uint32` for i32, `uint64` for
+            // i64), matching the unsigned lowerings. Go converts a signed
+            // integer to a wider unsigned one by sign-extending it, which is
+            // exactly the canonical ABI's lowering, and `api.EncodeI32` would
+            // hand back a `uint64` where an i32 is expected.
             Instruction::I64FromS64 => {
                 let tmp = self.tmp();
                 let value = format!("value{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $(&value) := $operand
+                    $(&value) := uint64($operand)
                 }
                 results.push(Operand::SingleValue(value.into()));
             }
-            Instruction::I32FromS32 => {
-                let tmp = self.tmp();
-                let value = format!("value{tmp}");
-                let operand = &operands[0];
-                quote_in! { self.body =>
-                    $['\r']
-                    $(&value) := $WAZERO_API_ENCODE_I32($operand)
-                }
-                results.push(Operand::SingleValue(value))
-            }
-            // All of these values should fit in Go's `int32` type which allows a safe cast
-            Instruction::I32FromU16
+            Instruction::I32FromS32
+            | Instruction::I32FromU16
             | Instruction::I32FromS16
             | Instruction::I32FromU8
             | Instruction::I32FromS8 => {
@@ -1228,10 +2223,14 @@ impl Bindgen for Func<'_> {
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $(&value) := $WAZERO_API_ENCODE_I32(int32($operand))
+                    $(&value) := uint32($operand)
                 }
                 results.push(Operand::SingleValue(value))
             }
+            // Host functions return native floats, so there's nothing to encode.
+            Instruction::CoreF32FromF32 | Instruction::CoreF64FromF64 if self.native_floats() => {
+                results.push(operands[0].clone());
+            }
             Instruction::CoreF32FromF32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
@@ -1252,47 +2251,47 @@ impl Bindgen for Func<'_> {
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            // TODO: Validate the Go cast truncates the upper bits in the I32
+            // Converting between Go integer types keeps the low bits, which is
+            // exactly the narrowing these lifts need. Plain casts also accept
+            // both the `uint32` of a memory load and the `uint64` of a call
+            // result, where `api.DecodeI32`/`api.DecodeU32` only take the latter.
             Instruction::S8FromI32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := int8($WAZERO_API_DECODE_I32($operand))
+                    $result := int8($operand)
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            // TODO: Validate the Go cast truncates the upper bits in the I32
             Instruction::U8FromI32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := uint8($WAZERO_API_DECODE_U32($operand))
+                    $result := uint8($operand)
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            // TODO: Validate the Go cast truncates the upper bits in the I32
             Instruction::S16FromI32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := int16($WAZERO_API_DECODE_I32($operand))
+                    $result := int16($operand)
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            // TODO: Validate the Go cast truncates the upper bits in the I32
             Instruction::U16FromI32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := uint16($WAZERO_API_DECODE_U32($operand))
+                    $result := uint16($operand)
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
@@ -1302,11 +2301,20 @@ impl Bindgen for Func<'_> {
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := $WAZERO_API_DECODE_I32($operand)
+                    $result := int32($operand)
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            Instruction::S64FromI64 => todo!("implement instruction: {inst:?}"),
+            Instruction::S64FromI64 => {
+                let tmp = self.tmp();
+                let value = format!("value{tmp}");
+                let operand = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    $(&value) := int64($operand)
+                }
+                results.push(Operand::SingleValue(value));
+            }
             Instruction::U64FromI64 => {
                 let tmp = self.tmp();
                 let value = format!("value{tmp}");
@@ -1317,14 +2325,43 @@ impl Bindgen for Func<'_> {
                 }
                 results.push(Operand::SingleValue(value.into()));
             }
-            Instruction::CharFromI32 => todo!("implement instruction: {inst:?}"),
+            Instruction::CharFromI32 => {
+                let tmp = self.tmp();
+                let result = &format!("result{tmp}");
+                let default = &format!("default{tmp}");
+                let operand = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    $result := rune($operand)
+                    if !$UTF8_VALID_RUNE($result) {
+                        $(match &self.result {
+                            GoResult::Anon(GoType::ValueOrError(typ)) => {
+                                var $default $(typ.as_ref())
+                                return $default, &ABIError{Message: "invalid char: not a Unicode scalar value"}
+                            }
+                            GoResult::Anon(GoType::Error) => {
+                                return &ABIError{Message: "invalid char: not a Unicode scalar value"}
+                            }
+                            GoResult::Anon(_) | GoResult::Empty => {
+                                $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                                panic(&ABIError{Message: "invalid char: not a Unicode scalar value"})
+                            }
+                        })
+                    }
+                };
+                results.push(Operand::SingleValue(result.into()));
+            }
+            // Host functions already receive native floats.
+            Instruction::F32FromCoreF32 | Instruction::F64FromCoreF64 if self.native_floats() => {
+                results.push(operands[0].clone());
+            }
             Instruction::F32FromCoreF32 => {
                 let tmp = self.tmp();
                 let result = &format!("result{tmp}");
                 let operand = &operands[0];
                 quote_in! { self.body =>
                     $['\r']
-                    $result := $WAZERO_API_DECODE_F32($operand)
+                    $result := $WAZERO_API_DECODE_F32(uint64($operand))
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
@@ -1338,10 +2375,70 @@ impl Bindgen for Func<'_> {
                 };
                 results.push(Operand::SingleValue(result.into()));
             }
-            Instruction::TupleLower { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::TupleLift { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FlagsLower { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FlagsLift { .. } => todo!("implement instruction: {inst:?}"),
+            Instruction::TupleLower { tuple, .. } => {
+                let tmp = self.tmp();
+                let operand = &operands[0];
+                for nth in 0..tuple.types.len() {
+                    let field = &format!("F{nth}");
+                    let var = &format!("tuple{tmp}_{nth}");
+                    quote_in! { self.body =>
+                        $['\r']
+                        $var := $operand.$field
+                    }
+                    results.push(Operand::SingleValue(var.into()))
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let typ = self.resolve_type(&Type::Id(*ty), resolve);
+                let fields = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(nth, op)| (format!("F{nth}"), op));
+
+                quote_in! { self.body =>
+                    $['\r']
+                    $value := $typ{
+                        $(for (name, op) in fields join ($['\r']) => $name: $op,)
+                    }
+                };
+                results.push(Operand::SingleValue(value.into()))
+            }
+            Instruction::FlagsLower { flags, .. } => {
+                let value = &operands[0];
+                let tmp = self.tmp();
+                // Flags travel as one `i32` per 32 members, lowest bits first.
+                for nth in 0..flags.repr().count() {
+                    let flags_tmp = &format!("flags{tmp}_{nth}");
+                    let word = match nth {
+                        0 => quote!(uint32($value)),
+                        _ => quote!(uint32($value >> $(nth * 32))),
+                    };
+                    quote_in! { self.body =>
+                        $['\r']
+                        $flags_tmp := $word
+                    };
+                    results.push(Operand::SingleValue(flags_tmp.into()));
+                }
+            }
+            Instruction::FlagsLift { flags, ty, .. } => {
+                let name = crate::qualified_type_name(*ty, resolve);
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let go_type = GoType::UserDefined(name);
+                let repr = &resolve_flags_repr(flags);
+                let words = operands.iter().enumerate().map(|(nth, op)| match nth {
+                    0 => quote!($repr($op)),
+                    _ => quote!($repr($op)<<$(nth * 32)),
+                });
+
+                quote_in! { self.body =>
+                    $['\r']
+                    $value := $go_type($(for word in words join ( | ) => $word))
+                };
+                results.push(Operand::SingleValue(value.into()));
+            }
             Instruction::VariantLift { variant, ty, .. } => {
                 let name = crate::qualified_type_name(*ty, resolve);
                 let blocks = self
@@ -1388,14 +2485,14 @@ impl Bindgen for Func<'_> {
                         $(match &self.result {
                             GoResult::Anon(GoType::ValueOrError(typ)) => {
                                 var default0 $(typ.as_ref())
-                                return default0, $ERRORS_NEW($(&err_msg))
+                                return default0, &ABIError{Message: $(&err_msg)}
                             }
                             GoResult::Anon(GoType::Error) => {
-                                return $ERRORS_NEW($(&err_msg))
+                                return &ABIError{Message: $(&err_msg)}
                             }
                             GoResult::Anon(_) | GoResult::Empty => {
                                 $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                                panic($ERRORS_NEW($(&err_msg)))
+                                panic(&ABIError{Message: $(&err_msg)})
                             }
                         })
                     }
@@ -1429,14 +2526,14 @@ impl Bindgen for Func<'_> {
                         $(match &self.result {
                             GoResult::Anon(GoType::ValueOrError(typ)) => {
                                 var default0 $(typ.as_ref())
-                                return default0, $ERRORS_NEW($(format!("\"invalid {name} discriminant\"")))
+                                return default0, &ABIError{Message: $(format!("\"invalid {name} discriminant\""))}
                             }
                             GoResult::Anon(GoType::Error) => {
-                                return $ERRORS_NEW($(format!("\"invalid {name} discriminant\"")))
+                                return &ABIError{Message: $(format!("\"invalid {name} discriminant\""))}
                             }
                             GoResult::Anon(_) | GoResult::Empty => {
                                 $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
-                                panic($ERRORS_NEW($(format!("\"invalid {name} discriminant\""))))
+                                panic(&ABIError{Message: $(format!("\"invalid {name} discriminant\""))})
                             }
                         })
                     }
@@ -1444,9 +2541,193 @@ impl Bindgen for Func<'_> {
 
                 results.push(Operand::SingleValue(enum_value.to_string()));
             }
-            Instruction::Malloc { .. } => todo!("implement instruction: {inst:?}"),
+            // Exports with more than `MAX_FLAT_PARAMS` parameters take them
+            // in memory the guest allocates, which it owns once called.
+            Instruction::Malloc {
+                realloc,
+                size,
+                align,
+            } => {
+                let tmp = self.tmp();
+                let result = &format!("result{tmp}");
+                let err = &format!("err{tmp}");
+                let default = &format!("default{tmp}");
+                let ptr = &format!("ptr{tmp}");
+                let size = size.size_wasm32();
+                let allocate = self.allocate(realloc, align.align_wasm32(), quote!($size));
+                quote_in! { self.body =>
+                    $['\r']
+                    $result, $err := $allocate
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if $err != nil {
+                                var $default $(typ.as_ref())
+                                return $default, $err
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if $err != nil {
+                                return $err
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if $err != nil {
+                                panic($err)
+                            }
+                        }
+                    })
+                    $ptr := uint32($result[0])
+                };
+                results.push(Operand::SingleValue(ptr.into()));
+            }
+            // The guest implements the resource, so it's lent the
+            // representation behind the handle rather than the handle itself.
+            Instruction::HandleLower {
+                handle: Handle::Borrow(resource),
+                ..
+            } if crate::is_guest_resource(*resource, resolve) => {
+                let tmp = self.tmp();
+                let rep = &format!("rep{tmp}");
+                let ok = &format!("ok{tmp}");
+                let default = &format!("default{tmp}");
+                let operand = &operands[0];
+                let handles = &crate::resource_handles(*resource, resolve);
+                let name = crate::qualified_type_name(*resource, resolve);
+                let message = &format!("invalid {name} handle");
+                let moved = &format!("{name} used after it was closed or moved");
+                quote_in! { self.body =>
+                    $['\r']
+                    if HandleChecks && $operand.handle == 0 {
+                        panic($ERRORS_NEW($(quoted(moved))))
+                    }
+                    $rep, $ok := $handles.of($module_handle).get($operand.handle)
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if !$ok {
+                                var $default $(typ.as_ref())
+                                return $default, &ABIError{Message: $(quoted(message))}
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if !$ok {
+                                return &ABIError{Message: $(quoted(message))}
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if !$ok {
+                                panic(&ABIError{Message: $(quoted(message))})
+                            }
+                        }
+                    })
+                };
+                results.push(Operand::SingleValue(rep.into()));
+            }
+            Instruction::HandleLower {
+                handle: Handle::Own(resource),
+                ..
+            } if crate::is_guest_resource(*resource, resolve) => {
+                let tmp = self.tmp();
+                let handle = &format!("handle{tmp}");
+                let operand = &operands[0];
+                let moved = &format!(
+                    "{} used after it was closed or moved",
+                    crate::qualified_type_name(*resource, resolve)
+                );
+                quote_in! { self.body =>
+                    $['\r']
+                    if HandleChecks && $operand.handle == 0 {
+                        panic($ERRORS_NEW($(quoted(moved))))
+                    }
+                    $handle := $operand.handle
+                    $(comment(&["Ownership moves to the guest, so the Go value can't be used anymore"]))
+                    $operand.handle = 0
+                };
+                results.push(Operand::SingleValue(handle.into()));
+            }
+            Instruction::HandleLift {
+                handle: Handle::Own(resource),
+                ..
+            } if crate::is_guest_resource(*resource, resolve) => {
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let operand = &operands[0];
+                let typ = GoIdentifier::public(crate::qualified_type_name(*resource, resolve));
+                quote_in! { self.body =>
+                    $['\r']
+                    $value := &$typ{instance: i, handle: uint32($operand)}
+                };
+                results.push(Operand::SingleValue(value.into()));
+            }
+            // The host implements the resource, so the guest is handed a new
+            // handle to the Go value in the instance's table.
+            Instruction::HandleLower {
+                handle: Handle::Own(resource),
+                ..
+            } => {
+                let tmp = self.tmp();
+                let handle = &format!("handle{tmp}");
+                let operand = &operands[0];
+                let handles = &crate::resource_handles(*resource, resolve);
+                quote_in! { self.body =>
+                    $['\r']
+                    $handle := $handles.of($module_handle).insert($operand)
+                };
+                results.push(Operand::SingleValue(handle.into()));
+            }
+            // The handle of a borrowed value is only lent to the guest until
+            // the call returns.
+            Instruction::HandleLower {
+                handle: Handle::Borrow(resource),
+                ..
+            } if matches!(self.direction, Direction::Export) => {
+                let tmp = self.tmp();
+                let handle = &format!("handle{tmp}");
+                let operand = &operands[0];
+                let handles = &crate::resource_handles(*resource, resolve);
+                quote_in! { self.body =>
+                    $['\r']
+                    $handle := $handles.of($module_handle).lend($operand)
+                    defer $handles.of($module_handle).remove($handle)
+                };
+                results.push(Operand::SingleValue(handle.into()));
+            }
+            // A borrowed handle stays in the table, while an owned one is
+            // taken out of it along with the Go value. Only the guest's own
+            // handles can be given away, not the ones it was lent.
+            Instruction::HandleLift {
+                handle: handle @ (Handle::Own(resource) | Handle::Borrow(resource)),
+                ..
+            } if matches!(self.direction, Direction::Import { .. }) => {
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let ok = &format!("ok{tmp}");
+                let operand = &operands[0];
+                let handles = &crate::resource_handles(*resource, resolve);
+                let lookup = match handle {
+                    Handle::Own(_) => "remove",
+                    Handle::Borrow(_) => "get",
+                };
+                let name = crate::qualified_type_name(*resource, resolve);
+                let message = &format!("invalid {name} handle");
+                let lent = &format!("cannot take ownership of a borrowed {name}");
+                quote_in! { self.body =>
+                    $['\r']
+                    $(if matches!(handle, Handle::Own(_)) {
+                        if $handles.of($module_handle).isBorrowed($operand) {
+                            panic(&ABIError{Message: $(quoted(lent))})
+                        }
+                    })
+                    $value, $ok := $handles.of($module_handle).$lookup($operand)
+                    if !$ok {
+                        panic(&ABIError{Message: $(quoted(message))})
+                    }
+                };
+                results.push(Operand::SingleValue(value.into()));
+            }
             Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => {
-                todo!("implement resources: {inst:?}")
+                todo!("TODO(#5): implement resources: {inst:?}")
             }
             Instruction::ListCanonLower { .. } | Instruction::ListCanonLift { .. } => {
                 unimplemented!("gravity doesn't represent lists as Canonical")
@@ -1458,27 +2739,173 @@ impl Bindgen for Func<'_> {
             | Instruction::GuestDeallocateVariant { .. } => {
                 unimplemented!("gravity doesn't generate the Guest code")
             }
-            Instruction::MapLower { .. }
-            | Instruction::MapLift { .. }
-            | Instruction::IterMapKey { .. }
-            | Instruction::IterMapValue { .. } => {
-                todo!("implement instruction: {inst:?}")
+            // The guest hands over the readable end of a future or stream it
+            // writes to through the `[future-write]` or `[stream-write]`
+            // intrinsics.
+            Instruction::FutureLift { payload, .. } | Instruction::StreamLift { payload, .. }
+                if matches!(self.direction, Direction::Export) =>
+            {
+                let tmp = self.tmp();
+                let end = &format!("end{tmp}");
+                let ok = &format!("ok{tmp}");
+                let value = &format!("value{tmp}");
+                let default = &format!("default{tmp}");
+                let operand = &operands[0];
+                let payload = payload
+                    .as_ref()
+                    .map(|typ| Box::new(self.resolve_type(typ, resolve)));
+                let (typ, ends, message) = match inst {
+                    Instruction::FutureLift { .. } => (
+                        GoType::Future(payload),
+                        quote!(futureEnds.of($module_handle)),
+                        "invalid future handle",
+                    ),
+                    _ => (
+                        GoType::Stream(payload),
+                        quote!(streamEnds.of($module_handle)),
+                        "invalid stream handle",
+                    ),
+                };
+                quote_in! { self.body =>
+                    $['\r']
+                    $end, $ok := $ends.remove(uint32($operand))
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if !$ok {
+                                var $default $(typ.as_ref())
+                                return $default, &ABIError{Message: $(quoted(message))}
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if !$ok {
+                                return &ABIError{Message: $(quoted(message))}
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if !$ok {
+                                panic(&ABIError{Message: $(quoted(message))})
+                            }
+                        }
+                    })
+                    $value := $end.($(&typ))
+                };
+                results.push(Operand::SingleValue(value.into()));
+            }
+            // Worlds passing futures or streams anywhere else are rejected
+            // up front, see `unsupported_futures_and_streams`.
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are only lifted from exports: {inst:?}")
+            }
+            // Error contexts cross the boundary as handles into the
+            // instance's `errorContexts` table, which the `$root` intrinsics
+            // share with the guest. The guest drops the handles it's given.
+            Instruction::ErrorContextLower => {
+                let tmp = self.tmp();
+                let handle = &format!("handle{tmp}");
+                let operand = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    $handle := errorContexts.of($module_handle).insert($operand)
+                };
+                results.push(Operand::SingleValue(handle.into()));
+            }
+            Instruction::ErrorContextLift => {
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let ok = &format!("ok{tmp}");
+                let default = &format!("default{tmp}");
+                let operand = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    $value, $ok := errorContexts.of($module_handle).get(uint32($operand))
+                    $(match &self.result {
+                        GoResult::Anon(GoType::ValueOrError(typ)) => {
+                            if !$ok {
+                                var $default $(typ.as_ref())
+                                return $default, &ABIError{Message: "invalid error-context handle"}
+                            }
+                        }
+                        GoResult::Anon(GoType::Error) => {
+                            if !$ok {
+                                return &ABIError{Message: "invalid error-context handle"}
+                            }
+                        }
+                        GoResult::Anon(_) | GoResult::Empty => {
+                            $(comment(&["The return type doesn't contain an error so we panic if one is encountered"]))
+                            if !$ok {
+                                panic(&ABIError{Message: "invalid error-context handle"})
+                            }
+                        }
+                    })
+                };
+                results.push(Operand::SingleValue(value.into()));
             }
-            Instruction::FutureLower { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FutureLift { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::StreamLower { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::StreamLift { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::ErrorContextLower => todo!("implement instruction: {inst:?}"),
-            Instruction::ErrorContextLift => todo!("implement instruction: {inst:?}"),
             Instruction::AsyncTaskReturn { .. } => todo!("implement instruction: {inst:?}"),
             Instruction::DropHandle { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FixedLengthListLift { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FixedLengthListLower { .. } => todo!("implement instruction: {inst:?}"),
-            Instruction::FixedLengthListLowerToMemory { .. } => {
-                todo!("implement instruction: {inst:?}")
+            Instruction::FixedLengthListLift { element, size, .. } => {
+                let tmp = self.tmp();
+                let value = &format!("value{tmp}");
+                let typ = GoType::Array(Box::new(self.resolve_type(element, resolve)), *size);
+                quote_in! { self.body =>
+                    $['\r']
+                    $value := $typ{$(for op in operands.iter() join (, ) => $op)}
+                };
+                results.push(Operand::SingleValue(value.into()));
             }
-            Instruction::FixedLengthListLiftFromMemory { .. } => {
-                todo!("implement instruction: {inst:?}")
+            Instruction::FixedLengthListLower { size, .. } => {
+                let tmp = self.tmp();
+                let array = &format!("array{tmp}");
+                let operand = &operands[0];
+                quote_in! { self.body =>
+                    $['\r']
+                    $array := $operand
+                };
+                for nth in 0..*size {
+                    results.push(Operand::SingleValue(format!("{array}[{nth}]")));
+                }
+            }
+            // Arrays are stored inline, so unlike `ListLower` there's nothing
+            // to allocate: each element is written in place after the last.
+            Instruction::FixedLengthListLowerToMemory { element, size, .. } => {
+                let (body, _) = self.pop_block();
+                let tmp = self.tmp();
+                let array = &format!("array{tmp}");
+                let value = &operands[0];
+                let addr = &operands[1];
+                let elem_size = self.sizes.size(element).size_wasm32();
+                quote_in! { self.body =>
+                    $['\r']
+                    $array := $value
+                    for idx := uint32(0); idx < $(*size); idx++ {
+                        $iter_element := $array[idx]
+                        $iter_base := uint32($addr) + idx * $elem_size
+                        $body
+                    }
+                };
+            }
+            Instruction::FixedLengthListLiftFromMemory { element, size, .. } => {
+                let (body, body_results) = self.pop_block();
+                let tmp = self.tmp();
+                let result = &format!("result{tmp}");
+                let idx = &format!("idx{tmp}");
+                let addr = &operands[0];
+                let body_result = &body_results[0];
+                let elem_size = self.sizes.size(element).size_wasm32();
+                let typ = GoType::Array(Box::new(self.resolve_type(element, resolve)), *size);
+                quote_in! { self.body =>
+                    $['\r']
+                    var $result $typ
+                    for $idx := uint32(0); $idx < $(*size); $idx++ {
+                        base := uint32($addr) + $idx * $elem_size
+                        $body
+                        $result[$idx] = $body_result
+                    }
+                };
+                results.push(Operand::SingleValue(result.into()));
             }
             Instruction::Flush { amt } => {
                 for op in operands.iter().take(*amt) {
@@ -1,11 +1,12 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use genco::prelude::*;
 use wit_bindgen_core::{
-    abi::{AbiVariant, LiftLower},
+    abi::{AbiVariant, LiftLower, WasmType},
+    dealias,
     wit_parser::{
-        Case, Function, InterfaceId, Param, Resolve, SizeAlign, Type, TypeDefKind, TypeId, World,
-        WorldItem,
+        Case, Function, FunctionKind, Handle, InterfaceId, Param, Resolve, SizeAlign, Type,
+        TypeDefKind, TypeId, World, WorldItem,
     },
 };
 
@@ -13,26 +14,60 @@ use crate::{
     codegen::{
         func::Func,
         ir::{
-            AnalyzedFunction, AnalyzedImports, AnalyzedInterface, AnalyzedType, CaseDispatch,
-            InterfaceMethod, Parameter, TypeDefinition, VariantCase, WitReturn,
+            AnalyzedFunction, AnalyzedImports, AnalyzedInterface, AnalyzedResource, AnalyzedType,
+            CaseDispatch, InterfaceMethod, Parameter, TypeDefinition, VariantCase, WitReturn,
         },
     },
     go::{
-        imports::{CONTEXT_CONTEXT, WAZERO_API_MODULE},
+        comment,
+        imports::{
+            CONTEXT_CONTEXT, CONTEXT_WITH_CANCEL, ERRORS_NEW, FMT_ERRORF, FMT_SPRINTF, GoImport,
+            STRINGS_JOIN, STRINGS_SPLIT, TESTING_T, TIME_AFTER, TIME_SECOND, WAZERO_API_DECODE_F32, WAZERO_API_DECODE_F64, WAZERO_API_DECODE_U32,
+            WAZERO_API_ENCODE_F32, WAZERO_API_ENCODE_F64, WAZERO_API_ENCODE_U32,
+            WAZERO_API_GO_MODULE_FUNC, WAZERO_API_MODULE, WAZERO_API_VALUE_TYPE,
+            WAZERO_API_VALUE_TYPE_F32, WAZERO_API_VALUE_TYPE_F64, WAZERO_API_VALUE_TYPE_I32,
+            WAZERO_API_VALUE_TYPE_I64,
+        },
         GoIdentifier, GoResult, GoType,
     },
-    resolve_param_type, resolve_type, resolve_wasm_type,
+    resolve_flags_repr, resolve_param_type_with, resolve_return_type_with, resolve_type_with,
+    resolve_wasm_type,
 };
 
 /// Analyzer for imports - only does analysis, no code generation
 pub struct ImportAnalyzer<'a> {
     resolve: &'a Resolve,
     world: &'a World,
+    /// Whether `option` and `result` resolve to the generated generic
+    /// `Option[T]` and `Result[T, E]`.
+    generic_containers: bool,
 }
 
 impl<'a> ImportAnalyzer<'a> {
     pub fn new(resolve: &'a Resolve, world: &'a World) -> Self {
-        Self { resolve, world }
+        Self {
+            resolve,
+            world,
+            generic_containers: false,
+        }
+    }
+
+    /// Resolve `option` and `result` to the generated generic containers.
+    pub fn with_generic_containers(mut self, generic_containers: bool) -> Self {
+        self.generic_containers = generic_containers;
+        self
+    }
+
+    fn resolve_type(&self, typ: &Type) -> GoType {
+        resolve_type_with(typ, self.resolve, self.generic_containers)
+    }
+
+    fn resolve_param_type(&self, typ: &Type) -> GoType {
+        resolve_param_type_with(typ, self.resolve, self.generic_containers)
+    }
+
+    fn resolve_return_type(&self, typ: &Type) -> GoType {
+        resolve_return_type_with(typ, self.resolve, self.generic_containers)
     }
 
     pub fn analyze(&self) -> AnalyzedImports {
@@ -40,14 +75,17 @@ impl<'a> ImportAnalyzer<'a> {
         let mut interfaces = Vec::new();
         let mut standalone_types = Vec::new();
         let mut standalone_functions = Vec::new();
+        // Every WIT type that's been analyzed so far, so that types `use`d
+        // by several interfaces are only generated once.
+        let mut generated = BTreeSet::new();
 
         for (_import_name, world_item) in world_imports.iter() {
             match world_item {
                 WorldItem::Interface { id, .. } => {
-                    interfaces.push(self.analyze_interface(*id));
+                    interfaces.push(self.analyze_interface(*id, &mut generated));
                 }
                 WorldItem::Type { id: type_id, .. } => {
-                    if let Some(t) = self.analyze_type(*type_id) {
+                    if let Some(t) = self.analyze_type(*type_id, &mut generated) {
                         standalone_types.push(t);
                     }
                 }
@@ -57,42 +95,95 @@ impl<'a> ImportAnalyzer<'a> {
             }
         }
 
+        // Exported interfaces can declare types of their own, or `use` those
+        // of the imports, which are only generated once as well.
+        let mut exported_types = Vec::new();
+        for world_item in self.world.exports.values() {
+            if let WorldItem::Interface { id, .. } = world_item {
+                exported_types.extend(
+                    self.resolve.interfaces[*id]
+                        .types
+                        .values()
+                        .filter_map(|&id| self.analyze_type(id, &mut generated)),
+                );
+            }
+        }
+
         // Generate factory-related identifiers
         let factory_name = GoIdentifier::public(format!("{}-factory", self.world.name));
         let instance_name = GoIdentifier::public(format!("{}-instance", self.world.name));
         let constructor_name = GoIdentifier::public(format!("new-{}-factory", self.world.name));
+        let factory_option_name =
+            GoIdentifier::public(format!("{}-factory-option", self.world.name));
+        let factory_options_name =
+            GoIdentifier::private(format!("{}-factory-options", self.world.name));
 
         AnalyzedImports {
             interfaces,
             standalone_types,
+            exported_types,
             standalone_functions,
             factory_name,
             instance_name,
             constructor_name,
+            factory_option_name,
+            factory_options_name,
         }
     }
 
-    fn analyze_interface(&self, interface_id: InterfaceId) -> AnalyzedInterface {
+    fn analyze_interface(
+        &self,
+        interface_id: InterfaceId,
+        generated: &mut BTreeSet<TypeId>,
+    ) -> AnalyzedInterface {
         let interface = &self.resolve.interfaces[interface_id];
         let interface_name = interface.name.as_ref().expect("interface missing name");
 
-        // Analyze methods
+        // Analyze methods, leaving those called on a resource to the
+        // resource. Its constructor and static functions have no `self`, so
+        // they're implemented alongside the rest of the interface.
         let methods = interface
             .functions
             .values()
+            .filter(|func| {
+                !matches!(
+                    func.kind,
+                    FunctionKind::Method(_) | FunctionKind::AsyncMethod(_)
+                )
+            })
             .map(|func| self.analyze_interface_method(func, interface_name))
             .collect();
 
+        // Analyze resources
+        let resources = interface
+            .types
+            .values()
+            .filter(|&&id| matches!(self.resolve.types[id].kind, TypeDefKind::Resource))
+            .map(|&id| self.analyze_resource(id, interface_id))
+            .collect();
+
         // Analyze interface types
         let types = interface
             .types
             .values()
-            .filter_map(|&id| self.analyze_type(id))
+            .filter_map(|&id| self.analyze_type(id, generated))
             .collect();
 
-        // Generate names
+        // Generate names, qualified with the package when the world imports
+        // another interface of the same name so that theirs don't collide.
+        let collides = self.world.imports.values().any(|item| {
+            matches!(item, WorldItem::Interface { id, .. }
+                if *id != interface_id && self.resolve.interfaces[*id].name == interface.name)
+        });
+        let qualified_name = match interface.package {
+            Some(package_id) if collides => {
+                let package = &self.resolve.packages[package_id].name;
+                format!("{}-{}-{interface_name}", package.namespace, package.name)
+            }
+            _ => interface_name.clone(),
+        };
         let go_interface_name =
-            GoIdentifier::public(format!("i-{}-{}", self.world.name, interface_name));
+            GoIdentifier::public(format!("i-{}-{qualified_name}", self.world.name));
 
         let wazero_module_name = if let Some(package_id) = interface.package {
             let package = &self.resolve.packages[package_id];
@@ -108,38 +199,88 @@ impl<'a> ImportAnalyzer<'a> {
             name: interface_name.clone(),
             methods,
             types,
-            constructor_param_name: GoIdentifier::private(interface_name),
+            resources,
+            constructor_param_name: GoIdentifier::private(&qualified_name),
             go_interface_name,
             wazero_module_name,
         }
     }
 
+    /// Analyzes a resource declared by `interface_id`, along with its methods.
+    fn analyze_resource(&self, resource: TypeId, interface_id: InterfaceId) -> AnalyzedResource {
+        let interface = &self.resolve.interfaces[interface_id];
+        let name = self.resolve.types[resource]
+            .name
+            .clone()
+            .expect("resources are named");
+
+        let methods = interface
+            .functions
+            .values()
+            .filter(|func| {
+                matches!(
+                    func.kind,
+                    FunctionKind::Method(id) | FunctionKind::AsyncMethod(id) if id == resource
+                )
+            })
+            .map(|func| self.analyze_interface_method(func, &name))
+            .collect();
+
+        AnalyzedResource {
+            go_interface_name: GoIdentifier::public(crate::qualified_type_name(
+                resource,
+                self.resolve,
+            )),
+            handles: crate::resource_handles(resource, self.resolve),
+            methods,
+            name,
+        }
+    }
+
     fn analyze_interface_method(&self, func: &Function, _interface_name: &str) -> InterfaceMethod {
-        let parameters = func
-            .params
+        // A method's `self` is the Go value it's called on.
+        let params = match func.kind {
+            FunctionKind::Method(_) | FunctionKind::AsyncMethod(_) => &func.params[1..],
+            _ => &func.params[..],
+        };
+        let parameters = params
             .iter()
             .map(|Param { name, ty, .. }| Parameter {
                 name: GoIdentifier::private(name),
-                go_type: resolve_param_type(ty, self.resolve),
+                go_type: self.resolve_param_type(ty),
                 wit_type: *ty,
             })
             .collect();
 
         let return_type = func.result.as_ref().map(|wit_type| WitReturn {
-            go_type: resolve_type(wit_type, self.resolve),
+            go_type: self.resolve_return_type(wit_type),
             wit_type: *wit_type,
         });
 
         InterfaceMethod {
             name: func.name.clone(),
-            go_method_name: GoIdentifier::public(&func.name),
+            go_method_name: crate::function_go_name(func),
             parameters,
             return_type,
             wit_function: func.clone(),
         }
     }
 
-    fn analyze_type(&self, type_id: TypeId) -> Option<AnalyzedType> {
+    /// Analyzes the type `type_id` refers to, unless it's already in
+    /// `generated`.
+    ///
+    /// A `use` is an alias to a type owned by another interface. Go code
+    /// refers to the aliased type directly, so that's what gets analyzed,
+    /// wherever it's first encountered.
+    fn analyze_type(
+        &self,
+        type_id: TypeId,
+        generated: &mut BTreeSet<TypeId>,
+    ) -> Option<AnalyzedType> {
+        let type_id = dealias(self.resolve, type_id);
+        if !generated.insert(type_id) {
+            return None;
+        }
         let type_def = &self.resolve.types[type_id];
         let qualified = crate::qualified_type_name(type_id, self.resolve);
         let go_type_name = GoIdentifier::public(&qualified);
@@ -164,7 +305,7 @@ impl<'a> ImportAnalyzer<'a> {
     }
 
     fn analyze_variant_case(&self, variant_name: &str, case: &Case) -> VariantCase {
-        let payload = case.ty.as_ref().map(|t| resolve_type(t, self.resolve));
+        let payload = case.ty.as_ref().map(|t| self.resolve_type(t));
         let dispatch = match crate::case_dispatch_kind(case, self.resolve) {
             crate::CaseDispatchKind::DirectRecord => CaseDispatch::DirectRecord {
                 record_type: payload
@@ -182,8 +323,8 @@ impl<'a> ImportAnalyzer<'a> {
         }
     }
 
-    /// Analyze a type definition. Returns `None` for `Type::Id` aliases
-    /// that just re-export an already-analyzed type.
+    /// Analyze a type definition. Returns `None` for resources, which are
+    /// analyzed along with their methods instead.
     fn analyze_type_definition(&self, kind: &TypeDefKind) -> Option<TypeDefinition> {
         Some(match kind {
             TypeDefKind::Record(record) => TypeDefinition::Record {
@@ -193,7 +334,7 @@ impl<'a> ImportAnalyzer<'a> {
                     .map(|field| {
                         (
                             GoIdentifier::public(&field.name),
-                            resolve_type(&field.ty, self.resolve),
+                            self.resolve_type(&field.ty),
                         )
                     })
                     .collect(),
@@ -205,40 +346,93 @@ impl<'a> ImportAnalyzer<'a> {
                 "Variant analysis is handled in `analyze_type` where the qualified name is in scope"
             ),
             TypeDefKind::Type(Type::Id(_)) => {
-                // TODO(#4):  Only skip this if we have already generated the type
-                return None;
+                unreachable!("aliases are resolved to their target in `analyze_type`")
             }
-            TypeDefKind::Type(Type::String) => TypeDefinition::Alias {
-                target: GoType::String,
+            // Named primitives are Go aliases, so values flow in and out of the
+            // generated bindings without conversions.
+            TypeDefKind::Type(
+                typ @ (Type::Bool
+                | Type::U8
+                | Type::U16
+                | Type::U32
+                | Type::U64
+                | Type::S8
+                | Type::S16
+                | Type::S32
+                | Type::S64
+                | Type::F32
+                | Type::F64
+                | Type::Char
+                | Type::String
+                | Type::ErrorContext),
+            ) => TypeDefinition::Alias {
+                target: self.resolve_type(typ),
+            },
+            TypeDefKind::FixedLengthList(inner, len) => TypeDefinition::Alias {
+                target: GoType::Array(Box::new(self.resolve_type(inner)), *len),
             },
-            TypeDefKind::Type(Type::Bool) => todo!("TODO(#4): generate bool type alias"),
-            TypeDefKind::Type(Type::U8) => todo!("TODO(#4): generate u8 type alias"),
-            TypeDefKind::Type(Type::U16) => todo!("TODO(#4): generate u16 type alias"),
-            TypeDefKind::Type(Type::U32) => todo!("TODO(#4): generate u32 type alias"),
-            TypeDefKind::Type(Type::U64) => todo!("TODO(#4): generate u64 type alias"),
-            TypeDefKind::Type(Type::S8) => todo!("TODO(#4): generate s8 type alias"),
-            TypeDefKind::Type(Type::S16) => todo!("TODO(#4): generate s16 type alias"),
-            TypeDefKind::Type(Type::S32) => todo!("TODO(#4): generate s32 type alias"),
-            TypeDefKind::Type(Type::S64) => todo!("TODO(#4): generate s64 type alias"),
-            TypeDefKind::Type(Type::F32) => todo!("TODO(#4): generate f32 type alias"),
-            TypeDefKind::Type(Type::F64) => todo!("TODO(#4): generate f64 type alias"),
-            TypeDefKind::Type(Type::Char) => todo!("TODO(#4): generate char type alias"),
-            TypeDefKind::Type(Type::ErrorContext) => {
-                todo!("TODO(#4): generate error context definition")
+            // A named `option<T>` resolves to `*T` (or `Option[T]`) wherever
+            // it's used, so the alias only gives the container a name.
+            TypeDefKind::Option(inner) if self.generic_containers => TypeDefinition::Alias {
+                target: GoType::GenericOption(Box::new(self.resolve_type(inner))),
+            },
+            TypeDefKind::Option(inner) => TypeDefinition::Alias {
+                target: GoType::Pointer(Box::new(self.resolve_type(inner))),
+            },
+            // Like options, a named `result` resolves structurally, to its
+            // `Result[T, E]`, so the alias only gives that a name.
+            TypeDefKind::Result(result) => {
+                let payload = |typ: &Option<Type>| {
+                    Box::new(
+                        typ.as_ref()
+                            .map_or(GoType::Nothing, |typ| self.resolve_type(typ)),
+                    )
+                };
+                TypeDefinition::Alias {
+                    target: GoType::GenericResult(payload(&result.ok), payload(&result.err)),
+                }
             }
-            TypeDefKind::FixedLengthList(_, _) => {
-                todo!("TODO(#4): generate fixed length list definition")
+            TypeDefKind::List(inner) => TypeDefinition::Alias {
+                target: GoType::Slice(Box::new(self.resolve_type(inner))),
+            },
+            TypeDefKind::Future(payload) => TypeDefinition::Alias {
+                target: GoType::Future(
+                    payload.as_ref().map(|typ| Box::new(self.resolve_type(typ))),
+                ),
+            },
+            TypeDefKind::Stream(payload) => TypeDefinition::Alias {
+                target: GoType::Stream(
+                    payload.as_ref().map(|typ| Box::new(self.resolve_type(typ))),
+                ),
+            },
+            TypeDefKind::Flags(flags) => TypeDefinition::Flags {
+                flags: flags.flags.iter().map(|flag| flag.name.clone()).collect(),
+                repr: resolve_flags_repr(flags),
+            },
+            // Like options and lists, a named tuple resolves structurally, to
+            // its `TupleN` struct, so the alias only gives that a name.
+            TypeDefKind::Tuple(tuple) => TypeDefinition::Alias {
+                target: GoType::Tuple(
+                    tuple
+                        .types
+                        .iter()
+                        .map(|typ| self.resolve_type(typ))
+                        .collect(),
+                ),
+            },
+            // Resources are generated with the interface declaring them.
+            TypeDefKind::Resource => return None,
+            TypeDefKind::Handle(Handle::Own(resource) | Handle::Borrow(resource)) => {
+                TypeDefinition::Alias {
+                    target: self.resolve_type(&Type::Id(*resource)),
+                }
             }
-            TypeDefKind::Option(_) => todo!("TODO(#4): generate option type definition"),
-            TypeDefKind::Result(_) => todo!("TODO(#4): generate result type definition"),
-            TypeDefKind::List(_) => todo!("TODO(#4): generate list type definition"),
-            TypeDefKind::Future(_) => todo!("TODO(#4): generate future type definition"),
-            TypeDefKind::Stream(_) => todo!("TODO(#4): generate stream type definition"),
-            TypeDefKind::Flags(_) => todo!("TODO(#4):generate flags type definition"),
-            TypeDefKind::Tuple(_) => todo!("TODO(#4):generate tuple type definition"),
-            TypeDefKind::Resource => todo!("TODO(#5): implement resources"),
-            TypeDefKind::Handle(_) => todo!("TODO(#5): implement resources"),
-            TypeDefKind::Map(_, _) => todo!("TODO(#4): generate map type definition"),
+            TypeDefKind::Map(key, value) => TypeDefinition::Alias {
+                target: GoType::Map(
+                    Box::new(self.resolve_type(key)),
+                    Box::new(self.resolve_type(value)),
+                ),
+            },
             TypeDefKind::Unknown => panic!("cannot generate Unknown type"),
         })
     }
@@ -249,7 +443,7 @@ impl<'a> ImportAnalyzer<'a> {
             .iter()
             .map(|Param { name, ty, .. }| Parameter {
                 name: GoIdentifier::private(name),
-                go_type: resolve_param_type(ty, self.resolve),
+                go_type: self.resolve_param_type(ty),
                 wit_type: *ty,
             })
             .collect();
@@ -257,7 +451,7 @@ impl<'a> ImportAnalyzer<'a> {
         let return_type = func
             .result
             .as_ref()
-            .map(|wit_type| resolve_type(wit_type, self.resolve));
+            .map(|wit_type| self.resolve_return_type(wit_type));
 
         AnalyzedFunction {
             name: func.name.clone(),
@@ -268,11 +462,105 @@ impl<'a> ImportAnalyzer<'a> {
     }
 }
 
+/// The Go type a core parameter of a host function is decoded to.
+///
+/// 64-bit integers need a 64-bit Go type, or their upper half would be lost.
+/// The core signature is derived from the Go one, so floats have to be
+/// native Go floats rather than their bits.
+pub(crate) fn host_param_type(typ: &WasmType) -> GoType {
+    match typ {
+        WasmType::I64 | WasmType::PointerOrI64 => GoType::Uint64,
+        WasmType::F32 => GoType::Float32,
+        WasmType::F64 => GoType::Float64,
+        WasmType::I32 | WasmType::Pointer | WasmType::Length => GoType::Uint32,
+    }
+}
+
+/// The core type of a Go value passed to or from a host function.
+fn host_value_type(typ: &GoType) -> GoImport {
+    match typ {
+        GoType::Uint32 => WAZERO_API_VALUE_TYPE_I32,
+        GoType::Uint64 => WAZERO_API_VALUE_TYPE_I64,
+        GoType::Float32 => WAZERO_API_VALUE_TYPE_F32,
+        GoType::Float64 => WAZERO_API_VALUE_TYPE_F64,
+        typ => unreachable!("{typ:?} isn't passed to or from host functions"),
+    }
+}
+
+/// Decode the Go value at the given index of a host function's stack.
+fn decode_stack_value(typ: &GoType, index: usize) -> Tokens<Go> {
+    match typ {
+        GoType::Uint32 => quote!($WAZERO_API_DECODE_U32(stack[$index])),
+        GoType::Uint64 => quote!(stack[$index]),
+        GoType::Float32 => quote!($WAZERO_API_DECODE_F32(stack[$index])),
+        GoType::Float64 => quote!($WAZERO_API_DECODE_F64(stack[$index])),
+        typ => unreachable!("{typ:?} isn't passed to host functions"),
+    }
+}
+
+/// Encode a Go value to put it on a host function's stack.
+fn encode_stack_value(typ: &GoType, value: impl FormatInto<Go>) -> Tokens<Go> {
+    match typ {
+        GoType::Uint32 => quote!($WAZERO_API_ENCODE_U32($value)),
+        GoType::Uint64 => quote!($value),
+        GoType::Float32 => quote!($WAZERO_API_ENCODE_F32($value)),
+        GoType::Float64 => quote!($WAZERO_API_ENCODE_F64($value)),
+        typ => unreachable!("{typ:?} isn't returned from host functions"),
+    }
+}
+
+/// Registers a host function taking `ctx`, `mod` and `params`, and returning
+/// `result`, as a `GoModuleFunc`, so that wazero hands it its parameters and
+/// results on a stack of `uint64`s rather than reflecting on it.
+pub(crate) fn go_module_func(
+    params: &[(&str, GoType)],
+    result: Option<GoType>,
+    body: impl FormatInto<Go>,
+) -> Tokens<Go> {
+    let args = params
+        .iter()
+        .enumerate()
+        .map(|(index, (_, typ))| decode_stack_value(typ, index));
+    let call = quote!(fn(ctx, mod$(for arg in args => , $arg)));
+    let call = match &result {
+        Some(typ) => quote!(stack[0] = $(encode_stack_value(typ, call))),
+        None => call,
+    };
+    let results = result.as_slice();
+    quote! {
+        WithGoModuleFunction($WAZERO_API_GO_MODULE_FUNC(func(ctx $CONTEXT_CONTEXT, mod $WAZERO_API_MODULE, stack []uint64) {
+            fn := func(ctx $CONTEXT_CONTEXT, mod $WAZERO_API_MODULE$(for (name, typ) in params => , $(*name) $typ)) $(for typ in results => $typ) {
+                $body
+            }
+            $call
+        }),
+        []$WAZERO_API_VALUE_TYPE{$(for (_, typ) in params join (, ) => $(host_value_type(typ)))},
+        []$WAZERO_API_VALUE_TYPE{$(for typ in results => $(host_value_type(typ)))}).
+    }
+}
+
+/// The most core parameters an async-lowered function takes its parameters
+/// as, before they're passed through a pointer instead.
+const MAX_FLAT_ASYNC_PARAMS: usize = 4;
+
 /// Code generator for imports - takes analysis results and generates Go code
 pub struct ImportCodeGenerator<'a> {
     resolve: &'a Resolve,
     analyzed: &'a AnalyzedImports,
     sizes: &'a SizeAlign,
+    /// Whether the guest async-lowers the functions declared `async`.
+    asynchronous: bool,
+    /// Whether `option` and `result` values are carried in the generated
+    /// generic `Option[T]` and `Result[T, E]`.
+    generic_containers: bool,
+    /// Whether host functions record an OpenTelemetry span.
+    tracing: bool,
+    /// Whether host functions are handed views of guest memory for strings
+    /// and `list<u8>`s rather than copies.
+    zero_copy: bool,
+    /// Whether host functions zero their copies of the guest's `list<u8>`s
+    /// once they return.
+    zero_buffers: bool,
 }
 
 impl<'a> ImportCodeGenerator<'a> {
@@ -282,34 +570,95 @@ impl<'a> ImportCodeGenerator<'a> {
             resolve,
             analyzed,
             sizes,
+            asynchronous: false,
+            generic_containers: false,
+            tracing: false,
+            zero_copy: false,
+            zero_buffers: false,
         }
     }
 
+    /// Also provide the `[async-lower]` host functions the guest calls the
+    /// functions declared `async` through.
+    pub fn with_async(mut self, asynchronous: bool) -> Self {
+        self.asynchronous = asynchronous;
+        self
+    }
+
+    /// Carry `option` and `result` values in the generated generic
+    /// containers, as the analysis resolved them.
+    pub fn with_generic_containers(mut self, generic_containers: bool) -> Self {
+        self.generic_containers = generic_containers;
+        self
+    }
+
+    /// Record a span with the factory's tracer around every call to a host
+    /// function.
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
+    /// Hand host functions the strings and `list<u8>`s they're called with
+    /// as views of guest memory, which are only valid until they return.
+    pub fn with_zero_copy(mut self, zero_copy: bool) -> Self {
+        self.zero_copy = zero_copy;
+        self
+    }
+
+    /// Zero the copies of the `list<u8>`s host functions are called with
+    /// once they return.
+    pub fn with_zero_buffers(mut self, zero_buffers: bool) -> Self {
+        self.zero_buffers = zero_buffers;
+        self
+    }
+
     /// Extract import chains for host module builders
     pub fn import_chains(&self) -> BTreeMap<String, Tokens<Go>> {
         let mut chains = BTreeMap::new();
 
         for (i, interface) in self.analyzed.interfaces.iter().enumerate() {
             let err = &GoIdentifier::private(format!("err{i}"));
+            let host_module = &GoIdentifier::private(format!("host-module{i}"));
+            // The namespace is empty unless the factory was asked to share its
+            // runtime, in which case the guest's imports are rewritten to match.
             let mut chain = quote! {
-                _, $err := wazeroRuntime.NewHostModuleBuilder($(quoted(&interface.wazero_module_name))).
+                $host_module, $err := wazeroRuntime.NewHostModuleBuilder(options.namespace + $(quoted(&interface.wazero_module_name))).
             };
 
             for method in &interface.methods {
                 chain.push();
                 let func_builder =
-                    self.generate_host_function_builder(method, &interface.constructor_param_name);
+                    self.generate_host_function_builders(method, &interface.constructor_param_name);
                 quote_in! { chain =>
                     $func_builder
                 };
             }
 
+            for resource in &interface.resources {
+                for method in &resource.methods {
+                    chain.push();
+                    let func_builder = self
+                        .generate_host_function_builders(method, &interface.constructor_param_name);
+                    quote_in! { chain =>
+                        $func_builder
+                    };
+                }
+
+                chain.push();
+                let drop_builder = self.generate_resource_drop_builder(resource);
+                quote_in! { chain =>
+                    $drop_builder
+                };
+            }
+
             chain.push();
             quote_in! { chain =>
                 Instantiate(ctx)
                 if $err != nil {
-                    return nil, $err
+                    return nil, &InstantiationError{Err: $err}
                 }
+                hostModules = append(hostModules, $host_module)
             };
 
             chains.insert(interface.wazero_module_name.clone(), chain);
@@ -324,6 +673,15 @@ impl FormatInto<Go> for ImportCodeGenerator<'_> {
         // Generate interface type definitions
         for interface in &self.analyzed.interfaces {
             self.generate_interface_type(interface, tokens);
+            self.generate_mock_interface(interface, tokens);
+            self.generate_noop_interface(interface, tokens);
+            if interface.wazero_module_name.starts_with("wasi:") {
+                self.generate_unsupported_interface(interface, tokens);
+            }
+
+            for resource in &interface.resources {
+                self.generate_resource_type(resource, tokens);
+            }
 
             for typ in &interface.types {
                 self.generate_type_definition(typ, tokens);
@@ -334,6 +692,11 @@ impl FormatInto<Go> for ImportCodeGenerator<'_> {
         for typ in &self.analyzed.standalone_types {
             self.generate_type_definition(typ, tokens);
         }
+
+        // Generate the types of exported interfaces
+        for typ in &self.analyzed.exported_types {
+            self.generate_type_definition(typ, tokens);
+        }
     }
 }
 
@@ -342,7 +705,7 @@ impl<'a> ImportCodeGenerator<'a> {
         let methods = interface
             .methods
             .iter()
-            .map(|method| self.generate_method_signature(method));
+            .map(|method| self.generate_interface_method(method));
 
         quote_in! { *tokens =>
             $['\n']
@@ -352,6 +715,281 @@ impl<'a> ImportCodeGenerator<'a> {
         }
     }
 
+    /// Generate an implementation of an interface which calls the functions
+    /// in its fields, so tests of the host's wiring don't need to write one.
+    fn generate_mock_interface(&self, interface: &AnalyzedInterface, tokens: &mut Tokens<Go>) {
+        // Named after the interface without its `I` prefix, so mocks of
+        // same-named interfaces in different packages don't collide.
+        let interface_name = String::from(&interface.go_interface_name);
+        let typ = &GoIdentifier::public(format!("mock-{}", &interface_name[1..]));
+        let doc = [
+            format!(
+                "{} implements {interface_name} by calling the function in the field named",
+                String::from(typ),
+            ),
+            "after each method, for tests. Calling a method whose function is nil panics.".into(),
+        ];
+        let fields = interface
+            .methods
+            .iter()
+            .map(|method| {
+                let method_name = String::from(&method.go_method_name);
+                let field = GoIdentifier::public(format!("{method_name}-func"));
+                let return_type = method
+                    .return_type
+                    .clone()
+                    .map(|t| GoResult::Anon(t.go_type))
+                    .unwrap_or(GoResult::Empty);
+                let func_type = quote! {
+                    func(ctx $CONTEXT_CONTEXT$(for param in &method.parameters => , $(&param.name) $(&param.go_type))) $return_type
+                };
+                let message = format!(
+                    "{}.{method_name} called without {}",
+                    String::from(typ),
+                    String::from(&field)
+                );
+                (method, field, func_type, message)
+            })
+            .collect::<Vec<_>>();
+        let call = |field: &GoIdentifier, method: &InterfaceMethod| quote!(m.$field(ctx$(for param in &method.parameters => , $(&param.name))));
+
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(doc))
+            type $typ struct {
+                $(for (_, field, func_type, _) in &fields join ($['\r']) => $field $func_type)
+            }
+            $(for (method, field, _, message) in &fields {
+                $['\n']
+                func (m *$typ) $(self.generate_method_signature(method)) {
+                    if m.$field == nil {
+                        panic($(quoted(message)))
+                    }
+                    $(if method.return_type.is_some() {
+                        return $(call(field, method))
+                    } else {
+                        $(call(field, method))
+                    })
+                }
+            })
+        }
+    }
+
+    /// Generate an implementation of an interface whose methods do nothing
+    /// and return zero values, for hosts only caring about some imports.
+    fn generate_noop_interface(&self, interface: &AnalyzedInterface, tokens: &mut Tokens<Go>) {
+        let interface_name = String::from(&interface.go_interface_name);
+        let typ = &GoIdentifier::public(format!("noop-{}", &interface_name[1..]));
+        let doc = [
+            format!(
+                "{} implements {interface_name} with methods that do nothing and",
+                String::from(typ),
+            ),
+            "return zero values.".into(),
+        ];
+        let methods = interface.methods.iter().map(|method| {
+            let results = match method.return_type.as_ref().map(|t| &t.go_type) {
+                None => vec![],
+                Some(GoType::ValueOrError(typ)) => vec![typ.as_ref().clone(), GoType::Error],
+                Some(GoType::ValueOrOk(typ)) => vec![typ.as_ref().clone(), GoType::Bool],
+                Some(GoType::MultiReturn(types)) => types.clone(),
+                Some(typ) => vec![typ.clone()],
+            };
+            let zeros = results
+                .iter()
+                .enumerate()
+                .map(|(i, _)| GoIdentifier::local(format!("zero{i}")))
+                .collect::<Vec<_>>();
+            quote! {
+                func ($typ) $(self.generate_method_signature(method)) {
+                    $(for (zero, typ) in zeros.iter().zip(&results) join ($['\r']) => var $zero $typ)
+                    $(if !zeros.is_empty() => return $(for zero in &zeros join (, ) => $zero))
+                }
+            }
+        });
+
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(doc))
+            type $typ struct{}
+            $(for method in methods => $['\n']$method)
+        }
+    }
+
+    /// Generate an implementation of a WASI interface whose functions trap
+    /// the guest, so components targeting WASI can be instantiated by hosts
+    /// only providing some of it.
+    fn generate_unsupported_interface(
+        &self,
+        interface: &AnalyzedInterface,
+        tokens: &mut Tokens<Go>,
+    ) {
+        // Named after the interface without its `I` prefix, like its mock.
+        let interface_name = String::from(&interface.go_interface_name);
+        let typ = &GoIdentifier::public(format!("unsupported-{}", &interface_name[1..]));
+        let doc = [
+            format!(
+                "{} implements {interface_name} by trapping the guest whenever it calls",
+                String::from(typ),
+            ),
+            format!(
+                "into `{}`, for hosts which don't provide it.",
+                interface.wazero_module_name
+            ),
+        ];
+        let methods = interface.methods.iter().map(|method| {
+            let message = format!(
+                "{}#{} is not supported by this host",
+                interface.wazero_module_name, method.name
+            );
+            quote! {
+                func ($typ) $(self.generate_method_signature(method)) {
+                    panic($ERRORS_NEW($(quoted(message))))
+                }
+            }
+        });
+
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(doc))
+            type $typ struct{}
+            $(for method in methods => $['\n']$method)
+        }
+    }
+
+    /// Generate a test skeleton checking that the host's implementation of
+    /// each imported interface honors its contract: that none of its
+    /// methods panic when called with zero values, and that they return
+    /// promptly once their context is done. It starts out testing the
+    /// `Noop` implementations, which are meant to be swapped for the host's.
+    pub fn conformance_tests(&self) -> Tokens<Go> {
+        let mut tokens = Tokens::new();
+        for interface in &self.analyzed.interfaces {
+            let interface_name = String::from(&interface.go_interface_name);
+            let name = &interface_name[1..];
+            let constructor = &GoIdentifier::private(format!("new-{name}-implementation"));
+            let noop = &GoIdentifier::public(format!("noop-{name}"));
+            let test = &GoIdentifier::public(format!("test-{name}-conformance"));
+            let doc = [
+                format!(
+                    "{} returns the implementation of {interface_name} under test.",
+                    String::from(constructor)
+                ),
+                format!("Replace {} with the host's own.", String::from(noop)),
+            ];
+            let cases = interface.methods.iter().map(|method| {
+                let args = method
+                    .parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(index, param)| {
+                        (GoIdentifier::local(format!("arg{index}")), &param.go_type)
+                    })
+                    .collect::<Vec<_>>();
+                quote! {
+                    {$(quoted(String::from(&method.go_method_name))), func(ctx $CONTEXT_CONTEXT) {
+                        $(for (arg, typ) in &args join ($['\r']) => var $arg $(*typ))
+                        impl.$(&method.go_method_name)(ctx$(for (arg, _) in &args => , $arg))
+                    }},
+                }
+            });
+            quote_in! { tokens =>
+                $['\n']
+                $(comment(doc))
+                func $constructor() $(&interface.go_interface_name) {
+                    return $noop{}
+                }
+                $['\n']
+                func $test(t *$TESTING_T) {
+                    impl := $constructor()
+                    tests := []struct {
+                        name string
+                        call func(ctx $CONTEXT_CONTEXT)
+                    }{
+                        $(for case in cases join ($['\r']) => $case)
+                    }
+                    for _, tt := range tests {
+                        t.Run(tt.name, func(t *$TESTING_T) {
+                            t.Run("does not panic", func(t *$TESTING_T) {
+                                defer func() {
+                                    if r := recover(); r != nil {
+                                        t.Fatalf("panicked: %v", r)
+                                    }
+                                }()
+                                tt.call(t.Context())
+                            })
+                            t.Run("respects context", func(t *$TESTING_T) {
+                                ctx, cancel := $CONTEXT_WITH_CANCEL(t.Context())
+                                cancel()
+                                done := make(chan struct{})
+                                go func() {
+                                    defer close(done)
+                                    defer func() { _ = recover() }()
+                                    tt.call(ctx)
+                                }()
+                                select {
+                                case <-done:
+                                case <-$TIME_AFTER($TIME_SECOND):
+                                    t.Fatal("didn't return within a second of its context being canceled")
+                                }
+                            })
+                        })
+                    }
+                }
+            }
+        }
+        tokens
+    }
+
+    fn generate_resource_type(&self, resource: &AnalyzedResource, tokens: &mut Tokens<Go>) {
+        let methods = resource
+            .methods
+            .iter()
+            .map(|method| self.generate_interface_method(method));
+        let doc = [
+            format!(
+                "{} is a `{}` resource implemented by the host, which guests refer to by",
+                String::from(&resource.go_interface_name),
+                resource.name
+            ),
+            "handle. If a value also has a `Drop(ctx context.Context)` method, it's called".into(),
+            "once the guest drops its handle.".into(),
+        ];
+
+        quote_in! { *tokens =>
+            $['\n']
+            $(comment(doc))
+            type $(&resource.go_interface_name) interface {
+                $(for method in methods join ($['\r']) => $method)
+            }
+            $['\n']
+            var $(&resource.handles) = newInstanceHandles[$(&resource.go_interface_name)]()
+        }
+    }
+
+    /// Generate the signature of an interface's method, which warns the
+    /// host with `--zero-copy` not to hold on to the strings and bytes it's
+    /// called with.
+    fn generate_interface_method(&self, method: &InterfaceMethod) -> Tokens<Go> {
+        let views = self.zero_copy
+            && method
+                .parameters
+                .iter()
+                .any(|param| crate::views_guest_memory(&param.wit_type, self.resolve));
+        quote! {
+            $(if views {
+                $(comment(&[
+                    format!(
+                        "The strings and byte slices {} is called with are views of the guest's",
+                        String::from(&method.go_method_name)
+                    ),
+                    "memory, which are only valid until it returns: copy them to keep them.".into(),
+                ]))
+            })
+            $(self.generate_method_signature(method))
+        }
+    }
+
     fn generate_method_signature(&self, method: &InterfaceMethod) -> Tokens<Go> {
         let return_type = method
             .return_type
@@ -384,22 +1022,150 @@ impl<'a> ImportCodeGenerator<'a> {
                 let enum_interface = &typ.go_type_name;
                 let enum_function = &GoIdentifier::private(format!("is-{}", &typ.name));
                 let variants = cases.iter().map(GoIdentifier::public);
+                let names = || {
+                    cases
+                        .iter()
+                        .map(|case| (GoIdentifier::public(case), quoted(case.as_str())))
+                };
+                let fallback = quoted(format!("{}(%d)", String::from(enum_type)));
+                let parse_function = &GoIdentifier::public(format!("parse-{}", &typ.name));
+                let parse_error = quoted(format!("unknown {} case %q", &typ.name));
                 quote_in! { *tokens =>
                     $['\n']
                     type $(enum_interface) interface {
                         $(enum_function)()
+                        String() string
+                        MarshalText() ([]byte, error)
                     }
                     $['\n']
                     type $(enum_type) int
                     $['\n']
                     func ($(enum_type)) $enum_function() {}
                     $['\n']
+                    $(comment(&["String returns the WIT name of the enum case."]))
+                    func (e $(enum_type)) String() string {
+                        switch e {
+                        $(for (name, wit_name) in names() join ($['\r']) =>
+                        case $name:
+                            return $wit_name
+                        )
+                        default:
+                            return $FMT_SPRINTF($fallback, int(e))
+                        }
+                    }
+                    $['\n']
+                    $(comment(&[
+                        "Error makes the enum case an error, so it can be matched with",
+                        "`errors.Is` when it's the error of a `result`.",
+                    ]))
+                    func (e $(enum_type)) Error() string {
+                        return e.String()
+                    }
+                    $['\n']
+                    $(comment([format!("{} returns the enum case with the given WIT name.", String::from(parse_function))]))
+                    func $parse_function(s string) ($enum_interface, error) {
+                        switch s {
+                        $(for (name, wit_name) in names() join ($['\r']) =>
+                        case $wit_name:
+                            return $name, nil
+                        )
+                        default:
+                            return nil, $FMT_ERRORF($parse_error, s)
+                        }
+                    }
+                    $['\n']
+                    $(comment(&["MarshalText encodes the enum case as its WIT name."]))
+                    func (e $(enum_type)) MarshalText() ([]byte, error) {
+                        return []byte(e.String()), nil
+                    }
+                    $['\n']
+                    $(comment(&["UnmarshalText decodes an enum case from its WIT name."]))
+                    func (e *$(enum_type)) UnmarshalText(text []byte) error {
+                        value, err := $parse_function(string(text))
+                        if err != nil {
+                            return err
+                        }
+                        *e = value.($enum_type)
+                        return nil
+                    }
+                    $['\n']
                     const (
                         $(for name in variants join ($['\r']) => $name $enum_type = iota)
                     )
                     $['\n']
                 }
             }
+            TypeDefinition::Flags { flags, repr } => {
+                let flags_type = &typ.go_type_name;
+                // Like variant cases, the constants are prefixed with the
+                // type's name, as flags types can share member names.
+                let flag_name =
+                    |flag: &String| GoIdentifier::public(format!("{}-{flag}", &typ.name));
+                let constants = flags.iter().enumerate().map(|(index, flag)| {
+                    let name = flag_name(flag);
+                    if index == 0 {
+                        quote!($name $flags_type = 1 << iota)
+                    } else {
+                        quote!($name)
+                    }
+                });
+                let wit_names = || {
+                    flags
+                        .iter()
+                        .map(|flag| (flag_name(flag), quoted(flag.as_str())))
+                };
+                let flag_count = flags.len();
+                let flag_error = quoted(format!("unknown {} flag %q", &typ.name));
+                quote_in! { *tokens =>
+                    $['\n']
+                    type $flags_type $repr
+                    $['\n']
+                    const (
+                        $(for constant in constants join ($['\r']) => $constant)
+                    )
+                    $['\n']
+                    $(comment(&["Has reports whether all of the given flags are set."]))
+                    func (f $flags_type) Has(flags $flags_type) bool {
+                        return f&flags == flags
+                    }
+                    $['\n']
+                    $(comment(&["Set returns a copy with the given flags set as well."]))
+                    func (f $flags_type) Set(flags $flags_type) $flags_type {
+                        return f | flags
+                    }
+                    $['\n']
+                    $(comment(&["MarshalText encodes the set flags as a comma-separated list of WIT names."]))
+                    func (f $flags_type) MarshalText() ([]byte, error) {
+                        names := make([]string, 0, $flag_count)
+                        $(for (name, wit_name) in wit_names() join ($['\r']) =>
+                        if f.Has($name) {
+                            names = append(names, $wit_name)
+                        }
+                        )
+                        return []byte($STRINGS_JOIN(names, ",")), nil
+                    }
+                    $['\n']
+                    $(comment(&["UnmarshalText decodes a comma-separated list of WIT flag names."]))
+                    func (f *$flags_type) UnmarshalText(text []byte) error {
+                        *f = 0
+                        if len(text) == 0 {
+                            return nil
+                        }
+                        for _, name := range $STRINGS_SPLIT(string(text), ",") {
+                            switch name {
+                            $(for (name, wit_name) in wit_names() join ($['\r']) =>
+                            case $wit_name:
+                                *f |= $name
+                            )
+                            default:
+                                return $FMT_ERRORF($flag_error, name)
+                            }
+                        }
+                        return nil
+                    }
+                    $['\n']
+                }
+            }
             TypeDefinition::Alias { target } => {
                 // TODO(#4): We might want a Type Definition (newtype) instead of Type Alias here
                 quote_in! { *tokens =>
@@ -424,6 +1190,10 @@ impl<'a> ImportCodeGenerator<'a> {
                     },
                     CaseDispatch::Wrapped { wrapper_name } => {
                         let payload_field = case.payload.as_ref().map(|p| quote!(Value $p));
+                        let message = match &case.payload {
+                            Some(_) => quote!($FMT_SPRINTF($(quoted(format!("{}: %v", case.name))), c.Value)),
+                            None => quote!($(quoted(&case.name))),
+                        };
                         quote! {
                             $['\n']
                             type $wrapper_name struct {
@@ -431,17 +1201,99 @@ impl<'a> ImportCodeGenerator<'a> {
                             }
                             $['\n']
                             func ($wrapper_name) $marker_method() {}
+                            $['\n']
+                            $(comment(&[
+                                "Error makes the case an error, so it can be matched with `errors.As`",
+                                "when it's the error of a `result`.",
+                            ]))
+                            func (c $wrapper_name) Error() string {
+                                return $message
+                            }
                         }
                     }
                 });
+                let tag_function = &GoIdentifier::public(format!("{}-tag", &typ.name));
+                let tag_cases = cases.iter().enumerate().map(|(i, case)| {
+                    let case_type = match &case.dispatch {
+                        CaseDispatch::DirectRecord { record_type } => quote!($record_type),
+                        CaseDispatch::Wrapped { wrapper_name } => quote!($wrapper_name),
+                    };
+                    quote! {
+                        case $case_type:
+                            return $i
+                    }
+                });
+                let tag_doc = format!(
+                    "{} returns the discriminant of the case held by `v`, as numbered in WIT.",
+                    String::from(tag_function)
+                );
                 quote_in! { *tokens =>
                     $['\n']
                     type $variant_interface interface {
                         $marker_method()
                     }
                     $(for def in case_definitions => $def)
+                    $['\n']
+                    $(comment([tag_doc]))
+                    func $tag_function(v $variant_interface) uint32 {
+                        switch v.(type) {
+                        $(for case in tag_cases join ($['\r']) => $case)
+                        default:
+                            panic($ERRORS_NEW($(quoted(format!("invalid {} case", &typ.name)))))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate the `[resource-drop]` host function, which the guest calls
+    /// once it's done with a handle.
+    fn generate_resource_drop_builder(&self, resource: &AnalyzedResource) -> Tokens<Go> {
+        let handles = &resource.handles;
+        let message = format!("invalid {} handle", resource.name);
+        let export_name = format!("[resource-drop]{}", resource.name);
+
+        quote! {
+            NewFunctionBuilder().
+            WithGoModuleFunction($WAZERO_API_GO_MODULE_FUNC(func(ctx $CONTEXT_CONTEXT, mod $WAZERO_API_MODULE, stack []uint64) {
+                handle := $WAZERO_API_DECODE_U32(stack[0])
+                defer recoverHostPanic($(quoted(export_name.as_str())))
+                logDebug(ctx, options.logger, "calling host function", "function", $(quoted(export_name.as_str())))
+                table := $handles.of(mod)
+                borrowed := table.isBorrowed(handle)
+                value, ok := table.remove(handle)
+                if !ok {
+                    panic(&ABIError{Message: $(quoted(message))})
+                }
+                $(comment(&["Dropping a borrowed handle only ends the borrow"]))
+                if borrowed {
+                    return
                 }
+                if dropper, ok := value.(interface{ Drop($CONTEXT_CONTEXT) }); ok {
+                    dropper.Drop(ctx)
+                }
+            }), []$WAZERO_API_VALUE_TYPE{$WAZERO_API_VALUE_TYPE_I32}, nil).
+            Export($(quoted(export_name))).
+        }
+    }
+
+    /// Generate the host functions the guest calls a function through: the
+    /// synchronous one, and the `[async-lower]` one if it's `async`.
+    fn generate_host_function_builders(
+        &self,
+        method: &InterfaceMethod,
+        param_name: &GoIdentifier,
+    ) -> Tokens<Go> {
+        let sync = self.generate_host_function_builder(method, param_name);
+        if self.asynchronous && crate::is_async(&method.wit_function) {
+            let lowered = self.generate_async_host_function_builder(method, param_name);
+            quote! {
+                $sync
+                $lowered
             }
+        } else {
+            sync
         }
     }
 
@@ -451,31 +1303,80 @@ impl<'a> ImportCodeGenerator<'a> {
         // The name of the parameter representing the interface instance
         // in the generated function.
         param_name: &GoIdentifier,
+    ) -> Tokens<Go> {
+        self.host_function_builder(method, param_name, false)
+    }
+
+    /// Generate the `[async-lower]` host function of a function, which
+    /// completes the call before returning.
+    fn generate_async_host_function_builder(
+        &self,
+        method: &InterfaceMethod,
+        param_name: &GoIdentifier,
+    ) -> Tokens<Go> {
+        self.host_function_builder(method, param_name, true)
+    }
+
+    fn host_function_builder(
+        &self,
+        method: &InterfaceMethod,
+        param_name: &GoIdentifier,
+        // Whether the function is async-lowered rather than called
+        // synchronously.
+        asynchronous: bool,
     ) -> Tokens<Go> {
         let func_name = &method.name;
 
         let wasm_sig = self
             .resolve
             .wasm_signature(AbiVariant::GuestImport, &method.wit_function);
-        let result = if wasm_sig.results.is_empty() {
-            GoResult::Empty
-        } else if wasm_sig.results.len() == 1 {
-            GoResult::Anon(resolve_wasm_type(&wasm_sig.results[0]))
-        } else {
-            todo!("implement handling of wasm signatures with multiple results");
+        // The async ABI flattens at most 4 parameters, where the sync one
+        // flattens up to 16, so only the two agree on how parameters beyond
+        // 16 are passed. Those in between are spilled to memory and passed
+        // through a pointer, along with the out pointer for the result.
+        let flat_params = wasm_sig.params.len() - usize::from(wasm_sig.retptr);
+        let spilled =
+            asynchronous && !wasm_sig.indirect_params && flat_params > MAX_FLAT_ASYNC_PARAMS;
+        // The core signature is derived from the Go one, so several core
+        // results are returned as several Go values.
+        let result = match wasm_sig.results.as_slice() {
+            [] => GoResult::Empty,
+            [result] => GoResult::Anon(resolve_wasm_type(result)),
+            results => GoResult::Anon(GoType::MultiReturn(
+                results.iter().map(resolve_wasm_type).collect(),
+            )),
         };
-        let mut f = Func::import(param_name, result, self.sizes);
+        let mut f = Func::import(param_name, result, self.sizes)
+            .with_name(func_name.to_string())
+            .with_generic_containers(self.generic_containers)
+            .with_zero_copy(self.zero_copy)
+            .with_zero_buffers(self.zero_buffers)
+            .with_tracing(self.tracing)
+            .with_wit_result(method.wit_function.result);
+        if asynchronous {
+            f = f.with_async();
+        }
 
         // Magic
-        wit_bindgen_core::abi::call(
-            self.resolve,
-            AbiVariant::GuestImport,
-            LiftLower::LiftArgsLowerResults,
-            &method.wit_function,
-            &mut f,
-            // async is not currently supported
-            false,
-        );
+        if spilled {
+            f.call_spilled(self.resolve, &method.wit_function);
+        } else {
+            wit_bindgen_core::abi::call(
+                self.resolve,
+                AbiVariant::GuestImport,
+                LiftLower::LiftArgsLowerResults,
+                &method.wit_function,
+                &mut f,
+                // The async ABI is handled by `Func` itself
+                false,
+            );
+        }
+        let core_sig = if spilled {
+            self.resolve
+                .wasm_signature(AbiVariant::GuestImportAsync, &method.wit_function)
+        } else {
+            wasm_sig.clone()
+        };
 
         // Collect all host function parameters into a single list so
         // that the join produces correct commas even when there are no
@@ -484,18 +1385,86 @@ impl<'a> ImportCodeGenerator<'a> {
             quote! { ctx $CONTEXT_CONTEXT },
             quote! { mod $WAZERO_API_MODULE },
         ];
-        for arg in f.args() {
-            all_params.push(quote! { $arg uint32 });
+        let mut core_params = Vec::new();
+        for (arg, typ) in f.args().iter().zip(&core_sig.params) {
+            let typ = host_param_type(typ);
+            all_params.push(quote! { $arg $(&typ) });
+            core_params.push(typ);
         }
 
+        // The async ABI passes results through an out pointer even when the
+        // sync one returns them, and returns the subtask's status instead.
+        let (export_name, result, core_results) = if asynchronous {
+            if !spilled && wasm_sig.results.len() == 1 {
+                all_params.push(quote! { retptr uint32 });
+                core_params.push(GoType::Uint32);
+            }
+            (
+                format!("[async-lower]{func_name}"),
+                quote!(uint32),
+                vec![GoType::Uint32],
+            )
+        } else {
+            let core_results = wasm_sig.results.iter().map(resolve_wasm_type).collect();
+            (func_name.to_string(), quote!($(f.result())), core_results)
+        };
+
+        // wazero passes the parameters and results on a stack of `uint64`s,
+        // which spares it reflecting on the typed function on every call.
+        let args = core_params
+            .iter()
+            .enumerate()
+            .map(|(index, typ)| decode_stack_value(typ, index));
+        let call = quote!(fn(ctx, mod$(for arg in args => , $arg)));
+        let call = match core_results.as_slice() {
+            [] => call,
+            [typ] => quote!(stack[0] = $(encode_stack_value(typ, call))),
+            types => {
+                let results = (0..types.len())
+                    .map(|index| GoIdentifier::local(format!("result{index}")))
+                    .collect::<Vec<_>>();
+                quote! {
+                    $(for result in &results join (, ) => $result) := $call
+                    $(for (index, (result, typ)) in results.iter().zip(types).enumerate() join ($['\r']) =>
+                        stack[$index] = $(encode_stack_value(typ, result))
+                    )
+                }
+            }
+        };
+        // Methods are called on a resource rather than on the interface,
+        // which may be nil when the factory allows optional imports.
+        let calls_interface = !matches!(
+            method.wit_function.kind,
+            FunctionKind::Method(_) | FunctionKind::AsyncMethod(_)
+        );
+
         quote! {
             NewFunctionBuilder().
-            WithFunc(func(
-                $(for param in all_params join (,$['\r']) => $param),
-            ) $(f.result()){
-                $(f.body())
-            }).
-            Export($(quoted(func_name))).
+            WithGoModuleFunction($WAZERO_API_GO_MODULE_FUNC(func(ctx $CONTEXT_CONTEXT, mod $WAZERO_API_MODULE, stack []uint64) {
+                fn := func(
+                    $(for param in all_params join (,$['\r']) => $param),
+                ) $result {
+                    $(if calls_interface {
+                        if $param_name == nil {
+                            panic(&ImportNotProvidedError{Import: $(quoted(String::from(param_name))), Function: $(quoted(export_name.as_str()))})
+                        }
+                    })
+                    defer recoverHostPanic($(quoted(export_name.as_str())))
+                    logDebug(ctx, options.logger, "calling host function", "function", $(quoted(export_name.as_str())))
+                    $(if self.tracing {
+                        ctx, span := tracer.Start(ctx, $(quoted(export_name.as_str())))
+                        defer span.End()
+                        defer traceHostPanic(span, $(quoted(export_name.as_str())))
+                    })
+                    $(f.body())
+                }
+                interceptHost(ctx, options.hostInterceptor, $(quoted(String::from(param_name))), $(quoted(export_name.as_str())), func(ctx $CONTEXT_CONTEXT) {
+                    $call
+                })
+            }),
+            []$WAZERO_API_VALUE_TYPE{$(for typ in &core_params join (, ) => $(host_value_type(typ)))},
+            []$WAZERO_API_VALUE_TYPE{$(for typ in &core_results join (, ) => $(host_value_type(typ)))}).
+            Export($(quoted(export_name))).
         }
     }
 }
@@ -505,13 +1474,14 @@ mod tests {
     use genco::prelude::*;
     use wit_bindgen_core::wit_parser::{
         Enum, EnumCase, Function, FunctionKind, Interface, Package, PackageName, Param, Resolve,
-        SizeAlign, Type, TypeDef, TypeDefKind, TypeOwner, World, WorldId, WorldItem, WorldKey,
+        Result_, SizeAlign, Tuple, Type, TypeDef, TypeDefKind, TypeOwner, World, WorldId,
+        WorldItem, WorldKey,
     };
 
     use crate::{
         codegen::{
             imports::{ImportAnalyzer, ImportCodeGenerator},
-            ir::{AnalyzedImports, InterfaceMethod, Parameter, WitReturn},
+            ir::{AnalyzedImports, AnalyzedInterface, InterfaceMethod, Parameter, WitReturn},
         },
         go::{GoIdentifier, GoType},
     };
@@ -542,8 +1512,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
 
         let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
@@ -582,8 +1555,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
         let resolve = Resolve::new();
         let sizes = SizeAlign::default();
@@ -638,8 +1614,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
         let resolve = Resolve::new();
         let sizes = SizeAlign::default();
@@ -727,8 +1706,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
 
         let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
@@ -788,8 +1770,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
         let resolve = Resolve::new();
         let sizes = SizeAlign::default();
@@ -840,13 +1825,16 @@ mod tests {
         let result = generator.generate_host_function_builder(&method, &param_name);
 
         let code_str = result.to_string().unwrap();
+        // Only the adapter reading the arguments off wazero's stack decodes
+        // them; the host function itself gets them as uint32s.
+        let body = code_str.split("\n    interceptHost(").next().unwrap();
         // Must use simple uint32() casts, NOT api.DecodeU32() which expects uint64
         assert!(
-            !code_str.contains("api.DecodeU32"),
+            !body.contains("api.DecodeU32"),
             "Import must not use api.DecodeU32 (expects uint64 but params are uint32), got:\n{code_str}"
         );
         assert!(
-            !code_str.contains("api.EncodeU32"),
+            !body.contains("api.EncodeU32"),
             "Import must not use api.EncodeU32 (returns uint64 but context expects uint32), got:\n{code_str}"
         );
         // Should use uint32() identity casts instead
@@ -869,8 +1857,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
         let resolve = Resolve::new();
         let sizes = SizeAlign::default();
@@ -911,7 +1902,9 @@ mod tests {
         );
         // The signature should close cleanly after mod api.Module
         assert!(
-            code_str.contains("mod api.Module,\n)") || code_str.contains("mod api.Module,\n\t)"),
+            code_str
+                .split_once("mod api.Module,\n")
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with(')')),
             "Expected host function params to end with 'mod api.Module,' followed by closing paren, got:\n{code_str}"
         );
     }
@@ -925,8 +1918,11 @@ mod tests {
             interfaces: vec![],
             standalone_functions: vec![],
             standalone_types: vec![],
+            exported_types: vec![],
             factory_name: GoIdentifier::public("TestFactory"),
             constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
         };
         let resolve = Resolve::new();
         let sizes = SizeAlign::default();
@@ -971,129 +1967,1594 @@ mod tests {
             code_str.contains("return"),
             "Expected a return statement, got:\n{code_str}"
         );
+        // Must be registered without reflection
+        assert!(
+            code_str.contains("WithGoModuleFunction(api.GoModuleFunc(func(ctx context.Context, mod api.Module, stack []uint64) {")
+                && code_str.contains("stack[0] = api.EncodeU32(fn(ctx, mod))")
+                && code_str.contains("[]api.ValueType{},\n[]api.ValueType{api.ValueTypeI32}).")
+                && !code_str.contains("WithFunc("),
+            "Expected a stack-based host function, got:\n{code_str}"
+        );
     }
 
-    fn create_test_world_with_interface() -> (Resolve, WorldId) {
+    /// Options lift to `*T` for the host and lower through the return
+    /// pointer, so `option<string>` round-trips without hitting a `todo!()`.
+    #[test]
+    fn test_import_with_option_param_and_return() {
         let mut resolve = Resolve::default();
-
-        // Create a package
-        let package_name = PackageName {
-            namespace: "test".to_string(),
-            name: "pkg".to_string(),
-            version: None,
-        };
-        let package_id = resolve.packages.alloc(Package {
-            name: package_name.clone(),
-            interfaces: Default::default(),
-            worlds: Default::default(),
+        let option_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Option(Type::String),
+            owner: TypeOwner::None,
             docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
         });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
 
-        // Create an interface with a function
-        let interface_id = resolve.interfaces.alloc(Interface {
-            name: Some("logger".to_string()),
-            package: Some(package_id),
-            functions: [(
-                "log".to_string(),
-                Function {
-                    name: "log".to_string(),
-                    params: vec![Param {
-                        name: "message".to_string(),
-                        ty: Type::String,
-                        span: Default::default(),
-                    }],
-                    result: None,
-                    kind: FunctionKind::Freestanding,
-                    docs: Default::default(),
-                    stability: Default::default(),
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let option_string = GoType::Pointer(Box::new(GoType::String));
+        let method = InterfaceMethod {
+            name: "lookup".to_string(),
+            go_method_name: GoIdentifier::public("Lookup"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("key"),
+                go_type: option_string.clone(),
+                wit_type: Type::Id(option_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: option_string,
+                wit_type: Type::Id(option_id),
+            }),
+            wit_function: Function {
+                name: "lookup".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "key".to_string(),
+                    ty: Type::Id(option_id),
                     span: Default::default(),
-                },
-            )]
-            .into(),
-            types: Default::default(),
+                }],
+                result: Some(Type::Id(option_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        // The discriminant, pointer and length of the param, plus the return pointer
+        assert!(
+            code_str.contains("arg3 uint32"),
+            "Expected a return pointer argument, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("if arg0 != 0 {"),
+            "Expected the option param to be lifted from its discriminant, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(" == nil {"),
+            "Expected the option return to be lowered from a nil check, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("writeString"),
+            "Expected the `some` payload to be written to memory, got:\n{code_str}"
+        );
+    }
+
+    /// A `result<T, E>` with a non-string error surfaces as `(T, error)`, and
+    /// the typed payload is recovered from a `ResultError[E]` when lowering.
+    #[test]
+    fn test_import_with_typed_result_error() {
+        let mut resolve = Resolve::default();
+        let code_id = resolve.types.alloc(TypeDef {
+            name: Some("code".to_string()),
+            kind: TypeDefKind::Enum(Enum {
+                cases: vec![
+                    EnumCase {
+                        name: "not-found".to_string(),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                    EnumCase {
+                        name: "denied".to_string(),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                ],
+            }),
+            owner: TypeOwner::None,
             docs: Default::default(),
             stability: Default::default(),
             span: Default::default(),
-            clone_of: None,
         });
-
-        // Create a world with the interface as import
-        let world = World {
-            name: "test-world".to_string(),
-            imports: [(
-                WorldKey::Name("logger".to_string()),
-                WorldItem::Interface {
-                    id: interface_id,
-                    stability: Default::default(),
-                    span: Default::default(),
-                },
-            )]
-            .into(),
-            exports: Default::default(),
+        let result_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Result(Result_ {
+                ok: Some(Type::String),
+                err: Some(Type::Id(code_id)),
+            }),
+            owner: TypeOwner::None,
             docs: Default::default(),
             stability: Default::default(),
-            package: Some(package_id),
-            includes: Default::default(),
             span: Default::default(),
-        };
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
 
-        let world_id = resolve.worlds.alloc(world);
-        (resolve, world_id)
-    }
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
 
-    #[test]
-    fn test_import_analyzer() {
-        let (resolve, world_id) = create_test_world_with_interface();
-        let world = &resolve.worlds[world_id];
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
 
-        let analyzer = ImportAnalyzer::new(&resolve, &world);
-        let analyzed = analyzer.analyze();
+        let go_type = crate::resolve_return_type(&Type::Id(result_id), &resolve);
+        assert_eq!(go_type, GoType::ValueOrError(Box::new(GoType::String)));
+        let method = InterfaceMethod {
+            name: "fetch".to_string(),
+            go_method_name: GoIdentifier::public("Fetch"),
+            parameters: vec![],
+            return_type: Some(WitReturn {
+                go_type,
+                wit_type: Type::Id(result_id),
+            }),
+            wit_function: Function {
+                name: "fetch".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![],
+                result: Some(Type::Id(result_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
 
-        // Check that we got one interface
-        assert_eq!(analyzed.interfaces.len(), 1);
-        let interface = &analyzed.interfaces[0];
+        let signature = generator.generate_method_signature(&method);
+        let signature_str = signature.to_string().unwrap();
+        assert!(
+            signature_str.contains("(string, error)"),
+            "Expected an idiomatic `(T, error)` signature, got:\n{signature_str}"
+        );
 
-        assert_eq!(interface.name, "logger");
-        assert_eq!(interface.methods.len(), 1);
+        let interface = AnalyzedInterface {
+            name: "fetcher".to_string(),
+            methods: vec![method.clone()],
+            types: vec![],
+            resources: vec![],
+            go_interface_name: GoIdentifier::public("ITestFetcher"),
+            constructor_param_name: GoIdentifier::private("fetcher"),
+            wazero_module_name: "test:fetcher".to_string(),
+        };
+        let mut tokens = Tokens::<Go>::new();
+        generator.generate_noop_interface(&interface, &mut tokens);
+        let noop_str = tokens.to_string().unwrap();
+        assert!(
+            noop_str.contains("var zero0 string")
+                && noop_str.contains("var zero1 error")
+                && noop_str.contains("return zero0, zero1"),
+            "Expected the no-op to return zero values, got:\n{noop_str}"
+        );
 
-        let method = &interface.methods[0];
-        assert_eq!(method.name, "log");
-        assert_eq!(method.parameters.len(), 1);
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
 
-        let param = &method.parameters[0];
-        assert!(matches!(param.go_type, GoType::String));
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("value0, err0 := handler.Fetch(ctx"),
+            "Expected the host to return `(T, error)`, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("var resultErr *ResultError[Code]"),
+            "Expected the typed error payload to be recovered, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("errors.As(err0, &resultErr)"),
+            "Expected `errors.As` to unwrap the error chain, got:\n{code_str}"
+        );
     }
 
+    /// A `result` without payloads is returned by the host as a bare
+    /// `error`, which is lowered to the result's discriminant.
     #[test]
-    fn test_import_code_generator() {
-        let (resolve, world_id) = create_test_world_with_interface();
-        let world = &resolve.worlds[world_id];
-        let sizes = SizeAlign::default();
+    fn test_import_with_empty_result_return() {
+        let mut resolve = Resolve::default();
+        let result_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Result(Result_ {
+                ok: None,
+                err: None,
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
 
-        // Analyze
-        let analyzer = ImportAnalyzer::new(&resolve, &world);
-        let analyzed = analyzer.analyze();
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
 
-        // Generate
         let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
-        let mut tokens = Tokens::<Go>::new();
-        generator.format_into(&mut tokens);
 
-        let output = tokens.to_string().unwrap();
-        assert!(output.contains("type ITestWorldLogger interface"));
-        assert!(output.contains("Log("));
+        let go_type = crate::resolve_return_type(&Type::Id(result_id), &resolve);
+        assert_eq!(go_type, GoType::Error);
+        let method = InterfaceMethod {
+            name: "check".to_string(),
+            go_method_name: GoIdentifier::public("Check"),
+            parameters: vec![],
+            return_type: Some(WitReturn {
+                go_type,
+                wit_type: Type::Id(result_id),
+            }),
+            wit_function: Function {
+                name: "check".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![],
+                result: Some(Type::Id(result_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let signature = generator.generate_method_signature(&method);
+        let signature_str = signature.to_string().unwrap();
+        assert!(
+            signature_str.contains(") error"),
+            "Expected an `error` result, got:\n{signature_str}"
+        );
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("err0 := handler.Check(ctx, )"),
+            "Expected the host to return an `error`, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("var variant1_0 uint32"),
+            "Expected the discriminant to be joined from both cases, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("return variant1_0"),
+            "Expected the discriminant to be returned, got:\n{code_str}"
+        );
     }
 
+    /// Lists lift into a Go slice element by element and lower through
+    /// `cabi_realloc`, writing each element at its offset in guest memory.
     #[test]
-    fn test_record_type_generation() {
-        use crate::codegen::ir::TypeDefinition;
-        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
-
+    fn test_import_with_list_param_and_return() {
         let mut resolve = Resolve::default();
-
-        // Create a package
-        let package_name = PackageName {
-            namespace: "test".to_string(),
+        let list_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let list_u32 = GoType::Slice(Box::new(GoType::Uint32));
+        let method = InterfaceMethod {
+            name: "double-all".to_string(),
+            go_method_name: GoIdentifier::public("DoubleAll"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("values"),
+                go_type: list_u32.clone(),
+                wit_type: Type::Id(list_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: list_u32,
+                wit_type: Type::Id(list_id),
+            }),
+            wit_function: Function {
+                name: "double-all".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "values".to_string(),
+                    ty: Type::Id(list_id),
+                    span: Default::default(),
+                }],
+                result: Some(Type::Id(list_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("make([]uint32, "),
+            "Expected the list param to be lifted into a slice, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.Memory().ReadUint32Le("),
+            "Expected each element to be read from memory, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.ExportedFunction(\"cabi_realloc\")"),
+            "Expected the returned list to be allocated in the guest, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.Memory().WriteUint32Le(base+0, uint32("),
+            "Expected each element to be written to memory, got:\n{code_str}"
+        );
+    }
+
+    /// Byte lists skip the element-wise loop and copy the whole payload with
+    /// a single `Read`/`Write` in each direction.
+    #[test]
+    fn test_import_with_byte_list_param_and_return() {
+        let mut resolve = Resolve::default();
+        let bytes_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::U8),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let bytes = GoType::Slice(Box::new(GoType::Uint8));
+        let method = InterfaceMethod {
+            name: "transform".to_string(),
+            go_method_name: GoIdentifier::public("Transform"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("payload"),
+                go_type: bytes.clone(),
+                wit_type: Type::Id(bytes_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: bytes,
+                wit_type: Type::Id(bytes_id),
+            }),
+            wit_function: Function {
+                name: "transform".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "payload".to_string(),
+                    ty: Type::Id(bytes_id),
+                    span: Default::default(),
+                }],
+                result: Some(Type::Id(bytes_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let signature = generator.generate_method_signature(&method);
+        let signature_str = signature.to_string().unwrap();
+        assert!(
+            signature_str.contains("payload []byte,") && signature_str.contains(") []byte"),
+            "Expected byte lists to be `[]byte`, got:\n{signature_str}"
+        );
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("mod.Memory().Read(arg0, arg1)"),
+            "Expected the param to be read in one go, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("if !mod.Memory().Write(uint32("),
+            "Expected the return to be written in one go, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(
+                r#"panic(&ABIError{Message: fmt.Sprintf("transform: failed to read bytes from memory at %d", arg0)})"#
+            ),
+            "Expected failed reads to trap naming the function, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("(options.maxLength > 0 && arg1 > options.maxLength)"),
+            "Expected the length to be checked before reading, got:\n{code_str}"
+        );
+        assert!(
+            !code_str.contains("for idx"),
+            "Expected no element-wise loops for byte lists, got:\n{code_str}"
+        );
+    }
+
+    /// With zero-copy on, strings and byte lists are handed to the host as
+    /// views of guest memory rather than copied out of it.
+    #[test]
+    fn test_import_zero_copy() {
+        let mut resolve = Resolve::default();
+        let bytes_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::U8),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let method = InterfaceMethod {
+            name: "send".to_string(),
+            go_method_name: GoIdentifier::public("Send"),
+            parameters: vec![
+                Parameter {
+                    name: GoIdentifier::private("topic"),
+                    go_type: GoType::String,
+                    wit_type: Type::String,
+                },
+                Parameter {
+                    name: GoIdentifier::private("payload"),
+                    go_type: GoType::Slice(Box::new(GoType::Uint8)),
+                    wit_type: Type::Id(bytes_id),
+                },
+            ],
+            return_type: None,
+            wit_function: Function {
+                name: "send".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![
+                    Param {
+                        name: "topic".to_string(),
+                        ty: Type::String,
+                        span: Default::default(),
+                    },
+                    Param {
+                        name: "payload".to_string(),
+                        ty: Type::Id(bytes_id),
+                        span: Default::default(),
+                    },
+                ],
+                result: None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let copying = ImportCodeGenerator::new(&resolve, &analyzed, &sizes)
+            .generate_host_function_builder(&method, &param_name)
+            .to_string()
+            .unwrap();
+        assert!(
+            !copying.contains("unsafe.String(") && copying.contains("copy("),
+            "Expected strings and bytes to be copied by default, got:\n{copying}"
+        );
+        assert!(!copying.contains("defer clear("));
+
+        let zeroing = ImportCodeGenerator::new(&resolve, &analyzed, &sizes)
+            .with_zero_buffers(true)
+            .generate_host_function_builder(&method, &param_name)
+            .to_string()
+            .unwrap();
+        assert!(
+            zeroing.contains("defer clear(result"),
+            "Expected the copied bytes to be zeroed once the host returns, got:\n{zeroing}"
+        );
+
+        let code_str = ImportCodeGenerator::new(&resolve, &analyzed, &sizes)
+            .with_zero_copy(true)
+            .generate_host_function_builder(&method, &param_name)
+            .to_string()
+            .unwrap();
+        assert!(
+            code_str.contains("unsafe.String(unsafe.SliceData(buf"),
+            "Expected the string to be a view of guest memory, got:\n{code_str}"
+        );
+        assert!(
+            !code_str.contains("copy("),
+            "Expected the bytes not to be copied, got:\n{code_str}"
+        );
+
+        // The host is warned not to keep the views past the call.
+        let signature = ImportCodeGenerator::new(&resolve, &analyzed, &sizes)
+            .with_zero_copy(true)
+            .generate_interface_method(&method)
+            .to_string()
+            .unwrap();
+        assert!(
+            signature.starts_with("// The strings and byte slices Send is called with are views"),
+            "Expected the method to document its arguments' lifetime, got:\n{signature}"
+        );
+    }
+
+    /// A tuple result comes back from the host as multiple Go values, which
+    /// are gathered into a `TupleN` before being written to the return area.
+    #[test]
+    fn test_import_with_tuple_param_and_return() {
+        let mut resolve = Resolve::default();
+        let tuple_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Tuple(Tuple {
+                types: vec![Type::U32, Type::U64],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let wit_function = Function {
+            name: "swap".to_string(),
+            kind: FunctionKind::Freestanding,
+            params: vec![Param {
+                name: "pair".to_string(),
+                ty: Type::Id(tuple_id),
+                span: Default::default(),
+            }],
+            result: Some(Type::Id(tuple_id)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let method = InterfaceMethod {
+            name: "swap".to_string(),
+            go_method_name: GoIdentifier::public("Swap"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("pair"),
+                go_type: crate::resolve_type(&Type::Id(tuple_id), &resolve),
+                wit_type: Type::Id(tuple_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: crate::resolve_return_type(&Type::Id(tuple_id), &resolve),
+                wit_type: Type::Id(tuple_id),
+            }),
+            wit_function,
+        };
+
+        let signature = generator.generate_method_signature(&method);
+        let interface_str = signature.to_string().unwrap();
+        assert!(
+            interface_str.contains("pair Tuple2[uint32, uint64]"),
+            "Expected the tuple param to be a `Tuple2`, got:\n{interface_str}"
+        );
+        assert!(
+            interface_str.contains(") (uint32, uint64)"),
+            "Expected the tuple result to be multiple return values, got:\n{interface_str}"
+        );
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("Tuple2[uint32, uint64]{"),
+            "Expected the tuple param to be lifted into a `Tuple2`, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(" := handler.Swap(ctx, "),
+            "Expected the host to be called, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(".F0") && code_str.contains(".F1"),
+            "Expected the returned tuple to be split into its elements, got:\n{code_str}"
+        );
+    }
+
+    /// Flags lower to a single `i32` and lift back into the named bitmask type.
+    #[test]
+    fn test_import_with_flags_param_and_return() {
+        use wit_bindgen_core::wit_parser::{Flag, Flags};
+
+        let mut resolve = Resolve::default();
+        let flags_id = resolve.types.alloc(TypeDef {
+            name: Some("permissions".to_string()),
+            kind: TypeDefKind::Flags(Flags {
+                flags: ["read", "write", "execute"]
+                    .into_iter()
+                    .map(|name| Flag {
+                        name: name.to_string(),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    })
+                    .collect(),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let permissions = GoType::UserDefined("permissions".to_string());
+        let method = InterfaceMethod {
+            name: "restrict".to_string(),
+            go_method_name: GoIdentifier::public("Restrict"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("perms"),
+                go_type: permissions.clone(),
+                wit_type: Type::Id(flags_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: permissions,
+                wit_type: Type::Id(flags_id),
+            }),
+            wit_function: Function {
+                name: "restrict".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "perms".to_string(),
+                    ty: Type::Id(flags_id),
+                    span: Default::default(),
+                }],
+                result: Some(Type::Id(flags_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains(" := Permissions(uint8(arg0))"),
+            "Expected the flags param to be lifted into `Permissions`, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(" := handler.Restrict(ctx, "),
+            "Expected the host to be called, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("flags2_0 := uint32(value1)"),
+            "Expected the returned flags to be lowered to an `i32`, got:\n{code_str}"
+        );
+    }
+
+    /// Host functions receive and return `char` as a validated `rune`.
+    #[test]
+    fn test_import_with_char_param_and_return() {
+        let resolve = Resolve::default();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let method = InterfaceMethod {
+            name: "upper".to_string(),
+            go_method_name: GoIdentifier::public("Upper"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("c"),
+                go_type: GoType::Rune,
+                wit_type: Type::Char,
+            }],
+            return_type: Some(WitReturn {
+                go_type: GoType::Rune,
+                wit_type: Type::Char,
+            }),
+            wit_function: Function {
+                name: "upper".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "c".to_string(),
+                    ty: Type::Char,
+                    span: Default::default(),
+                }],
+                result: Some(Type::Char),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("result0 := rune(arg0)"),
+            "Expected the param to be lifted into a rune, got:\n{code_str}"
+        );
+        assert_eq!(
+            code_str.matches("utf8.ValidRune(").count(),
+            2,
+            "Expected the param and the result to be validated, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("return uint32(result2)"),
+            "Expected the returned rune to be lowered to an `i32`, got:\n{code_str}"
+        );
+    }
+
+    /// Signed integers are sign-extended into unsigned core values, and 64-bit
+    /// params keep their upper half.
+    #[test]
+    fn test_import_with_signed_params_and_return() {
+        let resolve = Resolve::default();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let method = InterfaceMethod {
+            name: "clamp".to_string(),
+            go_method_name: GoIdentifier::public("Clamp"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("value"),
+                go_type: GoType::Int64,
+                wit_type: Type::S64,
+            }],
+            return_type: Some(WitReturn {
+                go_type: GoType::Int8,
+                wit_type: Type::S8,
+            }),
+            wit_function: Function {
+                name: "clamp".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "value".to_string(),
+                    ty: Type::S64,
+                    span: Default::default(),
+                }],
+                result: Some(Type::S8),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("arg0 uint64,"),
+            "Expected the s64 param to arrive as a uint64, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("value0 := int64(arg0)"),
+            "Expected the s64 param to be lifted into an int64, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("value2 := uint32(value1)"),
+            "Expected the s8 result to be sign-extended into a uint32, got:\n{code_str}"
+        );
+        assert!(
+            !code_str.contains("api.EncodeI32"),
+            "api.EncodeI32 returns a uint64 where an i32 is expected, got:\n{code_str}"
+        );
+    }
+
+    /// Host functions take and return floats natively, and float record
+    /// fields are written to memory as floats rather than as their bits.
+    #[test]
+    fn test_import_with_float_record_param_and_return() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
+
+        let mut resolve = Resolve::default();
+        let point_id = resolve.types.alloc(TypeDef {
+            name: Some("point".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![
+                    Field {
+                        name: "x".to_string(),
+                        ty: Type::F32,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                    Field {
+                        name: "y".to_string(),
+                        ty: Type::F64,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                ],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let point = GoType::UserDefined("point".to_string());
+        let method = InterfaceMethod {
+            name: "scale".to_string(),
+            go_method_name: GoIdentifier::public("Scale"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("p"),
+                go_type: point.clone(),
+                wit_type: Type::Id(point_id),
+            }],
+            return_type: Some(WitReturn {
+                go_type: point,
+                wit_type: Type::Id(point_id),
+            }),
+            wit_function: Function {
+                name: "scale".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "p".to_string(),
+                    ty: Type::Id(point_id),
+                    span: Default::default(),
+                }],
+                result: Some(Type::Id(point_id)),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("arg0 float32,") && code_str.contains("arg1 float64,"),
+            "Expected the float fields to arrive as native floats, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("WriteFloat32Le(") && code_str.contains("WriteFloat64Le("),
+            "Expected the returned fields to be written as floats, got:\n{code_str}"
+        );
+        let body = code_str.split("\n    interceptHost(").next().unwrap();
+        assert!(
+            !body.contains("api.EncodeF") && !body.contains("api.DecodeF"),
+            "Host functions don't deal in uint64 stack values, got:\n{code_str}"
+        );
+    }
+
+    /// Records with heap-allocated fields are lifted field by field, with each
+    /// string and list read out of guest memory.
+    #[test]
+    fn test_import_with_record_of_string_and_list_param() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
+
+        let mut resolve = Resolve::default();
+        let tags_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::List(Type::String),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let entry_id = resolve.types.alloc(TypeDef {
+            name: Some("entry".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![
+                    Field {
+                        name: "name".to_string(),
+                        ty: Type::String,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                    Field {
+                        name: "tags".to_string(),
+                        ty: Type::Id(tags_id),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                ],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let method = InterfaceMethod {
+            name: "describe".to_string(),
+            go_method_name: GoIdentifier::public("Describe"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("entry"),
+                go_type: GoType::UserDefined("entry".to_string()),
+                wit_type: Type::Id(entry_id),
+            }],
+            return_type: None,
+            wit_function: Function {
+                name: "describe".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "entry".to_string(),
+                    ty: Type::Id(entry_id),
+                    span: Default::default(),
+                }],
+                result: None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("arg3 uint32,"),
+            "Expected the record to be passed as four flat params, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.Memory().Read(arg0, arg1)"),
+            "Expected the name to be read from memory, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("make([]string, "),
+            "Expected the tags to be lifted into a slice, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("ReadUint32Le(uint32(base + 4))"),
+            "Expected each tag's length to be read from memory, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(":= Entry{"),
+            "Expected the fields to be gathered into an `Entry`, got:\n{code_str}"
+        );
+    }
+
+    /// Optional record fields are pointers, set only when the field's
+    /// discriminant marks it present.
+    #[test]
+    fn test_import_with_record_of_option_fields_param() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
+
+        let mut resolve = Resolve::default();
+        let name_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Option(Type::String),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let retries_id = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Option(Type::U32),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let config_id = resolve.types.alloc(TypeDef {
+            name: Some("config".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![
+                    Field {
+                        name: "name".to_string(),
+                        ty: Type::Id(name_id),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                    Field {
+                        name: "retries".to_string(),
+                        ty: Type::Id(retries_id),
+                        docs: Default::default(),
+                        span: Default::default(),
+                    },
+                ],
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        assert_eq!(
+            crate::resolve_type(&Type::Id(name_id), &resolve),
+            GoType::Pointer(Box::new(GoType::String))
+        );
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let method = InterfaceMethod {
+            name: "configure".to_string(),
+            go_method_name: GoIdentifier::public("Configure"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("config"),
+                go_type: GoType::UserDefined("config".to_string()),
+                wit_type: Type::Id(config_id),
+            }],
+            return_type: None,
+            wit_function: Function {
+                name: "configure".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "config".to_string(),
+                    ty: Type::Id(config_id),
+                    span: Default::default(),
+                }],
+                result: None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("arg4 uint32,"),
+            "Expected each field to be passed as a discriminant and payload, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("*string") && code_str.contains("*uint32"),
+            "Expected the fields to be lifted into pointers, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("if arg0 != 0 {") && code_str.contains("if arg3 != 0 {"),
+            "Expected each field to be set only when present, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(":= Config{"),
+            "Expected the fields to be gathered into a `Config`, got:\n{code_str}"
+        );
+    }
+
+    /// Records too large to flatten are passed by pointer, and each field is
+    /// read at its `SizeAlign` offset.
+    #[test]
+    fn test_import_with_indirect_record_param() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
+
+        let mut resolve = Resolve::default();
+        let labels_id = resolve.types.alloc(TypeDef {
+            name: Some("labels".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: (0..9)
+                    .map(|i| Field {
+                        name: format!("label{i}"),
+                        ty: Type::String,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    })
+                    .collect(),
+            }),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let method = InterfaceMethod {
+            name: "apply".to_string(),
+            go_method_name: GoIdentifier::public("Apply"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("labels"),
+                go_type: GoType::UserDefined("labels".to_string()),
+                wit_type: Type::Id(labels_id),
+            }],
+            return_type: None,
+            wit_function: Function {
+                name: "apply".to_string(),
+                kind: FunctionKind::Freestanding,
+                params: vec![Param {
+                    name: "labels".to_string(),
+                    ty: Type::Id(labels_id),
+                    span: Default::default(),
+                }],
+                result: None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let result = generator.generate_host_function_builder(&method, &param_name);
+
+        let code_str = result.to_string().unwrap();
+        assert!(
+            code_str.contains("arg0 uint32,") && !code_str.contains("arg1"),
+            "Expected the record to be passed by pointer, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("ReadUint32Le(uint32(arg0 + 68))"),
+            "Expected the last label's length to be read at its offset, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains(":= Labels{"),
+            "Expected the fields to be gathered into a `Labels`, got:\n{code_str}"
+        );
+    }
+
+    fn create_test_world_with_interface() -> (Resolve, WorldId) {
+        let mut resolve = Resolve::default();
+
+        // Create a package
+        let package_name = PackageName {
+            namespace: "test".to_string(),
+            name: "pkg".to_string(),
+            version: None,
+        };
+        let package_id = resolve.packages.alloc(Package {
+            name: package_name.clone(),
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
+
+        // Create an interface with a function
+        let interface_id = resolve.interfaces.alloc(Interface {
+            name: Some("logger".to_string()),
+            package: Some(package_id),
+            functions: [(
+                "log".to_string(),
+                Function {
+                    name: "log".to_string(),
+                    params: vec![Param {
+                        name: "message".to_string(),
+                        ty: Type::String,
+                        span: Default::default(),
+                    }],
+                    result: None,
+                    kind: FunctionKind::Freestanding,
+                    docs: Default::default(),
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            types: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+            clone_of: None,
+        });
+
+        // Create a world with the interface as import
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [(
+                WorldKey::Name("logger".to_string()),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: Some(package_id),
+            includes: Default::default(),
+            span: Default::default(),
+        };
+
+        let world_id = resolve.worlds.alloc(world);
+        (resolve, world_id)
+    }
+
+    #[test]
+    fn test_import_analyzer() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
+        let analyzed = analyzer.analyze();
+
+        // Check that we got one interface
+        assert_eq!(analyzed.interfaces.len(), 1);
+        let interface = &analyzed.interfaces[0];
+
+        assert_eq!(interface.name, "logger");
+        assert_eq!(interface.methods.len(), 1);
+
+        let method = &interface.methods[0];
+        assert_eq!(method.name, "log");
+        assert_eq!(method.parameters.len(), 1);
+
+        let param = &method.parameters[0];
+        assert!(matches!(param.go_type, GoType::String));
+    }
+
+    #[test]
+    fn test_import_code_generator() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        // Analyze
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
+        let analyzed = analyzer.analyze();
+
+        // Generate
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+
+        let output = tokens.to_string().unwrap();
+        assert!(output.contains("type ITestWorldLogger interface"));
+        assert!(output.contains("Log("));
+    }
+
+    #[test]
+    fn test_import_tracing() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes).with_tracing(true);
+        let chain = generator.import_chains()["test:pkg/logger"]
+            .to_string()
+            .unwrap();
+
+        assert!(
+            chain.contains(r#"ctx, span := tracer.Start(ctx, "log")"#)
+                && chain.contains("defer span.End()"),
+            "Expected a span around the host function, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(r#"defer traceHostPanic(span, "log")"#),
+            "Expected the span to be marked failed when the host function panics, got:\n{chain}"
+        );
+    }
+
+    #[test]
+    fn test_mock_interface() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let output = tokens.to_string().unwrap();
+
+        assert!(output.contains("type MockTestWorldLogger struct {"));
+        assert!(output.contains("LogFunc func(ctx context.Context, message string)"));
+        assert!(output.contains("func (m *MockTestWorldLogger) Log("));
+        assert!(output.contains(r#"panic("MockTestWorldLogger.Log called without LogFunc")"#));
+        assert!(output.contains("    }\n    m.LogFunc(ctx, message)\n}"));
+    }
+
+    #[test]
+    fn test_noop_interface() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let output = tokens.to_string().unwrap();
+
+        assert!(output.contains("type NoopTestWorldLogger struct{}"));
+        assert!(output.contains("func (NoopTestWorldLogger) Log("));
+        assert!(!output.contains("var zero0"));
+    }
+
+    #[test]
+    fn test_conformance_tests() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let output = generator.conformance_tests().to_string().unwrap();
+
+        assert!(output.contains("func newTestWorldLoggerImplementation() ITestWorldLogger {"));
+        assert!(output.contains("return NoopTestWorldLogger{}"));
+        assert!(output.contains("func TestTestWorldLoggerConformance(t *testing.T) {"));
+        assert!(output.contains("var arg0 string"));
+        assert!(output.contains("impl.Log(ctx, arg0)"));
+        assert!(output.contains("ctx, cancel := context.WithCancel(t.Context())"));
+    }
+
+    #[test]
+    fn test_unsupported_wasi_interface() {
+        let (resolve, world_id) = create_test_world_with_interface();
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
+        let mut analyzed = analyzer.analyze();
+
+        // Non-WASI imports must be provided by the host.
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let output = tokens.to_string().unwrap();
+        assert!(!output.contains("UnsupportedTestWorldLogger"));
+
+        analyzed.interfaces[0].wazero_module_name = "wasi:logging/logger".to_string();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let output = tokens.to_string().unwrap();
+        assert!(output.contains("type UnsupportedTestWorldLogger struct{}"));
+        assert!(output.contains("func (UnsupportedTestWorldLogger) Log("));
+        assert!(
+            output
+                .contains(r#"errors.New("wasi:logging/logger#log is not supported by this host")"#)
+        );
+    }
+
+    /// Same-named WASI interfaces from different packages get distinct
+    /// trapping implementations, as they do interfaces, mocks and noops.
+    #[test]
+    fn test_unsupported_same_named_wasi_interfaces() {
+        let mut resolve = Resolve::default();
+        let mut imports = Vec::new();
+        for package_name in ["http", "filesystem"] {
+            let package_id = resolve.packages.alloc(Package {
+                name: PackageName {
+                    namespace: "wasi".to_string(),
+                    name: package_name.to_string(),
+                    version: None,
+                },
+                interfaces: Default::default(),
+                worlds: Default::default(),
+                docs: Default::default(),
+            });
+            let interface_id = resolve.interfaces.alloc(Interface {
+                name: Some("types".to_string()),
+                package: Some(package_id),
+                functions: [(
+                    "ping".to_string(),
+                    Function {
+                        name: "ping".to_string(),
+                        params: vec![],
+                        result: None,
+                        kind: FunctionKind::Freestanding,
+                        docs: Default::default(),
+                        stability: Default::default(),
+                        span: Default::default(),
+                    },
+                )]
+                .into(),
+                types: Default::default(),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+                clone_of: None,
+            });
+            imports.push((
+                WorldKey::Interface(interface_id),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            ));
+        }
+        let world_id = resolve.worlds.alloc(World {
+            name: "test-world".to_string(),
+            imports: imports.into_iter().collect(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: None,
+            includes: Default::default(),
+            span: Default::default(),
+        });
+        let world = &resolve.worlds[world_id];
+        let sizes = SizeAlign::default();
+
+        let analyzed = ImportAnalyzer::new(&resolve, world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let output = tokens.to_string().unwrap();
+
+        for package_name in ["Http", "Filesystem"] {
+            let name = format!("TestWorldWasi{package_name}Types");
+            assert!(
+                output.contains(&format!("type I{name} interface {{"))
+                    && output.contains(&format!("type Unsupported{name} struct{{}}")),
+                "Expected the interfaces to be qualified with their package, got:\n{output}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_type_generation() {
+        use crate::codegen::ir::TypeDefinition;
+        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::default();
+
+        // Create a package
+        let package_name = PackageName {
+            namespace: "test".to_string(),
             name: "records".to_string(),
             version: None,
         };
@@ -1104,43 +3565,261 @@ mod tests {
             docs: Default::default(),
         });
 
-        // Create a record type similar to the "foo" record
-        let record_def = Record {
-            fields: vec![
-                Field {
-                    name: "float32".to_string(),
-                    ty: Type::F32,
-                    docs: Default::default(),
-                    span: Default::default(),
-                },
-                Field {
-                    name: "float64".to_string(),
-                    ty: Type::F64,
-                    docs: Default::default(),
-                    span: Default::default(),
-                },
-                Field {
-                    name: "uint32".to_string(),
-                    ty: Type::U32,
-                    docs: Default::default(),
-                    span: Default::default(),
-                },
-                Field {
-                    name: "uint64".to_string(),
-                    ty: Type::U64,
-                    docs: Default::default(),
-                    span: Default::default(),
-                },
-                Field {
-                    name: "s".to_string(),
-                    ty: Type::String,
-                    docs: Default::default(),
-                    span: Default::default(),
-                },
-            ],
-        };
-
-        // Create an interface that will own this type
+        // Create a record type similar to the "foo" record
+        let record_def = Record {
+            fields: vec![
+                Field {
+                    name: "float32".to_string(),
+                    ty: Type::F32,
+                    docs: Default::default(),
+                    span: Default::default(),
+                },
+                Field {
+                    name: "float64".to_string(),
+                    ty: Type::F64,
+                    docs: Default::default(),
+                    span: Default::default(),
+                },
+                Field {
+                    name: "uint32".to_string(),
+                    ty: Type::U32,
+                    docs: Default::default(),
+                    span: Default::default(),
+                },
+                Field {
+                    name: "uint64".to_string(),
+                    ty: Type::U64,
+                    docs: Default::default(),
+                    span: Default::default(),
+                },
+                Field {
+                    name: "s".to_string(),
+                    ty: Type::String,
+                    docs: Default::default(),
+                    span: Default::default(),
+                },
+            ],
+        };
+
+        // Create an interface that will own this type
+        let interface_id = resolve.interfaces.alloc(Interface {
+            name: Some("types".to_string()),
+            package: Some(package_id),
+            functions: Default::default(),
+            types: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+            clone_of: None,
+        });
+
+        // Create the TypeDef for the record with proper owner
+        let type_def = TypeDef {
+            name: Some("foo".to_string()),
+            kind: TypeDefKind::Record(record_def),
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let type_id = resolve.types.alloc(type_def);
+
+        // Add the type to the interface
+        resolve.interfaces[interface_id]
+            .types
+            .insert("foo".to_string(), type_id);
+
+        // Create a world that imports this interface
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [(
+                WorldKey::Name("types".to_string()),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: Some(package_id),
+            includes: Default::default(),
+            span: Default::default(),
+        };
+
+        let world_id = resolve.worlds.alloc(world);
+        let world = &resolve.worlds[world_id];
+
+        // Test the analyzer first
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
+
+        // Test analyze_type_definition directly with the record kind
+        let type_def = &resolve.types[type_id];
+        let analyzed_definition = analyzer.analyze_type_definition(&type_def.kind).unwrap();
+
+        println!(
+            "Direct analysis of type definition: {:?}",
+            analyzed_definition
+        );
+
+        // This should be a Record, not an Alias
+        match &analyzed_definition {
+            TypeDefinition::Record { fields } => {
+                println!(
+                    "✓ Correctly identified as Record with {} fields",
+                    fields.len()
+                );
+                assert_eq!(fields.len(), 5);
+            }
+            TypeDefinition::Alias { target } => {
+                panic!(
+                    "❌ Incorrectly identified as Alias with target: {:?}",
+                    target
+                );
+            }
+            other => {
+                panic!("❌ Unexpected type definition: {:?}", other);
+            }
+        }
+
+        // Test full analysis
+        let analyzed = analyzer.analyze();
+        println!("Full analysis result:");
+        println!("  Interfaces: {}", analyzed.interfaces.len());
+        println!("  Standalone types: {}", analyzed.standalone_types.len());
+
+        // Check analysis results
+        assert_eq!(analyzed.interfaces.len(), 1);
+        let interface = &analyzed.interfaces[0];
+        assert_eq!(interface.name, "types");
+        assert_eq!(interface.types.len(), 1);
+
+        let analyzed_type = &interface.types[0];
+        // Interface-scoped types are qualified only when their bare name
+        // would collide with another concrete type in the same world. The
+        // test world's `foo` is unique, so it stays flat.
+        assert_eq!(analyzed_type.name, "foo");
+        println!("Analyzed type definition: {:?}", analyzed_type.definition);
+
+        // This is the key assertion - it should be a Record, not an Alias
+        match &analyzed_type.definition {
+            TypeDefinition::Record { fields } => {
+                println!(
+                    "✓ Analysis correctly produced Record with {} fields",
+                    fields.len()
+                );
+                assert_eq!(fields.len(), 5);
+
+                // Check that field names are correct
+                let field_names: Vec<String> =
+                    fields.iter().map(|(name, _)| String::from(name)).collect();
+                println!("Field names: {:?}", field_names);
+
+                assert!(field_names.contains(&"Float32".to_string()));
+                assert!(field_names.contains(&"Float64".to_string()));
+                assert!(field_names.contains(&"Uint32".to_string()));
+                assert!(field_names.contains(&"Uint64".to_string()));
+                assert!(field_names.contains(&"S".to_string()));
+            }
+            TypeDefinition::Alias { target } => {
+                panic!(
+                    "❌ Analysis incorrectly produced Alias with target: {:?}",
+                    target
+                );
+            }
+            other => {
+                panic!(
+                    "❌ Analysis produced unexpected type definition: {:?}",
+                    other
+                );
+            }
+        }
+
+        // Test code generation
+        let sizes = SizeAlign::default();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+
+        let output = tokens.to_string().unwrap();
+        println!("\nGenerated code:\n{}", output);
+        println!("Generated code length: {}", output.len());
+
+        // Debug: let's see what's actually in the analyzed data that's being passed to the generator
+        println!("\nDebug - what's being passed to generator:");
+        println!("  analyzed.interfaces.len(): {}", analyzed.interfaces.len());
+        println!(
+            "  analyzed.standalone_types.len(): {}",
+            analyzed.standalone_types.len()
+        );
+
+        for (i, interface) in analyzed.interfaces.iter().enumerate() {
+            println!(
+                "  Interface {}: name='{}', types.len()={}",
+                i,
+                interface.name,
+                interface.types.len()
+            );
+            for (j, typ) in interface.types.iter().enumerate() {
+                println!(
+                    "    Type {}: name='{}', definition={:?}",
+                    j, typ.name, typ.definition
+                );
+            }
+        }
+
+        for (i, typ) in analyzed.standalone_types.iter().enumerate() {
+            println!(
+                "  Standalone type {}: name='{}', definition={:?}",
+                i, typ.name, typ.definition
+            );
+        }
+
+        // The issue: types are in interface.types but generator only looks at standalone_types
+        // Let's see if we can find where types should be moved to standalone_types
+
+        // Expected behavior: Should generate "type Foo struct {" not "type Foo Foo"
+        if output.contains("type Foo Foo") {
+            panic!(
+                "❌ Generated incorrect alias: 'type Foo Foo' - this creates infinite recursion!"
+            );
+        }
+
+        if !output.contains("type Foo struct") && analyzed.interfaces[0].types.len() > 0 {
+            println!(
+                "❌ Generated code doesn't contain struct definition, but types were analyzed correctly"
+            );
+            println!("This suggests the code generator isn't processing interface types properly");
+            // This is the actual bug - the generator doesn't handle interface types
+        }
+
+        // For now, let's just verify the analysis is correct (the generation bug is separate)
+        println!("✓ Test completed - analysis is working correctly");
+    }
+
+    #[test]
+    fn test_record_vs_alias_analysis() {
+        use crate::codegen::ir::TypeDefinition;
+        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
+
+        let mut resolve = Resolve::default();
+
+        // Create a package
+        let package_name = PackageName {
+            namespace: "test".to_string(),
+            name: "types".to_string(),
+            version: None,
+        };
+        let package_id = resolve.packages.alloc(Package {
+            name: package_name.clone(),
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
+
         let interface_id = resolve.interfaces.alloc(Interface {
             name: Some("types".to_string()),
             package: Some(package_id),
@@ -1149,218 +3828,551 @@ mod tests {
             docs: Default::default(),
             stability: Default::default(),
             span: Default::default(),
-            clone_of: None,
-        });
+            clone_of: None,
+        });
+
+        // Test 1: Create a proper record type
+        let record_def = Record {
+            fields: vec![Field {
+                name: "x".to_string(),
+                ty: Type::U32,
+                docs: Default::default(),
+                span: Default::default(),
+            }],
+        };
+
+        let record_type_def = TypeDef {
+            name: Some("my_record".to_string()),
+            kind: TypeDefKind::Record(record_def),
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        // Test 2: Create a type alias
+        let alias_type_def = TypeDef {
+            name: Some("my_alias".to_string()),
+            kind: TypeDefKind::Type(Type::String),
+            owner: TypeOwner::Interface(interface_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+
+        let record_type_id = resolve.types.alloc(record_type_def);
+        let alias_type_id = resolve.types.alloc(alias_type_def);
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [(
+                WorldKey::Name("types".to_string()),
+                WorldItem::Interface {
+                    id: interface_id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )]
+            .into(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: Some(package_id),
+            includes: Default::default(),
+            span: Default::default(),
+        };
+
+        let world_id = resolve.worlds.alloc(world);
+        let world = &resolve.worlds[world_id];
+
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
+
+        // Test record analysis
+        let record_def = &resolve.types[record_type_id];
+        let record_analysis = analyzer.analyze_type_definition(&record_def.kind).unwrap();
+
+        match record_analysis {
+            TypeDefinition::Record { .. } => {
+                println!("✓ Record correctly analyzed as Record");
+            }
+            other => {
+                panic!("❌ Record incorrectly analyzed as: {:?}", other);
+            }
+        }
+
+        // Test alias analysis
+        let alias_def = &resolve.types[alias_type_id];
+        let alias_analysis = analyzer.analyze_type_definition(&alias_def.kind).unwrap();
+
+        match alias_analysis {
+            TypeDefinition::Alias { .. } => {
+                println!("✓ Alias correctly analyzed as Alias");
+            }
+            other => {
+                panic!("❌ Alias incorrectly analyzed as: {:?}", other);
+            }
+        }
+
+        println!("✓ Both record and alias types analyzed correctly");
+    }
+
+    #[test]
+    fn test_option_alias_analysis() {
+        use crate::codegen::ir::TypeDefinition;
+
+        let resolve = Resolve::default();
+        let world = World {
+            name: "test-world".to_string(),
+            imports: Default::default(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: None,
+            includes: Default::default(),
+            span: Default::default(),
+        };
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
 
-        // Create the TypeDef for the record with proper owner
-        let type_def = TypeDef {
-            name: Some("foo".to_string()),
-            kind: TypeDefKind::Record(record_def),
-            owner: TypeOwner::Interface(interface_id),
+        let analysis = analyzer
+            .analyze_type_definition(&TypeDefKind::Option(Type::String))
+            .unwrap();
+
+        match analysis {
+            TypeDefinition::Alias { target } => {
+                assert_eq!(target, GoType::Pointer(Box::new(GoType::String)));
+            }
+            other => panic!("option incorrectly analyzed as: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_primitive_alias_analysis() {
+        use crate::codegen::ir::TypeDefinition;
+
+        let resolve = Resolve::default();
+        let world = World {
+            name: "test-world".to_string(),
+            imports: Default::default(),
+            exports: Default::default(),
             docs: Default::default(),
             stability: Default::default(),
+            package: None,
+            includes: Default::default(),
             span: Default::default(),
         };
+        let analyzer = ImportAnalyzer::new(&resolve, &world);
 
-        let type_id = resolve.types.alloc(type_def);
+        let cases = [
+            (Type::Bool, GoType::Bool),
+            (Type::U8, GoType::Uint8),
+            (Type::U16, GoType::Uint16),
+            (Type::U32, GoType::Uint32),
+            (Type::U64, GoType::Uint64),
+            (Type::S8, GoType::Int8),
+            (Type::S16, GoType::Int16),
+            (Type::S32, GoType::Int32),
+            (Type::S64, GoType::Int64),
+            (Type::F32, GoType::Float32),
+            (Type::F64, GoType::Float64),
+            (Type::Char, GoType::Rune),
+            (Type::String, GoType::String),
+        ];
+        for (typ, expected) in cases {
+            match analyzer.analyze_type_definition(&TypeDefKind::Type(typ)) {
+                Some(TypeDefinition::Alias { target }) => assert_eq!(target, expected),
+                other => panic!("{typ:?} alias incorrectly analyzed as: {other:?}"),
+            }
+        }
+    }
 
-        // Add the type to the interface
-        resolve.interfaces[interface_id]
-            .types
-            .insert("foo".to_string(), type_id);
+    #[test]
+    fn test_flags_type_generation() {
+        use crate::codegen::ir::{AnalyzedType, TypeDefinition};
+        use wit_bindgen_core::wit_parser::{Flag, Flags};
 
-        // Create a world that imports this interface
+        let resolve = Resolve::default();
         let world = World {
             name: "test-world".to_string(),
-            imports: [(
-                WorldKey::Name("types".to_string()),
-                WorldItem::Interface {
-                    id: interface_id,
-                    stability: Default::default(),
-                    span: Default::default(),
-                },
-            )]
-            .into(),
+            imports: Default::default(),
             exports: Default::default(),
             docs: Default::default(),
             stability: Default::default(),
-            package: Some(package_id),
+            package: None,
             includes: Default::default(),
             span: Default::default(),
         };
-
-        let world_id = resolve.worlds.alloc(world);
-        let world = &resolve.worlds[world_id];
-
-        // Test the analyzer first
         let analyzer = ImportAnalyzer::new(&resolve, &world);
 
-        // Test analyze_type_definition directly with the record kind
-        let type_def = &resolve.types[type_id];
-        let analyzed_definition = analyzer.analyze_type_definition(&type_def.kind).unwrap();
-
-        println!(
-            "Direct analysis of type definition: {:?}",
-            analyzed_definition
-        );
-
-        // This should be a Record, not an Alias
-        match &analyzed_definition {
-            TypeDefinition::Record { fields } => {
-                println!(
-                    "✓ Correctly identified as Record with {} fields",
-                    fields.len()
-                );
-                assert_eq!(fields.len(), 5);
-            }
-            TypeDefinition::Alias { target } => {
-                panic!(
-                    "❌ Incorrectly identified as Alias with target: {:?}",
-                    target
-                );
-            }
-            other => {
-                panic!("❌ Unexpected type definition: {:?}", other);
+        let flags = Flags {
+            flags: ["read", "write"]
+                .into_iter()
+                .map(|name| Flag {
+                    name: name.to_string(),
+                    docs: Default::default(),
+                    span: Default::default(),
+                })
+                .collect(),
+        };
+        let definition = analyzer
+            .analyze_type_definition(&TypeDefKind::Flags(flags))
+            .unwrap();
+        match &definition {
+            TypeDefinition::Flags { flags, repr } => {
+                assert_eq!(flags, &["read", "write"]);
+                assert_eq!(repr, &GoType::Uint8);
             }
+            other => panic!("flags incorrectly analyzed as: {other:?}"),
         }
 
-        // Test full analysis
-        let analyzed = analyzer.analyze();
-        println!("Full analysis result:");
-        println!("  Interfaces: {}", analyzed.interfaces.len());
-        println!("  Standalone types: {}", analyzed.standalone_types.len());
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+        let sizes = SizeAlign::default();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
 
-        // Check analysis results
-        assert_eq!(analyzed.interfaces.len(), 1);
-        let interface = &analyzed.interfaces[0];
-        assert_eq!(interface.name, "types");
-        assert_eq!(interface.types.len(), 1);
+        let typ = AnalyzedType {
+            name: "permissions".to_string(),
+            go_type_name: GoIdentifier::public("permissions"),
+            definition,
+        };
+        let mut tokens = Tokens::<Go>::new();
+        generator.generate_type_definition(&typ, &mut tokens);
+        let code_str = tokens.to_string().unwrap();
+
+        assert!(code_str.contains("type Permissions uint8"));
+        assert!(code_str.contains(
+            "const (\n    PermissionsRead Permissions = 1 << iota\n    PermissionsWrite\n"
+        ));
+        assert!(code_str.contains("func (f Permissions) Has(flags Permissions) bool {"));
+        assert!(code_str.contains("func (f Permissions) Set(flags Permissions) Permissions {"));
+        assert!(code_str.contains("func (f Permissions) MarshalText() ([]byte, error) {"));
+        assert!(code_str.contains("names = append(names, \"write\")"));
+        assert!(code_str.contains("func (f *Permissions) UnmarshalText(text []byte) error {"));
+        assert!(code_str.contains("case \"read\":\n            *f |= PermissionsRead"));
+    }
 
-        let analyzed_type = &interface.types[0];
-        // Interface-scoped types are qualified only when their bare name
-        // would collide with another concrete type in the same world. The
-        // test world's `foo` is unique, so it stays flat.
-        assert_eq!(analyzed_type.name, "foo");
-        println!("Analyzed type definition: {:?}", analyzed_type.definition);
+    #[test]
+    fn test_enum_string_generation() {
+        use crate::codegen::ir::{AnalyzedType, TypeDefinition};
 
-        // This is the key assertion - it should be a Record, not an Alias
-        match &analyzed_type.definition {
-            TypeDefinition::Record { fields } => {
-                println!(
-                    "✓ Analysis correctly produced Record with {} fields",
-                    fields.len()
-                );
-                assert_eq!(fields.len(), 5);
+        let resolve = Resolve::default();
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+        let sizes = SizeAlign::default();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
 
-                // Check that field names are correct
-                let field_names: Vec<String> =
-                    fields.iter().map(|(name, _)| String::from(name)).collect();
-                println!("Field names: {:?}", field_names);
+        let typ = AnalyzedType {
+            name: "log-level".to_string(),
+            go_type_name: GoIdentifier::public("log-level"),
+            definition: TypeDefinition::Enum {
+                cases: vec!["debug".to_string(), "warn-only".to_string()],
+            },
+        };
+        let mut tokens = Tokens::<Go>::new();
+        generator.generate_type_definition(&typ, &mut tokens);
+        let code_str = tokens.to_string().unwrap();
+
+        assert!(code_str.contains("func (e logLevel) String() string {"));
+        assert!(code_str.contains("case WarnOnly:\n        return \"warn-only\""));
+        assert!(code_str.contains("return fmt.Sprintf(\"logLevel(%d)\", int(e))"));
+        assert!(code_str.contains("func ParseLogLevel(s string) (LogLevel, error) {"));
+        assert!(code_str.contains("func (e *logLevel) UnmarshalText(text []byte) error {"));
+        assert!(code_str.contains("*e = value.(logLevel)"));
+        assert!(code_str.contains("func (e logLevel) Error() string {"));
+        assert!(code_str.contains("case \"warn-only\":\n        return WarnOnly, nil"));
+    }
 
-                assert!(field_names.contains(&"Float32".to_string()));
-                assert!(field_names.contains(&"Float64".to_string()));
-                assert!(field_names.contains(&"Uint32".to_string()));
-                assert!(field_names.contains(&"Uint64".to_string()));
-                assert!(field_names.contains(&"S".to_string()));
-            }
-            TypeDefinition::Alias { target } => {
-                panic!(
-                    "❌ Analysis incorrectly produced Alias with target: {:?}",
-                    target
-                );
-            }
-            other => {
-                panic!(
-                    "❌ Analysis produced unexpected type definition: {:?}",
-                    other
-                );
-            }
-        }
+    #[test]
+    fn test_variant_tag_generation() {
+        use crate::codegen::ir::{AnalyzedType, CaseDispatch, TypeDefinition, VariantCase};
 
-        // Test code generation
+        let resolve = Resolve::default();
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
         let sizes = SizeAlign::default();
         let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+
+        let typ = AnalyzedType {
+            name: "shape".to_string(),
+            go_type_name: GoIdentifier::public("shape"),
+            definition: TypeDefinition::Variant {
+                cases: vec![
+                    VariantCase {
+                        name: "circle".to_string(),
+                        payload: Some(GoType::Float64),
+                        dispatch: CaseDispatch::Wrapped {
+                            wrapper_name: GoIdentifier::public("shape-circle"),
+                        },
+                    },
+                    VariantCase {
+                        name: "empty".to_string(),
+                        payload: None,
+                        dispatch: CaseDispatch::Wrapped {
+                            wrapper_name: GoIdentifier::public("shape-empty"),
+                        },
+                    },
+                ],
+            },
+        };
         let mut tokens = Tokens::<Go>::new();
-        generator.format_into(&mut tokens);
+        generator.generate_type_definition(&typ, &mut tokens);
+        let code_str = tokens.to_string().unwrap();
+
+        assert!(code_str.contains("type Shape interface {"));
+        assert!(code_str.contains("Value float64"));
+        assert!(code_str.contains("func ShapeTag(v Shape) uint32 {"));
+        let circle = code_str.find("case ShapeCircle:").expect("circle case");
+        let empty = code_str.find("case ShapeEmpty:").expect("empty case");
+        assert!(code_str[circle..empty].contains("return 0"));
+        assert!(code_str[empty..].contains("return 1"));
+        assert!(code_str.contains("func (c ShapeCircle) Error() string {"));
+        assert!(code_str.contains("return fmt.Sprintf(\"circle: %v\", c.Value)"));
+        assert!(code_str.contains("func (c ShapeEmpty) Error() string {\n    return \"empty\""));
+    }
 
-        let output = tokens.to_string().unwrap();
-        println!("\nGenerated code:\n{}", output);
-        println!("Generated code length: {}", output.len());
+    /// A record `use`d by two interfaces is generated once, even when the
+    /// interfaces using it are imported before the one defining it.
+    #[test]
+    fn test_used_type_generated_once() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
 
-        // Debug: let's see what's actually in the analyzed data that's being passed to the generator
-        println!("\nDebug - what's being passed to generator:");
-        println!("  analyzed.interfaces.len(): {}", analyzed.interfaces.len());
-        println!(
-            "  analyzed.standalone_types.len(): {}",
-            analyzed.standalone_types.len()
-        );
+        let mut resolve = Resolve::default();
+        let package_id = resolve.packages.alloc(Package {
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "shared".to_string(),
+                version: None,
+            },
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
 
-        for (i, interface) in analyzed.interfaces.iter().enumerate() {
-            println!(
-                "  Interface {}: name='{}', types.len()={}",
-                i,
-                interface.name,
-                interface.types.len()
-            );
-            for (j, typ) in interface.types.iter().enumerate() {
-                println!(
-                    "    Type {}: name='{}', definition={:?}",
-                    j, typ.name, typ.definition
-                );
-            }
+        let mut interface = |name: &str| {
+            resolve.interfaces.alloc(Interface {
+                name: Some(name.to_string()),
+                package: Some(package_id),
+                functions: Default::default(),
+                types: Default::default(),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+                clone_of: None,
+            })
+        };
+        let types_id = interface("types");
+        let first_id = interface("first");
+        let second_id = interface("second");
+
+        let foo_id = resolve.types.alloc(TypeDef {
+            name: Some("foo".to_string()),
+            kind: TypeDefKind::Record(Record {
+                fields: vec![Field {
+                    name: "s".to_string(),
+                    ty: Type::String,
+                    docs: Default::default(),
+                    span: Default::default(),
+                }],
+            }),
+            owner: TypeOwner::Interface(types_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        resolve.interfaces[types_id]
+            .types
+            .insert("foo".to_string(), foo_id);
+
+        for user_id in [first_id, second_id] {
+            let alias_id = resolve.types.alloc(TypeDef {
+                name: Some("foo".to_string()),
+                kind: TypeDefKind::Type(Type::Id(foo_id)),
+                owner: TypeOwner::Interface(user_id),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            });
+            resolve.interfaces[user_id]
+                .types
+                .insert("foo".to_string(), alias_id);
         }
 
-        for (i, typ) in analyzed.standalone_types.iter().enumerate() {
-            println!(
-                "  Standalone type {}: name='{}', definition={:?}",
-                i, typ.name, typ.definition
-            );
-        }
+        let import = |id| {
+            (
+                WorldKey::Interface(id),
+                WorldItem::Interface {
+                    id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )
+        };
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [import(first_id), import(second_id), import(types_id)].into(),
+            exports: Default::default(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: Some(package_id),
+            includes: Default::default(),
+            span: Default::default(),
+        };
+
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+
+        let generated: Vec<_> = analyzed
+            .interfaces
+            .iter()
+            .map(|interface| (interface.name.as_str(), interface.types.len()))
+            .collect();
+        assert_eq!(generated, [("first", 1), ("second", 0), ("types", 0)]);
+        assert_eq!(analyzed.interfaces[0].types[0].name, "foo");
+    }
+
+    /// The types of an exported interface are generated with the imports',
+    /// apart from those it `use`s from an imported interface.
+    #[test]
+    fn test_exported_interface_types() {
+        use wit_bindgen_core::wit_parser::{Field, Record};
+
+        let mut resolve = Resolve::default();
+        let package_id = resolve.packages.alloc(Package {
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "shared".to_string(),
+                version: None,
+            },
+            interfaces: Default::default(),
+            worlds: Default::default(),
+            docs: Default::default(),
+        });
 
-        // The issue: types are in interface.types but generator only looks at standalone_types
-        // Let's see if we can find where types should be moved to standalone_types
+        let mut interface = |name: &str| {
+            resolve.interfaces.alloc(Interface {
+                name: Some(name.to_string()),
+                package: Some(package_id),
+                functions: Default::default(),
+                types: Default::default(),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+                clone_of: None,
+            })
+        };
+        let types_id = interface("types");
+        let exported_id = interface("exported");
+
+        let mut record = |name: &str, owner| {
+            resolve.types.alloc(TypeDef {
+                name: Some(name.to_string()),
+                kind: TypeDefKind::Record(Record {
+                    fields: vec![Field {
+                        name: "s".to_string(),
+                        ty: Type::String,
+                        docs: Default::default(),
+                        span: Default::default(),
+                    }],
+                }),
+                owner: TypeOwner::Interface(owner),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            })
+        };
+        let foo_id = record("foo", types_id);
+        let bar_id = record("bar", exported_id);
+        let alias_id = resolve.types.alloc(TypeDef {
+            name: Some("foo".to_string()),
+            kind: TypeDefKind::Type(Type::Id(foo_id)),
+            owner: TypeOwner::Interface(exported_id),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        resolve.interfaces[types_id]
+            .types
+            .insert("foo".to_string(), foo_id);
+        resolve.interfaces[exported_id]
+            .types
+            .extend([("foo".to_string(), alias_id), ("bar".to_string(), bar_id)]);
 
-        // Expected behavior: Should generate "type Foo struct {" not "type Foo Foo"
-        if output.contains("type Foo Foo") {
-            panic!(
-                "❌ Generated incorrect alias: 'type Foo Foo' - this creates infinite recursion!"
-            );
-        }
+        let item = |id| {
+            (
+                WorldKey::Interface(id),
+                WorldItem::Interface {
+                    id,
+                    stability: Default::default(),
+                    span: Default::default(),
+                },
+            )
+        };
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [item(types_id)].into(),
+            exports: [item(exported_id)].into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            package: Some(package_id),
+            includes: Default::default(),
+            span: Default::default(),
+        };
 
-        if !output.contains("type Foo struct") && analyzed.interfaces[0].types.len() > 0 {
-            println!(
-                "❌ Generated code doesn't contain struct definition, but types were analyzed correctly"
-            );
-            println!("This suggests the code generator isn't processing interface types properly");
-            // This is the actual bug - the generator doesn't handle interface types
-        }
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
 
-        // For now, let's just verify the analysis is correct (the generation bug is separate)
-        println!("✓ Test completed - analysis is working correctly");
+        assert_eq!(analyzed.interfaces[0].types[0].name, "foo");
+        let exported: Vec<_> = analyzed
+            .exported_types
+            .iter()
+            .map(|typ| typ.name.as_str())
+            .collect();
+        assert_eq!(exported, ["bar"]);
     }
 
+    /// Resources declared by an imported interface are implemented by Go
+    /// values the guest refers to by handle.
     #[test]
-    fn test_record_vs_alias_analysis() {
-        use crate::codegen::ir::TypeDefinition;
-        use wit_bindgen_core::wit_parser::{Field, Record, TypeDef, TypeDefKind, TypeOwner};
+    fn test_host_resource() {
+        use wit_bindgen_core::wit_parser::Handle;
 
         let mut resolve = Resolve::default();
-
-        // Create a package
-        let package_name = PackageName {
-            namespace: "test".to_string(),
-            name: "types".to_string(),
-            version: None,
-        };
         let package_id = resolve.packages.alloc(Package {
-            name: package_name.clone(),
+            name: PackageName {
+                namespace: "test".to_string(),
+                name: "pkg".to_string(),
+                version: None,
+            },
             interfaces: Default::default(),
             worlds: Default::default(),
             docs: Default::default(),
         });
-
         let interface_id = resolve.interfaces.alloc(Interface {
-            name: Some("types".to_string()),
+            name: Some("files".to_string()),
             package: Some(package_id),
             functions: Default::default(),
             types: Default::default(),
@@ -1369,43 +4381,93 @@ mod tests {
             span: Default::default(),
             clone_of: None,
         });
-
-        // Test 1: Create a proper record type
-        let record_def = Record {
-            fields: vec![Field {
-                name: "x".to_string(),
-                ty: Type::U32,
-                docs: Default::default(),
-                span: Default::default(),
-            }],
-        };
-
-        let record_type_def = TypeDef {
-            name: Some("my_record".to_string()),
-            kind: TypeDefKind::Record(record_def),
+        let typedef = |name: Option<&str>, kind| TypeDef {
+            name: name.map(str::to_string),
+            kind,
             owner: TypeOwner::Interface(interface_id),
             docs: Default::default(),
             stability: Default::default(),
             span: Default::default(),
         };
+        let file_id = resolve
+            .types
+            .alloc(typedef(Some("file"), TypeDefKind::Resource));
+        let borrow_id = resolve
+            .types
+            .alloc(typedef(None, TypeDefKind::Handle(Handle::Borrow(file_id))));
+        let own_id = resolve
+            .types
+            .alloc(typedef(None, TypeDefKind::Handle(Handle::Own(file_id))));
 
-        // Test 2: Create a type alias
-        let alias_type_def = TypeDef {
-            name: Some("my_alias".to_string()),
-            kind: TypeDefKind::Type(Type::String),
-            owner: TypeOwner::Interface(interface_id),
+        let param = |name: &str, ty| Param {
+            name: name.to_string(),
+            ty,
+            span: Default::default(),
+        };
+        let function = |name: &str, kind, params, result| Function {
+            name: name.to_string(),
+            kind,
+            params,
+            result,
             docs: Default::default(),
             stability: Default::default(),
             span: Default::default(),
         };
-
-        let record_type_id = resolve.types.alloc(record_type_def);
-        let alias_type_id = resolve.types.alloc(alias_type_def);
+        let interface = &mut resolve.interfaces[interface_id];
+        interface.types.insert("file".to_string(), file_id);
+        interface.functions.insert(
+            "open".to_string(),
+            function(
+                "open",
+                FunctionKind::Freestanding,
+                vec![],
+                Some(Type::Id(own_id)),
+            ),
+        );
+        interface.functions.insert(
+            "[constructor]file".to_string(),
+            function(
+                "[constructor]file",
+                FunctionKind::Constructor(file_id),
+                vec![param("fd", Type::U32)],
+                Some(Type::Id(own_id)),
+            ),
+        );
+        interface.functions.insert(
+            "[static]file.count".to_string(),
+            function(
+                "[static]file.count",
+                FunctionKind::Static(file_id),
+                vec![],
+                Some(Type::U32),
+            ),
+        );
+        interface.functions.insert(
+            "[static]file.close".to_string(),
+            function(
+                "[static]file.close",
+                FunctionKind::Static(file_id),
+                vec![param("f", Type::Id(own_id))],
+                None,
+            ),
+        );
+        interface.functions.insert(
+            "[method]file.size".to_string(),
+            function(
+                "[method]file.size",
+                FunctionKind::Method(file_id),
+                vec![
+                    param("self", Type::Id(borrow_id)),
+                    param("scale", Type::U32),
+                ],
+                Some(Type::U32),
+            ),
+        );
 
         let world = World {
             name: "test-world".to_string(),
             imports: [(
-                WorldKey::Name("types".to_string()),
+                WorldKey::Interface(interface_id),
                 WorldItem::Interface {
                     id: interface_id,
                     stability: Default::default(),
@@ -1421,37 +4483,223 @@ mod tests {
             span: Default::default(),
         };
 
-        let world_id = resolve.worlds.alloc(world);
-        let world = &resolve.worlds[world_id];
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+        let analyzed = ImportAnalyzer::new(&resolve, &world).analyze();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes);
+        let chains = generator.import_chains();
+        let mut tokens = Tokens::<Go>::new();
+        generator.format_into(&mut tokens);
+        let generated = tokens.to_string().unwrap();
 
-        let analyzer = ImportAnalyzer::new(&resolve, &world);
+        assert!(
+            generated.contains("type File interface {")
+                && generated.contains("Size(")
+                && generated.contains("var fileHandles = newInstanceHandles[File]()"),
+            "Expected a Go interface and handle table for the resource, got:\n{generated}"
+        );
+        assert!(
+            !generated.contains("FileSize("),
+            "Expected the method to be left out of the interface's functions, got:\n{generated}"
+        );
+        assert!(
+            generated.contains("NewFile(") && generated.contains("FileCount("),
+            "Expected the constructor and static function in the interface, got:\n{generated}"
+        );
 
-        // Test record analysis
-        let record_def = &resolve.types[record_type_id];
-        let record_analysis = analyzer.analyze_type_definition(&record_def.kind).unwrap();
+        let chain = chains["test:pkg/files"].to_string().unwrap();
+        assert!(
+            chain.contains(":= fileHandles.of(mod).insert(value"),
+            "Expected a returned resource to get a new handle, got:\n{chain}"
+        );
+        assert!(
+            chain.contains("if fileHandles.of(mod).isBorrowed(arg0) {")
+                && chain.contains(":= fileHandles.of(mod).remove(arg0)"),
+            "Expected an owned resource to be taken out of the table, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(":= fileHandles.of(mod).get(arg0)") && chain.contains(".Size(ctx, "),
+            "Expected the method to be called on the value behind `self`, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(":= files.NewFile(ctx, ") && chain.contains(":= files.FileCount(ctx"),
+            "Expected the constructor and static function to call the host, got:\n{chain}"
+        );
+        assert!(
+            chain.contains("Export(\"[method]file.size\")")
+                && chain.contains("Export(\"[constructor]file\")")
+                && chain.contains("Export(\"[resource-drop]file\")"),
+            "Expected the method and the drop intrinsic to be exported, got:\n{chain}"
+        );
+        assert!(
+            chain.contains("defer recoverHostPanic(\"[method]file.size\")")
+                && chain.contains("defer recoverHostPanic(\"[resource-drop]file\")"),
+            "Expected the host functions to recover from panics, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(
+                r#"logDebug(ctx, options.logger, "calling host function", "function", "[method]file.size")"#
+            ),
+            "Expected the host functions to log their calls, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(
+                r#"interceptHost(ctx, options.hostInterceptor, "files", "[method]file.size", func(ctx context.Context) {"#
+            ),
+            "Expected the host functions to go through the interceptor, got:\n{chain}"
+        );
+        assert!(
+            chain.contains(
+                r#"panic(&ImportNotProvidedError{Import: "files", Function: "[constructor]file"})"#
+            ) && chain.matches("if files == nil {").count() == 4,
+            "Expected every function but the method to check for the interface, got:\n{chain}"
+        );
+    }
 
-        match record_analysis {
-            TypeDefinition::Record { .. } => {
-                println!("✓ Record correctly analyzed as Record");
-            }
-            other => {
-                panic!("❌ Record incorrectly analyzed as: {:?}", other);
-            }
-        }
+    #[test]
+    fn test_import_async_lowered() {
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+        let resolve = Resolve::new();
+        let sizes = SizeAlign::default();
 
-        // Test alias analysis
-        let alias_def = &resolve.types[alias_type_id];
-        let alias_analysis = analyzer.analyze_type_definition(&alias_def.kind).unwrap();
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes).with_async(true);
 
-        match alias_analysis {
-            TypeDefinition::Alias { .. } => {
-                println!("✓ Alias correctly analyzed as Alias");
-            }
-            other => {
-                panic!("❌ Alias incorrectly analyzed as: {:?}", other);
-            }
-        }
+        let method = InterfaceMethod {
+            name: "lookup".to_string(),
+            go_method_name: GoIdentifier::public("Lookup"),
+            parameters: vec![Parameter {
+                name: GoIdentifier::private("key"),
+                go_type: GoType::Uint32,
+                wit_type: Type::U32,
+            }],
+            return_type: Some(WitReturn {
+                go_type: GoType::Uint32,
+                wit_type: Type::U32,
+            }),
+            wit_function: Function {
+                name: "lookup".to_string(),
+                kind: FunctionKind::AsyncFreestanding,
+                params: vec![Param {
+                    name: "key".to_string(),
+                    ty: Type::U32,
+                    span: Default::default(),
+                }],
+                result: Some(Type::U32),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
 
-        println!("✓ Both record and alias types analyzed correctly");
+        let param_name = GoIdentifier::private("handler");
+        let code_str = generator
+            .generate_host_function_builders(&method, &param_name)
+            .to_string()
+            .unwrap();
+
+        assert!(
+            code_str.contains("Export(\"lookup\")")
+                && code_str.contains("Export(\"[async-lower]lookup\")"),
+            "Expected both the sync and the async-lowered function, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("retptr uint32,") && code_str.contains(") uint32 {"),
+            "Expected the async-lowered function to take an out pointer, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.Memory().WriteUint32Le(retptr, ")
+                && code_str.contains("return 2"),
+            "Expected the result to be written through the out pointer, got:\n{code_str}"
+        );
+    }
+
+    #[test]
+    fn test_import_async_lowered_spilled_params() {
+        let analyzed = AnalyzedImports {
+            instance_name: GoIdentifier::public("TestInstance"),
+            interfaces: vec![],
+            standalone_functions: vec![],
+            standalone_types: vec![],
+            exported_types: vec![],
+            factory_name: GoIdentifier::public("TestFactory"),
+            constructor_name: GoIdentifier::public("NewTestFactory"),
+            factory_option_name: GoIdentifier::public("TestFactoryOption"),
+            factory_options_name: GoIdentifier::private("testFactoryOptions"),
+        };
+        let resolve = Resolve::new();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&resolve);
+
+        let generator = ImportCodeGenerator::new(&resolve, &analyzed, &sizes).with_async(true);
+
+        // Five flattened parameters are more than the async ABI flattens.
+        let names = ["a", "b", "c", "d", "e"];
+        let method = InterfaceMethod {
+            name: "sum".to_string(),
+            go_method_name: GoIdentifier::public("Sum"),
+            parameters: names
+                .iter()
+                .map(|name| Parameter {
+                    name: GoIdentifier::private(*name),
+                    go_type: GoType::Uint32,
+                    wit_type: Type::U32,
+                })
+                .collect(),
+            return_type: Some(WitReturn {
+                go_type: GoType::Uint64,
+                wit_type: Type::U64,
+            }),
+            wit_function: Function {
+                name: "sum".to_string(),
+                kind: FunctionKind::AsyncFreestanding,
+                params: names
+                    .iter()
+                    .map(|name| Param {
+                        name: name.to_string(),
+                        ty: Type::U32,
+                        span: Default::default(),
+                    })
+                    .collect(),
+                result: Some(Type::U64),
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            },
+        };
+
+        let param_name = GoIdentifier::private("handler");
+        let code_str = generator
+            .generate_host_function_builders(&method, &param_name)
+            .to_string()
+            .unwrap();
+
+        assert!(
+            code_str.contains("arg4 uint32,"),
+            "Expected the sync function to take the flattened parameters, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("params uint32,") && code_str.contains("retptr uint32,"),
+            "Expected the async-lowered function to take a parameters pointer, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("ReadUint32Le(uint32(params + 0))")
+                && code_str.contains("ReadUint32Le(uint32(params + 16 + 0))"),
+            "Expected the parameters to be read from memory, got:\n{code_str}"
+        );
+        assert!(
+            code_str.contains("mod.Memory().WriteUint64Le(retptr+0, ")
+                && code_str.contains("return 2"),
+            "Expected the result to be written through the out pointer, got:\n{code_str}"
+        );
     }
 }
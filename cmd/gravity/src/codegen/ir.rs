@@ -9,6 +9,9 @@ pub struct AnalyzedImports {
     pub interfaces: Vec<AnalyzedInterface>,
     /// All standalone types found in the input world.
     pub standalone_types: Vec<AnalyzedType>,
+    /// The types declared by the interfaces the world exports, which the
+    /// host needs to call them, less those the imports already declare.
+    pub exported_types: Vec<AnalyzedType>,
     /// All standalone functions found in the input world.
     pub standalone_functions: Vec<AnalyzedFunction>,
 
@@ -18,6 +21,10 @@ pub struct AnalyzedImports {
     pub instance_name: GoIdentifier,
     /// The name of the constructor for the factory type.
     pub constructor_name: GoIdentifier,
+    /// The name of the functional option type accepted by the constructor.
+    pub factory_option_name: GoIdentifier,
+    /// The name of the private struct the factory options are applied to.
+    pub factory_options_name: GoIdentifier,
 }
 
 /// An analyzed WIT interface with all its metadata.
@@ -42,6 +49,8 @@ pub struct AnalyzedInterface {
     pub name: String,
     pub methods: Vec<InterfaceMethod>,
     pub types: Vec<AnalyzedType>,
+    /// The resources the interface declares, which the host implements.
+    pub resources: Vec<AnalyzedResource>,
 
     /// The Go interface type name (e.g., "ITestWorldLogger")
     ///
@@ -58,6 +67,30 @@ pub struct AnalyzedInterface {
     pub wazero_module_name: String,
 }
 
+/// A WIT resource implemented by the host.
+///
+/// ```wit
+/// resource file {
+///     read: func(len: u32) -> list<u8>;
+/// }
+/// ```
+///
+/// The host implements it with Go values satisfying a generated interface,
+/// which the guest refers to by handle.
+#[derive(Debug, Clone)]
+pub struct AnalyzedResource {
+    /// The name of the resource.
+    pub name: String,
+    /// The Go interface the host implements the resource with.
+    ///
+    /// E.g. the `File` in `type File interface { ... }`.
+    pub go_interface_name: GoIdentifier,
+    /// The table mapping the guest's handles to Go values, one per instance.
+    pub handles: GoIdentifier,
+    /// The methods of the resource, without their `self` parameter.
+    pub methods: Vec<InterfaceMethod>,
+}
+
 /// Method signature for an interface
 #[derive(Debug, Clone)]
 pub struct InterfaceMethod {
@@ -117,6 +150,9 @@ pub enum TypeDefinition {
     Variant { cases: Vec<VariantCase> },
     /// A simple enumeration with named constants
     Enum { cases: Vec<String> },
+    /// A set of named bits, backed by the smallest unsigned integer holding
+    /// every flag.
+    Flags { flags: Vec<String>, repr: GoType },
     /// A type alias that wraps another type
     Alias { target: GoType },
     /// A primitive type that doesn't need special handling
@@ -10,4 +10,7 @@ pub use bindings::*;
 pub use exports::ExportGenerator;
 pub use factory::FactoryGenerator;
 pub use func::Func;
-pub use wasm::WasmData;
+pub use wasm::{
+    Compression, SCRATCH_BUFFER_SECTION, WasmData, export_mutable_globals, has_custom_section,
+    strip_custom_sections,
+};
@@ -1,4 +1,11 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
 use genco::prelude::*;
+use wasm_encoder::{Encode, ExportKind, RawSection};
+use wasmparser::{BinaryReader, ExternalKind, Parser, Payload, TypeRef, ValType};
 
 use crate::go::{GoIdentifier, embed};
 
@@ -10,6 +17,210 @@ pub enum WasmData<'a> {
     Embedded(&'a str),
 }
 
+/// How the guest's Wasm is compressed in the bindings, to be decompressed
+/// by the factory constructor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Compressed with gzip, which Go's standard library decompresses.
+    Gzip,
+    /// Compressed with zstd, which is smaller but makes the bindings depend
+    /// on `github.com/klauspost/compress/zstd`.
+    Zstd,
+}
+
+impl Compression {
+    /// Compresses the given Wasm as tightly as the algorithm allows, since
+    /// it's only done once when generating the bindings.
+    pub fn compress(self, wasm: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(wasm)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(wasm, 19),
+        }
+    }
+
+    /// The extension of the compressed file, after the `.wasm` one.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+}
+
+/// Drops the custom sections of the given Wasm module, which wazero has no
+/// use for, to keep the bindings small. With `keep_debug_info`, the `name`
+/// and DWARF sections are kept, as wazero reads them to symbolize the stack
+/// traces of errors.
+pub fn strip_custom_sections(wasm: &[u8], keep_debug_info: bool) -> wasmparser::Result<Vec<u8>> {
+    let mut module = wasm_encoder::Module::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        if let Payload::CustomSection(section) = &payload {
+            let name = section.name();
+            if !keep_debug_info || !(name == "name" || name.starts_with(".debug_")) {
+                continue;
+            }
+        }
+        if let Some((id, range)) = payload.as_section() {
+            module.section(&RawSection {
+                id,
+                data: &wasm[range],
+            });
+        }
+    }
+    Ok(module.finish())
+}
+
+/// The custom section a guest opts in to `--scratch-buffer` with, promising
+/// that it neither frees its arguments nor keeps them past the call.
+pub const SCRATCH_BUFFER_SECTION: &str = "gravity-scratch-buffer";
+
+/// Whether the given Wasm module has a custom section called `name`.
+pub fn has_custom_section(wasm: &[u8], name: &str) -> wasmparser::Result<bool> {
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CustomSection(section) = payload?
+            && section.name() == name
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The prefix of the names the mutable globals the guest doesn't export are
+/// exported under by [`export_mutable_globals`].
+const GLOBAL_EXPORT_PREFIX: &str = "gravity-global-";
+
+/// Exports the mutable globals the given Wasm module defines but doesn't
+/// export, as wazero only exposes exported globals, so that instances can
+/// snapshot them. Returns the module along with the names every mutable
+/// global it defines is exported under, in order.
+///
+/// The existing exports are copied as they are, and the export section is
+/// added if the module has none.
+pub fn export_mutable_globals(wasm: &[u8]) -> wasmparser::Result<(Vec<u8>, Vec<String>)> {
+    let mut module = wasm_encoder::Module::new();
+    let mut imported_globals = 0;
+    let mut mutable_globals = Vec::new();
+    let mut names = None;
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        match &payload {
+            Payload::ImportSection(reader) => {
+                for import in reader.clone().into_imports() {
+                    if let TypeRef::Global(_) = import?.ty {
+                        imported_globals += 1;
+                    }
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for (index, global) in (imported_globals..).zip(reader.clone()) {
+                    let ty = global?.ty;
+                    // wazero reads and writes globals as a `uint64`, which
+                    // only fits the numeric types.
+                    if ty.mutable
+                        && matches!(
+                            ty.content_type,
+                            ValType::I32 | ValType::I64 | ValType::F32 | ValType::F64
+                        )
+                    {
+                        mutable_globals.push(index);
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                let mut exported = BTreeMap::new();
+                for export in reader.clone() {
+                    let export = export?;
+                    if export.kind == ExternalKind::Global {
+                        exported.insert(export.index, export.name);
+                    }
+                }
+                let data = &wasm[reader.range()];
+                let mut count = BinaryReader::new(data, 0);
+                count.read_var_u32()?;
+                let entries = &data[count.current_position()..];
+                names = Some(export_globals(
+                    &mut module,
+                    reader.count(),
+                    entries,
+                    &mutable_globals,
+                    &exported,
+                ));
+                continue;
+            }
+            // The export section comes before these, so the module has none.
+            Payload::StartSection { .. }
+            | Payload::ElementSection(_)
+            | Payload::DataCountSection { .. }
+            | Payload::CodeSectionStart { .. }
+            | Payload::DataSection(_)
+            | Payload::End(_)
+                if names.is_none() && !mutable_globals.is_empty() =>
+            {
+                names = Some(export_globals(
+                    &mut module,
+                    0,
+                    &[],
+                    &mutable_globals,
+                    &BTreeMap::new(),
+                ));
+            }
+            _ => {}
+        }
+        if let Some((id, range)) = payload.as_section() {
+            module.section(&RawSection {
+                id,
+                data: &wasm[range],
+            });
+        }
+    }
+    Ok((module.finish(), names.unwrap_or_default()))
+}
+
+/// Adds the export section made of the `count` encoded `entries` of the
+/// guest's own, followed by the mutable globals it doesn't export, and
+/// returns the names the mutable globals are exported under.
+fn export_globals(
+    module: &mut wasm_encoder::Module,
+    count: u32,
+    entries: &[u8],
+    mutable_globals: &[u32],
+    exported: &BTreeMap<u32, &str>,
+) -> Vec<String> {
+    let mut added = Vec::new();
+    let mut unexported = 0;
+    let names = mutable_globals
+        .iter()
+        .map(|index| match exported.get(index) {
+            Some(name) => name.to_string(),
+            None => {
+                let name = format!("{GLOBAL_EXPORT_PREFIX}{index}");
+                name.as_str().encode(&mut added);
+                ExportKind::Global.encode(&mut added);
+                index.encode(&mut added);
+                unexported += 1;
+                name
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut data = Vec::new();
+    (count + unexported).encode(&mut data);
+    data.extend_from_slice(entries);
+    data.extend_from_slice(&added);
+    module.section(&RawSection {
+        id: wasm_encoder::SectionId::Export as u8,
+        data: &data,
+    });
+    names
+}
+
 pub(crate) struct Wasm<'a> {
     var: &'a GoIdentifier,
     data: WasmData<'a>,
@@ -56,11 +267,208 @@ impl FormatInto<Go> for Wasm<'_> {
 mod tests {
     use genco::{prelude::*, tokens::Tokens};
 
+    use std::io::Read;
+
     use crate::{
-        codegen::wasm::{Wasm, WasmData},
+        codegen::wasm::{
+            Compression, Wasm, WasmData, export_mutable_globals, has_custom_section,
+            strip_custom_sections,
+        },
         go::GoIdentifier,
     };
 
+    fn custom_section(name: &str) -> Vec<u8> {
+        let mut section = vec![0, name.len() as u8 + 2, name.len() as u8];
+        section.extend_from_slice(name.as_bytes());
+        section.push(0);
+        section
+    }
+
+    #[test]
+    fn test_strip_custom_sections() {
+        let header = b"\0asm\x01\0\0\0".to_vec();
+        // An empty type section
+        let types = vec![1, 1, 0];
+        let wasm = [
+            header.clone(),
+            custom_section("name"),
+            types.clone(),
+            custom_section("producers"),
+            custom_section(".debug_info"),
+        ]
+        .concat();
+
+        assert_eq!(
+            strip_custom_sections(&wasm, false).unwrap(),
+            [header.clone(), types.clone()].concat()
+        );
+        assert_eq!(
+            strip_custom_sections(&wasm, true).unwrap(),
+            [
+                header.clone(),
+                custom_section("name"),
+                types,
+                custom_section(".debug_info"),
+            ]
+            .concat()
+        );
+        assert!(strip_custom_sections(&[header, vec![1, 5, 0]].concat(), false).is_err());
+    }
+
+    #[test]
+    fn test_has_custom_section() {
+        let header = b"\0asm\x01\0\0\0".to_vec();
+        let wasm = [header.clone(), custom_section("gravity-scratch-buffer")].concat();
+
+        assert!(has_custom_section(&wasm, "gravity-scratch-buffer").unwrap());
+        assert!(!has_custom_section(&wasm, "name").unwrap());
+        assert!(!has_custom_section(&header, "gravity-scratch-buffer").unwrap());
+    }
+
+    /// The sections of a module built like a compiler would build it survive,
+    /// in order, while only the custom sections asked for are kept.
+    #[test]
+    fn test_strip_custom_sections_of_module() {
+        use wasm_encoder::{
+            CodeSection, CustomSection, ExportKind, ExportSection, Function, FunctionSection,
+            Instruction, Module, NameMap, NameSection, TypeSection, ValType,
+        };
+        use wasmparser::{Parser, Payload};
+
+        let custom = |name: &'static str| CustomSection {
+            name: name.into(),
+            data: [1, 2, 3].as_slice().into(),
+        };
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        types.ty().function([], [ValType::I32]);
+        module.section(&types);
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        module.section(&functions);
+        let mut exports = ExportSection::new();
+        exports.export("answer", ExportKind::Func, 0);
+        module.section(&exports);
+        let mut code = CodeSection::new();
+        let mut answer = Function::new([]);
+        answer.instruction(&Instruction::I32Const(42));
+        answer.instruction(&Instruction::End);
+        code.function(&answer);
+        module.section(&code);
+        let mut names = NameSection::new();
+        let mut function_names = NameMap::new();
+        function_names.append(0, "answer");
+        names.functions(&function_names);
+        module.section(&names);
+        module.section(&custom("producers"));
+        module.section(&custom(".debug_info"));
+        let wasm = module.finish();
+
+        let sections = |wasm: &[u8]| {
+            Parser::new(0)
+                .parse_all(wasm)
+                .filter_map(|payload| match payload.unwrap() {
+                    Payload::CustomSection(section) => Some(section.name().to_string()),
+                    payload => payload.as_section().map(|(id, _)| id.to_string()),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sections(&strip_custom_sections(&wasm, false).unwrap()),
+            ["1", "3", "7", "10"]
+        );
+        assert_eq!(
+            sections(&strip_custom_sections(&wasm, true).unwrap()),
+            ["1", "3", "7", "10", "name", ".debug_info"]
+        );
+    }
+
+    /// The mutable globals the guest doesn't export are exported after its own
+    /// exports, and those it does are snapshotted under their own names.
+    #[test]
+    fn test_export_mutable_globals() {
+        use wasm_encoder::{
+            ConstExpr, EntityType, ExportKind, ExportSection, GlobalSection, GlobalType,
+            ImportSection, Module, ValType,
+        };
+        use wasmparser::{ExternalKind, Parser, Payload};
+
+        let global = |mutable| GlobalType {
+            val_type: ValType::I32,
+            mutable,
+            shared: false,
+        };
+        let globals = || {
+            let mut globals = GlobalSection::new();
+            globals.global(global(true), &ConstExpr::i32_const(1024));
+            globals.global(global(false), &ConstExpr::i32_const(0));
+            globals.global(global(true), &ConstExpr::i32_const(0));
+            globals.global(global(true), &ConstExpr::i32_const(0));
+            globals
+        };
+        let exports = |wasm: &[u8]| {
+            Parser::new(0)
+                .parse_all(wasm)
+                .filter_map(|payload| match payload.unwrap() {
+                    Payload::ExportSection(reader) => Some(reader),
+                    _ => None,
+                })
+                .flat_map(|reader| reader.into_iter().map(Result::unwrap))
+                .map(|export| (export.name.to_string(), export.kind, export.index))
+                .collect::<Vec<_>>()
+        };
+
+        let mut module = Module::new();
+        let mut imports = ImportSection::new();
+        imports.import("env", "g", EntityType::Global(global(true)));
+        module.section(&imports);
+        module.section(&globals());
+        let mut section = ExportSection::new();
+        section.export("memory-base", ExportKind::Global, 2);
+        section.export("counter", ExportKind::Global, 4);
+        module.section(&section);
+        let (wasm, names) = export_mutable_globals(&module.finish()).unwrap();
+        assert_eq!(names, ["gravity-global-1", "gravity-global-3", "counter"]);
+        assert_eq!(
+            exports(&wasm),
+            [
+                ("memory-base".to_string(), ExternalKind::Global, 2),
+                ("counter".to_string(), ExternalKind::Global, 4),
+                ("gravity-global-1".to_string(), ExternalKind::Global, 1),
+                ("gravity-global-3".to_string(), ExternalKind::Global, 3),
+            ]
+        );
+
+        // Without an export section, one is added after the globals.
+        let mut module = Module::new();
+        module.section(&globals());
+        let (wasm, names) = export_mutable_globals(&module.finish()).unwrap();
+        assert_eq!(
+            names,
+            ["gravity-global-0", "gravity-global-2", "gravity-global-3"]
+        );
+        assert_eq!(exports(&wasm).len(), 3);
+
+        // Modules without mutable globals are left as they are.
+        let wasm = Module::new().finish();
+        assert_eq!(export_mutable_globals(&wasm).unwrap(), (wasm, Vec::new()));
+    }
+
+    #[test]
+    fn test_compression() {
+        let wasm = b"\0asm\x01\0\0\0".repeat(64);
+
+        let gzip = Compression::Gzip.compress(&wasm).unwrap();
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(gzip.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, wasm);
+
+        let zstd = Compression::Zstd.compress(&wasm).unwrap();
+        assert_eq!(zstd::decode_all(zstd.as_slice()).unwrap(), wasm);
+    }
+
     #[test]
     fn test_inline_wasm() {
         let var = GoIdentifier::private("wasm");
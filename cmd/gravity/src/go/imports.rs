@@ -9,17 +9,117 @@ impl FormatInto<Go> for GoImport {
     }
 }
 
+pub static BYTES_CLONE: GoImport = GoImport("bytes", "Clone");
+pub static BYTES_NEW_READER: GoImport = GoImport("bytes", "NewReader");
+pub static GZIP_NEW_READER: GoImport = GoImport("compress/gzip", "NewReader");
+pub static ZSTD_NEW_READER: GoImport = GoImport("github.com/klauspost/compress/zstd", "NewReader");
+pub static CONTEXT_BACKGROUND: GoImport = GoImport("context", "Background");
 pub static CONTEXT_CONTEXT: GoImport = GoImport("context", "Context");
+pub static CONTEXT_WITH_CANCEL: GoImport = GoImport("context", "WithCancel");
+pub static CONTEXT_WITH_TIMEOUT: GoImport = GoImport("context", "WithTimeout");
+pub static CONTEXT_WITH_VALUE: GoImport = GoImport("context", "WithValue");
+pub static CONTEXT_WITHOUT_CANCEL: GoImport = GoImport("context", "WithoutCancel");
+pub static CONTEXT_DEADLINE_EXCEEDED: GoImport = GoImport("context", "DeadlineExceeded");
 pub static ERRORS_NEW: GoImport = GoImport("errors", "New");
+pub static ERRORS_JOIN: GoImport = GoImport("errors", "Join");
+pub static ERRORS_AS: GoImport = GoImport("errors", "As");
+pub static ERRORS_IS: GoImport = GoImport("errors", "Is");
+pub static FMT_ERRORF: GoImport = GoImport("fmt", "Errorf");
 pub static FMT_PRINTF: GoImport = GoImport("fmt", "Printf");
+pub static FMT_PRINTLN: GoImport = GoImport("fmt", "Println");
+pub static FMT_SPRINTF: GoImport = GoImport("fmt", "Sprintf");
+pub static IO_READ_ALL: GoImport = GoImport("io", "ReadAll");
+pub static IO_READER: GoImport = GoImport("io", "Reader");
+pub static IO_WRITER: GoImport = GoImport("io", "Writer");
 pub static WAZERO_RUNTIME: GoImport = GoImport("github.com/tetratelabs/wazero", "Runtime");
 pub static WAZERO_NEW_RUNTIME: GoImport = GoImport("github.com/tetratelabs/wazero", "NewRuntime");
+pub static WAZERO_NEW_RUNTIME_WITH_CONFIG: GoImport =
+    GoImport("github.com/tetratelabs/wazero", "NewRuntimeWithConfig");
+pub static WAZERO_RUNTIME_CONFIG: GoImport =
+    GoImport("github.com/tetratelabs/wazero", "RuntimeConfig");
+pub static WAZERO_NEW_RUNTIME_CONFIG: GoImport =
+    GoImport("github.com/tetratelabs/wazero", "NewRuntimeConfig");
+pub static WAZERO_NEW_RUNTIME_CONFIG_INTERPRETER: GoImport = GoImport(
+    "github.com/tetratelabs/wazero",
+    "NewRuntimeConfigInterpreter",
+);
+pub static WAZERO_COMPILATION_CACHE: GoImport =
+    GoImport("github.com/tetratelabs/wazero", "CompilationCache");
+pub static WAZERO_NEW_COMPILATION_CACHE_WITH_DIR: GoImport = GoImport(
+    "github.com/tetratelabs/wazero",
+    "NewCompilationCacheWithDir",
+);
 pub static WAZERO_NEW_MODULE_CONFIG: GoImport =
     GoImport("github.com/tetratelabs/wazero", "NewModuleConfig");
+pub static WAZERO_MODULE_CONFIG: GoImport =
+    GoImport("github.com/tetratelabs/wazero", "ModuleConfig");
+pub static WAZERO_FS_CONFIG: GoImport = GoImport("github.com/tetratelabs/wazero", "FSConfig");
 pub static WAZERO_COMPILED_MODULE: GoImport =
     GoImport("github.com/tetratelabs/wazero", "CompiledModule");
+pub static WASI_SNAPSHOT_PREVIEW1_INSTANTIATE: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/imports/wasi_snapshot_preview1",
+    "Instantiate",
+);
+pub static WASI_SNAPSHOT_PREVIEW1_MODULE_NAME: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/imports/wasi_snapshot_preview1",
+    "ModuleName",
+);
+pub static WAZERO_SYS_WALLTIME: GoImport =
+    GoImport("github.com/tetratelabs/wazero/sys", "Walltime");
+pub static WAZERO_SYS_NANOTIME: GoImport =
+    GoImport("github.com/tetratelabs/wazero/sys", "Nanotime");
+pub static WAZERO_SYS_CLOCK_RESOLUTION: GoImport =
+    GoImport("github.com/tetratelabs/wazero/sys", "ClockResolution");
+pub static WAZERO_SYS_EXIT_CODE_CONTEXT_CANCELED: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/sys",
+    "ExitCodeContextCanceled",
+);
+pub static WAZERO_SYS_EXIT_CODE_DEADLINE_EXCEEDED: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/sys",
+    "ExitCodeDeadlineExceeded",
+);
+pub static WAZERO_EXPERIMENTAL_FUNCTION_LISTENER_FACTORY: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "FunctionListenerFactory",
+);
+pub static WAZERO_EXPERIMENTAL_WITH_FUNCTION_LISTENER_FACTORY: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "WithFunctionListenerFactory",
+);
+pub static WAZERO_EXPERIMENTAL_MEMORY_ALLOCATOR: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "MemoryAllocator",
+);
+pub static WAZERO_EXPERIMENTAL_WITH_MEMORY_ALLOCATOR: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "WithMemoryAllocator",
+);
+pub static WAZERO_API_GO_MODULE_FUNC: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "GoModuleFunc");
+pub static WAZERO_API_VALUE_TYPE: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "ValueType");
+pub static WAZERO_API_VALUE_TYPE_I32: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "ValueTypeI32");
+pub static WAZERO_API_VALUE_TYPE_I64: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "ValueTypeI64");
+pub static WAZERO_API_VALUE_TYPE_F32: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "ValueTypeF32");
+pub static WAZERO_API_VALUE_TYPE_F64: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "ValueTypeF64");
+pub static WAZERO_EXPERIMENTAL_IMPORT_RESOLVER: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "ImportResolver",
+);
+pub static WAZERO_EXPERIMENTAL_WITH_IMPORT_RESOLVER: GoImport = GoImport(
+    "github.com/tetratelabs/wazero/experimental",
+    "WithImportResolver",
+);
 pub static WAZERO_API_MODULE: GoImport = GoImport("github.com/tetratelabs/wazero/api", "Module");
 pub static WAZERO_API_MEMORY: GoImport = GoImport("github.com/tetratelabs/wazero/api", "Memory");
+pub static WAZERO_API_MUTABLE_GLOBAL: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "MutableGlobal");
+pub static WAZERO_API_FUNCTION: GoImport =
+    GoImport("github.com/tetratelabs/wazero/api", "Function");
 pub static WAZERO_API_ENCODE_U32: GoImport =
     GoImport("github.com/tetratelabs/wazero/api", "EncodeU32");
 pub static WAZERO_API_DECODE_U32: GoImport =
@@ -36,4 +136,39 @@ pub static WAZERO_API_ENCODE_F64: GoImport =
     GoImport("github.com/tetratelabs/wazero/api", "EncodeF64");
 pub static WAZERO_API_DECODE_F64: GoImport =
     GoImport("github.com/tetratelabs/wazero/api", "DecodeF64");
-pub static REFLECT_VALUE_OF: GoImport = GoImport("reflect", "ValueOf");
+pub static CODES_ERROR: GoImport = GoImport("go.opentelemetry.io/otel/codes", "Error");
+pub static TRACE_SPAN: GoImport = GoImport("go.opentelemetry.io/otel/trace", "Span");
+pub static TRACE_TRACER: GoImport = GoImport("go.opentelemetry.io/otel/trace", "Tracer");
+pub static TRACE_TRACER_PROVIDER: GoImport =
+    GoImport("go.opentelemetry.io/otel/trace", "TracerProvider");
+pub static TRACE_NOOP_NEW_TRACER_PROVIDER: GoImport =
+    GoImport("go.opentelemetry.io/otel/trace/noop", "NewTracerProvider");
+pub static LOG_FATAL: GoImport = GoImport("log", "Fatal");
+pub static SLOG_LOGGER: GoImport = GoImport("log/slog", "Logger");
+pub static SLICES_SORTED: GoImport = GoImport("slices", "Sorted");
+pub static SLICES_SORTED_FUNC: GoImport = GoImport("slices", "SortedFunc");
+pub static MAPS_KEYS: GoImport = GoImport("maps", "Keys");
+pub static MATH_FLOAT32_BITS: GoImport = GoImport("math", "Float32bits");
+pub static MATH_FLOAT32_FROM_BITS: GoImport = GoImport("math", "Float32frombits");
+pub static MATH_FLOAT64_BITS: GoImport = GoImport("math", "Float64bits");
+pub static MATH_FLOAT64_FROM_BITS: GoImport = GoImport("math", "Float64frombits");
+pub static STRINGS_CUT: GoImport = GoImport("strings", "Cut");
+pub static STRINGS_JOIN: GoImport = GoImport("strings", "Join");
+pub static STRINGS_SPLIT: GoImport = GoImport("strings", "Split");
+pub static RUNTIME_GOMAXPROCS: GoImport = GoImport("runtime", "GOMAXPROCS");
+pub static RUNTIME_DEBUG_STACK: GoImport = GoImport("runtime/debug", "Stack");
+pub static TESTING_B: GoImport = GoImport("testing", "B");
+pub static TESTING_F: GoImport = GoImport("testing", "F");
+pub static TESTING_T: GoImport = GoImport("testing", "T");
+pub static TIME_AFTER: GoImport = GoImport("time", "After");
+pub static TIME_DURATION: GoImport = GoImport("time", "Duration");
+pub static TIME_NOW: GoImport = GoImport("time", "Now");
+pub static TIME_SECOND: GoImport = GoImport("time", "Second");
+pub static TIME_SINCE: GoImport = GoImport("time", "Since");
+pub static TIME_TIME: GoImport = GoImport("time", "Time");
+pub static SYNC_MUTEX: GoImport = GoImport("sync", "Mutex");
+pub static SYNC_ONCE: GoImport = GoImport("sync", "Once");
+pub static SYNC_POOL: GoImport = GoImport("sync", "Pool");
+pub static UNSAFE_SLICE_DATA: GoImport = GoImport("unsafe", "SliceData");
+pub static UNSAFE_STRING: GoImport = GoImport("unsafe", "String");
+pub static UTF8_VALID_RUNE: GoImport = GoImport("unicode/utf8", "ValidRune");
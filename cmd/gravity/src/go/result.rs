@@ -39,7 +39,11 @@ impl FormatInto<Go> for GoResult {
 impl FormatInto<Go> for &GoResult {
     fn format_into(self, tokens: &mut Tokens<Go>) {
         match &self {
-            GoResult::Anon(typ @ GoType::ValueOrError(_) | typ @ GoType::ValueOrOk(_)) => {
+            GoResult::Anon(
+                typ @ GoType::ValueOrError(_)
+                | typ @ GoType::ValueOrOk(_)
+                | typ @ GoType::MultiReturn(_),
+            ) => {
                 // Be cautious here as there are `(` and `)` surrounding the type
                 tokens.append(quote!(($typ)))
             }
@@ -80,6 +84,15 @@ mod tests {
         assert_eq!(tokens.to_string().unwrap(), "(uint32, bool)");
     }
 
+    #[test]
+    fn test_go_result_multi_return() {
+        // GoResult with MultiReturn should add parentheses
+        let result = GoResult::Anon(GoType::MultiReturn(vec![GoType::Uint32, GoType::String]));
+        let mut tokens = Tokens::<Go>::new();
+        (&result).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "(uint32, string)");
+    }
+
     #[test]
     fn test_go_result_value_or_error() {
         // GoResult with ValueOrError should add parentheses
@@ -1,4 +1,7 @@
-use genco::{prelude::*, tokens::static_literal};
+use genco::{
+    prelude::*,
+    tokens::{ItemStr, static_literal},
+};
 
 use crate::go::GoIdentifier;
 
@@ -30,8 +33,12 @@ pub enum GoType {
     Float32,
     /// 64-bit floating point
     Float64,
+    /// Unicode scalar value, for WIT `char`
+    Rune,
     /// String type
     String,
+    /// WIT `error-context`, carried in the generated `*ErrorContext`
+    ErrorContext,
     /// Error type (represents Result<None, String>)
     Error,
     /// Interface type (for variants/discriminated unions)
@@ -44,13 +51,41 @@ pub enum GoType {
     ValueOrError(Box<GoType>),
     /// Slice/array of another type
     Slice(Box<GoType>),
+    /// Fixed-length array of another type
+    Array(Box<GoType>, u32),
+    /// Map from a key type to a value type
+    Map(Box<GoType>, Box<GoType>),
     /// Multi-return type (for functions returning arbitrary multiple values)
-    // MultiReturn(Vec<GoType>),
+    MultiReturn(Vec<GoType>),
+    /// Tuple in a parameter, record field or other nested position, carried in
+    /// the generated generic `TupleN` struct for its arity.
+    Tuple(Vec<GoType>),
     /// Pointer to another type. Used as the canonical Go representation of
     /// `option<T>` so the same lowering composes in every position (params,
     /// return values, record fields, list elements). `nil` is `none`,
     /// `&value` is `some`.
     Pointer(Box<GoType>),
+    /// WIT `option<T>` when generating generic containers, carried in the
+    /// generated generic `Option[T]`.
+    GenericOption(Box<GoType>),
+    /// WIT `result<T, E>` when generating generic containers, carried in the
+    /// generated generic `Result[T, E]`. A missing payload is [`GoType::Nothing`]
+    /// and carried as an empty struct.
+    GenericResult(Box<GoType>, Box<GoType>),
+    /// Handle to a resource exported by the guest, carried in a pointer to
+    /// the generated struct of that name
+    GuestResource(String),
+    /// Resource implemented by the host, carried in the generated Go
+    /// interface of that name
+    HostResource(String),
+    /// WIT `future<T>`, a value provided later, carried in a pointer to the
+    /// generated `Future` type. A `future` without a payload resolves to an
+    /// empty struct.
+    Future(Option<Box<GoType>>),
+    /// WIT `stream<T>`, values provided incrementally, carried in a pointer
+    /// to the generated `Stream` type. A `stream` without a payload carries
+    /// empty structs.
+    Stream(Option<Box<GoType>>),
     /// User-defined type (records, enums, type aliases)
     UserDefined(String),
     /// Represents no value/void
@@ -90,10 +125,19 @@ impl GoType {
             | GoType::Int32
             | GoType::Int64
             | GoType::Float32
-            | GoType::Float64 => false,
+            | GoType::Float64
+            | GoType::Rune => false,
+
+            // Error contexts and resources are passed by handle, with the
+            // debug message or resource state kept where it was created.
+            GoType::ErrorContext | GoType::GuestResource(_) | GoType::HostResource(_) => false,
+
+            // Futures and streams are passed by handle, and their values are
+            // copied out of the guest as soon as they're written.
+            GoType::Future(_) | GoType::Stream(_) => false,
 
             // String and slices allocate memory and need cleanup
-            GoType::String | GoType::Slice(_) => true,
+            GoType::String | GoType::Slice(_) | GoType::Map(_, _) => true,
 
             // Complex types need cleanup if their inner types do
             GoType::ValueOrOk(inner) => inner.needs_cleanup(),
@@ -103,6 +147,18 @@ impl GoType {
             // memory the guest allocated.
             GoType::Pointer(inner) => inner.needs_cleanup(),
 
+            // Generic containers own memory only through their payloads.
+            GoType::GenericOption(inner) => inner.needs_cleanup(),
+            GoType::GenericResult(ok, err) => ok.needs_cleanup() || err.needs_cleanup(),
+
+            // Arrays are stored inline, so only their elements can own memory.
+            GoType::Array(inner, _) => inner.needs_cleanup(),
+
+            // Tuples need cleanup if any of their elements do.
+            GoType::MultiReturn(typs) | GoType::Tuple(typs) => {
+                typs.iter().any(GoType::needs_cleanup)
+            }
+
             // The inner type of `Err` is always a String so it requires cleanup
             // TODO(#91): Store the error type to check both inner types.
             GoType::ValueOrError(_) => true,
@@ -150,7 +206,9 @@ impl FormatInto<Go> for &GoType {
             GoType::Int64 => tokens.append(static_literal("int64")),
             GoType::Float32 => tokens.append(static_literal("float32")),
             GoType::Float64 => tokens.append(static_literal("float64")),
+            GoType::Rune => tokens.append(static_literal("rune")),
             GoType::String => tokens.append(static_literal("string")),
+            GoType::ErrorContext => tokens.append(static_literal("*ErrorContext")),
             GoType::Error => tokens.append(static_literal("error")),
             GoType::Interface => tokens.append(static_literal("interface{}")),
             GoType::ValueOrOk(value_typ) => {
@@ -165,17 +223,65 @@ impl FormatInto<Go> for &GoType {
                 tokens.space();
                 tokens.append(static_literal("error"))
             }
+            // `list<u8>` is binary data, which Go spells `[]byte`.
+            GoType::Slice(typ) if **typ == GoType::Uint8 => {
+                tokens.append(static_literal("[]byte"));
+            }
             GoType::Slice(typ) => {
                 tokens.append(static_literal("[]"));
                 typ.as_ref().format_into(tokens);
             }
-            // GoType::MultiReturn(typs) => {
-            //     tokens.append(quote!($(for typ in typs join (, ) => $typ)))
-            // }
+            GoType::Array(typ, len) => {
+                tokens.append(ItemStr::from(format!("[{len}]")));
+                match typ.as_ref() {
+                    GoType::Uint8 => tokens.append(static_literal("byte")),
+                    typ => typ.format_into(tokens),
+                }
+            }
+            GoType::Map(key, value) => {
+                tokens.append(static_literal("map["));
+                key.as_ref().format_into(tokens);
+                tokens.append(static_literal("]"));
+                value.as_ref().format_into(tokens);
+            }
+            GoType::MultiReturn(typs) => {
+                tokens.append(quote!($(for typ in typs join (, ) => $typ)))
+            }
+            GoType::Tuple(typs) => {
+                let name = tuple_name(typs.len());
+                tokens.append(quote!($name[$(for typ in typs join (, ) => $typ)]))
+            }
             GoType::Pointer(typ) => {
                 tokens.append(static_literal("*"));
                 typ.as_ref().format_into(tokens);
             }
+            GoType::GenericOption(typ) => {
+                tokens.append(quote!(Option[$(container_payload(typ))]));
+            }
+            GoType::GenericResult(ok, err) => {
+                tokens.append(quote!(Result[$(container_payload(ok)), $(container_payload(err))]));
+            }
+            GoType::GuestResource(name) => {
+                tokens.append(static_literal("*"));
+                GoIdentifier::public(name).format_into(tokens)
+            }
+            GoType::HostResource(name) => GoIdentifier::public(name).format_into(tokens),
+            GoType::Future(payload) => {
+                tokens.append(static_literal("*Future["));
+                match payload {
+                    Some(typ) => typ.as_ref().format_into(tokens),
+                    None => tokens.append(static_literal("struct{}")),
+                }
+                tokens.append(static_literal("]"));
+            }
+            GoType::Stream(payload) => {
+                tokens.append(static_literal("*Stream["));
+                match payload {
+                    Some(typ) => typ.as_ref().format_into(tokens),
+                    None => tokens.append(static_literal("struct{}")),
+                }
+                tokens.append(static_literal("]"));
+            }
             GoType::UserDefined(name) => {
                 let id = GoIdentifier::public(name);
                 id.format_into(tokens)
@@ -185,6 +291,20 @@ impl FormatInto<Go> for &GoType {
     }
 }
 
+/// The type argument of a generic container for a payload, with a missing
+/// payload carried as an empty struct.
+pub fn container_payload(typ: &GoType) -> Tokens<Go> {
+    match typ {
+        GoType::Nothing => quote!($(static_literal("struct{}"))),
+        typ => quote!($typ),
+    }
+}
+
+/// The name of the generated generic struct holding a tuple of `arity` values.
+pub fn tuple_name(arity: usize) -> String {
+    format!("Tuple{arity}")
+}
+
 impl FormatInto<Go> for GoType {
     fn format_into(self, tokens: &mut Tokens<Go>) {
         (&self).format_into(tokens)
@@ -211,7 +331,9 @@ mod tests {
             (GoType::Int64, "int64"),
             (GoType::Float32, "float32"),
             (GoType::Float64, "float64"),
+            (GoType::Rune, "rune"),
             (GoType::String, "string"),
+            (GoType::ErrorContext, "*ErrorContext"),
             (GoType::Error, "error"),
             (GoType::Interface, "interface{}"),
             (GoType::Nothing, ""),
@@ -253,6 +375,106 @@ mod tests {
         assert_eq!(tokens.to_string().unwrap(), "[]int32");
     }
 
+    #[test]
+    fn test_byte_slice() {
+        let typ = GoType::Slice(Box::new(GoType::Uint8));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "[]byte");
+    }
+
+    #[test]
+    fn test_array() {
+        let typ = GoType::Array(Box::new(GoType::Uint32), 4);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "[4]uint32");
+
+        let typ = GoType::Array(Box::new(GoType::Uint8), 32);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "[32]byte");
+    }
+
+    #[test]
+    fn test_map() {
+        let typ = GoType::Map(Box::new(GoType::String), Box::new(GoType::Uint32));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "map[string]uint32");
+    }
+
+    #[test]
+    fn test_tuple() {
+        let typ = GoType::Tuple(vec![GoType::Uint32, GoType::String]);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "Tuple2[uint32, string]");
+    }
+
+    #[test]
+    fn test_guest_resource() {
+        let typ = GoType::GuestResource("word-counter".to_string());
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "*WordCounter");
+    }
+
+    #[test]
+    fn test_future() {
+        let typ = GoType::Future(Some(Box::new(GoType::String)));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "*Future[string]");
+
+        let typ = GoType::Future(None);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "*Future[struct{}]");
+    }
+
+    #[test]
+    fn test_generic_containers() {
+        let typ = GoType::GenericOption(Box::new(GoType::String));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "Option[string]");
+
+        let typ = GoType::GenericResult(Box::new(GoType::Nothing), Box::new(GoType::Uint32));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "Result[struct{}, uint32]");
+    }
+
+    #[test]
+    fn test_stream() {
+        let typ = GoType::Stream(Some(Box::new(GoType::Uint8)));
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "*Stream[uint8]");
+
+        let typ = GoType::Stream(None);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "*Stream[struct{}]");
+    }
+
+    #[test]
+    fn test_host_resource() {
+        let typ = GoType::HostResource("word-counter".to_string());
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "WordCounter");
+    }
+
+    #[test]
+    fn test_multi_return() {
+        let typ = GoType::MultiReturn(vec![GoType::Uint32, GoType::String]);
+        let mut tokens = Tokens::<Go>::new();
+        (&typ).format_into(&mut tokens);
+        assert_eq!(tokens.to_string().unwrap(), "uint32, string");
+    }
+
     // #[test]
     // fn test_pointer() {
     //     let typ = GoType::Pointer(Box::new(GoType::String));
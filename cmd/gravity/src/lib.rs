@@ -1,11 +1,14 @@
 pub mod codegen;
 pub mod go;
 
-use crate::go::GoType;
+use crate::go::{GoIdentifier, GoType};
 use wit_bindgen_core::{
     abi::WasmType,
     dealias,
-    wit_parser::{Case, Resolve, Result_, Type, TypeDef, TypeDefKind, TypeId, TypeOwner},
+    wit_parser::{
+        Case, Flags, FlagsRepr, Function, FunctionKind, Handle, Resolve, Result_, Type, TypeDef,
+        TypeDefKind, TypeId, TypeOwner, World, WorldItem,
+    },
 };
 
 // Temporary re-export while we migrate.
@@ -91,6 +94,265 @@ pub fn qualified_type_name(type_id: TypeId, resolve: &Resolve) -> String {
     }
 }
 
+/// Whether the resource `type_id` refers to is implemented by the guest,
+/// i.e. declared in an interface a world exports, rather than by the host.
+pub fn is_guest_resource(type_id: TypeId, resolve: &Resolve) -> bool {
+    let TypeOwner::Interface(owner) = resolve.types[dealias(resolve, type_id)].owner else {
+        return false;
+    };
+    resolve.worlds.iter().any(|(_, world)| {
+        world
+            .exports
+            .values()
+            .any(|item| matches!(item, WorldItem::Interface { id, .. } if *id == owner))
+    })
+}
+
+/// Whether a value of `typ` returned by the guest owns memory the guest
+/// allocated, which its post-return function frees once the value's copied
+/// out.
+pub fn needs_post_return(typ: &Type, resolve: &Resolve) -> bool {
+    let Type::Id(id) = typ else {
+        return matches!(typ, Type::String);
+    };
+    let needs = |typ: &Type| needs_post_return(typ, resolve);
+    match &resolve.types[*id].kind {
+        TypeDefKind::List(_) | TypeDefKind::Map(_, _) => true,
+        TypeDefKind::Record(record) => record.fields.iter().any(|field| needs(&field.ty)),
+        TypeDefKind::Tuple(tuple) => tuple.types.iter().any(needs),
+        TypeDefKind::Variant(variant) => variant
+            .cases
+            .iter()
+            .filter_map(|case| case.ty.as_ref())
+            .any(needs),
+        TypeDefKind::Option(typ)
+        | TypeDefKind::FixedLengthList(typ, _)
+        | TypeDefKind::Type(typ) => needs(typ),
+        TypeDefKind::Result(Result_ { ok, err }) => ok.iter().chain(err).any(needs),
+        // Flags and enums are plain integers, and handles, futures and
+        // streams are indices into tables.
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Handle(_)
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_)
+        | TypeDefKind::Unknown => false,
+    }
+}
+
+/// Whether a value of `typ` carries handles, futures, streams or error
+/// contexts, which are only valid in the instance that handed them out.
+pub fn has_handles(typ: &Type, resolve: &Resolve) -> bool {
+    let Type::Id(id) = typ else {
+        return matches!(typ, Type::ErrorContext);
+    };
+    let has = |typ: &Type| has_handles(typ, resolve);
+    match &resolve.types[*id].kind {
+        TypeDefKind::Resource
+        | TypeDefKind::Handle(_)
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_) => true,
+        TypeDefKind::Record(record) => record.fields.iter().any(|field| has(&field.ty)),
+        TypeDefKind::Tuple(tuple) => tuple.types.iter().any(has),
+        TypeDefKind::Variant(variant) => variant
+            .cases
+            .iter()
+            .filter_map(|case| case.ty.as_ref())
+            .any(has),
+        TypeDefKind::List(typ)
+        | TypeDefKind::Option(typ)
+        | TypeDefKind::FixedLengthList(typ, _)
+        | TypeDefKind::Type(typ) => has(typ),
+        TypeDefKind::Map(key, value) => has(key) || has(value),
+        TypeDefKind::Result(Result_ { ok, err }) => ok.iter().chain(err).any(has),
+        TypeDefKind::Flags(_) | TypeDefKind::Enum(_) | TypeDefKind::Unknown => false,
+    }
+}
+
+/// Whether a value of `typ` passed to a host function with `--zero-copy`
+/// holds strings or `list<u8>`s, which are then views of guest memory.
+pub fn views_guest_memory(typ: &Type, resolve: &Resolve) -> bool {
+    let Type::Id(id) = typ else {
+        return matches!(typ, Type::String);
+    };
+    let views = |typ: &Type| views_guest_memory(typ, resolve);
+    match &resolve.types[*id].kind {
+        TypeDefKind::List(Type::U8) => true,
+        TypeDefKind::Record(record) => record.fields.iter().any(|field| views(&field.ty)),
+        TypeDefKind::Tuple(tuple) => tuple.types.iter().any(views),
+        TypeDefKind::Variant(variant) => variant
+            .cases
+            .iter()
+            .filter_map(|case| case.ty.as_ref())
+            .any(views),
+        TypeDefKind::List(typ)
+        | TypeDefKind::Option(typ)
+        | TypeDefKind::FixedLengthList(typ, _)
+        | TypeDefKind::Type(typ) => views(typ),
+        TypeDefKind::Map(key, value) => views(key) || views(value),
+        TypeDefKind::Result(Result_ { ok, err }) => ok.iter().chain(err).any(views),
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Handle(_)
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_)
+        | TypeDefKind::Unknown => false,
+    }
+}
+
+/// The Go name of a function.
+///
+/// A resource's methods are named after their item, as they're called on the
+/// resource. Its constructor and static functions are called alongside the
+/// other functions of its interface, so they're prefixed: `[constructor]file`
+/// becomes `NewFile` and `[static]file.open` becomes `FileOpen`.
+pub fn function_go_name(func: &Function) -> GoIdentifier {
+    match func.kind {
+        FunctionKind::Constructor(_) => {
+            let name = func.name.strip_prefix("[constructor]").unwrap_or(&func.name);
+            GoIdentifier::public(format!("new-{name}"))
+        }
+        FunctionKind::Static(_) | FunctionKind::AsyncStatic(_) => {
+            let name = func.name.strip_prefix("[static]").unwrap_or(&func.name);
+            GoIdentifier::public(name.replace('.', "-"))
+        }
+        _ => GoIdentifier::public(func.item_name()),
+    }
+}
+
+/// Whether a function is declared `async` in WIT.
+pub fn is_async(func: &Function) -> bool {
+    matches!(
+        func.kind,
+        FunctionKind::AsyncFreestanding
+            | FunctionKind::AsyncMethod(_)
+            | FunctionKind::AsyncStatic(_)
+    )
+}
+
+/// The Go identifier of the table holding the handles of a resource.
+pub fn resource_handles(resource: TypeId, resolve: &Resolve) -> GoIdentifier {
+    let name = qualified_type_name(resource, resolve);
+    GoIdentifier::private(format!("{name}-handles"))
+}
+
+/// The Go identifier of the field of the generated `exportedFunctions` struct
+/// holding the function the core module exports as `name`, e.g.
+/// `cabiPostArcjetPkgApiRun` for `cabi_post_arcjet:pkg/api#run`.
+pub fn exported_function_field(name: &str) -> GoIdentifier {
+    let words = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    GoIdentifier::private(words.join("-"))
+}
+
+/// Resolves the unsigned integer a WIT `flags` type is stored in on the Go side.
+///
+/// # Panics
+///
+/// This function panics for flags with more than 64 members, which don't fit
+/// in any Go integer. See [`oversized_flags`] to reject those up front.
+pub fn resolve_flags_repr(flags: &Flags) -> GoType {
+    match flags.repr() {
+        FlagsRepr::U8 => GoType::Uint8,
+        FlagsRepr::U16 => GoType::Uint16,
+        FlagsRepr::U32(1) => GoType::Uint32,
+        FlagsRepr::U32(2) => GoType::Uint64,
+        FlagsRepr::U32(_) => panic!("flags with more than 64 members are not supported"),
+    }
+}
+
+/// Names the `flags` types of `world` with more than 64 members, which
+/// bindings can't be generated for.
+pub fn oversized_flags(resolve: &Resolve, world: &World) -> Vec<String> {
+    world
+        .imports
+        .values()
+        .chain(world.exports.values())
+        .flat_map(|item| match item {
+            WorldItem::Interface { id, .. } => {
+                resolve.interfaces[*id].types.values().copied().collect()
+            }
+            WorldItem::Type { id, .. } => vec![*id],
+            WorldItem::Function(_) => vec![],
+        })
+        .filter(|id| {
+            matches!(
+                &resolve.types[*id].kind,
+                TypeDefKind::Flags(flags) if matches!(flags.repr(), FlagsRepr::U32(n) if n > 2)
+            )
+        })
+        .filter_map(|id| resolve.types[id].name.clone())
+        .collect()
+}
+
+/// Whether a value of `typ` carries futures or streams.
+fn has_futures_or_streams(typ: &Type, resolve: &Resolve) -> bool {
+    let Type::Id(id) = typ else {
+        return false;
+    };
+    let has = |typ: &Type| has_futures_or_streams(typ, resolve);
+    match &resolve.types[*id].kind {
+        TypeDefKind::Future(_) | TypeDefKind::Stream(_) => true,
+        TypeDefKind::Record(record) => record.fields.iter().any(|field| has(&field.ty)),
+        TypeDefKind::Tuple(tuple) => tuple.types.iter().any(has),
+        TypeDefKind::Variant(variant) => variant
+            .cases
+            .iter()
+            .filter_map(|case| case.ty.as_ref())
+            .any(has),
+        TypeDefKind::List(typ)
+        | TypeDefKind::Option(typ)
+        | TypeDefKind::FixedLengthList(typ, _)
+        | TypeDefKind::Type(typ) => has(typ),
+        TypeDefKind::Map(key, value) => has(key) || has(value),
+        TypeDefKind::Result(Result_ { ok, err }) => ok.iter().chain(err).any(has),
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Handle(_)
+        | TypeDefKind::Unknown => false,
+    }
+}
+
+/// Names the functions of `world` passing futures or streams other than in
+/// the results of the functions it exports, which bindings can't be
+/// generated for. With `asynchronous`, the results of async-lifted exports
+/// are handed over through `[task-return]`, so they can't carry them either.
+pub fn unsupported_futures_and_streams(
+    resolve: &Resolve,
+    world: &World,
+    asynchronous: bool,
+) -> Vec<String> {
+    fn functions<'a>(resolve: &'a Resolve, item: &'a WorldItem) -> Vec<&'a Function> {
+        match item {
+            WorldItem::Interface { id, .. } => resolve.interfaces[*id].functions.values().collect(),
+            WorldItem::Function(func) => vec![func],
+            WorldItem::Type { .. } => vec![],
+        }
+    }
+    let has = |typ: &Type| has_futures_or_streams(typ, resolve);
+    let passes = |func: &Function| func.params.iter().any(|param| has(&param.ty));
+    let returns = |func: &Function| func.result.iter().any(has);
+    let imports = world
+        .imports
+        .values()
+        .flat_map(|item| functions(resolve, item))
+        .filter(|func| passes(func) || returns(func));
+    let exports = world
+        .exports
+        .values()
+        .flat_map(|item| functions(resolve, item))
+        .filter(|func| passes(func) || (asynchronous && is_async(func) && returns(func)));
+    imports
+        .chain(exports)
+        .map(|func| func.name.clone())
+        .collect()
+}
+
 /// Resolves a Wasm type to a Go type.
 pub fn resolve_wasm_type(typ: &WasmType) -> GoType {
     match typ {
@@ -114,6 +376,14 @@ pub fn resolve_wasm_type(typ: &WasmType) -> GoType {
 /// - The type is still unimplemented.
 /// - The type does not have a name when it is expected to have one (enums, records, type aliases).
 pub fn resolve_type(typ: &Type, resolve: &Resolve) -> GoType {
+    resolve_type_with(typ, resolve, false)
+}
+
+/// Like [`resolve_type`], but with `generic_containers` set a WIT `option`
+/// or `result` resolves to the generated generic `Option[T]` or
+/// `Result[T, E]` instead of a pointer or a Go `error`.
+pub fn resolve_type_with(typ: &Type, resolve: &Resolve, generic_containers: bool) -> GoType {
+    let resolve_inner = |typ: &Type| resolve_type_with(typ, resolve, generic_containers);
     match typ {
         // Basic types.
         Type::Bool => GoType::Bool,
@@ -127,12 +397,9 @@ pub fn resolve_type(typ: &Type, resolve: &Resolve) -> GoType {
         Type::S64 => GoType::Int64,
         Type::F32 => GoType::Float32,
         Type::F64 => GoType::Float64,
-        Type::Char => {
-            // Is this a Go "rune"?
-            todo!("TODO(#6): resolve char type")
-        }
+        Type::Char => GoType::Rune,
         Type::String => GoType::String,
-        Type::ErrorContext => todo!("TODO(#4): implement error context conversion"),
+        Type::ErrorContext => GoType::ErrorContext,
 
         // Complex types.
         Type::Id(id) => {
@@ -142,62 +409,103 @@ pub fn resolve_type(typ: &Type, resolve: &Resolve) -> GoType {
                 .expect("failed to find type definition");
             match kind {
                 TypeDefKind::Record(_) => GoType::UserDefined(qualified_type_name(*id, resolve)),
-                TypeDefKind::Resource => todo!("TODO(#5): implement resources"),
-                TypeDefKind::Handle(_) => todo!("TODO(#5): implement resources"),
-                TypeDefKind::Flags(_) => todo!("TODO(#4): implement flag conversion"),
-                TypeDefKind::Tuple(_) => todo!("TODO(#4): implement tuple conversion"),
+                TypeDefKind::Resource => resolve_resource(*id, resolve),
+                TypeDefKind::Handle(Handle::Own(resource) | Handle::Borrow(resource)) => {
+                    resolve_resource(*resource, resolve)
+                }
+                TypeDefKind::Flags(_) => GoType::UserDefined(qualified_type_name(*id, resolve)),
+                TypeDefKind::Tuple(tuple) => {
+                    GoType::Tuple(tuple.types.iter().map(resolve_inner).collect())
+                }
                 TypeDefKind::Variant(_) => GoType::UserDefined(qualified_type_name(*id, resolve)),
                 TypeDefKind::Enum(_) => GoType::UserDefined(qualified_type_name(*id, resolve)),
                 // `option<T>` is `*T`: `nil` is `none`, `&v` is `some`. A
                 // single pointer composes in every position (param, return,
                 // record field, list element); the prior `(T, bool)`
-                // comma-ok shape didn't.
+                // comma-ok shape didn't. Generic containers carry it in
+                // `Option[T]` instead.
                 TypeDefKind::Option(value) => {
-                    GoType::Pointer(Box::new(resolve_type(value, resolve)))
+                    let value = Box::new(resolve_inner(value));
+                    if generic_containers {
+                        GoType::GenericOption(value)
+                    } else {
+                        GoType::Pointer(value)
+                    }
+                }
+                // A result is carried in the generated generic
+                // `Result[T, E]`, so that it composes in every position. Only
+                // a function's own result is returned as a Go `error`, see
+                // `resolve_return_type`.
+                TypeDefKind::Result(Result_ { ok, err }) => {
+                    let payload = |typ: &Option<Type>| {
+                        Box::new(typ.as_ref().map_or(GoType::Nothing, resolve_inner))
+                    };
+                    GoType::GenericResult(payload(ok), payload(err))
                 }
 
-                // Various results, including specialised ones.
-                TypeDefKind::Result(Result_ {
-                    ok: Some(ok),
-                    err: Some(Type::String),
-                }) => GoType::ValueOrError(Box::new(resolve_type(ok, resolve))),
-                TypeDefKind::Result(Result_ {
-                    ok: Some(_),
-                    err: Some(_),
-                }) => {
-                    todo!("TODO(#4): implement remaining result conversion")
+                TypeDefKind::List(inner) => GoType::Slice(Box::new(resolve_inner(inner))),
+                TypeDefKind::Future(payload) => {
+                    GoType::Future(payload.as_ref().map(|typ| Box::new(resolve_inner(typ))))
+                }
+                TypeDefKind::Stream(payload) => {
+                    GoType::Stream(payload.as_ref().map(|typ| Box::new(resolve_inner(typ))))
                 }
-                TypeDefKind::Result(Result_ {
-                    ok: Some(ok),
-                    err: None,
-                }) => resolve_type(ok, resolve),
-                TypeDefKind::Result(Result_ {
-                    ok: None,
-                    err: Some(Type::String),
-                }) => GoType::Error,
-                TypeDefKind::Result(Result_ {
-                    ok: None,
-                    err: Some(_),
-                }) => todo!("TODO(#4): implement remaining result conversion"),
-                TypeDefKind::Result(Result_ {
-                    ok: None,
-                    err: None,
-                }) => GoType::Nothing,
-
-                TypeDefKind::List(inner) => GoType::Slice(Box::new(resolve_type(inner, resolve))),
-                TypeDefKind::Future(_) => todo!("TODO(#4): implement future conversion"),
-                TypeDefKind::Stream(_) => todo!("TODO(#4): implement stream conversion"),
                 TypeDefKind::Type(_) => GoType::UserDefined(qualified_type_name(*id, resolve)),
-                TypeDefKind::FixedLengthList(_, _) => {
-                    todo!("TODO(#4): implement fixed length list conversion")
+                TypeDefKind::FixedLengthList(inner, len) => {
+                    GoType::Array(Box::new(resolve_inner(inner)), *len)
+                }
+                TypeDefKind::Map(key, value) => {
+                    GoType::Map(Box::new(resolve_inner(key)), Box::new(resolve_inner(value)))
                 }
-                TypeDefKind::Map(_, _) => todo!("TODO(#4): implement map conversion"),
                 TypeDefKind::Unknown => todo!("TODO(#4): implement unknown conversion"),
             }
         }
     }
 }
 
+/// Resolves a resource, or a handle to one, to a Go type.
+fn resolve_resource(resource: TypeId, resolve: &Resolve) -> GoType {
+    if is_guest_resource(resource, resolve) {
+        GoType::GuestResource(qualified_type_name(resource, resolve))
+    } else {
+        GoType::HostResource(qualified_type_name(resource, resolve))
+    }
+}
+
+/// Like [`resolve_type`], but for a function's result: a tuple result
+/// becomes multiple Go return values instead of a `TupleN` struct.
+pub fn resolve_return_type(typ: &Type, resolve: &Resolve) -> GoType {
+    resolve_return_type_with(typ, resolve, false)
+}
+
+/// Like [`resolve_return_type`], but see [`resolve_type_with`].
+///
+/// Unless generic containers are enabled, a `result` is returned the way Go
+/// functions usually fail: as a Go `error`, after the ok value if it has one.
+/// Error payloads other than strings travel inside a generated
+/// `ResultError[E]`.
+pub fn resolve_return_type_with(typ: &Type, resolve: &Resolve, generic_containers: bool) -> GoType {
+    let result = match typ {
+        Type::Id(id) if !generic_containers => match &resolve.types[*id].kind {
+            TypeDefKind::Result(result) => Some(result),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(result) = result {
+        return match result {
+            Result_ { ok: Some(ok), .. } => {
+                GoType::ValueOrError(Box::new(resolve_type(ok, resolve)))
+            }
+            Result_ { ok: None, .. } => GoType::Error,
+        };
+    }
+    match resolve_type_with(typ, resolve, generic_containers) {
+        GoType::Tuple(typs) => GoType::MultiReturn(typs),
+        typ => typ,
+    }
+}
+
 /// Like [`resolve_type`], but downgrades a top-level Variant to
 /// `interface{}` so existing call sites can keep passing the variant
 /// payload through `any`-typed plumbing (rule config returns, generic
@@ -209,11 +517,161 @@ pub fn resolve_type(typ: &Type, resolve: &Resolve) -> GoType {
 /// Variants nested inside records, lists, or returns stay typed so
 /// generated record fields remain strongly typed.
 pub fn resolve_param_type(typ: &Type, resolve: &Resolve) -> GoType {
+    resolve_param_type_with(typ, resolve, false)
+}
+
+/// Like [`resolve_param_type`], but see [`resolve_type_with`].
+pub fn resolve_param_type_with(typ: &Type, resolve: &Resolve, generic_containers: bool) -> GoType {
     if let Type::Id(id) = typ {
         let def = &resolve.types[dealias(resolve, *id)];
         if matches!(def.kind, TypeDefKind::Variant(_)) {
             return GoType::Interface;
         }
     }
-    resolve_type(typ, resolve)
+    resolve_type_with(typ, resolve, generic_containers)
+}
+
+#[cfg(test)]
+mod tests {
+    use wit_bindgen_core::wit_parser::{Flag, Param, World, WorldKey};
+
+    use super::*;
+
+    /// Flags beyond 64 members are reported, rather than panicking once the
+    /// bindings are generated.
+    #[test]
+    fn test_oversized_flags() {
+        let mut resolve = Resolve::default();
+        let mut flags_type = |name: &str, count: usize| {
+            resolve.types.alloc(TypeDef {
+                name: Some(name.to_string()),
+                kind: TypeDefKind::Flags(Flags {
+                    flags: (0..count)
+                        .map(|nth| Flag {
+                            name: format!("flag{nth}"),
+                            docs: Default::default(),
+                            span: Default::default(),
+                        })
+                        .collect(),
+                }),
+                owner: TypeOwner::None,
+                docs: Default::default(),
+                stability: Default::default(),
+                span: Default::default(),
+            })
+        };
+        let small = flags_type("small", 64);
+        let large = flags_type("large", 65);
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [
+                (
+                    WorldKey::Name("small".to_string()),
+                    WorldItem::Type {
+                        id: small,
+                        span: Default::default(),
+                    },
+                ),
+                (
+                    WorldKey::Name("large".to_string()),
+                    WorldItem::Type {
+                        id: large,
+                        span: Default::default(),
+                    },
+                ),
+            ]
+            .into(),
+            exports: [].into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        assert_eq!(oversized_flags(&resolve, &world), ["large"]);
+    }
+
+    /// Futures and streams are only supported in the results of exports.
+    #[test]
+    fn test_unsupported_futures_and_streams() {
+        let mut resolve = Resolve::default();
+        let future = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Future(Some(Type::U32)),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let function = |name: &str, kind: FunctionKind, param: bool, result: bool| Function {
+            name: name.to_string(),
+            kind,
+            params: param
+                .then(|| Param {
+                    name: "value".to_string(),
+                    ty: Type::Id(future),
+                    span: Default::default(),
+                })
+                .into_iter()
+                .collect(),
+            result: result.then_some(Type::Id(future)),
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        };
+        let stream = resolve.types.alloc(TypeDef {
+            name: None,
+            kind: TypeDefKind::Stream(Some(Type::U8)),
+            owner: TypeOwner::None,
+            docs: Default::default(),
+            stability: Default::default(),
+            span: Default::default(),
+        });
+        let upload = Function {
+            params: vec![Param {
+                name: "bytes".to_string(),
+                ty: Type::Id(stream),
+                span: Default::default(),
+            }],
+            ..function("upload", FunctionKind::Freestanding, false, false)
+        };
+        let item = |func: Function| (WorldKey::Name(func.name.clone()), WorldItem::Function(func));
+
+        let world = World {
+            name: "test-world".to_string(),
+            imports: [
+                item(function("send", FunctionKind::Freestanding, true, false)),
+                item(function("receive", FunctionKind::Freestanding, false, true)),
+            ]
+            .into(),
+            exports: [
+                item(function("produce", FunctionKind::Freestanding, false, true)),
+                item(function(
+                    "produce-later",
+                    FunctionKind::AsyncFreestanding,
+                    false,
+                    true,
+                )),
+                item(function("consume", FunctionKind::Freestanding, true, false)),
+                item(upload),
+            ]
+            .into(),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            span: Default::default(),
+            package: None,
+        };
+
+        assert_eq!(
+            unsupported_futures_and_streams(&resolve, &world, false),
+            ["send", "receive", "consume", "upload"]
+        );
+        assert_eq!(
+            unsupported_futures_and_streams(&resolve, &world, true),
+            ["send", "receive", "produce-later", "consume", "upload"]
+        );
+    }
 }
@@ -1,10 +1,23 @@
-use std::{fs, path::Path, process::ExitCode};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
 use clap::{Arg, ArgAction, Command};
-use genco::lang::{Go, go};
+use genco::{
+    Tokens,
+    lang::{Go, go},
+};
 use wit_bindgen_core::wit_parser::SizeAlign;
 
-use arcjet_gravity::codegen::{Bindings, WasmData};
+use arcjet_gravity::{
+    codegen::{
+        Bindings, Compression, SCRATCH_BUFFER_SECTION, WasmData, export_mutable_globals,
+        has_custom_section, strip_custom_sections,
+    },
+    oversized_flags, unsupported_futures_and_streams,
+};
 
 // `wit_component::decode` uses `root` as an arbitrary name for the primary
 // world name, see
@@ -34,6 +47,120 @@ fn main() -> Result<ExitCode, ()> {
                 .help("include the WebAssembly file as hex bytes in the output code")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("external-wasm")
+                .long("external-wasm")
+                .help("make the factory constructor take the WebAssembly file instead of including it")
+                .conflicts_with("inline-wasm")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("compress")
+                .long("compress")
+                .help("compress the WebAssembly file, which the factory constructor decompresses")
+                .value_parser(["gzip", "zstd"])
+                .conflicts_with("external-wasm"),
+        )
+        .arg(
+            Arg::new("keep-debug-info")
+                .long("keep-debug-info")
+                .help("keep the name and DWARF custom sections of the WebAssembly file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("pre-initialized")
+                .long("pre-initialized")
+                .help("skip the start functions of a WebAssembly file pre-initialized with Wizer")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("async")
+                .long("async")
+                .help("call `async` functions through the component model's async ABI")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("generic-containers")
+                .long("generic-containers")
+                .help("map `option` and `result` to generated generic `Option[T]` and `Result[T, E]` types")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("interpreter")
+                .long("interpreter")
+                .help("make generated factories run the guest in wazero's interpreter by default")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tracing")
+                .long("tracing")
+                .help("trace calls into the guest and host functions with OpenTelemetry")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("zero-copy")
+                .long("zero-copy")
+                .help("pass strings and byte lists to host functions without copying them out of the guest's memory")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("scratch-buffer")
+                .long("scratch-buffer")
+                .help("write the arguments of calls into the guest to a reused buffer; only for guests opting in with a `gravity-scratch-buffer` custom section")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("zero-buffers")
+                .long("zero-buffers")
+                .help("zero the host's copies of guest data, and the scratch buffer, once done with them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("runtime-package")
+                .long("runtime-package")
+                .value_name("IMPORT_PATH")
+                .value_parser(runtime_package_path)
+                .help("use the helpers of the runtime package imported from IMPORT_PATH rather than copies of them"),
+        )
+        .arg(
+            Arg::new("emit-runtime")
+                .long("emit-runtime")
+                .value_name("DIR")
+                .help("also write the runtime package given by --runtime-package to DIR/runtime.go")
+                .requires("runtime-package")
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("benchmarks")
+                .long("benchmarks")
+                .help("also write benchmarks of the exported functions to a _bench_test.go file next to the output")
+                .requires("output")
+                .conflicts_with("external-wasm")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("emit-example")
+                .long("emit-example")
+                .value_name("PACKAGE")
+                .help("also write an example main package using the bindings, imported from PACKAGE, to example/main.go next to the output")
+                .requires("output")
+                .conflicts_with("external-wasm"),
+        )
+        .arg(
+            Arg::new("conformance-tests")
+                .long("conformance-tests")
+                .help("also write a skeleton of tests for the implementations of the imports to a _conformance_test.go file next to the output, unless it exists")
+                .requires("output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fuzz-targets")
+                .long("fuzz-targets")
+                .help("also write fuzz targets for the exported functions taking strings or bytes to a _fuzz_test.go file next to the output")
+                .requires("output")
+                .conflicts_with("external-wasm")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("file")
                 .help("the WebAssembly file to process")
@@ -54,6 +181,27 @@ fn main() -> Result<ExitCode, ()> {
         .get_one::<String>("file")
         .expect("should have a file");
     let inline_wasm = matches.get_flag("inline-wasm");
+    let external_wasm = matches.get_flag("external-wasm");
+    let compression = match matches.get_one::<String>("compress").map(String::as_str) {
+        Some("gzip") => Some(Compression::Gzip),
+        Some("zstd") => Some(Compression::Zstd),
+        _ => None,
+    };
+    let keep_debug_info = matches.get_flag("keep-debug-info");
+    let pre_initialized = matches.get_flag("pre-initialized");
+    let asynchronous = matches.get_flag("async");
+    let generic_containers = matches.get_flag("generic-containers");
+    let interpreter = matches.get_flag("interpreter");
+    let tracing = matches.get_flag("tracing");
+    let zero_copy = matches.get_flag("zero-copy");
+    let scratch_buffer = matches.get_flag("scratch-buffer");
+    let zero_buffers = matches.get_flag("zero-buffers");
+    let runtime_package = matches.get_one::<String>("runtime-package");
+    let runtime_dir = matches.get_one::<String>("emit-runtime");
+    let benchmarks = matches.get_flag("benchmarks");
+    let example_package = matches.get_one::<String>("emit-example");
+    let conformance_tests = matches.get_flag("conformance-tests");
+    let fuzz_targets = matches.get_flag("fuzz-targets");
     let output = matches.get_one::<String>("output");
 
     // Load the file specified as the `file` arg to clap
@@ -70,7 +218,52 @@ fn main() -> Result<ExitCode, ()> {
         .map(|(module, bindgen)| (module.unwrap_or(wasm), bindgen))
         .expect("file should be a valid WebAssembly module");
 
-    let wasm_file = &format!("{}.wasm", selected_world.replace('-', "_"));
+    // Reusing the arguments' memory is only safe for guests which say so, as
+    // those built with `wit-bindgen` free their arguments.
+    if scratch_buffer && !has_custom_section(&module, SCRATCH_BUFFER_SECTION).unwrap_or(false) {
+        eprintln!(
+            "--scratch-buffer needs a guest with a `{SCRATCH_BUFFER_SECTION}` custom section: {file}"
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
+    // The WIT custom section was already taken out by `decode`
+    let Ok(module) = strip_custom_sections(&module, keep_debug_info) else {
+        eprintln!("unable to strip custom sections from file: {file}");
+        return Ok(ExitCode::FAILURE);
+    };
+
+    // Instance snapshots capture the mutable globals, which wazero only
+    // exposes once exported. The Wasm isn't part of the bindings with
+    // `--external-wasm`, so it's left as it is.
+    let (module, mutable_globals) = if external_wasm {
+        (module, Vec::new())
+    } else {
+        match export_mutable_globals(&module) {
+            Ok(exported) => exported,
+            Err(_) => {
+                eprintln!("unable to export mutable globals from file: {file}");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    };
+
+    let module = match compression {
+        Some(compression) => match compression.compress(&module) {
+            Ok(compressed) => compressed,
+            Err(_) => {
+                eprintln!("unable to compress file: {file}");
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        None => module,
+    };
+
+    let mut wasm_file = format!("{}.wasm", selected_world.replace('-', "_"));
+    if let Some(compression) = compression {
+        wasm_file = format!("{wasm_file}.{}", compression.extension());
+    }
+    let wasm_file = &wasm_file;
 
     let Some((_, world)) = bindgen
         .resolve
@@ -82,21 +275,49 @@ fn main() -> Result<ExitCode, ()> {
         return Ok(ExitCode::FAILURE);
     };
 
+    if let Some(name) = oversized_flags(&bindgen.resolve, world).first() {
+        eprintln!("unsupported flags with more than 64 members: {name}");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if let Some(name) =
+        unsupported_futures_and_streams(&bindgen.resolve, world, asynchronous).first()
+    {
+        eprintln!(
+            "unsupported future or stream in {name}: only the results of exported functions can be futures or streams"
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
     let mut sizes = SizeAlign::default();
     sizes.fill(&bindgen.resolve);
-    let mut bindings = Bindings::new(&bindgen.resolve, world, &sizes);
+    let mut bindings = Bindings::new(&bindgen.resolve, world, &sizes)
+        .with_async(asynchronous)
+        .with_generic_containers(generic_containers)
+        .with_interpreter(interpreter)
+        .with_tracing(tracing)
+        .with_zero_copy(zero_copy)
+        .with_scratch_buffer(scratch_buffer)
+        .with_zero_buffers(zero_buffers)
+        .with_runtime_package(runtime_package.map(String::as_str))
+        .with_external_wasm(external_wasm)
+        .with_compression(compression)
+        .with_pre_initialized(pre_initialized)
+        .with_mutable_globals(mutable_globals);
 
-    bindings.include_wasm(if inline_wasm {
-        WasmData::Inline(&module)
-    } else {
-        WasmData::Embedded(wasm_file)
-    });
+    if !external_wasm {
+        bindings.include_wasm(if inline_wasm {
+            WasmData::Inline(&module)
+        } else {
+            WasmData::Embedded(wasm_file)
+        });
+    }
 
     bindings.generate();
 
     let header = "// Code generated by arcjet-gravity; DO NOT EDIT.\n\n".to_string();
-    let mut w = genco::fmt::FmtWriter::new(header);
-    let fmt = genco::fmt::Config::from_lang::<Go>().with_indentation(genco::fmt::Indentation::Tab);
+    let mut w = genco::fmt::FmtWriter::new(header.clone());
+    let fmt = go_fmt_config();
     let config = go::Config::default().with_package(selected_world.replace('-', "_"));
 
     // TODO(#16): Don't use the internal bindings.out field
@@ -107,7 +328,7 @@ fn main() -> Result<ExitCode, ()> {
 
     match output {
         Some(outpath) => {
-            if !inline_wasm {
+            if !inline_wasm && !external_wasm {
                 let wasm_outpath = Path::new(outpath).with_file_name(wasm_file);
                 match fs::write(&wasm_outpath, module) {
                     Ok(_) => (),
@@ -117,13 +338,63 @@ fn main() -> Result<ExitCode, ()> {
                     }
                 }
             }
-            match fs::write(outpath, w.into_inner()) {
-                Ok(_) => Ok(ExitCode::SUCCESS),
-                Err(_) => {
-                    eprintln!("failed to create file: {outpath}");
-                    Ok(ExitCode::FAILURE)
+            if fs::write(outpath, w.into_inner()).is_err() {
+                eprintln!("failed to create file: {outpath}");
+                return Ok(ExitCode::FAILURE);
+            }
+            if let (Some(dir), Some(import_path)) = (runtime_dir, runtime_package) {
+                // The package is named after the last element of its import
+                // path, which the bindings refer to it by.
+                let name = import_path.rsplit('/').next().unwrap_or_default();
+                let config = go::Config::default().with_package(name);
+                if fs::create_dir_all(dir).is_err()
+                    || !write_go_file(
+                        &Path::new(dir).join("runtime.go"),
+                        &header,
+                        &Bindings::generate_runtime(),
+                        &config,
+                    )
+                {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+            if benchmarks {
+                let path = sibling_path(outpath, "_bench_test.go");
+                if !write_go_file(&path, &header, &bindings.generate_benchmarks(), &config) {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+            if fuzz_targets {
+                let path = sibling_path(outpath, "_fuzz_test.go");
+                if !write_go_file(&path, &header, &bindings.generate_fuzz_targets(), &config) {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+            // The skeleton is meant to be edited, so it's only written once,
+            // without a header.
+            let path = sibling_path(outpath, "_conformance_test.go");
+            if conformance_tests
+                && !path.exists()
+                && !write_go_file(&path, "", &bindings.generate_conformance_tests(), &config)
+            {
+                return Ok(ExitCode::FAILURE);
+            }
+            if let Some(package) = example_package {
+                // The example is meant to be edited, so it has no header.
+                let example_dir = Path::new(outpath).with_file_name("example");
+                let config = go::Config::default().with_package("main");
+                if fs::create_dir_all(&example_dir).is_err()
+                    || !write_go_file(
+                        &example_dir.join("main.go"),
+                        "",
+                        &bindings.generate_example(package),
+                        &config,
+                    )
+                {
+                    return Ok(ExitCode::FAILURE);
                 }
             }
+            Ok(ExitCode::SUCCESS)
         }
         None => {
             println!("{}", w.into_inner());
@@ -131,3 +402,52 @@ fn main() -> Result<ExitCode, ()> {
         }
     }
 }
+
+/// Checks that the last element of the runtime package's import path is a Go
+/// identifier, as the bindings refer to the package by it without an alias,
+/// which rules out paths ending in e.g. `go-runtime`, `runtime.go` or a major
+/// version suffix like `v2` of another package.
+fn runtime_package_path(import_path: &str) -> Result<String, String> {
+    let name = import_path.rsplit('/').next().unwrap_or_default();
+    let mut chars = name.chars();
+    let is_identifier = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_');
+    if !is_identifier || name == "_" {
+        return Err(format!(
+            "the bindings refer to the package as `{name}`, which isn't a Go identifier"
+        ));
+    }
+    if name.len() > 1 && name.starts_with('v') && name[1..].bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!(
+            "the bindings refer to the package as `{name}`, which is a major version suffix Go doesn't name packages after"
+        ));
+    }
+    Ok(import_path.to_string())
+}
+
+/// How the generated Go files are formatted.
+fn go_fmt_config() -> genco::fmt::Config {
+    genco::fmt::Config::from_lang::<Go>().with_indentation(genco::fmt::Indentation::Tab)
+}
+
+/// The path of a file generated alongside the bindings written to `outpath`,
+/// named after them with `suffix`.
+fn sibling_path(outpath: &str, suffix: &str) -> PathBuf {
+    let outpath = Path::new(outpath);
+    let stem = outpath.file_stem().unwrap_or_default().to_string_lossy();
+    outpath.with_file_name(format!("{stem}{suffix}"))
+}
+
+/// Writes `tokens` to the Go file at `path`, starting with `header`. Returns
+/// whether it succeeded, having reported the failure otherwise.
+fn write_go_file(path: &Path, header: &str, tokens: &Tokens<Go>, config: &go::Config) -> bool {
+    let mut w = genco::fmt::FmtWriter::new(header.to_string());
+    tokens
+        .format_file(&mut w.as_formatter(&go_fmt_config()), config)
+        .unwrap();
+    if fs::write(path, w.into_inner()).is_err() {
+        eprintln!("failed to create file: {}", path.to_string_lossy());
+        return false;
+    }
+    true
+}
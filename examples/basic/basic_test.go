@@ -3,6 +3,7 @@ package basic
 import (
 	"context"
 	"log/slog"
+	"slices"
 	"strings"
 	"testing"
 )
@@ -20,6 +21,18 @@ func (Utils) Uppercase(ctx context.Context, value string) string {
 	return strings.ToUpper(value)
 }
 
+func (Utils) UppercaseAll(ctx context.Context, values []string) []string {
+	result := make([]string, len(values))
+	for i, value := range values {
+		result[i] = strings.ToUpper(value)
+	}
+	return result
+}
+
+func (Utils) Describe(ctx context.Context, entry Entry) string {
+	return entry.Name + ": " + strings.Join(entry.Tags, ", ")
+}
+
 func TestBasic(t *testing.T) {
 	fac, err := NewBasicFactory(t.Context(), SlogLogger{}, Utils{})
 	if err != nil {
@@ -113,3 +126,45 @@ func TestResultPrimitiveCleanup(t *testing.T) {
 		t.Errorf("expected: %t, but got: %t", expected, actual)
 	}
 }
+
+func TestListOfStrings(t *testing.T) {
+	fac, err := NewBasicFactory(t.Context(), SlogLogger{}, Utils{})
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	actual := ins.UppercaseHeaders(t.Context(), []string{"content-type", "", "x-forwarded-for"})
+
+	expected := []string{"CONTENT-TYPE", "", "X-FORWARDED-FOR"}
+	if !slices.Equal(actual, expected) {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
+
+func TestRecordOfStringAndList(t *testing.T) {
+	fac, err := NewBasicFactory(t.Context(), SlogLogger{}, Utils{})
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	actual := ins.DescribeEntry(t.Context(), "widget", []string{"blue", "", "large"})
+
+	const expected = "widget: blue, , large"
+	if actual != expected {
+		t.Errorf("expected: %s, but got: %s", expected, actual)
+	}
+}
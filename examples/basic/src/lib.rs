@@ -26,4 +26,10 @@ impl Guest for BasicWorld {
     fn optional_string(s: Option<String>) -> Option<String> {
         s
     }
+    fn uppercase_headers(names: Vec<String>) -> Vec<String> {
+        utils::uppercase_all(&names)
+    }
+    fn describe_entry(name: String, tags: Vec<String>) -> String {
+        utils::describe(&utils::Entry { name, tags })
+    }
 }
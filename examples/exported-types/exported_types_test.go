@@ -0,0 +1,72 @@
+package exported_types
+
+import "testing"
+
+func newInstance(t *testing.T) *ExportedTypesInstance {
+	t.Helper()
+	fac, err := NewExportedTypesFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { fac.Close(t.Context()) })
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { ins.Close(t.Context()) })
+	return ins
+}
+
+func TestTranslate(t *testing.T) {
+	ins := newInstance(t)
+
+	got := ins.Shapes().Translate(t.Context(), Point{X: 1, Y: -2}, 3, 4)
+	if want := (Point{X: 4, Y: 2}); got != want {
+		t.Errorf("expected %+v, but got %+v", want, got)
+	}
+}
+
+func TestArea(t *testing.T) {
+	ins := newInstance(t)
+
+	cases := []struct {
+		shape Shape
+		want  uint32
+	}{
+		{ShapeCircle{Value: 2}, 12},
+		{ShapeRectangle{Value: Tuple2[Point, Point]{F0: Point{X: -1, Y: 0}, F1: Point{X: 2, Y: 5}}}, 15},
+		{ShapeDot{}, 0},
+	}
+	for _, c := range cases {
+		if got := ins.Shapes().Area(t.Context(), c.shape); got != c.want {
+			t.Errorf("expected the area of %+v to be %d, but got %d", c.shape, c.want, got)
+		}
+	}
+}
+
+func TestBounds(t *testing.T) {
+	ins := newInstance(t)
+
+	got := ins.Shapes().Bounds(t.Context(), []Point{{X: 3, Y: 1}, {X: -2, Y: 4}, {X: 0, Y: -1}})
+	want := ShapeRectangle{Value: Tuple2[Point, Point]{F0: Point{X: -2, Y: -1}, F1: Point{X: 3, Y: 4}}}
+	if got != want {
+		t.Errorf("expected %+v, but got %+v", want, got)
+	}
+
+	if got := ins.Shapes().Bounds(t.Context(), nil); got != (ShapeDot{}) {
+		t.Errorf("expected no points to be bounded by a dot, but got %+v", got)
+	}
+}
+
+func TestEmbolden(t *testing.T) {
+	ins := newInstance(t)
+
+	got := ins.Shapes().Embolden(t.Context(), StyleFilled|StyleDashed)
+	if want := StyleFilled | StyleDashed | StyleBold; got != want {
+		t.Errorf("expected %v, but got %v", want, got)
+	}
+	if got := ins.Shapes().Embolden(t.Context(), got); got.Has(StyleBold) {
+		t.Errorf("expected bold to be toggled off, but got %v", got)
+	}
+}
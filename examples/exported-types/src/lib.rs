@@ -0,0 +1,52 @@
+use exports::arcjet::exported_types::shapes::{Guest, Point, Shape, Style};
+
+wit_bindgen::generate!({
+    world: "exported-types",
+});
+
+struct ExportedTypesWorld;
+
+export!(ExportedTypesWorld);
+
+impl Guest for ExportedTypesWorld {
+    fn translate(p: Point, dx: i32, dy: i32) -> Point {
+        Point {
+            x: p.x + dx,
+            y: p.y + dy,
+        }
+    }
+
+    fn area(s: Shape) -> u32 {
+        match s {
+            Shape::Circle(radius) => (std::f64::consts::PI * f64::from(radius).powi(2)) as u32,
+            Shape::Rectangle((from, to)) => from.x.abs_diff(to.x) * from.y.abs_diff(to.y),
+            Shape::Dot => 0,
+        }
+    }
+
+    fn bounds(points: Vec<Point>) -> Shape {
+        let Some(first) = points.first() else {
+            return Shape::Dot;
+        };
+        let (mut from, mut to) = (*first, *first);
+        for p in &points {
+            from = Point {
+                x: from.x.min(p.x),
+                y: from.y.min(p.y),
+            };
+            to = Point {
+                x: to.x.max(p.x),
+                y: to.y.max(p.y),
+            };
+        }
+        if (from.x, from.y) == (to.x, to.y) {
+            Shape::Dot
+        } else {
+            Shape::Rectangle((from, to))
+        }
+    }
+
+    fn embolden(s: Style) -> Style {
+        s ^ Style::BOLD
+    }
+}
@@ -6,6 +6,10 @@ package examples
 //go:generate cargo build -p example-instructions --target wasm32-unknown-unknown --release
 //go:generate cargo build -p example-regressions --target wasm32-unknown-unknown --release
 //go:generate cargo build -p example-variants --target wasm32-unknown-unknown --release
+//go:generate cargo build -p example-streams --target wasm32-unknown-unknown --release
+//go:generate cargo build -p example-exported-types --target wasm32-unknown-unknown --release
+//go:generate cargo build -p example-options --target wasm32-unknown-unknown --release
+//go:generate cargo build -p example-wasi --target wasm32-wasip1 --release
 
 //go:generate cargo run --bin gravity -- --world basic --output ./basic/basic.go ../target/wasm32-unknown-unknown/release/example_basic.wasm
 //go:generate cargo run --bin gravity -- --world records --output ./records/records.go ../target/wasm32-unknown-unknown/release/example_records.wasm
@@ -13,3 +17,7 @@ package examples
 //go:generate cargo run --bin gravity -- --world instructions --output ./instructions/bindings.go ../target/wasm32-unknown-unknown/release/example_instructions.wasm
 //go:generate cargo run --bin gravity -- --world regressions --output ./regressions/regressions.go ../target/wasm32-unknown-unknown/release/example_regressions.wasm
 //go:generate cargo run --bin gravity -- --world variants --output ./variants/variants.go ../target/wasm32-unknown-unknown/release/example_variants.wasm
+//go:generate cargo run --bin gravity -- --world streams --output ./streams/streams.go ../target/wasm32-unknown-unknown/release/example_streams.wasm
+//go:generate cargo run --bin gravity -- --world exported-types --output ./exported-types/exported_types.go ../target/wasm32-unknown-unknown/release/example_exported_types.wasm
+//go:generate cargo run --bin gravity -- --world options --output ./options/options.go ../target/wasm32-unknown-unknown/release/example_options.wasm
+//go:generate cargo run --bin gravity -- --world wasi --output ./wasi/wasi.go ../target/wasm32-wasip1/release/example_wasi.wasm
@@ -3,8 +3,10 @@ package instructions
 import (
 	"fmt"
 	"iter"
+	"maps"
 	"math"
 	"math/rand/v2"
+	"slices"
 	"testing"
 )
 
@@ -171,6 +173,48 @@ func Test_U32Roundtrip(t *testing.T) {
 	}
 }
 
+func Test_S64Roundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	for _, expected := range []int64{math.MinInt64, math.MinInt32 - 1, -1, 0, 1, math.MaxInt32 + 1, math.MaxInt64} {
+		actual := ins.S64Roundtrip(t.Context(), expected)
+		if actual != expected {
+			t.Errorf("expected: %d, but got: %d", expected, actual)
+		}
+	}
+}
+
+func Test_U64Roundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	for _, expected := range []uint64{0, 1, math.MaxUint32, math.MaxUint32 + 1, math.MaxUint64} {
+		actual := ins.U64Roundtrip(t.Context(), expected)
+		if actual != expected {
+			t.Errorf("expected: %d, but got: %d", expected, actual)
+		}
+	}
+}
+
 func Test_F32Roundtrip(t *testing.T) {
 	fac, err := NewInstructionsFactory(t.Context())
 	if err != nil {
@@ -240,3 +284,121 @@ func Test_EnumInput(t *testing.T) {
 	ins.EnumInput(t.Context(), Two)
 	ins.EnumInput(t.Context(), Three)
 }
+
+func Test_ListS16Roundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := []int16{math.MinInt16, -1, 0, 1, math.MaxInt16}
+	if actual := ins.ListS16Roundtrip(t.Context(), expected); !slices.Equal(actual, expected) {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
+
+func Test_ListS64Roundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := []int64{math.MinInt64, -1, 0, 1, math.MaxInt64}
+	if actual := ins.ListS64Roundtrip(t.Context(), expected); !slices.Equal(actual, expected) {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
+
+func Test_ListListU32Roundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := [][]uint32{{}, {1}, {2, 3}, {math.MaxUint32, 0, 4}}
+	actual := ins.ListListU32Roundtrip(t.Context(), expected)
+	if !slices.EqualFunc(actual, expected, slices.Equal) {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
+
+func Test_TupleRoundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := Tuple2[int32, float64]{F0: math.MinInt32, F1: math.MaxFloat64}
+	first, second := ins.TupleRoundtrip(t.Context(), expected)
+	if first != expected.F0 || second != expected.F1 {
+		t.Errorf("expected: %v, but got: (%v, %v)", expected, first, second)
+	}
+}
+
+func Test_PointRoundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := Point{X: -math.MaxFloat32, Y: math.SmallestNonzeroFloat64}
+	actual := ins.PointRoundtrip(t.Context(), expected)
+	if actual != expected {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
+
+func Test_MapBoolRoundtrip(t *testing.T) {
+	fac, err := NewInstructionsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	expected := map[bool]uint32{true: math.MaxUint32, false: 0}
+	actual := ins.MapBoolRoundtrip(t.Context(), expected)
+	if !maps.Equal(actual, expected) {
+		t.Errorf("expected: %v, but got: %v", expected, actual)
+	}
+}
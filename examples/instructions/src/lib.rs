@@ -1,3 +1,9 @@
+use std::collections::BTreeMap;
+
+// The generated bindings call the `WitMap` methods, which need the trait in
+// scope.
+use wit_bindgen::rt::WitMap as _;
+
 wit_bindgen::generate!({
     world: "instructions",
 });
@@ -31,6 +37,14 @@ impl Guest for InstructionsWorld {
         assert!((u32::MIN..=u32::MAX).contains(&val));
         val
     }
+    fn s64_roundtrip(val: i64) -> i64 {
+        assert!((i64::MIN..=i64::MAX).contains(&val));
+        val
+    }
+    fn u64_roundtrip(val: u64) -> u64 {
+        assert!((u64::MIN..=u64::MAX).contains(&val));
+        val
+    }
     fn f32_roundtrip(val: f32) -> f32 {
         assert!((f32::MIN..=f32::MAX).contains(&val));
         val
@@ -45,4 +59,22 @@ impl Guest for InstructionsWorld {
             EnumValues::One | EnumValues::Two | EnumValues::Three
         ));
     }
+    fn list_s16_roundtrip(val: Vec<i16>) -> Vec<i16> {
+        val
+    }
+    fn list_s64_roundtrip(val: Vec<i64>) -> Vec<i64> {
+        val
+    }
+    fn list_list_u32_roundtrip(val: Vec<Vec<u32>>) -> Vec<Vec<u32>> {
+        val
+    }
+    fn tuple_roundtrip(val: (i32, f64)) -> (i32, f64) {
+        val
+    }
+    fn point_roundtrip(val: Point) -> Point {
+        val
+    }
+    fn map_bool_roundtrip(val: BTreeMap<bool, u32>) -> BTreeMap<bool, u32> {
+        val
+    }
 }
@@ -0,0 +1,510 @@
+package options
+
+import (
+	"bytes"
+	"context"
+	"errors"
+	"log/slog"
+	"slices"
+	"strings"
+	"sync"
+	"testing"
+	"time"
+
+	"github.com/tetratelabs/wazero"
+	"github.com/tetratelabs/wazero/api"
+	"github.com/tetratelabs/wazero/experimental"
+)
+
+// echo replies to the guest with the value it was handed.
+var echo = &MockOptionsHost{
+	HandleFunc: func(ctx context.Context, value string) string { return value },
+}
+
+func newFactory(t *testing.T, host IOptionsHost, opts ...OptionsFactoryOption) *OptionsFactory {
+	t.Helper()
+	fac, err := NewOptionsFactory(t.Context(), host, opts...)
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { fac.Close(context.Background()) })
+	return fac
+}
+
+func newInstance(t *testing.T, fac *OptionsFactory) *OptionsInstance {
+	t.Helper()
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { ins.Close(context.Background()) })
+	return ins
+}
+
+func TestInstancePool(t *testing.T) {
+	for _, c := range []struct {
+		size int
+		want uint32
+	}{
+		// The instance the first call used is handed to the second.
+		{1, 2},
+		// Each call gets an instance of its own.
+		{0, 1},
+	} {
+		fac := newFactory(t, echo, WithInstancePoolSize(c.size))
+
+		var got uint32
+		for range 2 {
+			err := fac.WithInstance(t.Context(), func(ins *OptionsInstance) error {
+				got = ins.Count(t.Context())
+				return nil
+			})
+			if err != nil {
+				t.Fatal(err)
+			}
+		}
+		if got != c.want {
+			t.Errorf("expected the second call with a pool of %d to count %d, but got %d", c.size, c.want, got)
+		}
+	}
+}
+
+func TestFailedInstanceNotPooled(t *testing.T) {
+	fac := newFactory(t, echo, WithInstancePoolSize(1))
+
+	failure := errors.New("failed")
+	err := fac.WithInstance(t.Context(), func(ins *OptionsInstance) error {
+		ins.Count(t.Context())
+		return failure
+	})
+	if !errors.Is(err, failure) {
+		t.Fatalf("expected fn's error, but got %v", err)
+	}
+	err = fac.WithInstance(t.Context(), func(ins *OptionsInstance) error {
+		if got := ins.Count(t.Context()); got != 1 {
+			t.Errorf("expected a new instance, but it counted %d", got)
+		}
+		return nil
+	})
+	if err != nil {
+		t.Fatal(err)
+	}
+}
+
+func TestSnapshotRestore(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo))
+
+	ins.Count(t.Context())
+	snapshot, err := ins.Snapshot()
+	if err != nil {
+		t.Fatal(err)
+	}
+	for range 3 {
+		ins.Count(t.Context())
+	}
+	// Growing the memory past the snapshot must not keep it from restoring.
+	if _, err := ins.Grow(t.Context(), 1); err != nil {
+		t.Fatal(err)
+	}
+	if err := ins.Restore(t.Context(), snapshot); err != nil {
+		t.Fatal(err)
+	}
+
+	if got := ins.Count(t.Context()); got != 2 {
+		t.Errorf("expected the count to resume from the snapshot, but got %d", got)
+	}
+}
+
+func TestMemorySize(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo))
+
+	pages := ins.MemoryPages()
+	if pages == 0 || ins.MemorySize() != pages*65536 {
+		t.Fatalf("expected the size of %d pages, but got %d bytes", pages, ins.MemorySize())
+	}
+	previous, err := ins.Grow(t.Context(), 2)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if previous != pages || ins.MemoryPages() != pages+2 {
+		t.Errorf("expected %d pages grown to %d, but got %d grown to %d", pages, pages+2, previous, ins.MemoryPages())
+	}
+	if ins.Module() == nil {
+		t.Error("expected the instance's module")
+	}
+}
+
+func TestMemoryLimit(t *testing.T) {
+	// The guest starts with 17 pages.
+	ins := newInstance(t, newFactory(t, echo, WithMemoryLimitPages(20)))
+
+	if _, err := ins.Grow(t.Context(), 1); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := ins.Grow(t.Context(), 100); err == nil {
+		t.Error("expected growing past the limit to fail")
+	}
+}
+
+func TestCallTimeout(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo, WithCallTimeout(50*time.Millisecond)))
+
+	err := ins.Spin(t.Context())
+	if !errors.Is(err, context.DeadlineExceeded) {
+		t.Errorf("expected the call to time out, but got %v", err)
+	}
+	var trap *TrapError
+	if !errors.As(err, &trap) || trap.Function != "spin" {
+		t.Errorf("expected the guest to be stopped in spin, but got %v", err)
+	}
+}
+
+func TestCloseOnContextDone(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo))
+
+	ctx, cancel := context.WithCancel(t.Context())
+	time.AfterFunc(50*time.Millisecond, cancel)
+	if err := ins.Spin(ctx); !errors.Is(err, context.Canceled) {
+		t.Errorf("expected the call to be cancelled, but got %v", err)
+	}
+}
+
+func TestMaxConcurrentCalls(t *testing.T) {
+	entered := make(chan struct{}, 2)
+	release := make(chan struct{})
+	host := &MockOptionsHost{
+		HandleFunc: func(ctx context.Context, value string) string {
+			entered <- struct{}{}
+			<-release
+			return value
+		},
+	}
+	fac := newFactory(t, host, WithMaxConcurrentCalls(1))
+	first, second := newInstance(t, fac), newInstance(t, fac)
+
+	done := make(chan error)
+	go func() {
+		_, err := first.Forward(t.Context(), "first")
+		done <- err
+	}()
+	<-entered
+
+	// The first call holds the only slot, so the second waits for it until
+	// its context is done.
+	ctx, cancel := context.WithTimeout(t.Context(), 50*time.Millisecond)
+	defer cancel()
+	if _, err := second.Forward(ctx, "second"); err != context.DeadlineExceeded {
+		t.Errorf("expected the second call to give up waiting, but got %v", err)
+	}
+
+	close(release)
+	if err := <-done; err != nil {
+		t.Fatal(err)
+	}
+	if got, err := second.Forward(t.Context(), "second"); err != nil || got != "second" {
+		t.Errorf("expected the second call to go through once the first returned, but got %q, %v", got, err)
+	}
+}
+
+func TestMaxLength(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo, WithMaxLength(8)))
+
+	if got, err := ins.Repeat(t.Context(), "ab", 4); err != nil || got != "abababab" {
+		t.Errorf("expected a string of the maximum length, but got %q, %v", got, err)
+	}
+
+	var abiErr *ABIError
+	if _, err := ins.Repeat(t.Context(), "ab", 5); !errors.As(err, &abiErr) {
+		t.Errorf("expected a string returned past the maximum length to fail, but got %v", err)
+	}
+	if _, err := ins.Forward(t.Context(), "0123456789"); !errors.As(err, &abiErr) {
+		t.Errorf("expected a string passed to the host past the maximum length to fail, but got %v", err)
+	}
+}
+
+func TestTrap(t *testing.T) {
+	ins := newInstance(t, newFactory(t, echo))
+
+	var trap *TrapError
+	if err := ins.Crash(t.Context()); !errors.As(err, &trap) {
+		t.Fatalf("expected a trap, but got %v", err)
+	}
+	if trap.World != "options" || trap.Function != "crash" || trap.Stack == "" {
+		t.Errorf("expected the trap of crash with a stack trace, but got %+v", trap)
+	}
+}
+
+func TestHostPanic(t *testing.T) {
+	host := &MockOptionsHost{
+		HandleFunc: func(ctx context.Context, value string) string { panic("boom") },
+	}
+	ins := newInstance(t, newFactory(t, host))
+
+	_, err := ins.Forward(t.Context(), "value")
+	var panicErr *HostPanicError
+	if !errors.As(err, &panicErr) {
+		t.Fatalf("expected the panic to fail the call, but got %v", err)
+	}
+	if panicErr.Function != "handle" || panicErr.Value != "boom" {
+		t.Errorf("expected handle to have panicked with boom, but got %+v", panicErr)
+	}
+	var trap *TrapError
+	if errors.As(err, &trap) {
+		t.Errorf("expected the panic not to be reported as a trap, but got %v", err)
+	}
+}
+
+func TestOptionalImports(t *testing.T) {
+	if _, err := NewOptionsFactory(t.Context(), nil); err == nil {
+		t.Fatal("expected a nil import to be refused")
+	}
+
+	ins := newInstance(t, newFactory(t, nil, WithOptionalImports()))
+
+	if got := ins.Count(t.Context()); got != 1 {
+		t.Errorf("expected functions not calling the import to work, but got %d", got)
+	}
+	_, err := ins.Forward(t.Context(), "value")
+	var notProvided *ImportNotProvidedError
+	if !errors.As(err, &notProvided) || notProvided.Import != "host" || notProvided.Function != "handle" {
+		t.Errorf("expected calling the import to fail, but got %v", err)
+	}
+}
+
+func TestHostInterceptor(t *testing.T) {
+	var calls []string
+	interceptor := func(ctx context.Context, iface, method string, next func(context.Context)) {
+		calls = append(calls, iface+"."+method)
+		next(ctx)
+	}
+	ins := newInstance(t, newFactory(t, echo, WithHostInterceptor(interceptor)))
+
+	if got, err := ins.Forward(t.Context(), "value"); err != nil || got != "value" {
+		t.Fatalf("expected the host's reply, but got %q, %v", got, err)
+	}
+	if want := []string{"host.handle"}; !slices.Equal(calls, want) {
+		t.Errorf("expected %v to be intercepted, but got %v", want, calls)
+	}
+}
+
+func TestCallInterceptor(t *testing.T) {
+	var calls []string
+	interceptor := func(ctx context.Context, function string, next func(context.Context) error) error {
+		calls = append(calls, function)
+		return next(ctx)
+	}
+	ins := newInstance(t, newFactory(t, echo, WithCallInterceptor(interceptor)))
+
+	ins.Count(t.Context())
+	if _, err := ins.Forward(t.Context(), "value"); err != nil {
+		t.Fatal(err)
+	}
+	if want := []string{"count", "forward"}; !slices.Equal(calls, want) {
+		t.Errorf("expected %v to be intercepted, but got %v", want, calls)
+	}
+}
+
+type metrics struct {
+	mu      sync.Mutex
+	calls   []string
+	returns []string
+	errs    []error
+}
+
+func (m *metrics) OnCall(name string) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.calls = append(m.calls, name)
+}
+
+func (m *metrics) OnReturn(name string, duration time.Duration, err error) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.returns = append(m.returns, name)
+	m.errs = append(m.errs, err)
+}
+
+func TestMetrics(t *testing.T) {
+	m := &metrics{}
+	ins := newInstance(t, newFactory(t, echo, WithMetrics(m)))
+
+	ins.Count(t.Context())
+	ins.Crash(t.Context())
+
+	want := []string{"count", "crash"}
+	if !slices.Equal(m.calls, want) || !slices.Equal(m.returns, want) {
+		t.Fatalf("expected calls to and returns from %v, but got %v and %v", want, m.calls, m.returns)
+	}
+	if m.errs[0] != nil || m.errs[1] == nil {
+		t.Errorf("expected only crash to fail, but got %v", m.errs)
+	}
+}
+
+func TestLogger(t *testing.T) {
+	var buf bytes.Buffer
+	logger := slog.New(slog.NewTextHandler(&buf, &slog.HandlerOptions{Level: slog.LevelDebug}))
+	ins := newInstance(t, newFactory(t, echo, WithLogger(logger)))
+
+	if _, err := ins.Forward(t.Context(), "value"); err != nil {
+		t.Fatal(err)
+	}
+	for _, want := range []string{
+		`msg="instantiated guest"`,
+		`msg="calling guest function" function=forward`,
+		`msg="calling host function" function=handle`,
+	} {
+		if !strings.Contains(buf.String(), want) {
+			t.Errorf("expected %s to be logged, but got:\n%s", want, buf.String())
+		}
+	}
+}
+
+type listener struct {
+	mu     sync.Mutex
+	called []string
+}
+
+func (l *listener) NewFunctionListener(def api.FunctionDefinition) experimental.FunctionListener {
+	return l
+}
+
+func (l *listener) Before(ctx context.Context, mod api.Module, def api.FunctionDefinition, params []uint64, stack experimental.StackIterator) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	l.called = append(l.called, def.ExportNames()...)
+}
+
+func (l *listener) After(ctx context.Context, mod api.Module, def api.FunctionDefinition, results []uint64) {
+}
+
+func (l *listener) Abort(ctx context.Context, mod api.Module, def api.FunctionDefinition, err error) {
+}
+
+func TestFunctionListenerFactory(t *testing.T) {
+	// The listeners are attached when the guest is compiled, which lazy
+	// compilation puts off until it's first instantiated.
+	for _, lazy := range []bool{false, true} {
+		l := &listener{}
+		opts := []OptionsFactoryOption{WithFunctionListenerFactory(l)}
+		if lazy {
+			opts = append(opts, WithLazyCompilation())
+		}
+		ins := newInstance(t, newFactory(t, echo, opts...))
+
+		ins.Count(t.Context())
+		if !slices.Contains(l.called, "count") {
+			t.Errorf("expected count to be listened to with lazy compilation %t, but got %v", lazy, l.called)
+		}
+	}
+}
+
+// allocator hands the guest memory backed by Go slices, counting how much it
+// was asked for.
+type allocator struct {
+	allocated int
+}
+
+func (a *allocator) Allocate(cap, max uint64) experimental.LinearMemory {
+	a.allocated++
+	return &linearMemory{buf: make([]byte, 0, cap)}
+}
+
+type linearMemory struct {
+	buf []byte
+}
+
+func (m *linearMemory) Reallocate(size uint64) []byte {
+	if size > uint64(cap(m.buf)) {
+		m.buf = append(m.buf[:cap(m.buf)], make([]byte, size-uint64(cap(m.buf)))...)
+	}
+	m.buf = m.buf[:size]
+	return m.buf
+}
+
+func (m *linearMemory) Free() {}
+
+func TestMemoryAllocator(t *testing.T) {
+	a := &allocator{}
+	ins := newInstance(t, newFactory(t, echo, WithMemoryAllocator(a)))
+
+	if got, err := ins.Repeat(t.Context(), "ab", 3); err != nil || got != "ababab" {
+		t.Errorf("expected the guest to run in the allocated memory, but got %q, %v", got, err)
+	}
+	if a.allocated != 1 {
+		t.Errorf("expected the instance's memory to be allocated once, but got %d", a.allocated)
+	}
+}
+
+func TestLazyCompilation(t *testing.T) {
+	fac := newFactory(t, echo, WithLazyCompilation())
+
+	// Compiling with a context that's done by the time the guest is
+	// instantiated mustn't fail instantiating it.
+	ctx, cancel := context.WithCancel(t.Context())
+	cancel()
+	if err := fac.Warmup(ctx, false); err != nil {
+		t.Fatal(err)
+	}
+	if err := fac.Warmup(t.Context(), true); err != nil {
+		t.Fatal(err)
+	}
+
+	compiled, err := fac.Compiled(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	if _, ok := compiled.ExportedFunctions()["count"]; !ok {
+		t.Errorf("expected the compiled guest to export count, but got %v", compiled.ExportedFunctions())
+	}
+}
+
+func TestRuntimeOptions(t *testing.T) {
+	configured := false
+	opts := [][]OptionsFactoryOption{
+		{WithInterpreter()},
+		{WithCompiler()},
+		{WithCompilationCacheDir(t.TempDir())},
+		{WithRuntimeConfig(func(config wazero.RuntimeConfig) wazero.RuntimeConfig {
+			configured = true
+			return config
+		})},
+	}
+	for _, opts := range opts {
+		ins := newInstance(t, newFactory(t, echo, opts...))
+		if got := ins.Count(t.Context()); got != 1 {
+			t.Errorf("expected the guest to run, but got %d", got)
+		}
+	}
+	if !configured {
+		t.Error("expected the runtime config to be handed to WithRuntimeConfig")
+	}
+}
+
+func TestSharedRuntime(t *testing.T) {
+	runtime := wazero.NewRuntime(t.Context())
+	t.Cleanup(func() { runtime.Close(context.Background()) })
+
+	reply := func(prefix string) *MockOptionsHost {
+		return &MockOptionsHost{
+			HandleFunc: func(ctx context.Context, value string) string { return prefix + value },
+		}
+	}
+	// The second factory's host modules go under a namespace of their own,
+	// so each guest calls its own host.
+	first := newFactory(t, reply("first "), WithRuntime(runtime))
+	second := newFactory(t, reply("second "), WithRuntime(runtime))
+
+	for fac, want := range map[*OptionsFactory]string{first: "first value", second: "second value"} {
+		if got, err := newInstance(t, fac).Forward(t.Context(), "value"); err != nil || got != want {
+			t.Errorf("expected %q, but got %q, %v", want, got, err)
+		}
+	}
+
+	// Closing a factory leaves the runtime, and the other's modules, to it.
+	if err := first.Close(t.Context()); err != nil {
+		t.Fatal(err)
+	}
+	if got, err := newInstance(t, second).Forward(t.Context(), "value"); err != nil || got != "second value" {
+		t.Errorf("expected the second factory to outlive the first, but got %q, %v", got, err)
+	}
+}
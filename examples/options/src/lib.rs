@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use arcjet::options::host;
+
+wit_bindgen::generate!({
+    world: "options",
+});
+
+static CALLS: AtomicU32 = AtomicU32::new(0);
+
+struct OptionsWorld;
+
+export!(OptionsWorld);
+
+impl Guest for OptionsWorld {
+    fn count() -> u32 {
+        CALLS.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn forward(value: String) -> Result<String, String> {
+        Ok(host::handle(&value))
+    }
+
+    fn spin() -> Result<(), String> {
+        loop {
+            std::hint::black_box(());
+        }
+    }
+
+    fn repeat(value: String, times: u32) -> Result<String, String> {
+        Ok(value.repeat(times as usize))
+    }
+
+    fn grow(pages: u32) -> Result<u32, String> {
+        match core::arch::wasm32::memory_grow(0, pages as usize) {
+            usize::MAX => Err(format!("failed to grow the memory by {pages} pages")),
+            previous => Ok(previous as u32),
+        }
+    }
+
+    fn crash() -> Result<(), String> {
+        core::arch::wasm32::unreachable()
+    }
+}
@@ -133,3 +133,27 @@ func fooCmp(a, b Foo) bool {
 	}
 	return true
 }
+
+func TestNestedRecord(t *testing.T) {
+	fac, err := NewRecordsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	segment := Segment{
+		Start: Point{X: -1, Y: 2},
+		End:   Point{X: math.MaxInt32, Y: math.MinInt32},
+	}
+	got := ins.ReverseSegment(t.Context(), segment)
+	want := Segment{Start: segment.End, End: segment.Start}
+	if got != want {
+		t.Fatalf("got %+v, want %+v", got, want)
+	}
+}
@@ -54,4 +54,11 @@ impl Guest for RecordsWorld {
             })
         }
     }
+
+    fn reverse_segment(Segment { start, end }: Segment) -> Segment {
+        Segment {
+            start: end,
+            end: start,
+        }
+    }
 }
@@ -0,0 +1,30 @@
+use std::{
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use wit_bindgen::StreamReader;
+
+wit_bindgen::generate!({
+    world: "streams",
+});
+
+struct StreamsWorld;
+
+export!(StreamsWorld);
+
+impl Guest for StreamsWorld {
+    fn count_up(to: u32) -> StreamReader<u32> {
+        let (mut writer, reader) = wit_stream::new();
+        {
+            // The host buffers what's written until it receives it, so the
+            // write completes without anything to wait on.
+            let write = pin!(writer.write((1..=to).collect()));
+            let Poll::Ready((_, rest)) = write.poll(&mut Context::from_waker(Waker::noop())) else {
+                panic!("the host blocked the write");
+            };
+            assert_eq!(rest.remaining(), 0, "the host didn't take every value");
+        }
+        reader
+    }
+}
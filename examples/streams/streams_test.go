@@ -0,0 +1,54 @@
+package streams
+
+import (
+	"slices"
+	"testing"
+)
+
+func TestCountUp(t *testing.T) {
+	fac, err := NewStreamsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	// The guest writes every value before returning the stream, which would
+	// deadlock if the host didn't buffer them until they're received.
+	stream := ins.CountUp(t.Context(), 5)
+	defer stream.Close()
+
+	var values []uint32
+	for value := range stream.Values() {
+		values = append(values, value)
+	}
+	if want := []uint32{1, 2, 3, 4, 5}; !slices.Equal(values, want) {
+		t.Errorf("expected %v, but got %v", want, values)
+	}
+}
+
+func TestCountUpNothing(t *testing.T) {
+	fac, err := NewStreamsFactory(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer fac.Close(t.Context())
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(t.Context())
+
+	stream := ins.CountUp(t.Context(), 0)
+	defer stream.Close()
+
+	for value := range stream.Values() {
+		t.Errorf("expected no values, but got %d", value)
+	}
+}
@@ -0,0 +1,39 @@
+use std::{
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+wit_bindgen::generate!({
+    world: "wasi",
+});
+
+struct WasiWorld;
+
+export!(WasiWorld);
+
+impl Guest for WasiWorld {
+    fn print(message: String, warning: String) {
+        print!("{message}");
+        std::io::stdout().flush().unwrap();
+        eprint!("{warning}");
+    }
+
+    fn env(key: String) -> Option<String> {
+        std::env::var(key).ok()
+    }
+
+    fn args() -> Vec<String> {
+        std::env::args().collect()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn read_file(path: String) -> Result<String, String> {
+        std::fs::read_to_string(path).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,116 @@
+package wasi
+
+import (
+	"context"
+	"errors"
+	"os"
+	"path/filepath"
+	"slices"
+	"strings"
+	"testing"
+
+	"github.com/tetratelabs/wazero"
+	"github.com/tetratelabs/wazero/sys"
+)
+
+func newInstance(t *testing.T, opts ...WasiFactoryOption) *WasiInstance {
+	t.Helper()
+	fac, err := NewWasiFactory(t.Context(), append([]WasiFactoryOption{WithWASIPreview1()}, opts...)...)
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { fac.Close(context.Background()) })
+
+	ins, err := fac.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { ins.Close(context.Background()) })
+	return ins
+}
+
+func TestWASIPreview1Required(t *testing.T) {
+	_, err := NewWasiFactory(t.Context())
+	var instantiationErr *InstantiationError
+	if !errors.As(err, &instantiationErr) {
+		t.Errorf("expected a guest importing WASI to need WithWASIPreview1, but got %v", err)
+	}
+}
+
+func TestStdoutStderr(t *testing.T) {
+	var stdout, stderr strings.Builder
+	ins := newInstance(t, WithStdout(&stdout), WithStderr(&stderr))
+
+	ins.Print(t.Context(), "hello", "careful")
+
+	if stdout.String() != "hello" || stderr.String() != "careful" {
+		t.Errorf("expected hello on stdout and careful on stderr, but got %q and %q", stdout.String(), stderr.String())
+	}
+}
+
+func TestEnvAndArgs(t *testing.T) {
+	ins := newInstance(t, WithEnv("GREETING", "hi"), WithArgs("guest", "--flag"))
+
+	if got := ins.Env(t.Context(), "GREETING"); got == nil || *got != "hi" {
+		t.Errorf("expected GREETING to be hi, but got %v", got)
+	}
+	if got := ins.Env(t.Context(), "MISSING"); got != nil {
+		t.Errorf("expected MISSING not to be set, but got %q", *got)
+	}
+	if got, want := ins.Args(t.Context()), []string{"guest", "--flag"}; !slices.Equal(got, want) {
+		t.Errorf("expected the arguments %v, but got %v", want, got)
+	}
+}
+
+func TestWalltime(t *testing.T) {
+	walltime := func() (int64, int32) { return 1700000000, 0 }
+	ins := newInstance(t, WithWalltime(walltime, sys.ClockResolution(1)))
+
+	if got := ins.Now(t.Context()); got != 1700000000 {
+		t.Errorf("expected the guest to tell the time from the given clock, but got %d", got)
+	}
+}
+
+func TestFSConfig(t *testing.T) {
+	dir := t.TempDir()
+	if err := os.WriteFile(filepath.Join(dir, "greeting.txt"), []byte("hello"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	ins := newInstance(t, WithFSConfig(wazero.NewFSConfig().WithReadOnlyDirMount(dir, "/data")))
+
+	if got, err := ins.ReadFile(t.Context(), "/data/greeting.txt"); err != nil || got != "hello" {
+		t.Errorf("expected the mounted file, but got %q, %v", got, err)
+	}
+}
+
+func TestSharedRuntime(t *testing.T) {
+	runtime := wazero.NewRuntime(t.Context())
+	t.Cleanup(func() { runtime.Close(context.Background()) })
+
+	var stdout strings.Builder
+	first, err := NewWasiFactory(t.Context(), WithRuntime(runtime), WithWASIPreview1())
+	if err != nil {
+		t.Fatal(err)
+	}
+	second, err := NewWasiFactory(t.Context(), WithRuntime(runtime), WithWASIPreview1(), WithStdout(&stdout))
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer second.Close(context.Background())
+
+	// The factories share the runtime's WASI module, which closing one of
+	// them must leave to the other.
+	if err := first.Close(t.Context()); err != nil {
+		t.Fatal(err)
+	}
+	ins, err := second.Instantiate(t.Context())
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ins.Close(context.Background())
+
+	ins.Print(t.Context(), "hello", "")
+	if stdout.String() != "hello" {
+		t.Errorf("expected hello on stdout, but got %q", stdout.String())
+	}
+}